@@ -0,0 +1,225 @@
+use std::fmt::Display;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/**
+A problem found while loading `config.toml`.
+
+`line` and `column` are 1-based and point at the offending key (or the
+location of a syntax error) when it could be found in the file.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppConfigError {
+    pub file: String,
+    pub kind: AppConfigErrorKind,
+    pub key: Option<String>,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppConfigErrorKind {
+    // File could not be read
+    Io,
+    // File is not valid TOML, defaults are used for everything
+    Syntax,
+    // Key has a value of the wrong type, default is used for this key
+    InvalidValue,
+    // Key is not known to AppConfig and is ignored
+    UnknownKey,
+    // File was written by a newer version of the app
+    NewerVersion,
+}
+
+impl AppConfigError {
+    pub fn io(message: String) -> Self {
+        Self {
+            file: String::from(CONFIG_FILE_NAME),
+            kind: AppConfigErrorKind::Io,
+            key: None,
+            message,
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn syntax(error: &toml::de::Error, source: &str) -> Self {
+        let position = error
+            .span()
+            .map(|span| position_from_offset(source, span.start));
+
+        Self {
+            file: String::from(CONFIG_FILE_NAME),
+            kind: AppConfigErrorKind::Syntax,
+            key: None,
+            message: error.message().trim().to_string(),
+            line: position.map(|p| p.0),
+            column: position.map(|p| p.1),
+        }
+    }
+
+    pub fn invalid_value(key: String, message: String, source: &str) -> Self {
+        let position = locate_key(source, &key);
+
+        Self {
+            file: String::from(CONFIG_FILE_NAME),
+            kind: AppConfigErrorKind::InvalidValue,
+            key: Some(key),
+            message: message.trim().to_string(),
+            line: position.map(|p| p.0),
+            column: position.map(|p| p.1),
+        }
+    }
+
+    pub fn unknown_key(key: String, source: &str) -> Self {
+        let position = locate_key(source, &key);
+
+        Self {
+            file: String::from(CONFIG_FILE_NAME),
+            kind: AppConfigErrorKind::UnknownKey,
+            message: format!("unknown key '{key}'"),
+            key: Some(key),
+            line: position.map(|p| p.0),
+            column: position.map(|p| p.1),
+        }
+    }
+
+    pub fn newer_version(version: i64, source: &str) -> Self {
+        let position = locate_key(source, "version");
+
+        Self {
+            file: String::from(CONFIG_FILE_NAME),
+            kind: AppConfigErrorKind::NewerVersion,
+            key: Some(String::from("version")),
+            message: format!(
+                "file was written by a newer version of MyStudio IDE (version {version}), some settings may be ignored"
+            ),
+            line: position.map(|p| p.0),
+            column: position.map(|p| p.1),
+        }
+    }
+
+    /// Sets the name of the file this problem was found in.
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file = file.to_string();
+        self
+    }
+
+    /// Warnings don't affect the loaded config
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self.kind,
+            AppConfigErrorKind::UnknownKey | AppConfigErrorKind::NewerVersion
+        )
+    }
+}
+
+impl Display for AppConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = if self.is_warning() { "warning" } else { "error" };
+
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(
+                f,
+                "{}:{line}:{column}: {severity}: {}",
+                self.file, self.message
+            ),
+            _ => write!(f, "{}: {severity}: {}", self.file, self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppConfigError {}
+
+/**
+ * Converts a byte offset in `source` to a 1-based (line, column) pair.
+ */
+fn position_from_offset(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+/**
+ * Finds the position of a dotted key (ex: `Editor.tab_width`) in `source`.
+ *
+ * Only `[Section]` headers followed by `key = value` lines are supported,
+ * which is how `AppConfigProvider::save` writes the file.
+ */
+fn locate_key(source: &str, dotted_key: &str) -> Option<(usize, usize)> {
+    let (section, key) = match dotted_key.split_once('.') {
+        Some((section, key)) => (Some(section), key),
+        None => (None, dotted_key),
+    };
+
+    let mut current_section: Option<&str> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let indent = line.chars().count() - trimmed.chars().count();
+
+        if trimmed.starts_with('[') {
+            let header = trimmed
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .trim();
+
+            // Section itself is the key we're looking for
+            if section.is_none() && header == key {
+                return Some((index + 1, indent + 1));
+            }
+            current_section = Some(header);
+            continue;
+        }
+
+        if current_section != section {
+            continue;
+        }
+
+        let Some((name, _)) = trimmed.split_once('=') else {
+            continue;
+        };
+
+        if name.trim().trim_matches('"') == key {
+            return Some((index + 1, indent + 1));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locate_key, position_from_offset};
+
+    #[test]
+    fn position_from_offset_test() {
+        let source = "[General]\nfoo = 1\n";
+
+        assert_eq!(position_from_offset(source, 0), (1, 1));
+        assert_eq!(position_from_offset(source, 16), (2, 7));
+    }
+
+    #[test]
+    fn locate_key_test() {
+        let source = "[General]\nfoo = 1\n\n[Editor]\n  tab_width = 4\n";
+
+        assert_eq!(locate_key(source, "General.foo"), Some((2, 1)));
+        assert_eq!(locate_key(source, "Editor.tab_width"), Some((5, 3)));
+        assert_eq!(locate_key(source, "Editor"), Some((4, 1)));
+        assert_eq!(locate_key(source, "Editor.foo"), None);
+
+        assert_eq!(
+            locate_key("version = 1\n[General]\n", "version"),
+            Some((1, 1))
+        );
+    }
+}
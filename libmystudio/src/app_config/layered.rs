@@ -0,0 +1,186 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arc_swap::ArcSwap;
+use static_init::dynamic;
+
+use crate::workspace::Workspace;
+
+use super::{
+    default_config_value, error::AppConfigError, merge_lenient, AppConfig, AppConfigProvider,
+    DefaultAppConfigProvider,
+};
+
+pub const WORKSPACE_SETTINGS_FILE: &str = ".mystudio/settings.toml";
+
+// Holds reference to the config currently applied to the UI
+#[dynamic]
+static LAYERED_APP_CONFIG: ArcSwap<LayeredAppConfig> =
+    ArcSwap::new(Arc::new(LayeredAppConfig::default()));
+
+/// Where the value of a config key came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigLayer {
+    // Built-in default
+    Default,
+    // User config, `config.toml`
+    User,
+    // Workspace settings, `.mystudio/settings.toml` inside workspace root
+    Workspace,
+}
+
+/**
+The effective AppConfig, built from defaults, the user config file and the
+workspace settings file, in that order.
+
+It remembers which layer each key (ex: `Editor.tab_width`) was taken from.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct LayeredAppConfig {
+    config: Arc<AppConfig>,
+    origins: BTreeMap<String, ConfigLayer>,
+    errors: Vec<AppConfigError>,
+}
+
+impl LayeredAppConfig {
+    /**
+     * Builds the effective config from the contents of the user config file
+     * and workspace settings file.
+     */
+    pub fn from_sources(user_source: Option<&str>, workspace_source: Option<&str>) -> Self {
+        let mut merged = default_config_value();
+        let mut origins = BTreeMap::new();
+        let mut errors = vec![];
+
+        let layers = [
+            (user_source, ConfigLayer::User, "config.toml"),
+            (
+                workspace_source,
+                ConfigLayer::Workspace,
+                WORKSPACE_SETTINGS_FILE,
+            ),
+        ];
+
+        for (source, layer, file_name) in layers {
+            let Some(source) = source else {
+                continue;
+            };
+
+            let (applied_keys, layer_errors) = merge_lenient(&mut merged, source);
+            for key in applied_keys {
+                origins.insert(key, layer);
+            }
+            errors.extend(layer_errors.into_iter().map(|e| e.in_file(file_name)));
+        }
+
+        Self {
+            config: Arc::new(merged.try_into::<AppConfig>().unwrap_or_default()),
+            origins,
+            errors,
+        }
+    }
+
+    /// Adds a problem found while reading config files, before the others.
+    pub(crate) fn with_error(mut self, error: AppConfigError) -> Self {
+        self.errors.insert(0, error);
+        self
+    }
+
+    /// Returns the config currently applied to the UI.
+    pub fn current() -> Arc<LayeredAppConfig> {
+        LAYERED_APP_CONFIG.load_full()
+    }
+
+    /// Replaces the config currently applied to the UI.
+    pub fn replace(layered: LayeredAppConfig) {
+        LAYERED_APP_CONFIG.swap(Arc::new(layered));
+    }
+
+    /**
+     * Reloads config for the current workspace and makes it the current config.
+     *
+     * Returns the problems found in config files.
+     */
+    pub fn recompute() -> Vec<AppConfigError> {
+        let layered = DefaultAppConfigProvider::load_layered(&Workspace::get_path());
+        let errors = layered.errors.clone();
+        Self::replace(layered);
+
+        errors
+    }
+
+    pub fn config(&self) -> Arc<AppConfig> {
+        self.config.clone()
+    }
+
+    pub fn errors(&self) -> &[AppConfigError] {
+        &self.errors
+    }
+
+    /// Returns the layer a given key (ex: `Editor.tab_width`) was taken from.
+    pub fn origin(&self, key: &str) -> ConfigLayer {
+        self.origins
+            .get(key)
+            .copied()
+            .unwrap_or(ConfigLayer::Default)
+    }
+
+    /// Returns the effective value of a given key along with its layer.
+    pub fn value(&self, key: &str) -> Option<(toml::Value, ConfigLayer)> {
+        let (section, name) = key.split_once('.')?;
+        let config_value = toml::Value::try_from(self.config.as_ref()).ok()?;
+        let value = config_value.get(section)?.get(name)?.clone();
+
+        Some((value, self.origin(key)))
+    }
+}
+
+/**
+ * Returns path of settings file for a given workspace, if a workspace is open.
+ */
+pub fn workspace_settings_path(workspace_dir: &str) -> Option<PathBuf> {
+    if workspace_dir.is_empty() {
+        return None;
+    }
+
+    Some(Path::new(workspace_dir).join(WORKSPACE_SETTINGS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigLayer, LayeredAppConfig};
+
+    #[test]
+    fn workspace_layer_overrides_user_layer() {
+        let user = "[Editor]\ntab_width = 8\nfont_size = 14\n";
+        let workspace = "[Editor]\ntab_width = 2\n\n[Search]\nexclude = [\"dist\"]\n";
+
+        let layered = LayeredAppConfig::from_sources(Some(user), Some(workspace));
+        let config = layered.config();
+
+        assert_eq!(config.Editor.tab_width, 2);
+        assert_eq!(config.Editor.font_size, 14);
+        assert_eq!(config.Search.exclude, vec![String::from("dist")]);
+
+        assert_eq!(layered.origin("Editor.tab_width"), ConfigLayer::Workspace);
+        assert_eq!(layered.origin("Editor.font_size"), ConfigLayer::User);
+        assert_eq!(layered.origin("Editor.insert_spaces"), ConfigLayer::Default);
+
+        let (value, layer) = layered.value("Editor.tab_width").unwrap();
+        assert_eq!(value.as_integer(), Some(2));
+        assert_eq!(layer, ConfigLayer::Workspace);
+    }
+
+    #[test]
+    fn workspace_layer_errors_name_settings_file() {
+        let layered = LayeredAppConfig::from_sources(None, Some("[Editor]\ntab_width = \"x\"\n"));
+
+        assert_eq!(layered.config().Editor.tab_width, 4);
+        assert_eq!(layered.errors().len(), 1);
+        assert_eq!(layered.errors()[0].file, ".mystudio/settings.toml");
+        assert_eq!(layered.origin("Editor.tab_width"), ConfigLayer::Default);
+    }
+}
@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use toml::value::Table;
+
+use super::{error::AppConfigError, write_config_file_str};
+
+/// Schema version written to new config files.
+pub const CURRENT_CONFIG_VERSION: u32 = 1;
+
+type Migration = fn(&mut Table);
+
+/**
+ * Ordered list of migrations, `MIGRATIONS[n]` upgrades a config from version
+ * `n` to version `n + 1`.
+ *
+ * Whenever a key is renamed or moved, bump `CURRENT_CONFIG_VERSION` and append
+ * a migration here instead of changing an older one.
+ */
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/**
+ * Version 0 is the config written before versioning was introduced.
+ * Sections are unchanged in version 1, which only adds the `version` key.
+ */
+fn migrate_v0_to_v1(_config: &mut Table) {}
+
+/**
+ * Returns schema version of a config, configs without version are version 0.
+ */
+pub fn config_version(config: &Table) -> i64 {
+    config
+        .get("version")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or(0)
+}
+
+/**
+ * Upgrades `config` to `CURRENT_CONFIG_VERSION` in place.
+ *
+ * `source` is the text `config` was parsed from and is used for error positions.
+ * Returns the version `config` was upgraded from. Configs written by a newer
+ * version are left as they are and reported as a warning.
+ */
+pub fn migrate(config: &mut Table, source: &str) -> Result<i64, AppConfigError> {
+    migrate_with(config, source, MIGRATIONS)
+}
+
+fn migrate_with(
+    config: &mut Table,
+    source: &str,
+    migrations: &[Migration],
+) -> Result<i64, AppConfigError> {
+    let version = config_version(config);
+    let latest_version = migrations.len() as i64;
+
+    if version > latest_version {
+        return Err(AppConfigError::newer_version(version, source));
+    }
+
+    for (index, migration) in migrations.iter().enumerate().skip(version.max(0) as usize) {
+        migration(config);
+        config.insert(
+            String::from("version"),
+            toml::Value::Integer(index as i64 + 1),
+        );
+    }
+
+    Ok(version)
+}
+
+/**
+ * Upgrades config file at `path` if it was written by an older version and
+ * migrations change its settings.
+ *
+ * The original file is copied next to it (ex: `config.toml.v0.bak`) before it
+ * is rewritten. Returns the path of the backup if the file was migrated.
+ * Files only missing the newer `version` are left as they are, it is stamped
+ * in memory when they are read. Files with invalid TOML syntax are left as
+ * they are too.
+ */
+pub(crate) fn migrate_config_file(path: &Path) -> Result<Option<PathBuf>, AppConfigError> {
+    migrate_config_file_with(path, MIGRATIONS)
+}
+
+fn migrate_config_file_with(
+    path: &Path,
+    migrations: &[Migration],
+) -> Result<Option<PathBuf>, AppConfigError> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|error| AppConfigError::io(format!("Unable to read config file: {error}")))?;
+
+    let Ok(toml::Value::Table(mut config)) = toml::from_str::<toml::Value>(&source) else {
+        return Ok(None);
+    };
+
+    let version = config_version(&config);
+    if version >= migrations.len() as i64 {
+        return Ok(None);
+    }
+
+    let mut migrated = config.clone();
+    migrate_with(&mut migrated, &source, migrations)?;
+
+    let migrated_version = migrated.remove("version");
+    config.remove("version");
+    if migrated == config {
+        return Ok(None);
+    }
+    if let Some(migrated_version) = migrated_version {
+        migrated.insert(String::from("version"), migrated_version);
+    }
+
+    let backup_path = backup_path_for(path, version);
+    std::fs::copy(path, &backup_path).map_err(|error| {
+        AppConfigError::io(format!(
+            "Unable to back up config file before migration: {error}"
+        ))
+    })?;
+
+    let config_str = toml::to_string(&toml::Value::Table(migrated))
+        .map_err(|error| AppConfigError::io(format!("Unable to serialize config: {error}")))?;
+    write_config_file_str(path, &config_str);
+
+    Ok(Some(backup_path))
+}
+
+/**
+ * Returns a path next to `path` which doesn't exist yet, ex: `config.toml.v0.bak`.
+ */
+fn backup_path_for(path: &Path, version: i64) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut backup_path = path.with_file_name(format!("{file_name}.v{version}.bak"));
+    let mut counter = 1;
+    while backup_path.exists() {
+        backup_path = path.with_file_name(format!("{file_name}.v{version}.{counter}.bak"));
+        counter += 1;
+    }
+
+    backup_path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::copy, path::Path};
+
+    use tempfile::tempdir;
+    use toml::value::Table;
+
+    use crate::app_config::{error::AppConfigErrorKind, AppConfig};
+
+    use super::{
+        config_version, migrate_config_file, migrate_config_file_with, migrate_with, Migration,
+        CURRENT_CONFIG_VERSION,
+    };
+
+    fn copy_fixture_to_temp_dir(fixture_name: &str, temp_dir: &Path) -> std::path::PathBuf {
+        let fixture_path = Path::new("./src/test/fixtures/config").join(fixture_name);
+        let target_path = temp_dir.join("config.toml");
+        assert!(copy(fixture_path, &target_path).is_ok());
+
+        target_path
+    }
+
+    fn rename_a_to_b(config: &mut Table) {
+        if let Some(value) = config.remove("a") {
+            config.insert("b".into(), value);
+        }
+    }
+
+    fn rename_b_to_c(config: &mut Table) {
+        if let Some(value) = config.remove("b") {
+            config.insert("c".into(), value);
+        }
+    }
+
+    #[test]
+    fn v0_config_file_is_not_rewritten() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = copy_fixture_to_temp_dir("v0.toml", temp_dir.path());
+        let original = std::fs::read_to_string(&config_path).unwrap();
+
+        // Version 1 only adds `version`, there is nothing to back up
+        assert_eq!(migrate_config_file(&config_path).unwrap(), None);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+        assert!(!temp_dir.path().join("config.toml.v0.bak").exists());
+
+        // Version is stamped in memory and user values are kept
+        let (config, errors) = AppConfig::from_str_lenient(&original);
+        assert!(errors.is_empty());
+        assert_eq!(config.version, CURRENT_CONFIG_VERSION);
+        assert_eq!(config.General.application_width, 1280);
+        assert_eq!(config.General.application_height, 720);
+    }
+
+    #[test]
+    fn migrated_config_file_is_backed_up() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        std::fs::write(&config_path, "a = 1\n").unwrap();
+        let migrations: &[Migration] = &[rename_a_to_b, rename_b_to_c];
+
+        let backup_path = migrate_config_file_with(&config_path, migrations).unwrap();
+
+        // Original file is backed up as it was
+        let backup_path = backup_path.expect("v0 config should be migrated");
+        assert_eq!(backup_path.file_name().unwrap(), "config.toml.v0.bak");
+        assert_eq!(std::fs::read_to_string(backup_path).unwrap(), "a = 1\n");
+
+        let migrated = std::fs::read_to_string(&config_path).unwrap();
+        let config = toml::from_str::<Table>(&migrated).unwrap();
+        assert_eq!(config.get("c").and_then(toml::Value::as_integer), Some(1));
+        assert_eq!(config_version(&config), 2);
+
+        // Running it again is a no-op
+        assert_eq!(
+            migrate_config_file_with(&config_path, migrations).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn current_config_file_is_not_migrated() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = copy_fixture_to_temp_dir("v1.toml", temp_dir.path());
+        let original = std::fs::read_to_string(&config_path).unwrap();
+
+        assert_eq!(migrate_config_file(&config_path).unwrap(), None);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+
+        let (config, errors) = AppConfig::from_str_lenient(&original);
+        assert!(errors.is_empty());
+        assert_eq!(config.Editor.tab_width, 2);
+    }
+
+    #[test]
+    fn migrations_run_in_order() {
+        let migrations: &[Migration] = &[rename_a_to_b, rename_b_to_c];
+
+        let mut config = toml::from_str::<Table>("a = 1\n").unwrap();
+        assert_eq!(migrate_with(&mut config, "", migrations).unwrap(), 0);
+        assert_eq!(config.get("c").and_then(toml::Value::as_integer), Some(1));
+        assert_eq!(config_version(&config), 2);
+
+        // Only newer migrations are applied
+        let mut config = toml::from_str::<Table>("version = 1\nb = 2\na = 3\n").unwrap();
+        assert_eq!(migrate_with(&mut config, "", migrations).unwrap(), 1);
+        assert_eq!(config.get("c").and_then(toml::Value::as_integer), Some(2));
+        assert_eq!(config.get("a").and_then(toml::Value::as_integer), Some(3));
+    }
+
+    #[test]
+    fn newer_config_is_reported() {
+        let source = "version = 99\n\n[Editor]\ntab_width = 2\n";
+        let (config, errors) = AppConfig::from_str_lenient(source);
+
+        assert_eq!(config.Editor.tab_width, 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, AppConfigErrorKind::NewerVersion);
+        assert!(errors[0].is_warning());
+        assert_eq!(errors[0].line, Some(1));
+    }
+}
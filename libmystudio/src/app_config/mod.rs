@@ -1,18 +1,61 @@
 use std::{
+    collections::BTreeMap,
     fs::File,
-    io::BufReader,
-    io::{BufWriter, Read, Write},
+    io::{BufWriter, Write},
+    path::Path,
     str::FromStr,
+    sync::Arc,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::fs::get_config_file_path;
+use crate::{
+    completion::CompletionConfig,
+    debugger::{default_debug_adapters, DebugAdapterConfig, LaunchConfig},
+    format::{default_formatters, FormatterConfig},
+    fs::get_config_file_path,
+    keymap::{check_keybindings, effective_keybindings, is_known_command, KeymapPreset},
+    lsp::{default_language_servers, LanguageServerConfig},
+    tasks::TaskConfig,
+    terminal::TerminalConfig,
+};
+
+use self::{
+    error::{AppConfigError, AppConfigErrorKind},
+    layered::{workspace_settings_path, LayeredAppConfig},
+    migrations::{migrate, migrate_config_file, CURRENT_CONFIG_VERSION},
+};
+
+pub mod error;
+pub mod layered;
+pub mod migrations;
+pub mod watcher;
+
+// Sections keyed by user-chosen names, they accept keys missing from defaults
+const OPEN_SECTIONS: &[&str] = &[
+    "LanguageServers",
+    "Formatters",
+    "Tasks",
+    "DebugAdapters",
+    "Launch",
+];
+
+const CONFIG_FILE_HEADER: &str = "\
+# MyStudio IDE configuration.
+#
+# Changes to this file are applied while the IDE is running. Settings can
+# also be changed from Preferences (Ctrl+,), which rewrites this file.
+# Per-workspace overrides go in `.mystudio/settings.toml` inside the workspace.
 
-#[derive(Serialize, Deserialize)]
+";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
 pub struct AppConfigGeneralOptions {
     pub application_width: i32,
     pub application_height: i32,
+    // Keybindings to start from, `[Keybindings]` customizes them
+    pub keymap_preset: KeymapPreset,
 }
 
 impl Default for AppConfigGeneralOptions {
@@ -20,14 +63,402 @@ impl Default for AppConfigGeneralOptions {
         Self {
             application_width: 1024,
             application_height: 768,
+            keymap_preset: KeymapPreset::Default,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AppConfigEditorOptions {
+    pub font_family: String,
+    pub font_size: u32,
+    pub style_scheme: String,
+    pub tab_width: u32,
+    pub insert_spaces: bool,
+    pub show_line_numbers: bool,
+    pub highlight_current_line: bool,
+    // Modal editing with Vim keys
+    pub vim_mode: bool,
+    // Runs the formatter of the file, see `[Formatters]`
+    pub format_on_save: bool,
+}
+
+impl Default for AppConfigEditorOptions {
+    fn default() -> Self {
+        Self {
+            font_family: String::from("Monospace"),
+            font_size: 11,
+            style_scheme: String::from("classic"),
+            tab_width: 4,
+            insert_spaces: true,
+            show_line_numbers: true,
+            highlight_current_line: true,
+            vim_mode: false,
+            format_on_save: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AppConfigSearchOptions {
+    // File and directory names (or glob patterns) skipped by "Find in Files"
+    pub exclude: Vec<String>,
+}
+
+impl Default for AppConfigSearchOptions {
+    fn default() -> Self {
+        Self {
+            exclude: vec![
+                String::from(".git"),
+                String::from("target"),
+                String::from("node_modules"),
+            ],
         }
     }
 }
 
 #[allow(non_snake_case)]
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
 pub struct AppConfig {
+    // Schema version of the config file, see `migrations`
+    pub version: u32,
     pub General: AppConfigGeneralOptions,
+    pub Editor: AppConfigEditorOptions,
+    pub Completion: CompletionConfig,
+    pub Search: AppConfigSearchOptions,
+    pub Terminal: TerminalConfig,
+    // Command name to key sequence replacing the one of the keymap preset,
+    // ex: `save_file = "Ctrl+S"`
+    pub Keybindings: BTreeMap<String, String>,
+    // Language name to server, ex: `[LanguageServers.rust]`
+    pub LanguageServers: BTreeMap<String, LanguageServerConfig>,
+    // Language name to formatter, ex: `[Formatters.rust]`
+    pub Formatters: BTreeMap<String, FormatterConfig>,
+    // Task name to command, ex: `[Tasks.lint]`, usually in workspace settings
+    pub Tasks: BTreeMap<String, TaskConfig>,
+    // Adapter name to command, ex: `[DebugAdapters.codelldb]`
+    pub DebugAdapters: BTreeMap<String, DebugAdapterConfig>,
+    // Launch configuration name to program, ex: `[Launch.server]`
+    pub Launch: BTreeMap<String, LaunchConfig>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_CONFIG_VERSION,
+            General: AppConfigGeneralOptions::default(),
+            Editor: AppConfigEditorOptions::default(),
+            Completion: CompletionConfig::default(),
+            Search: AppConfigSearchOptions::default(),
+            Terminal: TerminalConfig::default(),
+            Keybindings: BTreeMap::new(),
+            LanguageServers: default_language_servers(),
+            Formatters: default_formatters(),
+            Tasks: BTreeMap::new(),
+            DebugAdapters: default_debug_adapters(),
+            Launch: BTreeMap::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Returns the effective config (user config with workspace settings applied).
+    pub fn current() -> Arc<AppConfig> {
+        LayeredAppConfig::current().config()
+    }
+
+    /// Returns keybindings of the selected preset with `[Keybindings]` applied.
+    pub fn keybindings(&self) -> BTreeMap<String, String> {
+        effective_keybindings(self.General.keymap_preset, &self.Keybindings)
+    }
+
+    /**
+     * Parses `s` without failing on bad input.
+     *
+     * Keys with invalid values fall back to their defaults and unknown keys are
+     * ignored; each of them is reported as an `AppConfigError`. A file with
+     * invalid TOML syntax yields the default config.
+     */
+    pub fn from_str_lenient(s: &str) -> (AppConfig, Vec<AppConfigError>) {
+        let mut merged = default_config_value();
+        let (_, errors) = merge_lenient(&mut merged, s);
+
+        let config = merged.try_into::<AppConfig>().unwrap_or_default();
+
+        (config, errors)
+    }
+
+    /**
+     * Checks values which are well-typed but out of range.
+     *
+     * Returns (key, message) pairs, ex: `("Editor.tab_width", "must be between 1 and 32")`.
+     */
+    pub fn validate(&self) -> Vec<(String, String)> {
+        let mut problems = vec![];
+        let mut check = |valid: bool, key: &str, message: &str| {
+            if !valid {
+                problems.push((key.to_string(), message.to_string()));
+            }
+        };
+
+        check(
+            self.General.application_width > 0,
+            "General.application_width",
+            "must be greater than 0",
+        );
+        check(
+            self.General.application_height > 0,
+            "General.application_height",
+            "must be greater than 0",
+        );
+        check(
+            !self.Editor.font_family.trim().is_empty(),
+            "Editor.font_family",
+            "must not be empty",
+        );
+        check(
+            (4..=96).contains(&self.Editor.font_size),
+            "Editor.font_size",
+            "must be between 4 and 96",
+        );
+        check(
+            (1..=32).contains(&self.Editor.tab_width),
+            "Editor.tab_width",
+            "must be between 1 and 32",
+        );
+        check(
+            (1..=10).contains(&self.Completion.min_prefix_length),
+            "Completion.min_prefix_length",
+            "must be between 1 and 10",
+        );
+
+        check(
+            self.Terminal.font_size == 0 || (4..=96).contains(&self.Terminal.font_size),
+            "Terminal.font_size",
+            "must be 0 (editor font size) or between 4 and 96",
+        );
+
+        for (language, server) in self.LanguageServers.iter() {
+            check(
+                !server.enabled || !server.command.trim().is_empty(),
+                &format!("LanguageServers.{language}"),
+                "command must not be empty",
+            );
+        }
+
+        for (language, formatter) in self.Formatters.iter() {
+            check(
+                !formatter.enabled || !formatter.command.trim().is_empty(),
+                &format!("Formatters.{language}"),
+                "command must not be empty",
+            );
+        }
+
+        for (name, task) in self.Tasks.iter() {
+            check(
+                !task.command.trim().is_empty(),
+                &format!("Tasks.{name}"),
+                "command must not be empty",
+            );
+        }
+
+        for (name, adapter) in self.DebugAdapters.iter() {
+            check(
+                !adapter.command.trim().is_empty(),
+                &format!("DebugAdapters.{name}"),
+                "command must not be empty",
+            );
+        }
+
+        for (name, launch) in self.Launch.iter() {
+            check(
+                !launch.program.trim().is_empty() || !launch.cargo.is_empty(),
+                &format!("Launch.{name}"),
+                "needs `program` or `cargo`",
+            );
+            check(
+                self.DebugAdapters.contains_key(&launch.adapter),
+                &format!("Launch.{name}"),
+                "unknown adapter",
+            );
+        }
+
+        for (command, message) in check_keybindings(&self.keybindings()) {
+            problems.push((format!("Keybindings.{command}"), message));
+        }
+
+        problems
+    }
+
+    /// Serializes config to a TOML table with one child table per section.
+    pub fn to_value(&self) -> toml::Value {
+        toml::Value::try_from(self).expect("Unable to serialize config.")
+    }
+
+    /**
+     * Returns a copy of config with a given key (ex: `Editor.tab_width`) set to `value`.
+     *
+     * Fails if the key is unknown, or if the value has the wrong type or is out of range.
+     */
+    pub fn with_value(&self, key: &str, value: toml::Value) -> Result<AppConfig, String> {
+        let (section, name) = key
+            .split_once('.')
+            .ok_or_else(|| format!("invalid key '{key}'"))?;
+
+        let mut config_value = self.to_value();
+        let section_value = config_value
+            .get_mut(section)
+            .and_then(toml::Value::as_table_mut)
+            .ok_or_else(|| format!("unknown section '{section}'"))?;
+
+        if !is_known_key(section, name, section_value) {
+            return Err(format!("unknown key '{key}'"));
+        }
+        section_value.insert(name.to_string(), value);
+
+        let config = config_value
+            .try_into::<AppConfig>()
+            .map_err(|error| error.to_string())?;
+
+        match config.validate().into_iter().find(|(k, _)| k == key) {
+            Some((_, message)) => Err(message),
+            None => Ok(config),
+        }
+    }
+}
+
+// Keys missing from defaults are accepted by open sections, and by
+// `[Keybindings]` for commands it doesn't customize yet
+fn is_known_key(section_name: &str, key: &str, default_section: &toml::value::Table) -> bool {
+    match section_name {
+        _ if default_section.contains_key(key) => true,
+        "Keybindings" => is_known_command(key),
+        _ => OPEN_SECTIONS.contains(&section_name),
+    }
+}
+
+/**
+ * Returns the value of a key once `value` is applied on top of `current`.
+ * Entries of open sections are merged key by key, ex: a
+ * `[LanguageServers.rust]` setting only `args` keeps the default `command`.
+ */
+fn merge_entry(
+    section_name: &str,
+    current: Option<&toml::Value>,
+    value: &toml::Value,
+) -> toml::Value {
+    match (current, value) {
+        (Some(toml::Value::Table(current)), toml::Value::Table(user))
+            if OPEN_SECTIONS.contains(&section_name) =>
+        {
+            let mut entry = current.clone();
+            entry.extend(user.clone());
+            toml::Value::Table(entry)
+        }
+        _ => value.clone(),
+    }
+}
+
+pub(crate) fn default_config_value() -> toml::Value {
+    AppConfig::default().to_value()
+}
+
+/**
+ * Applies keys from `s` on top of `merged`, skipping the ones that are unknown
+ * or have invalid values.
+ *
+ * Returns the dotted names (ex: `Editor.tab_width`) of applied keys and the
+ * problems found in `s`.
+ */
+pub(crate) fn merge_lenient(
+    merged: &mut toml::Value,
+    s: &str,
+) -> (Vec<String>, Vec<AppConfigError>) {
+    let mut applied_keys = vec![];
+    let mut errors = vec![];
+
+    let user_value = match toml::from_str::<toml::Value>(s) {
+        Ok(value) => value,
+        Err(error) => {
+            errors.push(AppConfigError::syntax(&error, s));
+            return (applied_keys, errors);
+        }
+    };
+
+    let Some(mut user_table) = user_value.as_table().cloned() else {
+        return (applied_keys, errors);
+    };
+
+    // Upgrade files written by older versions (in memory only)
+    if let Err(error) = migrate(&mut user_table, s) {
+        errors.push(error);
+    }
+
+    for (section_name, section_value) in user_table.iter() {
+        // Schema version always matches the running app after migration
+        if section_name == "version" {
+            continue;
+        }
+
+        let Some(default_section) = merged.get(section_name).cloned() else {
+            errors.push(AppConfigError::unknown_key(section_name.clone(), s));
+            continue;
+        };
+
+        let (Some(user_section), Some(default_section)) =
+            (section_value.as_table(), default_section.as_table())
+        else {
+            errors.push(AppConfigError::invalid_value(
+                section_name.clone(),
+                format!("expected '{section_name}' to be a section"),
+                s,
+            ));
+            continue;
+        };
+
+        for (key, value) in user_section {
+            let dotted_key = format!("{section_name}.{key}");
+
+            if !is_known_key(section_name, key, default_section) {
+                errors.push(AppConfigError::unknown_key(dotted_key, s));
+                continue;
+            }
+
+            // Try the value on a copy so a bad key doesn't affect the rest
+            let mut candidate = merged.clone();
+            if let Some(section) = candidate[section_name.as_str()].as_table_mut() {
+                let value = merge_entry(section_name, section.get(key), value);
+                section.insert(key.clone(), value);
+            }
+
+            let result = candidate
+                .clone()
+                .try_into::<AppConfig>()
+                .map_err(|error| error.to_string())
+                .and_then(|config| {
+                    match config.validate().into_iter().find(|(k, _)| *k == dotted_key) {
+                        Some((_, message)) => Err(message),
+                        None => Ok(config),
+                    }
+                });
+
+            match result {
+                Ok(_) => {
+                    *merged = candidate;
+                    applied_keys.push(dotted_key);
+                }
+                Err(message) => errors.push(AppConfigError::invalid_value(dotted_key, message, s)),
+            }
+        }
+    }
+
+    // Report problems in the order they appear in the file
+    errors.sort_by_key(|error| error.line);
+
+    (applied_keys, errors)
 }
 
 impl FromStr for AppConfig {
@@ -47,7 +478,37 @@ impl ToString for AppConfig {
 pub trait AppConfigProvider {
     fn get_config() -> AppConfig;
 
+    /// Loads the config along with the problems found in the config file.
+    fn load_config() -> (AppConfig, Vec<AppConfigError>);
+
+    /**
+     * Loads the user config with the settings of a given workspace applied,
+     * see `LayeredAppConfig`.
+     */
+    fn load_layered(workspace_dir: &str) -> LayeredAppConfig;
+
     fn save(config: &AppConfig);
+
+    /**
+     * Sets a given key (ex: `Editor.tab_width`) of the user config file to `value`.
+     *
+     * Only that key is rewritten, comments and other keys of the file are kept.
+     * Fails if the value is invalid, or if the file can't be read or parsed
+     * since its settings would be lost.
+     */
+    fn save_value(key: &str, value: toml::Value) -> Result<(), String>;
+}
+
+pub(crate) fn read_config_file(path: &Path) -> (AppConfig, Vec<AppConfigError>) {
+    match std::fs::read_to_string(path) {
+        Ok(file_contents) => AppConfig::from_str_lenient(&file_contents),
+        Err(error) => (
+            AppConfig::default(),
+            vec![AppConfigError::io(format!(
+                "Unable to read config file contents: {error}"
+            ))],
+        ),
+    }
 }
 
 /**
@@ -59,6 +520,15 @@ pub struct DefaultAppConfigProvider;
 
 impl AppConfigProvider for DefaultAppConfigProvider {
     fn get_config() -> AppConfig {
+        let (config, errors) = DefaultAppConfigProvider::load_config();
+        for error in errors {
+            eprintln!("{error}");
+        }
+
+        config
+    }
+
+    fn load_config() -> (AppConfig, Vec<AppConfigError>) {
         let path_buf = get_config_file_path();
 
         // Create a config file with app defaults
@@ -66,43 +536,308 @@ impl AppConfigProvider for DefaultAppConfigProvider {
             let default_config = AppConfig::default();
             DefaultAppConfigProvider::save(&default_config);
 
-            default_config
-        } else {
-            let file = File::open(path_buf).unwrap();
+            return (default_config, vec![]);
+        }
+
+        // Rewrite config file written by an older version
+        let migration_result = migrate_config_file(&path_buf);
+
+        let (config, mut errors) = read_config_file(&path_buf);
+        if let Err(error) = migration_result {
+            errors.insert(0, error);
+        }
+
+        (config, errors)
+    }
+
+    fn load_layered(workspace_dir: &str) -> LayeredAppConfig {
+        let user_path = get_config_file_path();
+        if !user_path.exists() {
+            DefaultAppConfigProvider::save(&AppConfig::default());
+        }
+
+        // Rewrite config file written by an older version
+        let migration_result = migrate_config_file(&user_path);
 
-            let mut file_contents = String::new();
+        let user_source = std::fs::read_to_string(&user_path);
+        let workspace_source = workspace_settings_path(workspace_dir)
+            .and_then(|path| std::fs::read_to_string(path).ok());
 
-            let mut reader = BufReader::new(file);
-            reader
-                .read_to_string(&mut file_contents)
-                .expect("Unable to read config file contents.");
+        let mut layered = LayeredAppConfig::from_sources(
+            user_source.as_deref().ok(),
+            workspace_source.as_deref(),
+        );
 
-            AppConfig::from_str(&file_contents).expect("Unable to parse config file contents.")
+        if let Err(error) = user_source {
+            layered = layered.with_error(AppConfigError::io(format!(
+                "Unable to read config file contents: {error}"
+            )));
+        }
+        if let Err(error) = migration_result {
+            layered = layered.with_error(error);
         }
+
+        layered
     }
 
     fn save(config: &AppConfig) {
-        let config_str = config.to_string();
+        write_config_file(&get_config_file_path(), config);
+    }
 
+    fn save_value(key: &str, value: toml::Value) -> Result<(), String> {
         let path_buf = get_config_file_path();
+        if !path_buf.exists() {
+            DefaultAppConfigProvider::save(&AppConfig::default());
+        }
+
+        let source = std::fs::read_to_string(&path_buf)
+            .map_err(|error| format!("unable to read config file: {error}"))?;
+
+        let (config, errors) = AppConfig::from_str_lenient(&source);
+        if let Some(error) = errors
+            .iter()
+            .find(|error| error.kind == AppConfigErrorKind::Syntax)
+        {
+            return Err(format!("not saved, fix the config file first ({error})"));
+        }
+        config.with_value(key, value.clone())?;
+
+        let config_str = set_value_in_source(&source, key, &value)?;
+        std::fs::write(&path_buf, config_str)
+            .map_err(|error| format!("unable to write config file: {error}"))
+    }
+}
+
+/**
+ * Returns `source` with a given key (ex: `Editor.tab_width`) set to `value`,
+ * leaving the rest of the file as it was.
+ */
+pub(crate) fn set_value_in_source(
+    source: &str,
+    key: &str,
+    value: &toml::Value,
+) -> Result<String, String> {
+    let (section, name) = key
+        .split_once('.')
+        .ok_or_else(|| format!("invalid key '{key}'"))?;
 
-        let mut writer = BufWriter::new(File::create(path_buf).unwrap());
+    let mut document = source
+        .parse::<toml_edit::Document>()
+        .map_err(|error| error.to_string())?;
+    let mut new_value = value
+        .to_string()
+        .parse::<toml_edit::Value>()
+        .map_err(|error| error.to_string())?;
 
-        writer
-            .write_all("# Config file documentation URL STUB\n\n".as_bytes())
-            .unwrap();
-        writer.write_all(config_str.as_bytes()).unwrap();
-        writer.flush().unwrap();
+    let section_table = document
+        .entry(section)
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .ok_or_else(|| format!("expected '{section}' to be a section"))?;
+
+    match section_table.get_mut(name) {
+        Some(toml_edit::Item::Value(old_value)) => {
+            // Keep comments and spacing around the value
+            *new_value.decor_mut() = old_value.decor().clone();
+            *old_value = new_value;
+        }
+        _ => {
+            section_table.insert(name, toml_edit::Item::Value(new_value));
+        }
     }
+
+    Ok(document.to_string())
+}
+
+pub(crate) fn write_config_file(path: &Path, config: &AppConfig) {
+    write_config_file_str(path, &config.to_string());
+}
+
+pub(crate) fn write_config_file_str(path: &Path, config_str: &str) {
+    let mut writer = BufWriter::new(File::create(path).unwrap());
+
+    writer
+        .write_all(CONFIG_FILE_HEADER.as_bytes())
+        .unwrap();
+    writer.write_all(config_str.as_bytes()).unwrap();
+    writer.flush().unwrap();
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::{DefaultAppConfigProvider,AppConfigProvider};
+    use std::str::FromStr;
+
+    use crate::keymap::KeymapPreset;
+
+    use super::{
+        error::AppConfigErrorKind, set_value_in_source, AppConfig, AppConfigProvider,
+        DefaultAppConfigProvider,
+    };
 
     #[test]
     fn app_config_is_created_at_startup() {
         assert!(DefaultAppConfigProvider::get_config().General.application_width > 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn app_config_without_editor_section_uses_defaults() {
+        let config = AppConfig::from_str(
+            "[General]\napplication_width = 800\napplication_height = 600\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.General.application_width, 800);
+        assert_eq!(config.Editor, AppConfig::default().Editor);
+    }
+
+    #[test]
+    fn app_config_lenient_keeps_valid_keys() {
+        let source = "[General]\napplication_width = \"wide\"\napplication_height = 600\n\n[Editor]\ntab_width = 8\nfoo = true\n";
+        let (config, errors) = AppConfig::from_str_lenient(source);
+
+        assert_eq!(config.General.application_width, 1024);
+        assert_eq!(config.General.application_height, 600);
+        assert_eq!(config.Editor.tab_width, 8);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind, AppConfigErrorKind::InvalidValue);
+        assert_eq!(errors[0].key.as_deref(), Some("General.application_width"));
+        assert_eq!((errors[0].line, errors[0].column), (Some(2), Some(1)));
+        assert_eq!(errors[1].kind, AppConfigErrorKind::UnknownKey);
+        assert_eq!(errors[1].line, Some(7));
+    }
+
+    #[test]
+    fn app_config_lenient_syntax_error() {
+        let (config, errors) = AppConfig::from_str_lenient("[General]\napplication_width = \n");
+
+        assert_eq!(config, AppConfig::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, AppConfigErrorKind::Syntax);
+        assert_eq!(errors[0].line, Some(2));
+    }
+
+    #[test]
+    fn app_config_partial_file_uses_defaults() {
+        let (config, errors) = AppConfig::from_str_lenient("[Editor]\nfont_size = 14\n");
+
+        assert!(errors.is_empty());
+        assert_eq!(config.General, AppConfig::default().General);
+        assert_eq!(config.Editor.font_size, 14);
+    }
+
+    #[test]
+    fn app_config_lenient_rejects_out_of_range_values() {
+        let (config, errors) = AppConfig::from_str_lenient("[Editor]\ntab_width = 0\n");
+
+        assert_eq!(config.Editor.tab_width, 4);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, AppConfigErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn app_config_with_value_test() {
+        let config = AppConfig::default();
+
+        let updated = config.with_value("Editor.tab_width", toml::Value::Integer(2));
+        assert_eq!(updated.unwrap().Editor.tab_width, 2);
+
+        assert!(config
+            .with_value("Editor.tab_width", toml::Value::String("2".into()))
+            .is_err());
+        assert!(config
+            .with_value("Editor.tab_width", toml::Value::Integer(64))
+            .is_err());
+        assert!(config
+            .with_value("Editor.foo", toml::Value::Integer(1))
+            .is_err());
+    }
+
+    #[test]
+    fn set_value_in_source_test() {
+        let source = "# Fonts\n[Editor]\nfont_size = 14 # larger\ntab_width = 2\nfoo = 1\n";
+
+        let edited = set_value_in_source(source, "Editor.font_size", &toml::Value::Integer(16));
+        assert_eq!(
+            edited.unwrap(),
+            "# Fonts\n[Editor]\nfont_size = 16 # larger\ntab_width = 2\nfoo = 1\n"
+        );
+
+        // Missing sections and keys are added
+        let edited = set_value_in_source(
+            source,
+            "Search.exclude",
+            &toml::Value::Array(vec![toml::Value::String("dist".into())]),
+        )
+        .unwrap();
+        let (config, _) = AppConfig::from_str_lenient(&edited);
+        assert_eq!(config.Search.exclude, vec![String::from("dist")]);
+        assert_eq!(config.Editor.font_size, 14);
+        assert!(edited.starts_with(source));
+    }
+
+    #[test]
+    fn app_config_keybindings_test() {
+        let source = "[Keybindings]\nsave_file = \"Ctrl+Alt+S\"\ngoto_line = \"Ctrl+Shift+F\"\nfoo = \"Ctrl+F\"\n";
+        let (config, errors) = AppConfig::from_str_lenient(source);
+
+        assert_eq!(config.keybindings()["save_file"], "Ctrl+Alt+S");
+        // Conflicting binding falls back to its default
+        assert_eq!(config.keybindings()["goto_line"], "Ctrl+G");
+        assert_eq!(config.keybindings()["find_in_files"], "Ctrl+Shift+F");
+        // Only bindings set by the user are kept
+        assert_eq!(config.Keybindings.len(), 1);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind, AppConfigErrorKind::InvalidValue);
+        assert_eq!(errors[0].key.as_deref(), Some("Keybindings.goto_line"));
+        assert_eq!(errors[1].kind, AppConfigErrorKind::UnknownKey);
+    }
+
+    #[test]
+    fn app_config_keymap_preset_test() {
+        let source = "[General]\nkeymap_preset = \"emacs\"\n[Keybindings]\nfind_in_files = \"Ctrl+S\"\n";
+        let (config, errors) = AppConfig::from_str_lenient(source);
+
+        assert_eq!(config.General.keymap_preset, KeymapPreset::Emacs);
+        assert_eq!(config.keybindings()["save_file"], "Ctrl+X Ctrl+S");
+        // `Ctrl+S` starts an incremental search with Emacs keys
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key.as_deref(), Some("Keybindings.find_in_files"));
+
+        let (_, errors) = AppConfig::from_str_lenient("[General]\nkeymap_preset = \"vi\"\n");
+        assert_eq!(errors[0].kind, AppConfigErrorKind::InvalidValue);
+    }
+
+    #[test]
+    fn app_config_language_servers_test() {
+        let source = "[LanguageServers.go]\ncommand = \"gopls\"\nextensions = [\"go\"]\n\n[LanguageServers.rust]\nenabled = false\n\n[LanguageServers.zig]\nextensions = [\"zig\"]\n";
+        let (config, errors) = AppConfig::from_str_lenient(source);
+
+        // Languages missing from defaults are not unknown keys
+        assert_eq!(config.LanguageServers["go"].command, "gopls");
+        assert!(!config.LanguageServers["rust"].enabled);
+        assert_eq!(config.LanguageServers["python"].command, "pyright-langserver");
+
+        assert!(!config.LanguageServers.contains_key("zig"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, AppConfigErrorKind::InvalidValue);
+        assert_eq!(errors[0].key.as_deref(), Some("LanguageServers.zig"));
+    }
+
+    #[test]
+    fn app_config_language_server_partial_override_test() {
+        let source = "[LanguageServers.rust]\nargs = [\"--log-file\", \"ra.log\"]\n";
+        let (config, errors) = AppConfig::from_str_lenient(source);
+
+        // Keys missing from the entry keep their default
+        assert!(errors.is_empty());
+        assert_eq!(config.LanguageServers["rust"].command, "rust-analyzer");
+        assert_eq!(
+            config.LanguageServers["rust"].args,
+            vec!["--log-file", "ra.log"]
+        );
+        assert_eq!(config.LanguageServers["rust"].extensions, vec!["rs"]);
+    }
+}
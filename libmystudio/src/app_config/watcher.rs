@@ -0,0 +1,63 @@
+use std::{
+    fs::metadata,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{fs::get_config_file_path, workspace::Workspace};
+
+use super::{
+    layered::{workspace_settings_path, LayeredAppConfig},
+    AppConfigProvider, DefaultAppConfigProvider,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/**
+Watches `config.toml` and the workspace settings file for changes and reloads them.
+
+Files are polled for modification time changes on a background thread.
+`callback` receives the freshly loaded config and is called from that thread,
+so it should forward the result to the UI thread (ex: using `glib::Sender`).
+*/
+pub struct AppConfigWatcher;
+
+impl AppConfigWatcher {
+    pub fn watch<F>(callback: F)
+    where
+        F: Fn(LayeredAppConfig) + Send + 'static,
+    {
+        let user_path = get_config_file_path();
+
+        thread::spawn(move || {
+            let mut last_modified = (modified_time(&user_path), workspace_modified_time());
+
+            loop {
+                thread::sleep(POLL_INTERVAL);
+
+                let user_modified = modified_time(&user_path);
+                // Skip while config file is being replaced by an editor
+                if user_modified.is_none() {
+                    continue;
+                }
+
+                let modified = (user_modified, workspace_modified_time());
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+
+                callback(DefaultAppConfigProvider::load_layered(&Workspace::get_path()));
+            }
+        });
+    }
+}
+
+fn workspace_modified_time() -> Option<SystemTime> {
+    workspace_settings_path(&Workspace::get_path()).and_then(|path| modified_time(&path))
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,237 @@
+// Side-by-side comparison of two texts: their changed lines, the parts of
+// changed lines which differ and the line of one side shown next to a line of
+// the other side
+
+use std::ops::Range;
+
+use crate::diff::{diff, split_lines, Hunk};
+
+// Longer lines are marked as a whole, comparing their words would be slow
+const MAX_INLINE_LENGTH: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Old,
+    New,
+}
+
+/**
+A line of the old text replaced by a line of the new text, with the changed
+parts of both as ranges of characters.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineChange {
+    pub old_line: usize,
+    pub new_line: usize,
+    pub old_ranges: Vec<Range<usize>>,
+    pub new_ranges: Vec<Range<usize>>,
+}
+
+/**
+Differences between an old and a new text, `hunks` have lines of the old
+text as old items and lines of the new text as new items.
+
+Line terminators are ignored, so that the last line without a newline or
+`\r\n` line endings aren't marked as changed.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comparison {
+    pub hunks: Vec<Hunk>,
+    // Lines of hunks changing as many lines as they add, paired in order
+    pub inline_changes: Vec<InlineChange>,
+}
+
+impl Comparison {
+    pub fn new(old: &str, new: &str) -> Comparison {
+        let old_lines = trimmed_lines(old);
+        let new_lines = trimmed_lines(new);
+        let hunks = diff(&old_lines, &new_lines);
+
+        let inline_changes = hunks
+            .iter()
+            .flat_map(|hunk| {
+                let pairs = hunk.old_len.min(hunk.new_len);
+                (0..pairs).map(move |i| (hunk.old_start + i, hunk.new_start + i))
+            })
+            .map(|(old_line, new_line)| {
+                let (old_ranges, new_ranges) =
+                    inline_ranges(old_lines[old_line], new_lines[new_line]);
+                InlineChange {
+                    old_line,
+                    new_line,
+                    old_ranges,
+                    new_ranges,
+                }
+            })
+            .collect();
+
+        Comparison {
+            hunks,
+            inline_changes,
+        }
+    }
+
+    /**
+     * Returns the line of the other side shown next to `line` of `side`, ex:
+     * to scroll both sides together.
+     *
+     * Lines of a hunk are matched in order with those replacing them, extra
+     * lines are matched with the last of them.
+     */
+    pub fn matching_line(&self, line: usize, side: Side) -> usize {
+        let mut other_line = line;
+
+        for hunk in &self.hunks {
+            let (start, len, other_start, other_len) = match side {
+                Side::Old => (hunk.old_start, hunk.old_len, hunk.new_start, hunk.new_len),
+                Side::New => (hunk.new_start, hunk.new_len, hunk.old_start, hunk.old_len),
+            };
+            if line < start {
+                break;
+            }
+            if line < start + len {
+                return other_start + (line - start).min(other_len.saturating_sub(1));
+            }
+            other_line = other_start + other_len + (line - start - len);
+        }
+
+        other_line
+    }
+}
+
+/**
+ * Returns the changed parts of `old` and `new` as ranges of characters.
+ *
+ * Words are compared rather than characters, so that a renamed identifier is
+ * marked as a whole.
+ */
+pub fn inline_ranges(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    if old.len() > MAX_INLINE_LENGTH || new.len() > MAX_INLINE_LENGTH {
+        let whole = |line: &str| 0..line.chars().count();
+        return (vec![whole(old)], vec![whole(new)]);
+    }
+
+    let (old_words, old_offsets) = words(old);
+    let (new_words, new_offsets) = words(new);
+
+    let mut old_ranges = vec![];
+    let mut new_ranges = vec![];
+    for hunk in diff(&old_words, &new_words) {
+        // Nothing to mark on the side of pure insertions or deletions
+        if hunk.old_len > 0 {
+            old_ranges.push(old_offsets[hunk.old_start]..old_offsets[hunk.old_end()]);
+        }
+        if hunk.new_len > 0 {
+            new_ranges.push(new_offsets[hunk.new_start]..new_offsets[hunk.new_end()]);
+        }
+    }
+
+    (old_ranges, new_ranges)
+}
+
+fn trimmed_lines(text: &str) -> Vec<&str> {
+    split_lines(text)
+        .into_iter()
+        .map(|line| line.trim_end_matches(['\n', '\r']))
+        .collect()
+}
+
+// Splits a line into identifiers, runs of whitespace and single other
+// characters. Returns them with their character offsets, followed by the
+// length of the line.
+fn words(line: &str) -> (Vec<&str>, Vec<usize>) {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut words = vec![];
+    let mut offsets = vec![];
+    let mut chars = line.char_indices().enumerate().peekable();
+
+    while let Some((offset, (start, c))) = chars.next() {
+        let mut end = start + c.len_utf8();
+        while let Some(&(_, (index, next))) = chars.peek() {
+            let same_kind =
+                (is_word(c) && is_word(next)) || (c.is_whitespace() && next.is_whitespace());
+            if !same_kind {
+                break;
+            }
+            end = index + next.len_utf8();
+            chars.next();
+        }
+
+        words.push(&line[start..end]);
+        offsets.push(offset);
+    }
+    offsets.push(line.chars().count());
+
+    (words, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(bounds: &[(usize, usize)]) -> Vec<Range<usize>> {
+        bounds.iter().map(|&(start, end)| start..end).collect()
+    }
+
+    #[test]
+    fn comparison_test() {
+        let old = "fn main() {\n    let count = 1;\n    run(count);\n}\n";
+        let new = "fn main() {\r\n    let total = 1;\r\n    init();\r\n    run(total);\r\n}";
+        let comparison = Comparison::new(old, new);
+
+        assert_eq!(
+            comparison.hunks,
+            [Hunk {
+                old_start: 1,
+                old_len: 2,
+                new_start: 1,
+                new_len: 3,
+            }]
+        );
+        // Extra lines of the hunk aren't paired
+        let pairs: Vec<(usize, usize)> = comparison
+            .inline_changes
+            .iter()
+            .map(|change| (change.old_line, change.new_line))
+            .collect();
+        assert_eq!(pairs, [(1, 1), (2, 2)]);
+        assert_eq!(comparison.inline_changes[0].old_ranges, ranges(&[(8, 13)]));
+        assert_eq!(comparison.inline_changes[0].new_ranges, ranges(&[(8, 13)]));
+
+        assert_eq!(Comparison::new(old, old), Comparison::default());
+    }
+
+    #[test]
+    fn matching_line_test() {
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "a\nB\nB2\nB3\nc\ne\nf\n";
+        let comparison = Comparison::new(old, new);
+
+        let old_to_new: Vec<usize> = (0..6)
+            .map(|line| comparison.matching_line(line, Side::Old))
+            .collect();
+        // `d` was deleted, it faces the line following it
+        assert_eq!(old_to_new, [0, 1, 4, 5, 5, 7]);
+
+        let new_to_old: Vec<usize> = (0..8)
+            .map(|line| comparison.matching_line(line, Side::New))
+            .collect();
+        assert_eq!(new_to_old, [0, 1, 1, 1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn inline_ranges_test() {
+        assert_eq!(
+            inline_ranges("let width = 1;", "let height = 1;"),
+            (ranges(&[(4, 9)]), ranges(&[(4, 10)]))
+        );
+        // Offsets count characters
+        assert_eq!(
+            inline_ranges("é = a + b", "é = a - b"),
+            (ranges(&[(6, 7)]), ranges(&[(6, 7)]))
+        );
+        assert_eq!(inline_ranges("", "new"), (vec![], ranges(&[(0, 3)])));
+        assert_eq!(inline_ranges("same", "same"), (vec![], vec![]));
+    }
+}
@@ -0,0 +1,263 @@
+// Code completion, candidates come from pluggable sources (ex: words of open
+// files) and are filtered with a fuzzy match on the text before the cursor
+
+use serde::{Deserialize, Serialize};
+
+use crate::fuzzy::fuzzy_match;
+
+pub mod paths;
+pub mod snippets;
+pub mod words;
+
+// Candidates shown at most per source, the best ones are kept
+const MAX_CANDIDATES: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct CompletionConfig {
+    // Show completions while typing, they can always be asked for
+    pub auto_trigger: bool,
+    // Chars of a word typed before completions show up on their own
+    pub min_prefix_length: u32,
+    // Text showing completions right away, ex: `.` before a method
+    pub trigger_characters: Vec<String>,
+    // Sources of candidates
+    pub words: bool,
+    pub paths: bool,
+    pub snippets: bool,
+    pub language_server: bool,
+}
+
+impl Default for CompletionConfig {
+    fn default() -> Self {
+        Self {
+            auto_trigger: true,
+            min_prefix_length: 3,
+            trigger_characters: vec![String::from("."), String::from("::"), String::from("/")],
+            words: true,
+            paths: true,
+            snippets: true,
+            language_server: true,
+        }
+    }
+}
+
+impl CompletionConfig {
+    /// Tells whether completions show up on their own after typing `line_before_cursor`.
+    pub fn triggers(&self, line_before_cursor: &str) -> bool {
+        if !self.auto_trigger {
+            return false;
+        }
+
+        let after_trigger = self
+            .trigger_characters
+            .iter()
+            .any(|trigger| !trigger.is_empty() && line_before_cursor.ends_with(trigger.as_str()));
+
+        after_trigger
+            || word_prefix(line_before_cursor).chars().count() >= self.min_prefix_length as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateKind {
+    Word,
+    File,
+    Directory,
+    // `text` is a snippet body, see `snippets::expand_snippet`
+    Snippet,
+    LanguageServer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub label: String,
+    // Replaces the prefix when accepted
+    pub text: String,
+    // Shown next to the label, ex: a function signature
+    pub detail: Option<String>,
+    // Shown in a side pane
+    pub documentation: Option<String>,
+    pub kind: CandidateKind,
+}
+
+impl Candidate {
+    pub fn new(label: &str, kind: CandidateKind) -> Self {
+        Self {
+            label: label.to_string(),
+            text: label.to_string(),
+            detail: None,
+            documentation: None,
+            kind,
+        }
+    }
+}
+
+/// Editor state completions are asked for.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionRequest {
+    pub file_path: String,
+    // Language id of the editor, ex: `rust`
+    pub language: Option<String>,
+    // Zero-based, `column` counts chars
+    pub line: u32,
+    pub column: u32,
+    pub line_before_cursor: String,
+    // Text of open files, the current one first, see `uses_documents`
+    pub documents: Vec<String>,
+}
+
+/**
+A provider of completion candidates.
+
+Sources return every candidate they know for a prefix, `complete` takes care
+of filtering and ranking them.
+*/
+pub trait CompletionSource {
+    fn name(&self) -> &str;
+
+    /// Tells whether `CompletionRequest::documents` should be filled, it is costly.
+    fn uses_documents(&self) -> bool {
+        false
+    }
+
+    /**
+     * Returns the text before the cursor replaced by candidates, or `None`
+     * when the source has nothing to offer at the cursor.
+     */
+    fn prefix<'a>(&self, request: &'a CompletionRequest) -> Option<&'a str> {
+        Some(word_prefix(&request.line_before_cursor))
+    }
+
+    fn candidates(&self, request: &CompletionRequest, prefix: &str) -> Vec<Candidate>;
+}
+
+/// Candidates of a source matching the text before the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    // Length of the replaced prefix, in chars
+    pub prefix_length: usize,
+    pub candidates: Vec<Candidate>,
+}
+
+/**
+ * Asks `source` for candidates and keeps those matching the prefix, best
+ * matches first.
+ */
+pub fn complete(source: &dyn CompletionSource, request: &CompletionRequest) -> Option<Completion> {
+    let prefix = source.prefix(request)?;
+    let candidates = source.candidates(request, prefix);
+
+    Some(Completion {
+        prefix_length: prefix.chars().count(),
+        candidates: filter_candidates(prefix, candidates),
+    })
+}
+
+/**
+ * Keeps candidates fuzzy matching `prefix`, sorted by score then by length.
+ *
+ * Candidates equal to the prefix are dropped, there is nothing to complete.
+ */
+pub fn filter_candidates(prefix: &str, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    let mut scored: Vec<(i64, Candidate)> = candidates
+        .into_iter()
+        .filter(|candidate| prefix.is_empty() || candidate.label != prefix)
+        .filter_map(|candidate| {
+            let matched = fuzzy_match(prefix, &candidate.label)?;
+            Some((matched.score, candidate))
+        })
+        .collect();
+
+    scored.sort_by(|(score_a, a), (score_b, b)| {
+        score_b
+            .cmp(score_a)
+            .then(a.label.len().cmp(&b.label.len()))
+            .then(a.label.cmp(&b.label))
+    });
+    scored.truncate(MAX_CANDIDATES);
+
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/**
+ * Returns the identifier ending at the end of `line_before_cursor`, ex: `pri`
+ * in `    pri`.
+ */
+pub fn word_prefix(line_before_cursor: &str) -> &str {
+    let start = line_before_cursor
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(line_before_cursor.len(), |(index, _)| index);
+
+    &line_before_cursor[start..]
+}
+
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        complete, word_prefix, Candidate, CandidateKind, CompletionConfig, CompletionRequest,
+        CompletionSource,
+    };
+
+    struct FixedSource;
+
+    impl CompletionSource for FixedSource {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn candidates(&self, _request: &CompletionRequest, _prefix: &str) -> Vec<Candidate> {
+            ["print", "println", "eprintln", "format", "pr"]
+                .iter()
+                .map(|label| Candidate::new(label, CandidateKind::Word))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn word_prefix_test() {
+        assert_eq!(word_prefix("    let total_sum"), "total_sum");
+        assert_eq!(word_prefix("self."), "");
+        assert_eq!(word_prefix("ünïcode"), "ünïcode");
+        assert_eq!(word_prefix(""), "");
+    }
+
+    #[test]
+    fn triggers_test() {
+        let mut config = CompletionConfig::default();
+
+        assert!(config.triggers("    let val"));
+        assert!(!config.triggers("    let va"));
+        assert!(config.triggers("self."));
+        assert!(config.triggers("std::"));
+
+        config.auto_trigger = false;
+        assert!(!config.triggers("self."));
+    }
+
+    #[test]
+    fn complete_test() {
+        let request = CompletionRequest {
+            line_before_cursor: String::from("    pr"),
+            ..CompletionRequest::default()
+        };
+
+        let completion = complete(&FixedSource, &request).unwrap();
+        let labels: Vec<_> = completion
+            .candidates
+            .iter()
+            .map(|c| c.label.as_str())
+            .collect();
+
+        assert_eq!(completion.prefix_length, 2);
+        // Typed word itself is left out, word starts rank first
+        assert_eq!(labels, ["print", "println", "eprintln"]);
+    }
+}
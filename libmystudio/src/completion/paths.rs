@@ -0,0 +1,150 @@
+use std::{fs, path::Path};
+
+use super::{Candidate, CandidateKind, CompletionRequest, CompletionSource};
+
+/**
+Completes file paths typed in string literals, ex: `"./src/ma"`.
+
+Relative paths are resolved from the directory of the current file.
+*/
+pub struct PathsSource;
+
+impl CompletionSource for PathsSource {
+    fn name(&self) -> &str {
+        "Paths"
+    }
+
+    // Only the last component of the path is replaced
+    fn prefix<'a>(&self, request: &'a CompletionRequest) -> Option<&'a str> {
+        let literal = string_literal_prefix(&request.line_before_cursor)?;
+        if !looks_like_path(literal) {
+            return None;
+        }
+
+        Some(literal.rsplit('/').next().unwrap_or(literal))
+    }
+
+    fn candidates(&self, request: &CompletionRequest, prefix: &str) -> Vec<Candidate> {
+        let Some(literal) = string_literal_prefix(&request.line_before_cursor) else {
+            return vec![];
+        };
+        let directory = &literal[..literal.len() - prefix.len()];
+
+        let directory_path = match directory.strip_prefix("~/") {
+            Some(directory) => dirs::home_dir().unwrap_or_default().join(directory),
+            None => Path::new(&request.file_path)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(directory),
+        };
+        let Ok(entries) = fs::read_dir(directory_path) else {
+            return vec![];
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                // Hidden files are offered once a dot is typed
+                if name.starts_with('.') && !prefix.starts_with('.') {
+                    return None;
+                }
+
+                let candidate = if entry.file_type().ok()?.is_dir() {
+                    let mut candidate = Candidate::new(&name, CandidateKind::Directory);
+                    candidate.text.push('/');
+                    candidate
+                } else {
+                    Candidate::new(&name, CandidateKind::File)
+                };
+                Some(candidate)
+            })
+            .collect()
+    }
+}
+
+/**
+ * Returns the text typed so far in the string literal open at the end of
+ * `line_before_cursor`, if any.
+ */
+pub fn string_literal_prefix(line_before_cursor: &str) -> Option<&str> {
+    let mut open_quote: Option<(char, usize)> = None;
+    let mut escaped = false;
+
+    for (index, c) in line_before_cursor.char_indices() {
+        match (open_quote, c) {
+            (_, _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some((quote, _)), c) if c == quote => open_quote = None,
+            (None, '"' | '\'' | '`') => open_quote = Some((c, index + c.len_utf8())),
+            _ => {}
+        }
+    }
+
+    open_quote.map(|(_, start)| &line_before_cursor[start..])
+}
+
+// Strings are only completed when they look like a path, not for any text
+fn looks_like_path(text: &str) -> bool {
+    text.starts_with('.') || text.starts_with('/') || text.starts_with('~') || text.contains('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::completion::{complete, CompletionRequest};
+
+    use super::{string_literal_prefix, PathsSource};
+
+    #[test]
+    fn string_literal_prefix_test() {
+        assert_eq!(
+            string_literal_prefix("let p = \"./src/ma"),
+            Some("./src/ma")
+        );
+        assert_eq!(string_literal_prefix("let p = \"a\\\"b"), Some("a\\\"b"));
+        assert_eq!(string_literal_prefix("f(\"a\", 'b"), Some("b"));
+        assert_eq!(string_literal_prefix("f(\"a\", b"), None);
+    }
+
+    #[test]
+    fn paths_source_test() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/ui")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        fs::write(dir.path().join("src/.hidden"), "").unwrap();
+        let file_path = dir.path().join("build.rs");
+
+        let request = CompletionRequest {
+            file_path: file_path.to_string_lossy().to_string(),
+            line_before_cursor: String::from("include_str!(\"./src/"),
+            ..CompletionRequest::default()
+        };
+        let completion = complete(&PathsSource, &request).unwrap();
+        let texts: Vec<_> = completion
+            .candidates
+            .iter()
+            .map(|c| c.text.as_str())
+            .collect();
+        assert_eq!(completion.prefix_length, 0);
+        assert_eq!(texts, ["ui/", "main.rs"]);
+
+        let request = CompletionRequest {
+            line_before_cursor: String::from("include_str!(\"src/mai"),
+            ..request
+        };
+        let completion = complete(&PathsSource, &request).unwrap();
+        assert_eq!(completion.prefix_length, 3);
+        assert_eq!(completion.candidates[0].label, "main.rs");
+
+        // Plain strings are not paths
+        let request = CompletionRequest {
+            line_before_cursor: String::from("println!(\"mai"),
+            ..request
+        };
+        assert_eq!(complete(&PathsSource, &request), None);
+    }
+}
@@ -0,0 +1,228 @@
+use super::{Candidate, CandidateKind, CompletionRequest, CompletionSource};
+
+/**
+A template inserted by typing its prefix.
+
+The body may hold tab stops, `$0` or `${1:default text}`, the first one is
+selected once the snippet is inserted.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub prefix: &'static str,
+    pub description: &'static str,
+    pub body: &'static str,
+}
+
+const fn snippet(prefix: &'static str, description: &'static str, body: &'static str) -> Snippet {
+    Snippet {
+        prefix,
+        description,
+        body,
+    }
+}
+
+const RUST_SNIPPETS: &[Snippet] = &[
+    snippet("fn", "Function", "fn ${1:name}(${2}) {\n    $0\n}"),
+    snippet(
+        "test",
+        "Test function",
+        "#[test]\nfn ${1:name}_test() {\n    $0\n}",
+    ),
+    snippet("impl", "Impl block", "impl ${1:Type} {\n    $0\n}"),
+    snippet("match", "Match expression", "match ${1:value} {\n    $0\n}"),
+    snippet(
+        "for",
+        "For loop",
+        "for ${1:item} in ${2:items} {\n    $0\n}",
+    ),
+    snippet(
+        "iflet",
+        "If let",
+        "if let Some(${1:value}) = ${2:option} {\n    $0\n}",
+    ),
+];
+
+const PYTHON_SNIPPETS: &[Snippet] = &[
+    snippet("def", "Function", "def ${1:name}(${2}):\n    ${0:pass}"),
+    snippet(
+        "class",
+        "Class",
+        "class ${1:Name}:\n    def __init__(self):\n        ${0:pass}",
+    ),
+    snippet(
+        "for",
+        "For loop",
+        "for ${1:item} in ${2:items}:\n    ${0:pass}",
+    ),
+    snippet(
+        "ifmain",
+        "Main guard",
+        "if __name__ == \"__main__\":\n    ${0:main()}",
+    ),
+];
+
+const JAVASCRIPT_SNIPPETS: &[Snippet] = &[
+    snippet(
+        "function",
+        "Function",
+        "function ${1:name}(${2}) {\n    $0\n}",
+    ),
+    snippet("arrow", "Arrow function", "(${1}) => {\n    $0\n}"),
+    snippet(
+        "for",
+        "For of loop",
+        "for (const ${1:item} of ${2:items}) {\n    $0\n}",
+    ),
+    snippet("log", "Console log", "console.log($0);"),
+];
+
+/// Returns built-in snippets of a language, named like sourceview does (ex: `js`).
+pub fn snippets_for_language(language: &str) -> &'static [Snippet] {
+    match language {
+        "rust" => RUST_SNIPPETS,
+        "python" | "python3" => PYTHON_SNIPPETS,
+        "js" | "javascript" | "typescript" | "jsx" | "typescript-jsx" => JAVASCRIPT_SNIPPETS,
+        _ => &[],
+    }
+}
+
+/// Completes snippet prefixes of the current language.
+pub struct SnippetsSource;
+
+impl CompletionSource for SnippetsSource {
+    fn name(&self) -> &str {
+        "Snippets"
+    }
+
+    fn prefix<'a>(&self, request: &'a CompletionRequest) -> Option<&'a str> {
+        let prefix = super::word_prefix(&request.line_before_cursor);
+        (!prefix.is_empty()).then_some(prefix)
+    }
+
+    fn candidates(&self, request: &CompletionRequest, _prefix: &str) -> Vec<Candidate> {
+        let language = request.language.as_deref().unwrap_or_default();
+
+        snippets_for_language(language)
+            .iter()
+            .map(|snippet| Candidate {
+                label: snippet.prefix.to_string(),
+                text: snippet.body.to_string(),
+                detail: Some(snippet.description.to_string()),
+                documentation: Some(expand_snippet(snippet.body).text),
+                kind: CandidateKind::Snippet,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedSnippet {
+    pub text: String,
+    // Char offsets in `text` of the first tab stop, selected after insertion
+    pub selection: (usize, usize),
+}
+
+/**
+ * Replaces tab stops of a snippet body with their default text.
+ *
+ * The selection covers the lowest numbered tab stop, `$0` comes last. Without
+ * tab stops the cursor goes to the end.
+ */
+pub fn expand_snippet(body: &str) -> ExpandedSnippet {
+    let mut text = String::new();
+    // (tab stop number, start, end) in chars
+    let mut tab_stops: Vec<(u32, usize, usize)> = vec![];
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            text.push(c);
+            continue;
+        }
+
+        let start = text.chars().count();
+        let braced = chars.next_if_eq(&'{').is_some();
+
+        let mut number = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            number.push(digit);
+        }
+
+        // Default text runs up to the matching brace
+        if braced && chars.next_if_eq(&':').is_some() {
+            let mut depth = 0;
+            for c in chars.by_ref() {
+                match c {
+                    '}' if depth == 0 => break,
+                    '}' => depth -= 1,
+                    '{' => depth += 1,
+                    _ => {}
+                }
+                text.push(c);
+            }
+        } else if braced {
+            chars.next_if_eq(&'}');
+        }
+
+        match number.parse::<u32>() {
+            Ok(number) => tab_stops.push((number, start, text.chars().count())),
+            // A lone dollar sign
+            Err(_) => text.push('$'),
+        }
+    }
+
+    let end = text.chars().count();
+    let selection = tab_stops
+        .iter()
+        .min_by_key(|(number, _, _)| if *number == 0 { u32::MAX } else { *number })
+        .map_or((end, end), |(_, start, end)| (*start, *end));
+
+    ExpandedSnippet { text, selection }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::{complete, CompletionRequest};
+
+    use super::{expand_snippet, ExpandedSnippet, SnippetsSource};
+
+    #[test]
+    fn expand_snippet_test() {
+        assert_eq!(
+            expand_snippet("fn ${1:name}(${2}) {\n    $0\n}"),
+            ExpandedSnippet {
+                text: String::from("fn name() {\n    \n}"),
+                selection: (3, 7)
+            }
+        );
+        assert_eq!(expand_snippet("console.log($0);").selection, (12, 12));
+        assert_eq!(expand_snippet("${0:pass}").selection, (0, 4));
+        assert_eq!(expand_snippet("cost: $").text, "cost: $");
+        assert_eq!(expand_snippet("no stops").selection, (8, 8));
+    }
+
+    #[test]
+    fn snippets_source_test() {
+        let request = CompletionRequest {
+            language: Some(String::from("rust")),
+            line_before_cursor: String::from("    te"),
+            ..CompletionRequest::default()
+        };
+
+        let completion = complete(&SnippetsSource, &request).unwrap();
+        assert_eq!(completion.candidates[0].label, "test");
+        assert_eq!(
+            completion.candidates[0].documentation.as_deref(),
+            Some("#[test]\nfn name_test() {\n    \n}")
+        );
+
+        let request = CompletionRequest {
+            language: None,
+            ..request
+        };
+        assert!(complete(&SnippetsSource, &request)
+            .unwrap()
+            .candidates
+            .is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+use super::{is_word_char, Candidate, CandidateKind, CompletionRequest, CompletionSource};
+
+// Shorter words are quicker to type than to pick
+const MIN_WORD_LENGTH: usize = 3;
+
+/// Completes words found in open files.
+pub struct WordsSource;
+
+impl CompletionSource for WordsSource {
+    fn name(&self) -> &str {
+        "Words"
+    }
+
+    fn uses_documents(&self) -> bool {
+        true
+    }
+
+    fn prefix<'a>(&self, request: &'a CompletionRequest) -> Option<&'a str> {
+        let prefix = super::word_prefix(&request.line_before_cursor);
+        (!prefix.is_empty()).then_some(prefix)
+    }
+
+    fn candidates(&self, request: &CompletionRequest, _prefix: &str) -> Vec<Candidate> {
+        let mut words = BTreeSet::new();
+        for document in &request.documents {
+            words.extend(words_in_text(document));
+        }
+
+        words
+            .into_iter()
+            .map(|word| Candidate::new(word, CandidateKind::Word))
+            .collect()
+    }
+}
+
+/**
+ * Returns identifiers of `text`, ex: `open_file` and `path` in `open_file(path)`.
+ *
+ * Numbers and short words are left out.
+ */
+pub fn words_in_text(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_word_char(c)).filter(|word| {
+        word.chars().count() >= MIN_WORD_LENGTH && !word.starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::{complete, CompletionRequest};
+
+    use super::{words_in_text, WordsSource};
+
+    #[test]
+    fn words_in_text_test() {
+        let words: Vec<_> = words_in_text("let übersicht = open_file(path, 1024);").collect();
+
+        assert_eq!(words, ["let", "übersicht", "open_file", "path"]);
+    }
+
+    #[test]
+    fn words_source_test() {
+        let request = CompletionRequest {
+            line_before_cursor: String::from("    op"),
+            documents: vec![
+                String::from("fn open_file() {}\n    op"),
+                String::from("// Opens options\nopen_file();"),
+            ],
+            ..CompletionRequest::default()
+        };
+
+        let completion = complete(&WordsSource, &request).unwrap();
+        let labels: Vec<_> = completion
+            .candidates
+            .iter()
+            .map(|c| c.label.as_str())
+            .collect();
+        assert_eq!(labels, ["Opens", "options", "open_file"]);
+
+        let request = CompletionRequest {
+            line_before_cursor: String::from("self."),
+            ..request
+        };
+        assert_eq!(complete(&WordsSource, &request), None);
+    }
+}
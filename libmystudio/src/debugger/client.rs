@@ -0,0 +1,465 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::BufReader,
+    path::Path,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+use super::{
+    error::DapError,
+    protocol::{read_message, write_message, DapMessage},
+    types::{BreakpointState, DapEvent, Scope, StackFrame, Thread, Variable},
+    DebugAdapterConfig,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+// Time given to an adapter to exit after `disconnect`, it is killed afterwards
+const EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+// Frames asked for at once, deeper ones are rarely useful
+const MAX_STACK_FRAMES: u32 = 200;
+
+type PendingRequests = Mutex<HashMap<u64, mpsc::Sender<Result<Value, DapError>>>>;
+
+/**
+ * Writing half of an adapter connection, shared with the thread reading
+ * adapter messages.
+ */
+struct Connection {
+    stdin: Mutex<ChildStdin>,
+    // `seq` of the next message sent
+    next_seq: AtomicU64,
+    pending: PendingRequests,
+    // Set under `pending` lock once the adapter stopped sending messages
+    closed: AtomicBool,
+    // Told about the `initialized` event, see `DapClient::launch`
+    initialized: Mutex<Option<mpsc::Sender<()>>>,
+}
+
+impl Connection {
+    fn send(&self, seq: u64, message: &DapMessage) -> Result<(), DapError> {
+        let mut stdin = self.stdin.lock().unwrap();
+        write_message(&mut *stdin, seq, message).map_err(|_| DapError::Exited)
+    }
+
+    fn next_seq(&self) -> u64 {
+        self.next_seq.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/**
+A running debug adapter, spoken to over stdio.
+
+Requests block until the adapter answers or the timeout passes. Events (ex:
+the debuggee stopped at a breakpoint) are passed to `on_event` from a
+background thread.
+*/
+pub struct DapClient {
+    process: Mutex<Child>,
+    connection: Arc<Connection>,
+    timeout: Duration,
+    // `Capabilities` from the `initialize` response
+    capabilities: Value,
+    initialized: Mutex<Option<mpsc::Receiver<()>>>,
+}
+
+impl DapClient {
+    /**
+     * Spawns the adapter of `config` in `cwd` and initializes it, `adapter_id`
+     * is the adapter name, ex: `lldb-dap`.
+     */
+    pub fn start<F>(
+        adapter_id: &str,
+        config: &DebugAdapterConfig,
+        cwd: &str,
+        on_event: F,
+    ) -> Result<DapClient, DapError>
+    where
+        F: Fn(DapEvent) + Send + 'static,
+    {
+        let mut command = Command::new(&config.command);
+        if !cwd.is_empty() {
+            command.current_dir(cwd);
+        }
+
+        let mut process = command
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| {
+                DapError::Io(format!("unable to run '{}': {error}", config.command))
+            })?;
+
+        // Some adapters send `initialized` right after `initialize`
+        let (initialized_tx, initialized_rx) = mpsc::channel();
+        let connection = Arc::new(Connection {
+            stdin: Mutex::new(process.stdin.take().unwrap()),
+            next_seq: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+            initialized: Mutex::new(Some(initialized_tx)),
+        });
+
+        let stdout = process.stdout.take().unwrap();
+        let reader_connection = connection.clone();
+        thread::spawn(move || {
+            read_messages(stdout, &reader_connection, &on_event);
+            on_event(DapEvent::AdapterExited);
+        });
+
+        let mut client = DapClient {
+            process: Mutex::new(process),
+            connection,
+            timeout: DEFAULT_TIMEOUT,
+            capabilities: Value::Null,
+            initialized: Mutex::new(Some(initialized_rx)),
+        };
+
+        let result = client.request(
+            "initialize",
+            json!({
+                "clientID": "mystudio",
+                "clientName": "MyStudio IDE",
+                "adapterID": adapter_id,
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+                "pathFormat": "path",
+                "supportsVariableType": true,
+                "supportsRunInTerminalRequest": false,
+            }),
+        );
+        match result {
+            Ok(capabilities) => client.capabilities = capabilities,
+            Err(error) => {
+                client.kill();
+                return Err(error);
+            }
+        }
+
+        Ok(client)
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Checks a `Capabilities` field, ex: `supportsConfigurationDoneRequest`.
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities[capability].as_bool().unwrap_or_default()
+    }
+
+    /**
+     * Sends a request and waits for its body.
+     */
+    pub fn request(&self, command: &str, arguments: Value) -> Result<Value, DapError> {
+        let (seq, rx) = self.send_request(command, arguments)?;
+        self.wait_response(command, seq, rx, self.timeout)
+    }
+
+    fn send_request(
+        &self,
+        command: &str,
+        arguments: Value,
+    ) -> Result<(u64, mpsc::Receiver<Result<Value, DapError>>), DapError> {
+        let seq = self.connection.next_seq();
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let mut pending = self.connection.pending.lock().unwrap();
+            if self.connection.closed.load(Ordering::SeqCst) {
+                return Err(DapError::Exited);
+            }
+            pending.insert(seq, tx);
+        }
+        self.connection
+            .send(seq, &DapMessage::request(command, arguments))?;
+
+        Ok((seq, rx))
+    }
+
+    fn wait_response(
+        &self,
+        command: &str,
+        seq: u64,
+        rx: mpsc::Receiver<Result<Value, DapError>>,
+        timeout: Duration,
+    ) -> Result<Value, DapError> {
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.connection.pending.lock().unwrap().remove(&seq);
+                Err(DapError::Timeout(command.to_string()))
+            }
+            // Reader thread stopped
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(DapError::Exited),
+        }
+    }
+
+    /**
+     * Starts debugging with `launch` arguments of the adapter, see
+     * `launch_arguments`.
+     *
+     * Breakpoints, by file, are set once the adapter is initialized and
+     * before the debuggee runs. Returns their state by file.
+     */
+    pub fn launch(
+        &self,
+        arguments: Value,
+        breakpoints: &BTreeMap<String, Vec<u32>>,
+    ) -> Result<BTreeMap<String, Vec<BreakpointState>>, DapError> {
+        // Adapters may only answer once configuration is done
+        let (launch_seq, launch_rx) = self.send_request("launch", arguments)?;
+        let mut launched = false;
+
+        let initialized = self.initialized.lock().unwrap().take();
+        if let Some(initialized) = initialized {
+            let deadline = Instant::now() + self.timeout;
+            loop {
+                // A failed launch is never followed by `initialized`
+                if let Ok(result) = launch_rx.try_recv() {
+                    result?;
+                    launched = true;
+                }
+
+                match initialized.recv_timeout(Duration::from_millis(20)) {
+                    Ok(()) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return Err(DapError::Exited),
+                    Err(mpsc::RecvTimeoutError::Timeout) if Instant::now() >= deadline => {
+                        return Err(DapError::Timeout(String::from("initialized")));
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                }
+            }
+        }
+
+        let mut states = BTreeMap::new();
+        for (path, lines) in breakpoints {
+            states.insert(path.clone(), self.set_breakpoints(path, lines)?);
+        }
+        if self.supports("supportsConfigurationDoneRequest") {
+            self.request("configurationDone", Value::Null)?;
+        }
+
+        if !launched {
+            self.wait_response("launch", launch_seq, launch_rx, self.timeout)?;
+        }
+        Ok(states)
+    }
+
+    /**
+     * Replaces breakpoints of a file with breakpoints at `lines`.
+     */
+    pub fn set_breakpoints(
+        &self,
+        path: &str,
+        lines: &[u32],
+    ) -> Result<Vec<BreakpointState>, DapError> {
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let breakpoints: Vec<Value> = lines.iter().map(|line| json!({ "line": line })).collect();
+
+        let body = self.request(
+            "setBreakpoints",
+            json!({
+                "source": { "name": name, "path": path },
+                "breakpoints": breakpoints,
+                "lines": lines,
+            }),
+        )?;
+        Ok(serde_json::from_value(body["breakpoints"].clone()).unwrap_or_default())
+    }
+
+    pub fn threads(&self) -> Result<Vec<Thread>, DapError> {
+        let body = self.request("threads", Value::Null)?;
+        Ok(serde_json::from_value(body["threads"].clone()).unwrap_or_default())
+    }
+
+    pub fn stack_trace(&self, thread_id: i64) -> Result<Vec<StackFrame>, DapError> {
+        let body = self.request(
+            "stackTrace",
+            json!({ "threadId": thread_id, "startFrame": 0, "levels": MAX_STACK_FRAMES }),
+        )?;
+        Ok(StackFrame::list_from_value(&body))
+    }
+
+    pub fn scopes(&self, frame_id: i64) -> Result<Vec<Scope>, DapError> {
+        let body = self.request("scopes", json!({ "frameId": frame_id }))?;
+        Ok(serde_json::from_value(body["scopes"].clone()).unwrap_or_default())
+    }
+
+    /// Returns children of a scope or a variable.
+    pub fn variables(&self, variables_reference: i64) -> Result<Vec<Variable>, DapError> {
+        let body = self.request(
+            "variables",
+            json!({ "variablesReference": variables_reference }),
+        )?;
+        Ok(serde_json::from_value(body["variables"].clone()).unwrap_or_default())
+    }
+
+    /**
+     * Evaluates a watch expression in a frame, the result is returned as a
+     * variable named after the expression.
+     */
+    pub fn evaluate(&self, expression: &str, frame_id: Option<i64>) -> Result<Variable, DapError> {
+        let body = self.request(
+            "evaluate",
+            json!({ "expression": expression, "frameId": frame_id, "context": "watch" }),
+        )?;
+
+        Ok(Variable {
+            name: expression.to_string(),
+            value: body["result"].as_str().unwrap_or_default().to_string(),
+            type_name: body["type"].as_str().map(String::from),
+            variables_reference: body["variablesReference"].as_i64().unwrap_or_default(),
+        })
+    }
+
+    pub fn continue_thread(&self, thread_id: i64) -> Result<(), DapError> {
+        self.request("continue", json!({ "threadId": thread_id }))
+            .map(|_| ())
+    }
+
+    /// Steps over the current line.
+    pub fn next(&self, thread_id: i64) -> Result<(), DapError> {
+        self.request("next", json!({ "threadId": thread_id }))
+            .map(|_| ())
+    }
+
+    pub fn step_in(&self, thread_id: i64) -> Result<(), DapError> {
+        self.request("stepIn", json!({ "threadId": thread_id }))
+            .map(|_| ())
+    }
+
+    pub fn step_out(&self, thread_id: i64) -> Result<(), DapError> {
+        self.request("stepOut", json!({ "threadId": thread_id }))
+            .map(|_| ())
+    }
+
+    pub fn pause(&self, thread_id: i64) -> Result<(), DapError> {
+        self.request("pause", json!({ "threadId": thread_id }))
+            .map(|_| ())
+    }
+
+    /**
+     * Ends debugging, the debuggee is terminated. The adapter is killed if it
+     * doesn't exit.
+     */
+    pub fn disconnect(&self) -> Result<(), DapError> {
+        let result = self
+            .request("disconnect", json!({ "terminateDebuggee": true }))
+            .map(|_| ());
+
+        let deadline = Instant::now() + EXIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if self.has_exited() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        self.kill();
+        result
+    }
+
+    pub fn has_exited(&self) -> bool {
+        let mut process = self.process.lock().unwrap();
+        !matches!(process.try_wait(), Ok(None))
+    }
+
+    fn kill(&self) {
+        let mut process = self.process.lock().unwrap();
+        process.kill().ok();
+        process.wait().ok();
+    }
+}
+
+impl Drop for DapClient {
+    fn drop(&mut self) {
+        if !self.has_exited() {
+            self.kill();
+        }
+    }
+}
+
+fn read_messages<F>(stdout: ChildStdout, connection: &Connection, on_event: &F)
+where
+    F: Fn(DapEvent),
+{
+    let mut reader = BufReader::new(stdout);
+
+    loop {
+        let (seq, message) = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(error) => {
+                eprintln!("Unable to read debug adapter message: {error}");
+                break;
+            }
+        };
+
+        match message {
+            DapMessage::Response {
+                request_seq,
+                success,
+                command,
+                message,
+                body,
+            } => {
+                let sender = connection.pending.lock().unwrap().remove(&request_seq);
+                let Some(sender) = sender else {
+                    continue;
+                };
+
+                let result = if success {
+                    Ok(body)
+                } else {
+                    // Details are in `body.error` for some adapters
+                    let message = body["error"]["format"]
+                        .as_str()
+                        .map(String::from)
+                        .or(message)
+                        .unwrap_or_default();
+                    Err(DapError::Adapter { command, message })
+                };
+                sender.send(result).ok();
+            }
+            DapMessage::Event { event, body } => {
+                if event == "initialized" {
+                    if let Some(initialized) = connection.initialized.lock().unwrap().take() {
+                        initialized.send(()).ok();
+                    }
+                } else if let Some(event) = DapEvent::from_event(&event, &body) {
+                    on_event(event);
+                }
+            }
+            // Ex: `runInTerminal`, the debuggee runs with the adapter's stdio instead
+            DapMessage::Request { command, .. } => {
+                let response = DapMessage::Response {
+                    request_seq: seq,
+                    success: false,
+                    message: Some(format!("unsupported request '{command}'")),
+                    command,
+                    body: Value::Null,
+                };
+                connection.send(connection.next_seq(), &response).ok();
+            }
+        }
+    }
+
+    // Fail requests still waiting for an answer
+    let mut pending = connection.pending.lock().unwrap();
+    connection.closed.store(true, Ordering::SeqCst);
+    pending.clear();
+    connection.initialized.lock().unwrap().take();
+}
@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use crate::lsp::error::LspError;
+
+/// A failed exchange with a debug adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DapError {
+    // Adapter could not be spawned, or reading/writing its pipes failed
+    Io(String),
+    // Message is not a valid DAP message
+    Protocol(String),
+    // Adapter answered a request with `success: false`
+    Adapter { command: String, message: String },
+    // No response to a request within the timeout
+    Timeout(String),
+    // Adapter process is gone
+    Exited,
+}
+
+impl Display for DapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DapError::Io(message) => write!(f, "I/O error: {message}"),
+            DapError::Protocol(message) => write!(f, "protocol error: {message}"),
+            DapError::Adapter { command, message } => write!(f, "'{command}' failed: {message}"),
+            DapError::Timeout(command) => write!(f, "request '{command}' timed out"),
+            DapError::Exited => write!(f, "debug adapter exited"),
+        }
+    }
+}
+
+impl std::error::Error for DapError {}
+
+impl From<std::io::Error> for DapError {
+    fn from(error: std::io::Error) -> Self {
+        DapError::Io(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for DapError {
+    fn from(error: serde_json::Error) -> Self {
+        DapError::Protocol(error.to_string())
+    }
+}
+
+// Framing errors of the shared reader, see `read_frame`
+impl From<LspError> for DapError {
+    fn from(error: LspError) -> Self {
+        match error {
+            LspError::Io(message) => DapError::Io(message),
+            LspError::Exited => DapError::Exited,
+            error => DapError::Protocol(error.to_string()),
+        }
+    }
+}
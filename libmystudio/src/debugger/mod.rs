@@ -0,0 +1,343 @@
+// Debugging through the Debug Adapter Protocol, adapters are configured in
+// `[DebugAdapters]` and what to debug in `[Launch]` of config.toml
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    tasks::{cargo_packages, Task},
+    test_explorer::TestCase,
+};
+
+pub mod client;
+pub mod error;
+pub mod protocol;
+pub mod types;
+pub mod worker;
+
+// Adapter of launch configurations which don't name one
+pub const DEFAULT_ADAPTER: &str = "lldb-dap";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DebugAdapterConfig {
+    // Program to run, looked up in `PATH`
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl DebugAdapterConfig {
+    fn new(command: &str, args: &[&str]) -> Self {
+        Self {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+/// Adapters known out of the box, keyed by name.
+pub fn default_debug_adapters() -> BTreeMap<String, DebugAdapterConfig> {
+    BTreeMap::from([
+        (
+            String::from("lldb-dap"),
+            DebugAdapterConfig::new("lldb-dap", &[]),
+        ),
+        (
+            String::from("codelldb"),
+            DebugAdapterConfig::new("codelldb", &[]),
+        ),
+        (
+            String::from("gdb"),
+            DebugAdapterConfig::new("gdb", &["--interpreter=dap"]),
+        ),
+    ])
+}
+
+/// What to debug, ex: `[Launch.server]`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct LaunchConfig {
+    // Name of a `[DebugAdapters]` entry
+    pub adapter: String,
+    // Executable relative to the workspace, the one built by `cargo` when empty
+    pub program: String,
+    pub args: Vec<String>,
+    // Directory relative to the workspace, the workspace itself when empty
+    pub cwd: String,
+    pub env: BTreeMap<String, String>,
+    pub stop_on_entry: bool,
+    // Arguments of the cargo command building the program, ex: `["build", "--bin", "app"]`
+    pub cargo: Vec<String>,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            adapter: String::from(DEFAULT_ADAPTER),
+            program: String::new(),
+            args: vec![],
+            cwd: String::new(),
+            env: BTreeMap::new(),
+            stop_on_entry: false,
+            cargo: vec![],
+        }
+    }
+}
+
+impl LaunchConfig {
+    fn cargo(args: &[&str]) -> Self {
+        Self {
+            cargo: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /**
+     * Returns the task building the program before it is debugged, its
+     * executable is reported with `TaskEvent::Artifact`.
+     */
+    pub fn build_task(&self, name: &str, root: &str) -> Option<Task> {
+        if self.cargo.is_empty() {
+            return None;
+        }
+
+        let args: Vec<&str> = self.cargo.iter().map(String::as_str).collect();
+        Some(Task {
+            label: format!("debug: build {name}"),
+            ..Task::cargo(Path::new(root), &args)
+        })
+    }
+
+    /**
+     * Returns arguments of the `launch` request, `program` is the executable
+     * built by `build_task` if any.
+     *
+     * Adapters name some arguments differently, ex: gdb stops at `main` with
+     * `stopAtBeginningOfMainSubprogram` and lldb-dap wants `env` as a list.
+     */
+    pub fn launch_arguments(&self, name: &str, root: &str, program: Option<&str>) -> Value {
+        let root = Path::new(root);
+        let program = program
+            .map(PathBuf::from)
+            .unwrap_or_else(|| root.join(&self.program));
+        let cwd = match self.cwd.as_str() {
+            "" => root.to_path_buf(),
+            cwd => root.join(cwd),
+        };
+
+        let mut arguments = json!({
+            "name": name,
+            "type": self.adapter,
+            "request": "launch",
+            "program": program,
+            "args": self.args,
+            "cwd": cwd,
+        });
+
+        match self.adapter.as_str() {
+            "gdb" => {
+                arguments["stopAtBeginningOfMainSubprogram"] = json!(self.stop_on_entry);
+                arguments["env"] = json!(self.env);
+            }
+            "lldb-dap" => {
+                arguments["stopOnEntry"] = json!(self.stop_on_entry);
+                let env: Vec<String> = self
+                    .env
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect();
+                arguments["env"] = json!(env);
+            }
+            _ => {
+                arguments["stopOnEntry"] = json!(self.stop_on_entry);
+                arguments["env"] = json!(self.env);
+            }
+        }
+
+        arguments
+    }
+}
+
+/**
+ * Returns launch configurations of the workspace at `root`: one per binary
+ * of its cargo packages followed by `user_configs`, by name.
+ */
+pub fn discover_launch_configs(
+    root: &str,
+    user_configs: &BTreeMap<String, LaunchConfig>,
+) -> Vec<(String, LaunchConfig)> {
+    let (packages, _) = cargo_packages(Path::new(root));
+    let mut configs = vec![];
+
+    for package in packages {
+        let mut binaries = vec![];
+        if package.dir.join("src/main.rs").is_file() {
+            binaries.push(package.name.clone());
+        }
+
+        // `src/bin/tool.rs` and `src/bin/tool/main.rs`
+        let mut bin_names: Vec<String> = fs::read_dir(package.dir.join("src/bin"))
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter_map(|path| match path.extension() {
+                Some(extension) if extension == "rs" => path.file_stem().map(PathBuf::from),
+                None if path.join("main.rs").is_file() => path.file_name().map(PathBuf::from),
+                _ => None,
+            })
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+        bin_names.sort();
+        binaries.extend(bin_names);
+
+        for binary in binaries {
+            configs.push((
+                format!("cargo: {binary}"),
+                LaunchConfig::cargo(&["build", "-p", &package.name, "--bin", &binary]),
+            ));
+        }
+    }
+
+    configs.extend(
+        user_configs
+            .iter()
+            .map(|(name, config)| (name.clone(), config.clone())),
+    );
+    configs
+}
+
+/**
+ * Returns the launch configuration debugging a single test, its test binary
+ * is built without running it.
+ */
+pub fn test_launch_config(test: &TestCase) -> LaunchConfig {
+    let mut cargo = vec![
+        String::from("test"),
+        String::from("--no-run"),
+        String::from("-p"),
+        test.crate_name.clone(),
+    ];
+    cargo.extend(test.target.cargo_args());
+
+    LaunchConfig {
+        args: vec![
+            test.path.clone(),
+            String::from("--exact"),
+            String::from("--nocapture"),
+        ],
+        cargo,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs};
+
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    use crate::test_explorer::{TestCase, TestTarget};
+
+    use super::{discover_launch_configs, test_launch_config, LaunchConfig};
+
+    #[test]
+    fn discover_launch_configs_test() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"app\"\n\n[workspace]\nmembers = [\"lib\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("src/bin/migrate")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/bin/seed.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/bin/migrate/main.rs"), "fn main() {}\n").unwrap();
+        fs::create_dir_all(root.join("lib/src")).unwrap();
+        fs::write(root.join("lib/Cargo.toml"), "[package]\nname = \"lib\"\n").unwrap();
+        fs::write(root.join("lib/src/lib.rs"), "").unwrap();
+
+        let user_configs = BTreeMap::from([(
+            String::from("server"),
+            LaunchConfig {
+                program: String::from("target/debug/server"),
+                ..Default::default()
+            },
+        )]);
+        let root_path = root.to_string_lossy().to_string();
+        let configs = discover_launch_configs(&root_path, &user_configs);
+        let names: Vec<&str> = configs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["cargo: app", "cargo: migrate", "cargo: seed", "server"]
+        );
+
+        let build_task = configs[2].1.build_task(&configs[2].0, &root_path).unwrap();
+        assert_eq!(build_task.label, "debug: build cargo: seed");
+        assert_eq!(
+            build_task.command_line(),
+            "cargo build --message-format=json -p app --bin seed"
+        );
+        assert!(configs[3].1.build_task("server", &root_path).is_none());
+    }
+
+    #[test]
+    fn launch_arguments_test() {
+        let config = LaunchConfig {
+            program: String::from("target/debug/app"),
+            args: vec![String::from("--verbose")],
+            cwd: String::from("data"),
+            env: BTreeMap::from([(String::from("RUST_LOG"), String::from("debug"))]),
+            stop_on_entry: true,
+            ..Default::default()
+        };
+
+        let arguments = config.launch_arguments("app", "/ws", None);
+        assert_eq!(arguments["request"], "launch");
+        assert_eq!(arguments["program"], "/ws/target/debug/app");
+        assert_eq!(arguments["args"], json!(["--verbose"]));
+        assert_eq!(arguments["cwd"], "/ws/data");
+        assert_eq!(arguments["stopOnEntry"], true);
+        assert_eq!(arguments["env"], json!(["RUST_LOG=debug"]));
+
+        let gdb = LaunchConfig {
+            adapter: String::from("gdb"),
+            ..config
+        };
+        let arguments = gdb.launch_arguments("app", "/ws", Some("/ws/target/debug/deps/app-1f2e"));
+        assert_eq!(arguments["program"], "/ws/target/debug/deps/app-1f2e");
+        assert_eq!(arguments["stopAtBeginningOfMainSubprogram"], true);
+        assert_eq!(arguments["env"], json!({ "RUST_LOG": "debug" }));
+    }
+
+    #[test]
+    fn test_launch_config_test() {
+        let test = TestCase {
+            crate_name: String::from("app"),
+            target: TestTarget::Test(String::from("api")),
+            path: String::from("routes::lists_users"),
+            file: String::from("/ws/tests/api.rs"),
+            line: 10,
+            ignored: false,
+        };
+
+        let config = test_launch_config(&test);
+        assert_eq!(
+            config.cargo,
+            ["test", "--no-run", "-p", "app", "--test", "api"]
+        );
+        assert_eq!(
+            config.args,
+            ["routes::lists_users", "--exact", "--nocapture"]
+        );
+        assert!(config.program.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+// Debug Adapter Protocol messages, framed by `Content-Length` headers like
+// LSP but with their own envelope: `seq`, `type` and per-type fields
+
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::lsp::jsonrpc::{read_frame, write_frame};
+
+use super::error::DapError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DapMessage {
+    Request {
+        command: String,
+        arguments: Value,
+    },
+    Response {
+        // `seq` of the request
+        request_seq: u64,
+        success: bool,
+        command: String,
+        // Error text when `success` is false
+        message: Option<String>,
+        body: Value,
+    },
+    Event {
+        event: String,
+        body: Value,
+    },
+}
+
+impl DapMessage {
+    pub fn request(command: &str, arguments: Value) -> Self {
+        DapMessage::Request {
+            command: command.to_string(),
+            arguments,
+        }
+    }
+
+    pub fn event(event: &str, body: Value) -> Self {
+        DapMessage::Event {
+            event: event.to_string(),
+            body,
+        }
+    }
+
+    /// Returns the message as sent, `seq` numbers messages of each side.
+    pub fn to_value(&self, seq: u64) -> Value {
+        match self {
+            DapMessage::Request { command, arguments } => json!({
+                "seq": seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            }),
+            DapMessage::Response {
+                request_seq,
+                success,
+                command,
+                message,
+                body,
+            } => json!({
+                "seq": seq,
+                "type": "response",
+                "request_seq": request_seq,
+                "success": success,
+                "command": command,
+                "message": message,
+                "body": body,
+            }),
+            DapMessage::Event { event, body } => json!({
+                "seq": seq,
+                "type": "event",
+                "event": event,
+                "body": body,
+            }),
+        }
+    }
+
+    /// Parses a message, returns it with its `seq`.
+    pub fn from_value(mut value: Value) -> Result<(u64, Self), DapError> {
+        let seq = value["seq"].as_u64().unwrap_or_default();
+        let text = |value: &Value, field: &str| -> Result<String, DapError> {
+            value[field]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| DapError::Protocol(format!("message without '{field}'")))
+        };
+
+        let message = match value["type"].as_str() {
+            Some("request") => DapMessage::Request {
+                command: text(&value, "command")?,
+                arguments: value["arguments"].take(),
+            },
+            Some("response") => DapMessage::Response {
+                request_seq: value["request_seq"].as_u64().unwrap_or_default(),
+                success: value["success"].as_bool().unwrap_or_default(),
+                command: text(&value, "command")?,
+                message: value["message"].as_str().map(String::from),
+                body: value["body"].take(),
+            },
+            Some("event") => DapMessage::Event {
+                event: text(&value, "event")?,
+                body: value["body"].take(),
+            },
+            _ => return Err(DapError::Protocol(String::from("unknown message type"))),
+        };
+
+        Ok((seq, message))
+    }
+}
+
+/**
+ * Reads the next message with its `seq`, returns `None` once the stream is
+ * closed.
+ */
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<(u64, DapMessage)>, DapError> {
+    let Some(content) = read_frame(reader)? else {
+        return Ok(None);
+    };
+
+    let value = serde_json::from_slice::<Value>(&content)?;
+    DapMessage::from_value(value).map(Some)
+}
+
+pub fn write_message(
+    writer: &mut impl Write,
+    seq: u64,
+    message: &DapMessage,
+) -> Result<(), DapError> {
+    write_frame(writer, &message.to_value(seq).to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use serde_json::json;
+
+    use super::{read_message, write_message, DapMessage};
+
+    #[test]
+    fn message_round_trip_test() {
+        let messages = [
+            DapMessage::request("setBreakpoints", json!({ "lines": [3] })),
+            DapMessage::Response {
+                request_seq: 1,
+                success: false,
+                command: String::from("launch"),
+                message: Some(String::from("no such file")),
+                body: json!(null),
+            },
+            DapMessage::event("stopped", json!({ "reason": "breakpoint", "threadId": 1 })),
+        ];
+
+        let mut stream = vec![];
+        for (seq, message) in messages.iter().enumerate() {
+            write_message(&mut stream, seq as u64 + 1, message).unwrap();
+        }
+
+        let mut reader = BufReader::new(stream.as_slice());
+        for (seq, message) in messages.into_iter().enumerate() {
+            assert_eq!(
+                read_message(&mut reader).unwrap(),
+                Some((seq as u64 + 1, message))
+            );
+        }
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+
+        let jsonrpc = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let stream = format!("Content-Length: {}\r\n\r\n{jsonrpc}", jsonrpc.len());
+        assert!(read_message(&mut BufReader::new(stream.as_bytes())).is_err());
+    }
+}
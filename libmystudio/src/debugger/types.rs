@@ -0,0 +1,197 @@
+// Subset of DAP structures used by the IDE, lines and columns are one-based
+// as asked in `initialize`
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{error::DapError, worker::DapResponse};
+
+/// Sent by the adapter while debugging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DapEvent {
+    Stopped {
+        // Ex: `breakpoint`, `step` or `exception`
+        reason: String,
+        thread_id: Option<i64>,
+        description: Option<String>,
+        all_threads_stopped: bool,
+    },
+    Continued {
+        thread_id: Option<i64>,
+    },
+    // Text printed by the debuggee or the adapter, with its line breaks
+    Output {
+        // Ex: `stdout`, `stderr` or `console`
+        category: String,
+        output: String,
+    },
+    // Exit code of the debuggee
+    Exited(i64),
+    // Debugging ended, the adapter can be disconnected
+    Terminated,
+    // Adapter process is gone
+    AdapterExited,
+    // Adapter could not be started by `DapWorker::start`
+    StartFailed(DapError),
+    // Answer to a request sent with `DapWorker::request`, by request id
+    Response {
+        id: u64,
+        result: Result<DapResponse, DapError>,
+    },
+}
+
+impl DapEvent {
+    pub(crate) fn from_event(event: &str, body: &Value) -> Option<Self> {
+        let thread_id = body["threadId"].as_i64();
+
+        match event {
+            "stopped" => Some(DapEvent::Stopped {
+                reason: body["reason"].as_str().unwrap_or_default().to_string(),
+                thread_id,
+                description: body["description"]
+                    .as_str()
+                    .or_else(|| body["text"].as_str())
+                    .map(String::from),
+                all_threads_stopped: body["allThreadsStopped"].as_bool().unwrap_or_default(),
+            }),
+            "continued" => Some(DapEvent::Continued { thread_id }),
+            "output" => Some(DapEvent::Output {
+                category: body["category"].as_str().unwrap_or("console").to_string(),
+                output: body["output"].as_str()?.to_string(),
+            }),
+            "exited" => Some(DapEvent::Exited(
+                body["exitCode"].as_i64().unwrap_or_default(),
+            )),
+            "terminated" => Some(DapEvent::Terminated),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Thread {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Source {
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    pub id: i64,
+    // Function of the frame, ex: `app::main`
+    pub name: String,
+    // Missing for frames without sources, ex: in libc
+    pub path: Option<String>,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl StackFrame {
+    pub(crate) fn list_from_value(body: &Value) -> Vec<Self> {
+        #[derive(Deserialize)]
+        struct Frame {
+            id: i64,
+            name: String,
+            source: Option<Source>,
+            #[serde(default)]
+            line: u32,
+            #[serde(default)]
+            column: u32,
+        }
+
+        serde_json::from_value::<Vec<Frame>>(body["stackFrames"].clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|frame| StackFrame {
+                id: frame.id,
+                name: frame.name,
+                path: frame.source.and_then(|source| source.path),
+                line: frame.line,
+                column: frame.column,
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Scope {
+    // Ex: `Locals`
+    pub name: String,
+    pub variables_reference: i64,
+    // Fetching its variables is slow, ex: globals
+    #[serde(default)]
+    pub expensive: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    pub type_name: Option<String>,
+    // Non-zero when the variable has children, ex: fields of a struct
+    #[serde(default)]
+    pub variables_reference: i64,
+}
+
+/// Breakpoint of a `setBreakpoints` response.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BreakpointState {
+    // Adapters can't always set breakpoints, ex: on a line without code
+    pub verified: bool,
+    // Line the breakpoint was moved to
+    pub line: Option<u32>,
+    pub message: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{DapEvent, StackFrame};
+
+    #[test]
+    fn from_value_test() {
+        let body = json!({
+            "stackFrames": [
+                {
+                    "id": 1000,
+                    "name": "app::main",
+                    "source": { "name": "main.rs", "path": "/ws/src/main.rs" },
+                    "line": 4,
+                    "column": 9,
+                },
+                { "id": 1001, "name": "__libc_start_main", "line": 0, "column": 0 },
+            ]
+        });
+        let frames = StackFrame::list_from_value(&body);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].path.as_deref(), Some("/ws/src/main.rs"));
+        assert_eq!((frames[0].line, frames[0].column), (4, 9));
+        assert_eq!(frames[1].path, None);
+
+        assert_eq!(
+            DapEvent::from_event(
+                "stopped",
+                &json!({ "reason": "breakpoint", "threadId": 7, "allThreadsStopped": true })
+            ),
+            Some(DapEvent::Stopped {
+                reason: String::from("breakpoint"),
+                thread_id: Some(7),
+                description: None,
+                all_threads_stopped: true,
+            })
+        );
+        assert_eq!(
+            DapEvent::from_event("exited", &json!({ "exitCode": 3 })),
+            Some(DapEvent::Exited(3))
+        );
+        assert_eq!(DapEvent::from_event("module", &json!({})), None);
+    }
+}
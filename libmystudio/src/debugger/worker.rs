@@ -0,0 +1,198 @@
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use serde_json::Value;
+
+use super::{
+    client::DapClient,
+    error::DapError,
+    types::{BreakpointState, DapEvent, Scope, StackFrame, Variable},
+    DebugAdapterConfig,
+};
+
+type Job = Box<dyn FnOnce(&DapClient) + Send>;
+// Called from the worker and the adapter reader threads
+type EventCallback = Arc<Mutex<Box<dyn Fn(DapEvent) + Send>>>;
+
+/// A request to a debug adapter, see `DapWorker::request`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DapRequest {
+    // `launch` arguments and breakpoint lines by file, see `DapClient::launch`
+    Launch {
+        arguments: Value,
+        breakpoints: BTreeMap<String, Vec<u32>>,
+    },
+    SetBreakpoints {
+        path: String,
+        lines: Vec<u32>,
+    },
+    // Call stack of a thread, of the first one when `None`
+    StackTrace(Option<i64>),
+    // Scopes of a frame
+    Scopes(i64),
+    // Children of a scope or a variable
+    Variables(i64),
+    Evaluate {
+        expression: String,
+        frame_id: i64,
+    },
+    Continue(i64),
+    Next(i64),
+    StepIn(i64),
+    StepOut(i64),
+    // Adapters pause every thread, the first one is asked
+    Pause,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DapResponse {
+    // Breakpoint states by file
+    Launched(BTreeMap<String, Vec<BreakpointState>>),
+    Breakpoints(Vec<BreakpointState>),
+    StackTrace {
+        thread_id: i64,
+        frames: Vec<StackFrame>,
+    },
+    Scopes(Vec<Scope>),
+    Variables(Vec<Variable>),
+    Evaluated(Variable),
+    // Stepping and pausing have no answer
+    Done,
+}
+
+/**
+Runs a `DapClient` on a background thread, so that starting the adapter and
+waiting for its answers never blocks the caller.
+
+Requests are sent in order, each once the previous one was answered. Answers
+are passed to `on_event` as `DapEvent::Response`, along with events of the
+adapter. The adapter is disconnected once the worker is dropped.
+*/
+pub struct DapWorker {
+    jobs: mpsc::Sender<Job>,
+    on_event: EventCallback,
+}
+
+impl DapWorker {
+    /**
+     * Starts the adapter of `config` in `cwd`, see `DapClient::start`. A
+     * failure is sent as `DapEvent::StartFailed`, requests are then dropped.
+     */
+    pub fn start<F>(adapter_id: &str, config: &DebugAdapterConfig, cwd: &str, on_event: F) -> Self
+    where
+        F: Fn(DapEvent) + Send + 'static,
+    {
+        let on_event: EventCallback = Arc::new(Mutex::new(Box::new(on_event)));
+        let (jobs, jobs_rx) = mpsc::channel::<Job>();
+
+        let adapter_id = adapter_id.to_string();
+        let config = config.clone();
+        let cwd = cwd.to_string();
+        let thread_on_event = on_event.clone();
+        thread::spawn(move || {
+            let client_on_event = thread_on_event.clone();
+            let client = DapClient::start(&adapter_id, &config, &cwd, move |event| {
+                send_event(&client_on_event, event)
+            });
+            let client = match client {
+                Ok(client) => client,
+                Err(error) => {
+                    send_event(&thread_on_event, DapEvent::StartFailed(error));
+                    return;
+                }
+            };
+
+            for job in jobs_rx {
+                job(&client);
+            }
+
+            if !client.has_exited() {
+                client.disconnect().ok();
+            }
+        });
+
+        Self { jobs, on_event }
+    }
+
+    /**
+     * Sends a request once the previous ones were answered, its answer is
+     * sent as `DapEvent::Response` with `id`.
+     */
+    pub fn request(&self, id: u64, request: DapRequest) {
+        let on_event = self.on_event.clone();
+
+        let job: Job = Box::new(move |client| {
+            let result = send_request(client, request);
+            send_event(&on_event, DapEvent::Response { id, result });
+        });
+        self.jobs.send(job).ok();
+    }
+}
+
+fn send_event(on_event: &EventCallback, event: DapEvent) {
+    if let Ok(on_event) = on_event.lock() {
+        on_event(event);
+    }
+}
+
+fn send_request(client: &DapClient, request: DapRequest) -> Result<DapResponse, DapError> {
+    let response = match request {
+        DapRequest::Launch {
+            arguments,
+            breakpoints,
+        } => DapResponse::Launched(client.launch(arguments, &breakpoints)?),
+        DapRequest::SetBreakpoints { path, lines } => {
+            DapResponse::Breakpoints(client.set_breakpoints(&path, &lines)?)
+        }
+        DapRequest::StackTrace(thread_id) => {
+            // Some adapters don't tell which thread stopped
+            let thread_id = match thread_id {
+                Some(thread_id) => thread_id,
+                None => first_thread(client)?,
+            };
+            DapResponse::StackTrace {
+                thread_id,
+                frames: client.stack_trace(thread_id)?,
+            }
+        }
+        DapRequest::Scopes(frame_id) => DapResponse::Scopes(client.scopes(frame_id)?),
+        DapRequest::Variables(reference) => DapResponse::Variables(client.variables(reference)?),
+        DapRequest::Evaluate {
+            expression,
+            frame_id,
+        } => DapResponse::Evaluated(client.evaluate(&expression, Some(frame_id))?),
+        DapRequest::Continue(thread_id) => {
+            client.continue_thread(thread_id)?;
+            DapResponse::Done
+        }
+        DapRequest::Next(thread_id) => {
+            client.next(thread_id)?;
+            DapResponse::Done
+        }
+        DapRequest::StepIn(thread_id) => {
+            client.step_in(thread_id)?;
+            DapResponse::Done
+        }
+        DapRequest::StepOut(thread_id) => {
+            client.step_out(thread_id)?;
+            DapResponse::Done
+        }
+        DapRequest::Pause => {
+            client.pause(first_thread(client)?)?;
+            DapResponse::Done
+        }
+    };
+
+    Ok(response)
+}
+
+fn first_thread(client: &DapClient) -> Result<i64, DapError> {
+    client
+        .threads()?
+        .first()
+        .map(|thread| thread.id)
+        .ok_or_else(|| DapError::Protocol(String::from("no threads")))
+}
@@ -0,0 +1,349 @@
+// Problems reported for workspace files, ex: by a language server
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::lsp::types::{self, DiagnosticSeverity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+impl From<DiagnosticSeverity> for Severity {
+    fn from(severity: DiagnosticSeverity) -> Self {
+        match severity {
+            DiagnosticSeverity::Error => Severity::Error,
+            DiagnosticSeverity::Warning => Severity::Warning,
+            DiagnosticSeverity::Information => Severity::Info,
+            DiagnosticSeverity::Hint => Severity::Hint,
+        }
+    }
+}
+
+/// Zero-based position, `column` counts chars like GTK does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextRange {
+    pub start: TextPosition,
+    pub end: TextPosition,
+}
+
+impl TextRange {
+    /// Tells whether `position` is inside, an empty range contains its start.
+    pub fn contains(&self, position: TextPosition) -> bool {
+        (self.start..self.end).contains(&position) || position == self.start
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: String,
+    pub range: TextRange,
+    pub severity: Severity,
+    pub message: String,
+    // Tool reporting it, ex: `rustc`
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    /**
+     * Converts a diagnostic published by a language server.
+     *
+     * `text` is the content of the file, used to turn UTF-16 columns into
+     * char columns. Columns are kept as is without it.
+     */
+    pub fn from_lsp(path: &str, diagnostic: &types::Diagnostic, text: Option<&str>) -> Self {
+        let lines: Vec<&str> = text.map(|text| text.lines().collect()).unwrap_or_default();
+
+        let position = |position: &types::Position| {
+            let column = match lines.get(position.line as usize) {
+                Some(line) => position.char_column(line) as u32,
+                None => position.character,
+            };
+
+            TextPosition {
+                line: position.line,
+                column,
+            }
+        };
+
+        Self {
+            path: path.to_string(),
+            range: TextRange {
+                start: position(&diagnostic.range.start),
+                end: position(&diagnostic.range.end),
+            },
+            severity: diagnostic
+                .severity
+                .map(Severity::from)
+                .unwrap_or(Severity::Error),
+            message: diagnostic.message.clone(),
+            source: diagnostic.source.clone(),
+        }
+    }
+
+    /// Message shown in tooltips, ex: `rustc: unused variable`.
+    pub fn display_message(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{source}: {}", self.message),
+            None => self.message.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiagnosticCounts {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl Display for DiagnosticCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+
+        match (self.errors, self.warnings) {
+            (0, 0) => write!(f, "No problems"),
+            (errors, 0) => write!(f, "{errors} error{}", plural(errors)),
+            (0, warnings) => write!(f, "{warnings} warning{}", plural(warnings)),
+            (errors, warnings) => write!(
+                f,
+                "{errors} error{}, {warnings} warning{}",
+                plural(errors),
+                plural(warnings)
+            ),
+        }
+    }
+}
+
+/**
+Diagnostics of all files, kept per owner so that each tool (ex: a language
+server or a build task) replaces only its own.
+*/
+#[derive(Debug, Default)]
+pub struct DiagnosticCollection {
+    // Keyed by owner, then by file path
+    entries: BTreeMap<String, BTreeMap<String, Vec<Diagnostic>>>,
+}
+
+impl DiagnosticCollection {
+    /// Replaces diagnostics of a file reported by `owner`.
+    pub fn set(&mut self, owner: &str, path: &str, diagnostics: Vec<Diagnostic>) {
+        let files = self.entries.entry(owner.to_string()).or_default();
+
+        if diagnostics.is_empty() {
+            files.remove(path);
+        } else {
+            files.insert(path.to_string(), diagnostics);
+        }
+    }
+
+    pub fn clear_owner(&mut self, owner: &str) {
+        self.entries.remove(owner);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns diagnostics of a file from all owners, in file order.
+    pub fn for_path(&self, path: &str) -> Vec<&Diagnostic> {
+        let mut diagnostics: Vec<&Diagnostic> = self
+            .entries
+            .values()
+            .filter_map(|files| files.get(path))
+            .flatten()
+            .collect();
+
+        diagnostics.sort_by_key(|d| (d.range.start, d.severity));
+        diagnostics
+    }
+
+    /// Returns diagnostics grouped by file path, each group in file order.
+    pub fn by_file(&self) -> BTreeMap<&str, Vec<&Diagnostic>> {
+        let mut grouped: BTreeMap<&str, Vec<&Diagnostic>> = BTreeMap::new();
+
+        for (path, diagnostics) in self.entries.values().flatten() {
+            grouped.entry(path).or_default().extend(diagnostics);
+        }
+        for diagnostics in grouped.values_mut() {
+            diagnostics.sort_by_key(|d| (d.range.start, d.severity));
+        }
+
+        grouped
+    }
+
+    pub fn counts(&self) -> DiagnosticCounts {
+        let mut counts = DiagnosticCounts::default();
+
+        for diagnostic in self
+            .entries
+            .values()
+            .flat_map(|files| files.values().flatten())
+        {
+            match diagnostic.severity {
+                Severity::Error => counts.errors += 1,
+                Severity::Warning => counts.warnings += 1,
+                _ => {}
+            }
+        }
+
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lsp::types::{self, DiagnosticSeverity, Position, Range};
+
+    use super::{
+        Diagnostic, DiagnosticCollection, DiagnosticCounts, Severity, TextPosition, TextRange,
+    };
+
+    fn diagnostic(path: &str, line: u32, severity: Severity) -> Diagnostic {
+        Diagnostic {
+            path: path.to_string(),
+            range: TextRange {
+                start: TextPosition { line, column: 0 },
+                end: TextPosition { line, column: 4 },
+            },
+            severity,
+            message: format!("{} on line {line}", severity.name()),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn from_lsp_test() {
+        let lsp_diagnostic = types::Diagnostic {
+            range: Range {
+                start: Position {
+                    line: 1,
+                    character: 10,
+                },
+                end: Position {
+                    line: 1,
+                    character: 12,
+                },
+            },
+            severity: None,
+            message: String::from("unused variable"),
+            source: Some(String::from("rustc")),
+            code: None,
+        };
+        let text = "fn main() {\n    let 😀 = x;\n}";
+
+        let converted = Diagnostic::from_lsp("/src/main.rs", &lsp_diagnostic, Some(text));
+        assert_eq!(converted.severity, Severity::Error);
+        assert_eq!(converted.range.start, TextPosition { line: 1, column: 9 });
+        assert_eq!(
+            converted.range.end,
+            TextPosition {
+                line: 1,
+                column: 11
+            }
+        );
+        assert_eq!(converted.display_message(), "rustc: unused variable");
+        assert!(converted.range.contains(TextPosition {
+            line: 1,
+            column: 10
+        }));
+        assert!(!converted.range.contains(TextPosition {
+            line: 1,
+            column: 11
+        }));
+
+        let converted = Diagnostic::from_lsp("/src/main.rs", &lsp_diagnostic, None);
+        assert_eq!(converted.range.start.column, 10);
+
+        assert_eq!(
+            Severity::from(DiagnosticSeverity::Information),
+            Severity::Info
+        );
+    }
+
+    #[test]
+    fn collection_test() {
+        let mut collection = DiagnosticCollection::default();
+        collection.set(
+            "lsp",
+            "/src/main.rs",
+            vec![
+                diagnostic("/src/main.rs", 7, Severity::Warning),
+                diagnostic("/src/main.rs", 2, Severity::Error),
+            ],
+        );
+        collection.set(
+            "cargo",
+            "/src/main.rs",
+            vec![diagnostic("/src/main.rs", 2, Severity::Hint)],
+        );
+        collection.set(
+            "lsp",
+            "/src/lib.rs",
+            vec![diagnostic("/src/lib.rs", 0, Severity::Warning)],
+        );
+
+        let lines: Vec<_> = collection
+            .for_path("/src/main.rs")
+            .iter()
+            .map(|d| (d.range.start.line, d.severity))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (2, Severity::Error),
+                (2, Severity::Hint),
+                (7, Severity::Warning)
+            ]
+        );
+
+        let by_file = collection.by_file();
+        assert_eq!(
+            by_file.keys().collect::<Vec<_>>(),
+            [&"/src/lib.rs", &"/src/main.rs"]
+        );
+        assert_eq!(
+            collection.counts(),
+            DiagnosticCounts {
+                errors: 1,
+                warnings: 2
+            }
+        );
+
+        // Each owner replaces only its own diagnostics
+        collection.set("lsp", "/src/main.rs", vec![]);
+        assert_eq!(collection.for_path("/src/main.rs").len(), 1);
+        collection.clear_owner("cargo");
+        assert!(collection.for_path("/src/main.rs").is_empty());
+        assert_eq!(collection.by_file().len(), 1);
+    }
+
+    #[test]
+    fn counts_text_test() {
+        let counts = |errors, warnings| DiagnosticCounts { errors, warnings }.to_string();
+
+        assert_eq!(counts(0, 0), "No problems");
+        assert_eq!(counts(1, 0), "1 error");
+        assert_eq!(counts(0, 3), "3 warnings");
+        assert_eq!(counts(2, 1), "2 errors, 1 warning");
+    }
+}
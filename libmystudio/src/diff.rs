@@ -0,0 +1,220 @@
+// Differences between two sequences, ex: lines of a file before and after formatting
+
+/**
+A changed region, `old_len` items at `old_start` were replaced by `new_len`
+items at `new_start`. Indices are zero-based.
+
+Pure insertions have `old_len == 0` and pure deletions have `new_len == 0`.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+}
+
+impl Hunk {
+    pub fn old_end(&self) -> usize {
+        self.old_start + self.old_len
+    }
+
+    pub fn new_end(&self) -> usize {
+        self.new_start + self.new_len
+    }
+}
+
+/**
+ * Returns the changed regions turning `old` into `new`, in order.
+ *
+ * Uses the Myers algorithm, so the changes are minimal: items are only
+ * reported as changed if no longer common subsequence keeps them.
+ */
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    // Common ends are usual (ex: a small edit in a big file) and cheap to skip
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let (deleted, inserted) = myers(old_middle, new_middle);
+
+    let mut hunks = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        let is_deleted = |i: usize| i < old_middle.len() && deleted[i];
+        let is_inserted = |j: usize| j < new_middle.len() && inserted[j];
+
+        if !is_deleted(i) && !is_inserted(j) {
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let (old_start, new_start) = (i, j);
+        while is_deleted(i) || is_inserted(j) {
+            if is_deleted(i) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        hunks.push(Hunk {
+            old_start: prefix + old_start,
+            old_len: i - old_start,
+            new_start: prefix + new_start,
+            new_len: j - new_start,
+        });
+    }
+
+    hunks
+}
+
+/**
+ * Splits `text` into lines which keep their line terminator, so that joining
+ * them gives back `text`.
+ */
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+// Marks items of `old` deleted and items of `new` inserted by a shortest edit script
+fn myers<T: PartialEq>(old: &[T], new: &[T]) -> (Vec<bool>, Vec<bool>) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let mut deleted = vec![false; old.len()];
+    let mut inserted = vec![false; new.len()];
+
+    if n == 0 || m == 0 {
+        deleted.fill(true);
+        inserted.fill(true);
+        return (deleted, inserted);
+    }
+
+    let max = n + m;
+    let offset = max as usize;
+    // v[k]: furthest x reached on diagonal k = x - y, one copy per edit count
+    let mut v = vec![0isize; 2 * offset + 2];
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let index = (k + max) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end, each step undoes one edit and the common run after it
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let d = d as isize;
+        let k = x - y;
+        let index = (k + max) as usize;
+
+        let previous_k = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + max) as usize];
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+        }
+
+        if previous_k == k + 1 {
+            inserted[previous_y as usize] = true;
+        } else {
+            deleted[previous_x as usize] = true;
+        }
+
+        x = previous_x;
+        y = previous_y;
+    }
+
+    (deleted, inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, split_lines, Hunk};
+
+    fn hunk(old_start: usize, old_len: usize, new_start: usize, new_len: usize) -> Hunk {
+        Hunk {
+            old_start,
+            old_len,
+            new_start,
+            new_len,
+        }
+    }
+
+    #[test]
+    fn diff_test() {
+        let old: Vec<char> = "ABCABBA".chars().collect();
+        let new: Vec<char> = "CBABAC".chars().collect();
+        let hunks = diff(&old, &new);
+
+        // Shortest edit script of the Myers paper, 5 edits
+        let edits: usize = hunks.iter().map(|h| h.old_len + h.new_len).sum();
+        assert_eq!(edits, 5);
+
+        // Applying hunks to `old` gives `new`
+        let mut patched = vec![];
+        let mut old_index = 0;
+        for h in hunks.iter() {
+            patched.extend_from_slice(&old[old_index..h.old_start]);
+            patched.extend_from_slice(&new[h.new_start..h.new_end()]);
+            old_index = h.old_end();
+        }
+        patched.extend_from_slice(&old[old_index..]);
+        assert_eq!(patched, new);
+
+        let old = ["fn main() {", "    run();", "}"];
+        assert_eq!(diff(&old, &old), []);
+        assert_eq!(
+            diff(&old, &["fn main() {", "    init();", "    run();", "}"]),
+            [hunk(1, 0, 1, 1)]
+        );
+        assert_eq!(diff(&old, &["fn main() {", "}"]), [hunk(1, 1, 1, 0)]);
+        assert_eq!(
+            diff(&old, &["fn main() {", "    start();", "}"]),
+            [hunk(1, 1, 1, 1)]
+        );
+        assert_eq!(diff::<&str>(&[], &["a", "b"]), [hunk(0, 0, 0, 2)]);
+    }
+
+    #[test]
+    fn split_lines_test() {
+        assert_eq!(split_lines("a\nb\r\n\nc"), ["a\n", "b\r\n", "\n", "c"]);
+        assert!(split_lines("").is_empty());
+    }
+}
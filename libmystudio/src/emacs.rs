@@ -0,0 +1,268 @@
+// Emacs-style editing helpers, offsets are char offsets into the text
+
+use std::collections::VecDeque;
+
+use crate::vim::motions::line_end;
+
+// Oldest kills are dropped past this size, same as Emacs' `kill-ring-max`
+const KILL_RING_CAPACITY: usize = 120;
+
+/**
+ * Killed (cut) text, newest first.
+ *
+ * `yank` inserts the newest kill and `yank_pop` walks back to older ones.
+ */
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    // Entry returned by the last `yank` or `yank_pop`
+    yank_index: usize,
+}
+
+impl KillRing {
+    /**
+     * Stores killed text, `append` joins it to the newest kill so that
+     * consecutive kills are yanked back at once.
+     */
+    pub fn kill(&mut self, text: &str, append: bool) {
+        match self.entries.front_mut() {
+            Some(newest) if append => newest.push_str(text),
+            _ => {
+                self.entries.push_front(text.to_string());
+                self.entries.truncate(KILL_RING_CAPACITY);
+            }
+        }
+
+        self.yank_index = 0;
+    }
+
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.front().map(String::as_str)
+    }
+
+    /// Returns the kill before the one yanked last, wrapping around to the newest.
+    pub fn yank_pop(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/**
+ * Range removed by `kill-line`: the rest of the line, or the line break when
+ * the cursor is at the end of a line.
+ */
+pub fn kill_line_range(text: &[char], cursor: usize) -> (usize, usize) {
+    let cursor = cursor.min(text.len());
+    let end = line_end(text, cursor);
+
+    // Only whitespace left also kills the line break, like Emacs
+    let rest_is_blank = text[cursor..end].iter().all(|c| c.is_whitespace());
+    if rest_is_blank && end < text.len() {
+        (cursor, end + 1)
+    } else {
+        (cursor, end)
+    }
+}
+
+/**
+ * Finds `query` in `text`, returning the offset of the match.
+ *
+ * Searching forward matches at or after `from`, backward strictly before it,
+ * and both wrap around. Matching ignores case unless `query` has uppercase
+ * letters.
+ */
+pub fn find_match(text: &[char], query: &str, from: usize, forward: bool) -> Option<usize> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() || query.len() > text.len() {
+        return None;
+    }
+
+    let ignore_case = !query.iter().any(|c| c.is_uppercase());
+    let matches_at = |offset: usize| {
+        text[offset..offset + query.len()]
+            .iter()
+            .zip(query.iter())
+            .all(|(a, b)| {
+                if ignore_case {
+                    a.to_lowercase().eq(b.to_lowercase())
+                } else {
+                    a == b
+                }
+            })
+    };
+
+    let last_offset = text.len() - query.len();
+    let from = from.min(last_offset + 1);
+
+    if forward {
+        (from..=last_offset)
+            .chain(0..from)
+            .find(|offset| matches_at(*offset))
+    } else {
+        (0..from)
+            .rev()
+            .chain((from..=last_offset).rev())
+            .find(|offset| matches_at(*offset))
+    }
+}
+
+/**
+ * State of an incremental search (`C-s` / `C-r`), the match moves as the
+ * query is typed.
+ */
+#[derive(Debug, Clone)]
+pub struct IncrementalSearch {
+    pub query: String,
+    pub forward: bool,
+    // Cursor position when the search started
+    start: usize,
+    // Start and end of the current match
+    current: Option<(usize, usize)>,
+    // Match before each typed char, restored when it is deleted
+    history: Vec<Option<(usize, usize)>>,
+}
+
+impl IncrementalSearch {
+    pub fn new(start: usize, forward: bool) -> Self {
+        Self {
+            query: String::new(),
+            forward,
+            start,
+            current: None,
+            history: vec![],
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn current(&self) -> Option<(usize, usize)> {
+        self.current
+    }
+
+    /// The query has no match.
+    pub fn is_failing(&self) -> bool {
+        !self.query.is_empty() && self.current.is_none()
+    }
+
+    /// Text shown in the status bar, ex: `I-search backward: foo`.
+    pub fn prompt(&self) -> String {
+        let failing = if self.is_failing() { "Failing " } else { "" };
+        let direction = if self.forward { "" } else { " backward" };
+
+        format!("{failing}I-search{direction}: {}", self.query)
+    }
+
+    pub fn push_char(&mut self, c: char, text: &[char]) -> Option<(usize, usize)> {
+        self.query.push(c);
+        self.history.push(self.current);
+
+        // A longer query can still match where the current match is
+        let anchor = self.current.map_or(self.start, |(start, _)| start);
+        let from = if self.forward { anchor } else { anchor + 1 };
+
+        self.search(text, from)
+    }
+
+    pub fn pop_char(&mut self) -> Option<(usize, usize)> {
+        if self.query.pop().is_some() {
+            self.current = self.history.pop().flatten();
+        }
+
+        self.current
+    }
+
+    /// Moves to the next match in a given direction, ex: pressing `C-s` again.
+    pub fn next(&mut self, text: &[char], forward: bool) -> Option<(usize, usize)> {
+        self.forward = forward;
+
+        let anchor = self.current.map_or(self.start, |(start, _)| start);
+        let from = if forward { anchor + 1 } else { anchor };
+
+        self.search(text, from)
+    }
+
+    fn search(&mut self, text: &[char], from: usize) -> Option<(usize, usize)> {
+        self.current = find_match(text, &self.query, from, self.forward)
+            .map(|start| (start, start + self.query.chars().count()));
+
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_match, kill_line_range, IncrementalSearch, KillRing};
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn kill_ring_test() {
+        let mut kill_ring = KillRing::default();
+        assert!(kill_ring.yank().is_none());
+
+        kill_ring.kill("one", false);
+        kill_ring.kill("two", false);
+        // Consecutive kill
+        kill_ring.kill("\n", true);
+
+        assert_eq!(kill_ring.yank(), Some("two\n"));
+        assert_eq!(kill_ring.yank_pop(), Some("one"));
+        assert_eq!(kill_ring.yank_pop(), Some("two\n"));
+    }
+
+    #[test]
+    fn kill_line_range_test() {
+        let text = chars("let a = 1;  \nnext");
+
+        assert_eq!(kill_line_range(&text, 4), (4, 12));
+        // Trailing whitespace goes along with the line break
+        assert_eq!(kill_line_range(&text, 10), (10, 13));
+        assert_eq!(kill_line_range(&text, 12), (12, 13));
+        assert_eq!(kill_line_range(&text, 17), (17, 17));
+    }
+
+    #[test]
+    fn find_match_test() {
+        let text = chars("Foo bar foo");
+
+        assert_eq!(find_match(&text, "foo", 0, true), Some(0));
+        assert_eq!(find_match(&text, "foo", 1, true), Some(8));
+        // Uppercase makes the search case sensitive
+        assert_eq!(find_match(&text, "Foo", 1, true), Some(0));
+        assert_eq!(find_match(&text, "foo", 8, false), Some(0));
+        assert_eq!(find_match(&text, "foo", 0, false), Some(8));
+        assert_eq!(find_match(&text, "baz", 0, true), None);
+    }
+
+    #[test]
+    fn incremental_search_test() {
+        let text = chars("fn foo() { food(); }");
+        let mut search = IncrementalSearch::new(0, true);
+
+        assert_eq!(search.push_char('f', &text), Some((0, 1)));
+        assert_eq!(search.push_char('o', &text), Some((3, 5)));
+        assert_eq!(search.next(&text, true), Some((11, 13)));
+        assert_eq!(search.push_char('o', &text), Some((11, 14)));
+        assert_eq!(search.prompt(), "I-search: foo");
+
+        assert_eq!(search.push_char('x', &text), None);
+        assert_eq!(search.prompt(), "Failing I-search: foox");
+
+        assert_eq!(search.pop_char(), Some((11, 14)));
+        assert_eq!(search.next(&text, false), Some((3, 6)));
+        assert_eq!(search.prompt(), "I-search backward: foo");
+    }
+}
@@ -0,0 +1,344 @@
+// Formatting with external programs configured per language in `[Formatters]`
+// of config.toml, ex: rustfmt. Text is piped through the formatter and the
+// result is turned into edits touching only the lines it changed.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diff::{diff, split_lines};
+
+// Formatters taking longer are killed
+const FORMAT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Replaced by the path of the formatted file in `args`
+const FILE_PLACEHOLDER: &str = "{file}";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct FormatterConfig {
+    pub enabled: bool,
+    // Program to run, looked up in `PATH`. It reads text on stdin and writes
+    // the formatted text on stdout.
+    pub command: String,
+    // `{file}` is replaced by the path of the file, ex: for `--stdin-filepath`
+    pub args: Vec<String>,
+    // File extensions handled by the formatter, without the dot
+    pub extensions: Vec<String>,
+}
+
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            command: String::new(),
+            args: vec![],
+            extensions: vec![],
+        }
+    }
+}
+
+impl FormatterConfig {
+    fn new(command: &str, args: &[&str], extensions: &[&str]) -> Self {
+        Self {
+            enabled: true,
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+        }
+    }
+}
+
+/// Formatters known out of the box, keyed by language.
+pub fn default_formatters() -> BTreeMap<String, FormatterConfig> {
+    BTreeMap::from([
+        (
+            String::from("rust"),
+            FormatterConfig::new("rustfmt", &["--edition", "2021"], &["rs"]),
+        ),
+        (
+            String::from("python"),
+            FormatterConfig::new(
+                "black",
+                &["--quiet", "--stdin-filename", FILE_PLACEHOLDER, "-"],
+                &["py", "pyi"],
+            ),
+        ),
+        (
+            String::from("javascript"),
+            FormatterConfig::new(
+                "prettier",
+                &["--stdin-filepath", FILE_PLACEHOLDER],
+                &[
+                    "js", "jsx", "mjs", "cjs", "ts", "tsx", "json", "css", "scss", "html", "md",
+                    "yaml", "yml",
+                ],
+            ),
+        ),
+        (
+            String::from("c"),
+            FormatterConfig::new(
+                "clang-format",
+                &["--assume-filename={file}"],
+                &["c", "h", "cc", "cpp", "cxx", "hpp", "hh"],
+            ),
+        ),
+    ])
+}
+
+/**
+ * Returns the enabled formatter handling a file, going by its extension.
+ */
+pub fn formatter_for_path<'a>(
+    formatters: &'a BTreeMap<String, FormatterConfig>,
+    path: &str,
+) -> Option<&'a FormatterConfig> {
+    let extension = Path::new(path)
+        .extension()?
+        .to_string_lossy()
+        .to_lowercase();
+
+    formatters.values().find(|formatter| {
+        formatter.enabled
+            && !formatter.command.trim().is_empty()
+            && formatter.extensions.contains(&extension)
+    })
+}
+
+/// A failed formatter run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    // Formatter could not be spawned, or reading/writing its pipes failed
+    Io(String),
+    // Formatter exited with an error, usually the text doesn't parse
+    Failed(String),
+    // Formatter didn't finish within the timeout
+    Timeout,
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Io(message) => write!(f, "I/O error: {message}"),
+            FormatError::Failed(message) => write!(f, "formatter failed: {message}"),
+            FormatError::Timeout => write!(f, "formatter timed out"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<std::io::Error> for FormatError {
+    fn from(error: std::io::Error) -> Self {
+        FormatError::Io(error.to_string())
+    }
+}
+
+/**
+ * Pipes `text` of the file at `path` through `formatter` and returns its output.
+ *
+ * The formatter runs in the directory of the file so that it finds project
+ * settings, ex: `rustfmt.toml`.
+ */
+pub fn format_text(
+    formatter: &FormatterConfig,
+    path: &str,
+    text: &str,
+) -> Result<String, FormatError> {
+    let args: Vec<String> = formatter
+        .args
+        .iter()
+        .map(|arg| arg.replace(FILE_PLACEHOLDER, path))
+        .collect();
+
+    let mut command = Command::new(&formatter.command);
+    command
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(parent) = Path::new(path).parent().filter(|parent| parent.is_dir()) {
+        command.current_dir(parent);
+    }
+
+    let mut child = command.spawn().map_err(|error| {
+        FormatError::Io(format!("unable to run '{}': {error}", formatter.command))
+    })?;
+
+    // Pipes are drained on threads, a formatter may not read all of stdin
+    // before writing to stdout
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = text.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let error_reader = thread::spawn(move || {
+        let mut output = String::new();
+        stderr.read_to_string(&mut output).ok();
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() > FORMAT_TIMEOUT {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(FormatError::Timeout);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let write_result = writer.join().unwrap_or(Ok(()));
+    let output = reader.join().unwrap_or_else(|_| Ok(String::new()))?;
+    let error_output = error_reader.join().unwrap_or_default();
+
+    if !status.success() {
+        let message = error_output
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map_or_else(|| status.to_string(), str::to_string);
+        return Err(FormatError::Failed(message));
+    }
+    // Output of a formatter which exited before reading all of the text is partial
+    write_result?;
+
+    Ok(output)
+}
+
+/// Replacement of the chars from `start` to `end` by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    // Char offsets in the original text, as used by `TextBuffer::iter_at_offset`
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/**
+ * Returns edits turning `old` into `new` which replace whole lines only where
+ * they differ, so that marks elsewhere (ex: the cursor) stay in place.
+ *
+ * Edits are in order and don't overlap, apply them last to first so that
+ * offsets of the remaining ones stay valid.
+ */
+pub fn minimal_edits(old: &str, new: &str) -> Vec<TextEdit> {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+
+    // Char offset of the start of each old line, plus the end of the text
+    let mut line_offsets = Vec::with_capacity(old_lines.len() + 1);
+    let mut offset = 0;
+    for line in old_lines.iter() {
+        line_offsets.push(offset);
+        offset += line.chars().count();
+    }
+    line_offsets.push(offset);
+
+    diff(&old_lines, &new_lines)
+        .into_iter()
+        .map(|hunk| TextEdit {
+            start: line_offsets[hunk.old_start],
+            end: line_offsets[hunk.old_end()],
+            text: new_lines[hunk.new_start..hunk.new_end()].concat(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        default_formatters, format_text, formatter_for_path, minimal_edits, FormatError,
+        FormatterConfig,
+    };
+
+    fn apply(text: &str, edits: &[super::TextEdit]) -> String {
+        let mut chars: Vec<char> = text.chars().collect();
+        for edit in edits.iter().rev() {
+            chars.splice(edit.start..edit.end, edit.text.chars());
+        }
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn minimal_edits_test() {
+        let old = "fn  main(){\n    let é = 1;\nprintln!(\"{é}\");\n}\n";
+        let new = "fn main() {\n    let é = 1;\n    println!(\"{é}\");\n}\n";
+
+        let edits = minimal_edits(old, new);
+        // The unchanged second line is kept
+        assert_eq!(edits.len(), 2);
+        assert_eq!((edits[0].start, edits[0].end), (0, 12));
+        // Offsets count chars, not bytes
+        assert_eq!((edits[1].start, edits[1].end), (27, 44));
+        assert_eq!(apply(old, &edits), new);
+
+        assert!(minimal_edits(new, new).is_empty());
+        assert_eq!(apply("a", &minimal_edits("a", "a\n")), "a\n");
+    }
+
+    #[test]
+    fn formatter_for_path_test() {
+        let mut formatters = default_formatters();
+        assert_eq!(
+            formatter_for_path(&formatters, "/src/main.RS").map(|f| f.command.as_str()),
+            Some("rustfmt")
+        );
+        assert!(formatter_for_path(&formatters, "/notes.txt").is_none());
+
+        formatters.get_mut("rust").unwrap().enabled = false;
+        assert!(formatter_for_path(&formatters, "/src/main.rs").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn format_text_test() {
+        let upper = FormatterConfig {
+            command: String::from("tr"),
+            args: vec![String::from("a-z"), String::from("A-Z")],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_text(&upper, "/tmp/a.txt", "abc\n"),
+            Ok(String::from("ABC\n"))
+        );
+
+        let failing = FormatterConfig {
+            command: String::from("sh"),
+            args: vec![
+                String::from("-c"),
+                String::from("echo \"error in {file}\" >&2; exit 1"),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            format_text(&failing, "/tmp/a.txt", ""),
+            Err(FormatError::Failed(String::from("error in /tmp/a.txt")))
+        );
+
+        let missing = FormatterConfig {
+            command: String::from("mystudio-missing-formatter"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            format_text(&missing, "/tmp/a.txt", ""),
+            Err(FormatError::Io(_))
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     fs::File,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use byteorder::{BigEndian, LittleEndian};
@@ -38,6 +38,25 @@ pub fn get_config_file_path() -> PathBuf {
     path_buf
 }
 
+/// Directory of files which can be rebuilt when missing, ex: the symbol index.
+pub fn get_cache_dir() -> PathBuf {
+    let mut path_buf = if cfg!(not(test)) {
+        dirs::cache_dir().expect("Unable to open cache directory.")
+    } else {
+        tempfile::tempdir()
+            .expect("Unable to open cache directory.")
+            .into_path()
+    };
+
+    path_buf.push("mystudio-ide");
+
+    if !path_buf.exists() {
+        std::fs::create_dir_all(&path_buf).expect("Unable to create cache directory.");
+    }
+
+    path_buf
+}
+
 pub fn read_dir_recursive(root_dir: String) -> Vec<jwalk::DirEntry<((), ())>> {
     let result = WalkDir::new(root_dir)
         .skip_hidden(false)
@@ -49,6 +68,56 @@ pub fn read_dir_recursive(root_dir: String) -> Vec<jwalk::DirEntry<((), ())>> {
     iter.filter_map(|f| f.ok()).collect()
 }
 
+/**
+ * Checks if any component of `relative_path` matches one of `patterns`.
+ *
+ * Patterns without '/' are matched against each file or directory name and
+ * support `*` and `?` wildcards (ex: `target`, `*.min.js`). Patterns with '/'
+ * are matched against the whole relative path.
+ */
+pub fn is_path_excluded(relative_path: &Path, patterns: &[String]) -> bool {
+    let relative_path_str = relative_path.to_string_lossy().replace('\\', "/");
+
+    patterns.iter().any(|pattern| {
+        if pattern.contains('/') {
+            let pattern = pattern.trim_matches('/');
+            wildcard_match(pattern, &relative_path_str)
+                || relative_path_str.starts_with(&format!("{pattern}/"))
+        } else {
+            relative_path
+                .components()
+                .any(|c| wildcard_match(pattern, &c.as_os_str().to_string_lossy()))
+        }
+    })
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of last '*' in pattern and the text position it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub fn read_file_contents(input_file: &str) -> Option<String> {
     let file = File::open(input_file);
     if file.is_err() {
@@ -118,9 +187,11 @@ mod tests {
     use content_inspector::ContentType;
     use tempfile::tempdir;
 
+    use std::path::Path;
+
     use crate::{encoding::detect_encoding, fs::read_dir_recursive};
 
-    use super::save_file_changes;
+    use super::{is_path_excluded, save_file_changes};
 
     #[test]
     fn save_file_changes_utf8_test() {
@@ -226,4 +297,22 @@ mod tests {
 
         assert_eq!(root_dir_resolved.unwrap().path(), temp_dir.path());
     }
+
+    #[test]
+    fn is_path_excluded_test() {
+        let patterns = vec![
+            "target".to_string(),
+            "*.min.js".to_string(),
+            "docs/generated".to_string(),
+        ];
+
+        assert!(is_path_excluded(Path::new("target/debug/app"), &patterns));
+        assert!(is_path_excluded(Path::new("web/app.min.js"), &patterns));
+        assert!(is_path_excluded(
+            Path::new("docs/generated/index.html"),
+            &patterns
+        ));
+        assert!(!is_path_excluded(Path::new("src/target.rs"), &patterns));
+        assert!(!is_path_excluded(Path::new("docs/index.html"), &patterns));
+    }
 }
@@ -0,0 +1,142 @@
+// Fuzzy matching used by pickers like the command palette
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 32;
+const BONUS_WORD_START: i64 = 32;
+const BONUS_FIRST_CHAR: i64 = 16;
+const PENALTY_GAP: i64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    // Higher is better
+    pub score: i64,
+    // Char indices of `text` matched by the pattern
+    pub positions: Vec<usize>,
+}
+
+/**
+ * Matches `pattern` against `text` as a case-insensitive subsequence.
+ *
+ * Returns `None` if some char of `pattern` is missing from `text`. Matches at
+ * word starts (ex: `gl` in `Go to Line`) and runs of consecutive chars score higher.
+ * Whitespace in `pattern` is ignored.
+ */
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let text: Vec<char> = text.chars().collect();
+
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: vec![],
+        });
+    }
+
+    // best[i][j]: best score of pattern[..=i] with pattern[i] matched at text[j]
+    let mut best: Vec<Vec<Option<i64>>> = vec![vec![None; text.len()]; pattern.len()];
+    let mut previous: Vec<Vec<usize>> = vec![vec![0; text.len()]; pattern.len()];
+
+    for (i, pattern_char) in pattern.iter().enumerate() {
+        for (j, text_char) in text.iter().enumerate() {
+            if !text_char.to_lowercase().eq(std::iter::once(*pattern_char)) {
+                continue;
+            }
+
+            let mut char_score = SCORE_MATCH;
+            if is_word_start(&text, j) {
+                char_score += BONUS_WORD_START;
+            }
+            if j == 0 {
+                char_score += BONUS_FIRST_CHAR;
+            }
+
+            if i == 0 {
+                best[i][j] = Some(char_score - j as i64 * PENALTY_GAP);
+                continue;
+            }
+
+            for k in 0..j {
+                let Some(score) = best[i - 1][k] else {
+                    continue;
+                };
+
+                let score = if k + 1 == j {
+                    score + char_score + BONUS_CONSECUTIVE
+                } else {
+                    score + char_score - (j - k - 1) as i64 * PENALTY_GAP
+                };
+
+                match best[i][j] {
+                    Some(current) if current >= score => {}
+                    _ => {
+                        best[i][j] = Some(score);
+                        previous[i][j] = k;
+                    }
+                }
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|(j, score)| (*score, std::cmp::Reverse(*j)))?;
+
+    let mut positions = vec![j];
+    for i in (1..pattern.len()).rev() {
+        j = previous[i][j];
+        positions.push(j);
+    }
+    positions.reverse();
+
+    Some(FuzzyMatch { score, positions })
+}
+
+fn is_word_start(text: &[char], index: usize) -> bool {
+    if index == 0 {
+        return true;
+    }
+
+    let previous = text[index - 1];
+    let current = text[index];
+
+    !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_match;
+
+    #[test]
+    fn fuzzy_match_test() {
+        let result = fuzzy_match("gtl", "Go to Line").unwrap();
+        assert_eq!(result.positions, vec![0, 3, 6]);
+
+        assert!(fuzzy_match("", "Save File").is_some());
+        assert!(fuzzy_match("SAVE", "Save File").is_some());
+        assert!(fuzzy_match("sf", "Save File").is_some());
+        assert!(fuzzy_match("fs", "Save File").is_none());
+        assert!(fuzzy_match("x", "Save File").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_word_starts() {
+        let word_start = fuzzy_match("of", "Open Folder").unwrap();
+        let inner = fuzzy_match("of", "Proof").unwrap();
+        assert!(word_start.score > inner.score);
+
+        // Prefers "Files" over "Fi" of "Find" followed by "le"
+        let result = fuzzy_match("file", "Find in Files").unwrap();
+        assert_eq!(result.positions, vec![8, 9, 10, 11]);
+
+        let consecutive = fuzzy_match("save", "Save File").unwrap();
+        let scattered = fuzzy_match("save", "Show All Versions Everywhere").unwrap();
+        assert!(consecutive.score > scattered.score);
+    }
+}
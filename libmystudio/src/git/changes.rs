@@ -0,0 +1,346 @@
+// Changes of a file compared to a committed version, shown in the editor
+// gutter, and patches to stage some of them
+
+use std::path::Path;
+
+use crate::diff::{diff, split_lines, Hunk};
+
+use super::{error::GitError, repository_file, run_git_with_input};
+
+// Unchanged lines around each hunk of a patch
+const PATCH_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/**
+A changed region of a file, `hunk` has lines of the committed version as old
+items and lines of the current text as new items.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineChange {
+    pub kind: ChangeKind,
+    pub hunk: Hunk,
+}
+
+impl LineChange {
+    /**
+     * Zero-based lines of the current text to mark, a deletion marks the line
+     * following it, or the last line when nothing follows.
+     */
+    pub fn marked_lines(&self, line_count: usize) -> (usize, usize) {
+        match self.kind {
+            ChangeKind::Deleted => {
+                let line = self.hunk.new_start.min(line_count.saturating_sub(1));
+                (line, line + 1)
+            }
+            _ => (self.hunk.new_start, self.hunk.new_end()),
+        }
+    }
+}
+
+/**
+ * Returns the changes turning `base` into `text`, in order.
+ *
+ * Line terminators are ignored so that a missing newline at the end of the
+ * file doesn't mark its last line as modified.
+ */
+pub fn line_changes(base: &str, text: &str) -> Vec<LineChange> {
+    let base_lines = trimmed_lines(base);
+    let lines = trimmed_lines(text);
+
+    diff(&base_lines, &lines)
+        .into_iter()
+        .map(|hunk| {
+            let kind = if hunk.old_len == 0 {
+                ChangeKind::Added
+            } else if hunk.new_len == 0 {
+                ChangeKind::Deleted
+            } else {
+                ChangeKind::Modified
+            };
+            LineChange { kind, hunk }
+        })
+        .collect()
+}
+
+fn trimmed_lines(text: &str) -> Vec<&str> {
+    split_lines(text)
+        .into_iter()
+        .map(|line| line.trim_end_matches(['\n', '\r']))
+        .collect()
+}
+
+/// Returns `text` with the lines of `hunk` turned back into those of `base`.
+pub fn revert_hunk(base: &str, text: &str, hunk: &Hunk) -> String {
+    let base_lines = split_lines(base);
+    let lines = split_lines(text);
+
+    join_lines(&[
+        &lines[..hunk.new_start],
+        &base_lines[hunk.old_start..hunk.old_end()],
+        &lines[hunk.new_end()..],
+    ])
+}
+
+/// Returns `base` with only the lines of `hunk` changed as in `text`.
+pub fn apply_hunk(base: &str, text: &str, hunk: &Hunk) -> String {
+    let base_lines = split_lines(base);
+    let lines = split_lines(text);
+
+    join_lines(&[
+        &base_lines[..hunk.old_start],
+        &lines[hunk.new_start..hunk.new_end()],
+        &base_lines[hunk.old_end()..],
+    ])
+}
+
+// Joins parts of different texts, only their very last line may lack a newline
+fn join_lines(parts: &[&[&str]]) -> String {
+    let lines: Vec<&str> = parts.iter().flat_map(|part| part.iter().copied()).collect();
+
+    let mut text = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        text.push_str(line);
+        if i + 1 < lines.len() && !line.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/**
+ * Returns a patch turning `old` into `new` for the file at `path`, relative
+ * to the repository root, in the unified format read by `git apply`.
+ *
+ * The patch is empty when the texts are the same.
+ */
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let hunks = diff(&old_lines, &new_lines);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut patch = format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n");
+
+    // Hunks with overlapping context are shown together
+    let mut groups: Vec<Vec<Hunk>> = vec![];
+    for hunk in hunks {
+        match groups.last_mut() {
+            Some(group)
+                if hunk.old_start <= group.last().unwrap().old_end() + 2 * PATCH_CONTEXT =>
+            {
+                group.push(hunk)
+            }
+            _ => groups.push(vec![hunk]),
+        }
+    }
+
+    for group in groups {
+        let (first, last) = (group[0], group[group.len() - 1]);
+        let old_start = first.old_start.saturating_sub(PATCH_CONTEXT);
+        let old_end = (last.old_end() + PATCH_CONTEXT).min(old_lines.len());
+        let new_start = first.new_start - (first.old_start - old_start);
+        let new_end = last.new_end() + (old_end - last.old_end());
+
+        patch.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+
+        let mut line = old_start;
+        for hunk in group {
+            push_lines(&mut patch, ' ', &old_lines[line..hunk.old_start]);
+            push_lines(&mut patch, '-', &old_lines[hunk.old_start..hunk.old_end()]);
+            push_lines(&mut patch, '+', &new_lines[hunk.new_start..hunk.new_end()]);
+            line = hunk.old_end();
+        }
+        push_lines(&mut patch, ' ', &old_lines[line..old_end]);
+    }
+
+    patch
+}
+
+// Empty ranges start at the line before them
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{start},0")
+    } else {
+        format!("{},{len}", start + 1)
+    }
+}
+
+fn push_lines(patch: &mut String, prefix: char, lines: &[&str]) {
+    for line in lines {
+        patch.push(prefix);
+        patch.push_str(line);
+        if !line.ends_with('\n') {
+            patch.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/**
+ * Stages the lines of `hunk` of the file at `path`, `base` being its text in
+ * the index or in HEAD and `text` its current text.
+ *
+ * A patch built against HEAD still applies when other changes of the file
+ * were staged, as long as they don't touch the lines around `hunk`.
+ */
+pub fn stage_hunk(path: &Path, base: &str, text: &str, hunk: &Hunk) -> Result<(), GitError> {
+    apply_to_index(path, base, &apply_hunk(base, text, hunk))
+}
+
+/**
+ * Unstages the lines of `hunk` of the file at `path`, `head` being its text in
+ * HEAD and `index` its text in the index.
+ */
+pub fn unstage_hunk(path: &Path, head: &str, index: &str, hunk: &Hunk) -> Result<(), GitError> {
+    apply_to_index(path, index, &revert_hunk(head, index, hunk))
+}
+
+// Turns `old` into `new` in the index with a patch, the working tree is kept
+fn apply_to_index(path: &Path, old: &str, new: &str) -> Result<(), GitError> {
+    let (root, relative) = repository_file(path)?;
+    let patch = unified_diff(&relative, old, new);
+
+    run_git_with_input(
+        &root,
+        &["apply", "--cached", "--whitespace=nowarn", "-"],
+        Some(&patch),
+    )
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process::Command};
+
+    use crate::git::index_content;
+
+    use super::*;
+
+    const BASE: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+
+    #[test]
+    fn line_changes_test() {
+        let text = "a\nB\nc\nd\nnew\ne\nf\ng\nh\nj";
+        let changes = line_changes(BASE, text);
+
+        let kinds: Vec<ChangeKind> = changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [ChangeKind::Modified, ChangeKind::Added, ChangeKind::Deleted]
+        );
+        assert_eq!(changes[0].marked_lines(10), (1, 2));
+        assert_eq!(changes[1].marked_lines(10), (4, 5));
+        // Line `i` was deleted before `j`, missing newline at the end is ignored
+        assert_eq!(changes[2].hunk.new_start, 9);
+        assert_eq!(changes[2].marked_lines(10), (9, 10));
+
+        assert!(line_changes("a\r\n", "a\n").is_empty());
+        // Deleted at the end of the file
+        assert_eq!(line_changes("a\nb\n", "a\n")[0].marked_lines(1), (0, 1));
+    }
+
+    #[test]
+    fn revert_and_apply_test() {
+        let text = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk";
+        let changes = line_changes(BASE, text);
+        assert_eq!(changes.len(), 2);
+
+        assert_eq!(
+            revert_hunk(BASE, text, &changes[0].hunk),
+            "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk"
+        );
+        assert_eq!(
+            revert_hunk(BASE, text, &changes[1].hunk),
+            "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\n"
+        );
+        assert_eq!(
+            apply_hunk(BASE, text, &changes[1].hunk),
+            "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk"
+        );
+
+        // Line without newline followed by reverted lines
+        let changes = line_changes("a\nb\n", "a");
+        assert_eq!(revert_hunk("a\nb\n", "a", &changes[0].hunk), "a\nb\n");
+    }
+
+    #[test]
+    fn unified_diff_test() {
+        assert_eq!(unified_diff("f.txt", BASE, BASE), "");
+
+        let patch = unified_diff("src/f.txt", BASE, "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk");
+        assert_eq!(
+            patch,
+            "diff --git a/src/f.txt b/src/f.txt\n--- a/src/f.txt\n+++ b/src/f.txt\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -8,3 +8,4 @@\n h\n i\n j\n+k\n\\ No newline at end of file\n"
+        );
+
+        // Close hunks share their context, insertion at the start
+        let patch = unified_diff("f", "b\nc\n", "a\nb\nC\n");
+        assert!(patch.ends_with("@@ -1,2 +1,3 @@\n+a\n b\n-c\n+C\n"));
+        assert!(unified_diff("f", "", "a\n").ends_with("@@ -0,0 +1,1 @@\n+a\n"));
+    }
+
+    #[test]
+    fn stage_hunk_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(root)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+
+        let path = root.join("f.txt");
+        fs::write(&path, BASE).unwrap();
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "initial"]);
+
+        let text = "a\nB\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+        fs::write(&path, text).unwrap();
+        let changes = line_changes(BASE, text);
+
+        stage_hunk(&path, BASE, text, &changes[1].hunk).unwrap();
+        assert_eq!(
+            index_content(&path).unwrap().as_deref(),
+            Some("a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n")
+        );
+        // Still applies after the other hunk was staged
+        stage_hunk(&path, BASE, text, &changes[0].hunk).unwrap();
+        assert_eq!(index_content(&path).unwrap().as_deref(), Some(text));
+
+        // Staged lines are unstaged one hunk at a time
+        let staged = line_changes(BASE, text);
+        unstage_hunk(&path, BASE, text, &staged[0].hunk).unwrap();
+        assert_eq!(
+            index_content(&path).unwrap().as_deref(),
+            Some("a\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n")
+        );
+
+        assert_eq!(
+            crate::git::head_content(&path).unwrap().as_deref(),
+            Some(BASE)
+        );
+        assert_eq!(
+            crate::git::head_content(&root.join("missing")).unwrap(),
+            None
+        );
+    }
+}
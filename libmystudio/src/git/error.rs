@@ -0,0 +1,30 @@
+use std::fmt::Display;
+
+/// A failed `git` invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitError {
+    // `git` could not be spawned, usually it isn't installed
+    Io(String),
+    // `git` exited with an error, message is its stderr
+    Failed(String),
+    // Path is not inside a git repository
+    NotARepository,
+}
+
+impl Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitError::Io(message) => write!(f, "I/O error: {message}"),
+            GitError::Failed(message) => write!(f, "git failed: {message}"),
+            GitError::NotARepository => write!(f, "not a git repository"),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+impl From<std::io::Error> for GitError {
+    fn from(error: std::io::Error) -> Self {
+        GitError::Io(error.to_string())
+    }
+}
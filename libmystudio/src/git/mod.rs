@@ -0,0 +1,139 @@
+// Git integration through the `git` program. Only local commands are run,
+// nothing here fetches from or pushes to remotes.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use self::error::GitError;
+
+pub mod changes;
+pub mod error;
+pub mod repository;
+pub mod status;
+pub mod watcher;
+
+/**
+ * Runs `git` with `args` in `cwd` and returns its stdout.
+ *
+ * Optional locks are disabled so that background status refreshes don't get in
+ * the way of git commands run by the user in a terminal.
+ */
+pub(crate) fn run_git(cwd: &Path, args: &[&str]) -> Result<Vec<u8>, GitError> {
+    run_git_with_input(cwd, args, None)
+}
+
+/// Same as `run_git`, writing `input` to the stdin of `git`, ex: a patch.
+pub(crate) fn run_git_with_input(
+    cwd: &Path,
+    args: &[&str],
+    input: Option<&str>,
+) -> Result<Vec<u8>, GitError> {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| GitError::Io(format!("unable to run 'git': {error}")))?;
+
+    // Written from another thread so that a full stdout pipe can't block git
+    let writer = child.stdin.take().zip(input).map(|(mut stdin, input)| {
+        let input = input.to_string();
+        thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        writer.join().unwrap_or(Ok(()))?;
+    }
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(GitError::Failed(message));
+    }
+
+    Ok(output.stdout)
+}
+
+/**
+ * Returns the top-level directory of the repository containing `path`.
+ *
+ * The result is derived from `path` rather than asking git for an absolute
+ * path, so it stays comparable with paths under `path` even through symlinks.
+ */
+pub fn repository_root(path: &Path) -> Result<PathBuf, GitError> {
+    let cdup = match run_git(path, &["rev-parse", "--show-cdup"]) {
+        Ok(output) => output,
+        Err(GitError::Failed(_)) => return Err(GitError::NotARepository),
+        Err(error) => return Err(error),
+    };
+    let cdup = String::from_utf8_lossy(&cdup);
+    let depth = Path::new(cdup.trim()).components().count();
+
+    path.ancestors()
+        .nth(depth)
+        .map(Path::to_path_buf)
+        .ok_or(GitError::NotARepository)
+}
+
+/**
+ * Returns the root of the repository containing the file at `path` and the
+ * path of the file relative to it, as used in git object names.
+ */
+pub(crate) fn repository_file(path: &Path) -> Result<(PathBuf, String), GitError> {
+    // Directory of a deleted file may be gone too
+    let dir = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .ok_or(GitError::NotARepository)?;
+    let root = repository_root(dir)?;
+    let relative = path
+        .strip_prefix(&root)
+        .map_err(|_| GitError::NotARepository)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Ok((root, relative))
+}
+
+/**
+ * Returns the text of the file at `path` as committed in `HEAD`, `None` when
+ * the file isn't committed or isn't inside a repository.
+ */
+pub fn head_content(path: &Path) -> Result<Option<String>, GitError> {
+    blob_content(path, "HEAD:")
+}
+
+/// Same as `head_content` for the version of the file in the index.
+pub fn index_content(path: &Path) -> Result<Option<String>, GitError> {
+    blob_content(path, ":")
+}
+
+fn blob_content(path: &Path, revision: &str) -> Result<Option<String>, GitError> {
+    let (root, relative) = match repository_file(path) {
+        Ok(file) => file,
+        Err(GitError::NotARepository) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    // Filters turn line endings into those of the working tree
+    let object = format!("{revision}{relative}");
+    match run_git(&root, &["cat-file", "--filters", &object]) {
+        Ok(content) => Ok(Some(String::from_utf8_lossy(&content).to_string())),
+        Err(GitError::Failed(_)) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,150 @@
+// Changes to a repository made from the Source Control panel: staging whole
+// files, discarding changes, committing and local branches
+
+use std::{fs, path::Path};
+
+use super::{
+    error::GitError,
+    repository_file, run_git, run_git_with_input,
+    status::{FileChange, FileStatus},
+};
+
+/// Stages all changes of the file or untracked directory at `path`, deletions included.
+pub fn stage_file(path: &Path) -> Result<(), GitError> {
+    let (root, relative) = repository_file(path)?;
+
+    run_git(&root, &["add", "-A", "--", &relative]).map(|_| ())
+}
+
+/// Unstages all changes of the file at `path`, its working tree is kept.
+pub fn unstage_file(path: &Path) -> Result<(), GitError> {
+    let (root, relative) = repository_file(path)?;
+
+    // Before the first commit there is no HEAD to reset to
+    if run_git(&root, &["rev-parse", "-q", "--verify", "HEAD"]).is_err() {
+        return run_git(&root, &["rm", "-q", "-r", "--cached", "--", &relative]).map(|_| ());
+    }
+    run_git(&root, &["reset", "-q", "--", &relative]).map(|_| ())
+}
+
+/**
+ * Throws away unstaged changes of a file, the file is restored from the
+ * index. Untracked files and directories are deleted.
+ */
+pub fn discard_changes(change: &FileChange) -> Result<(), GitError> {
+    if change.unstaged == Some(FileStatus::Untracked) {
+        if change.path.is_dir() {
+            fs::remove_dir_all(&change.path)?;
+        } else {
+            fs::remove_file(&change.path)?;
+        }
+        return Ok(());
+    }
+
+    let (root, relative) = repository_file(&change.path)?;
+    run_git(&root, &["checkout", "-q", "--", &relative]).map(|_| ())
+}
+
+/**
+ * Commits staged changes of the repository at `root`, or replaces the last
+ * commit with them and `message` when `amend` is set.
+ *
+ * Comment lines and surrounding blank lines of `message` are dropped.
+ */
+pub fn commit(root: &Path, message: &str, amend: bool) -> Result<(), GitError> {
+    let mut args = vec!["commit", "-q", "--cleanup=strip", "-F", "-"];
+    if amend {
+        args.push("--amend");
+    }
+
+    run_git_with_input(root, &args, Some(message)).map(|_| ())
+}
+
+/// Returns the message of the last commit, ex: to edit it before amending.
+pub fn last_commit_message(root: &Path) -> Result<String, GitError> {
+    let message = run_git(root, &["log", "-1", "--format=%B"])?;
+
+    Ok(String::from_utf8_lossy(&message).trim_end().to_string())
+}
+
+/// Returns names of the local branches, sorted.
+pub fn branches(root: &Path) -> Result<Vec<String>, GitError> {
+    let output = run_git(
+        root,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads/"],
+    )?;
+
+    Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Creates a branch at HEAD and switches to it, changes are carried over.
+pub fn create_branch(root: &Path, name: &str) -> Result<(), GitError> {
+    run_git(root, &["checkout", "-q", "-b", name]).map(|_| ())
+}
+
+/// Switches to a local branch, fails when changes would be overwritten.
+pub fn switch_branch(root: &Path, name: &str) -> Result<(), GitError> {
+    run_git(root, &["checkout", "-q", name, "--"]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::git::{index_content, status::GitStatus};
+
+    use super::*;
+
+    #[test]
+    fn repository_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(root).output();
+            String::from_utf8(output.unwrap().stdout).unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["config", "user.name", "test"]);
+        git(&["config", "user.email", "test@example.com"]);
+
+        let path = root.join("a.txt");
+        fs::write(&path, "a\n").unwrap();
+        stage_file(&path).unwrap();
+        unstage_file(&path).unwrap();
+        assert_eq!(index_content(&path).unwrap(), None);
+
+        stage_file(&path).unwrap();
+        commit(root, "First\n\n# comment\n", false).unwrap();
+        assert_eq!(last_commit_message(root).unwrap(), "First");
+        // Nothing staged
+        assert!(matches!(
+            commit(root, "Empty", false),
+            Err(GitError::Failed(_))
+        ));
+
+        fs::write(&path, "b\n").unwrap();
+        stage_file(&path).unwrap();
+        commit(root, "First, amended", true).unwrap();
+        assert_eq!(git(&["rev-list", "--count", "HEAD"]).trim(), "1");
+        assert_eq!(last_commit_message(root).unwrap(), "First, amended");
+
+        fs::write(&path, "c\n").unwrap();
+        fs::write(root.join("new.txt"), "").unwrap();
+        let status = GitStatus::load(root).unwrap();
+        for change in status.changes() {
+            discard_changes(change).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\n");
+        assert!(!root.join("new.txt").exists());
+
+        let current = status.branch.unwrap();
+        create_branch(root, "feature").unwrap();
+        assert_eq!(branches(root).unwrap(), ["feature", current.as_str()]);
+        switch_branch(root, &current).unwrap();
+        assert_eq!(GitStatus::load(root).unwrap().branch, Some(current));
+        assert!(switch_branch(root, "missing").is_err());
+    }
+}
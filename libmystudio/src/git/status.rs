@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arc_swap::ArcSwap;
+use static_init::dynamic;
+
+use super::{error::GitError, repository_root, run_git};
+
+// Holds reference to the status shown by the explorer tree
+#[dynamic]
+static GIT_STATUS: ArcSwap<GitStatus> = ArcSwap::new(Arc::new(GitStatus::default()));
+
+/// State of a file compared to HEAD and the index.
+///
+/// Variants are ordered by importance, a directory shows the most important
+/// status of its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FileStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Deleted,
+    Modified,
+    Conflicted,
+}
+
+impl FileStatus {
+    // Status of one side of `XY` codes, `None` when unchanged
+    fn from_code(code: char) -> Option<FileStatus> {
+        match code {
+            ' ' | '!' => None,
+            '?' => Some(FileStatus::Untracked),
+            'A' | 'R' | 'C' => Some(FileStatus::Added),
+            'D' => Some(FileStatus::Deleted),
+            'U' => Some(FileStatus::Conflicted),
+            _ => Some(FileStatus::Modified),
+        }
+    }
+
+    // `XY` codes of `git status --porcelain`, see git-status(1)
+    fn from_codes(x: char, y: char) -> FileStatus {
+        match (x, y) {
+            ('?', '?') => FileStatus::Untracked,
+            ('!', '!') => FileStatus::Ignored,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => FileStatus::Conflicted,
+            ('A' | 'R' | 'C', _) => FileStatus::Added,
+            ('D', _) | (_, 'D') => FileStatus::Deleted,
+            _ => FileStatus::Modified,
+        }
+    }
+}
+
+/// A changed file of a repository, as listed by `git status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    // Change recorded in the index, compared to HEAD
+    pub staged: Option<FileStatus>,
+    // Change of the working tree compared to the index, conflicts are
+    // unstaged until resolved
+    pub unstaged: Option<FileStatus>,
+}
+
+/**
+Status of the files of a repository which aren't clean, by absolute path.
+
+Directories containing changes get the most important status of their
+content. Untracked or ignored directories are listed as a whole by git,
+files below them inherit their status.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitStatus {
+    // Top-level directory of the repository, `None` outside of repositories
+    pub root: Option<PathBuf>,
+    // Commit checked out, `None` before the first commit. It tells when the
+    // committed versions of files changed, ex: after switching branches.
+    pub head: Option<String>,
+    // Branch checked out, `None` when HEAD is detached
+    pub branch: Option<String>,
+    changes: Vec<FileChange>,
+    files: HashMap<PathBuf, FileStatus>,
+    directories: HashMap<PathBuf, FileStatus>,
+}
+
+impl GitStatus {
+    /// Runs `git status` for the repository containing `path`.
+    pub fn load(path: &Path) -> Result<GitStatus, GitError> {
+        let root = match repository_root(path) {
+            Ok(root) => root,
+            Err(GitError::NotARepository) => return Ok(GitStatus::default()),
+            Err(error) => return Err(error),
+        };
+        let output = run_git(
+            &root,
+            &[
+                "status",
+                "--porcelain=v1",
+                "-z",
+                "--ignored",
+                "--untracked-files=normal",
+            ],
+        )?;
+
+        let head = run_git(&root, &["rev-parse", "-q", "--verify", "HEAD"])
+            .ok()
+            .map(|head| String::from_utf8_lossy(&head).trim().to_string());
+        // Also named before the first commit
+        let branch = run_git(&root, &["symbolic-ref", "-q", "--short", "HEAD"])
+            .ok()
+            .map(|branch| String::from_utf8_lossy(&branch).trim().to_string());
+
+        Ok(GitStatus {
+            head,
+            branch,
+            ..GitStatus::parse(&root, &String::from_utf8_lossy(&output))
+        })
+    }
+
+    /// Builds status from the output of `git status --porcelain=v1 -z`.
+    pub fn parse(root: &Path, output: &str) -> GitStatus {
+        let mut status = GitStatus {
+            root: Some(root.to_path_buf()),
+            ..GitStatus::default()
+        };
+
+        let mut records = output.split('\0').filter(|record| !record.is_empty());
+        while let Some(record) = records.next() {
+            let mut chars = record.chars();
+            let (Some(x), Some(y)) = (chars.next(), chars.next()) else {
+                continue;
+            };
+            // Renames and copies are followed by the original path
+            let original = matches!(x, 'R' | 'C').then(|| records.next()).flatten();
+            let Some(path) = record.get(3..) else {
+                continue;
+            };
+
+            status.insert(root, path, FileStatus::from_codes(x, y));
+
+            if (x, y) == ('!', '!') {
+                continue;
+            }
+            let conflicted = FileStatus::from_codes(x, y) == FileStatus::Conflicted;
+            status.changes.push(FileChange {
+                path: root.join(path.trim_end_matches('/')),
+                // Untracked files are `??`, nothing is staged
+                staged: if conflicted || x == '?' {
+                    None
+                } else {
+                    FileStatus::from_code(x)
+                },
+                unstaged: if conflicted {
+                    Some(FileStatus::Conflicted)
+                } else {
+                    FileStatus::from_code(y)
+                },
+            });
+            // The original path of a rename is deleted in the index
+            if let (Some(original), 'R') = (original, x) {
+                status.changes.push(FileChange {
+                    path: root.join(original),
+                    staged: Some(FileStatus::Deleted),
+                    unstaged: None,
+                });
+            }
+        }
+
+        status
+    }
+
+    fn insert(&mut self, root: &Path, path: &str, file_status: FileStatus) {
+        let path = root.join(path.trim_end_matches('/'));
+
+        if file_status != FileStatus::Ignored {
+            for dir in path.ancestors().skip(1) {
+                let rolled_up = self
+                    .directories
+                    .entry(dir.to_path_buf())
+                    .or_insert(file_status);
+                *rolled_up = file_status.max(*rolled_up);
+
+                if dir == root {
+                    break;
+                }
+            }
+        }
+
+        self.files.insert(path, file_status);
+    }
+
+    /// Changed files, in the order listed by git. Ignored files aren't included.
+    pub fn changes(&self) -> &[FileChange] {
+        &self.changes
+    }
+
+    /// Status of the file or directory at `path`, `None` when clean.
+    pub fn status_of(&self, path: &Path) -> Option<FileStatus> {
+        if let Some(status) = self.files.get(path) {
+            return Some(*status);
+        }
+        if let Some(status) = self.directories.get(path) {
+            return Some(*status);
+        }
+
+        // Inside an untracked or ignored directory
+        let root = self.root.as_deref()?;
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root))
+            .find_map(|dir| self.files.get(dir).copied())
+    }
+
+    /// Returns the status currently shown by the UI.
+    pub fn current() -> Arc<GitStatus> {
+        GIT_STATUS.load_full()
+    }
+
+    /// Replaces the status currently shown by the UI.
+    pub fn replace(status: GitStatus) {
+        GIT_STATUS.swap(Arc::new(status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process::Command};
+
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let root = Path::new("/repo");
+        let output = " M src/main.rs\0A  src/new.rs\0R  src/moved.rs\0src/old.rs\0\
+                      UU src/merge.rs\0?? notes/\0!! target/\0 D README.md\0";
+        let status = GitStatus::parse(root, output);
+
+        let status_of = |path: &str| status.status_of(&root.join(path));
+        assert_eq!(status_of("src/main.rs"), Some(FileStatus::Modified));
+        assert_eq!(status_of("src/new.rs"), Some(FileStatus::Added));
+        assert_eq!(status_of("src/moved.rs"), Some(FileStatus::Added));
+        assert_eq!(status_of("src/old.rs"), None);
+        assert_eq!(status_of("src/merge.rs"), Some(FileStatus::Conflicted));
+        assert_eq!(status_of("README.md"), Some(FileStatus::Deleted));
+        assert_eq!(status_of("Cargo.toml"), None);
+
+        // Rolled up from content
+        assert_eq!(status_of("src"), Some(FileStatus::Conflicted));
+        assert_eq!(status.status_of(root), Some(FileStatus::Conflicted));
+
+        // Inherited from directories
+        assert_eq!(status_of("notes/todo.md"), Some(FileStatus::Untracked));
+        assert_eq!(status_of("target/debug/app"), Some(FileStatus::Ignored));
+    }
+
+    #[test]
+    fn changes_test() {
+        let root = Path::new("/repo");
+        let output = "MM src/main.rs\0R  new.rs\0old.rs\0UU merge.rs\0?? notes/\0!! target/\0";
+        let status = GitStatus::parse(root, output);
+
+        let change = |path: &str, staged, unstaged| FileChange {
+            path: root.join(path),
+            staged,
+            unstaged,
+        };
+        assert_eq!(
+            status.changes(),
+            [
+                change(
+                    "src/main.rs",
+                    Some(FileStatus::Modified),
+                    Some(FileStatus::Modified)
+                ),
+                change("new.rs", Some(FileStatus::Added), None),
+                change("old.rs", Some(FileStatus::Deleted), None),
+                change("merge.rs", None, Some(FileStatus::Conflicted)),
+                change("notes", None, Some(FileStatus::Untracked)),
+            ]
+        );
+    }
+
+    #[test]
+    fn roll_up_test() {
+        let root = Path::new("/repo");
+        let status = GitStatus::parse(root, "?? a/b/new.rs\0 M a/b/c/lib.rs\0!! a/out.log\0");
+
+        assert_eq!(
+            status.status_of(&root.join("a/b/c")),
+            Some(FileStatus::Modified)
+        );
+        assert_eq!(
+            status.status_of(&root.join("a/b")),
+            Some(FileStatus::Modified)
+        );
+        // Ignored files don't mark their directory
+        let status = GitStatus::parse(root, "!! a/out.log\0?? b.rs\0");
+        assert_eq!(status.status_of(&root.join("a")), None);
+        assert_eq!(status.status_of(root), Some(FileStatus::Untracked));
+    }
+
+    #[test]
+    fn load_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(root)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "initial"]);
+
+        fs::write(root.join("src/main.rs"), "fn main() { }\n").unwrap();
+        fs::write(root.join("new.rs"), "").unwrap();
+        fs::write(root.join("build.log"), "").unwrap();
+
+        let status = GitStatus::load(&root.join("src")).unwrap();
+        assert_eq!(status.root.as_deref(), Some(root));
+        assert_eq!(
+            status.status_of(&root.join("src/main.rs")),
+            Some(FileStatus::Modified)
+        );
+        assert_eq!(
+            status.status_of(&root.join("src")),
+            Some(FileStatus::Modified)
+        );
+        assert_eq!(
+            status.status_of(&root.join("new.rs")),
+            Some(FileStatus::Untracked)
+        );
+        assert_eq!(
+            status.status_of(&root.join("build.log")),
+            Some(FileStatus::Ignored)
+        );
+        assert_eq!(status.status_of(&root.join(".gitignore")), None);
+        assert_eq!(status.head.map(|head| head.len()), Some(40));
+        assert!(status.branch.is_some());
+
+        let outside = tempfile::tempdir().unwrap();
+        assert_eq!(GitStatus::load(outside.path()), Ok(GitStatus::default()));
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+use crate::workspace::Workspace;
+
+use super::status::GitStatus;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/**
+Keeps the git status of the workspace up to date.
+
+Status is read again on a background thread every few seconds, and right away
+after `refresh`. `callback` is called from that thread when the status
+changed, so it should forward the result to the UI thread (ex: using
+`glib::Sender`).
+*/
+pub struct GitStatusWatcher {
+    wake: Sender<()>,
+}
+
+impl GitStatusWatcher {
+    pub fn watch<F>(callback: F) -> Self
+    where
+        F: Fn(GitStatus) + Send + 'static,
+    {
+        let (wake, woken) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut last_status = None;
+
+            // Stops once the watcher is dropped
+            while !matches!(
+                woken.recv_timeout(POLL_INTERVAL),
+                Err(RecvTimeoutError::Disconnected)
+            ) {
+                // Refreshes requested meanwhile are covered by this one
+                while woken.try_recv().is_ok() {}
+
+                let workspace_path = Workspace::get_path();
+                if workspace_path.is_empty() {
+                    continue;
+                }
+                // Missing `git` is treated like a folder without repository
+                let status = GitStatus::load(Path::new(&workspace_path)).unwrap_or_default();
+                if last_status.as_ref() == Some(&status) {
+                    continue;
+                }
+
+                last_status = Some(status.clone());
+                callback(status);
+            }
+        });
+
+        // Read status of the workspace opened at startup right away
+        wake.send(()).ok();
+
+        GitStatusWatcher { wake }
+    }
+
+    /// Reads status again without waiting for the next poll.
+    pub fn refresh(&self) {
+        self.wake.send(()).ok();
+    }
+}
@@ -0,0 +1,668 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+// Longest supported key sequence, ex: `Ctrl+K Ctrl+C`
+const MAX_SEQUENCE_LENGTH: usize = 2;
+
+/**
+ * Default keybindings, mapping command names to key sequences.
+ *
+ * An empty string leaves a command unbound.
+ */
+pub fn default_keybindings() -> BTreeMap<String, String> {
+    preset_keybindings(KeymapPreset::Default)
+}
+
+/**
+ * Set of keybindings selected with `General.keymap_preset`.
+ */
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Emacs,
+}
+
+impl KeymapPreset {
+    pub const ALL: [KeymapPreset; 2] = [KeymapPreset::Default, KeymapPreset::Emacs];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeymapPreset::Default => "default",
+            KeymapPreset::Emacs => "emacs",
+        }
+    }
+}
+
+/**
+ * Keybindings of a preset, every command known to any preset is listed.
+ */
+pub fn preset_keybindings(preset: KeymapPreset) -> BTreeMap<String, String> {
+    let bindings: &[(&str, &str, &str)] = &[
+        // (command, default, emacs)
+        ("open_workspace", "Ctrl+Shift+O", "Ctrl+Shift+O"),
+        ("open_file", "Ctrl+O", "Ctrl+X Ctrl+F"),
+        ("save_file", "Ctrl+S", "Ctrl+X Ctrl+S"),
+        ("goto_line", "Ctrl+G", "Alt+G G"),
+        ("find", "Ctrl+F", "Ctrl+C F"),
+        ("replace", "Ctrl+H", "Ctrl+C R"),
+        ("find_next", "F3", "F3"),
+        ("find_previous", "Shift+F3", "Shift+F3"),
+        ("find_in_files", "Ctrl+Shift+F", "Ctrl+Shift+F"),
+        ("symbol_search", "Ctrl+T", "Ctrl+C T"),
+        ("show_hover", "Ctrl+K Ctrl+I", "Ctrl+C H"),
+        ("show_completion", "Ctrl+Space", "Alt+/"),
+        ("format_document", "Ctrl+Shift+I", "Ctrl+C Ctrl+F"),
+        ("go_to_definition", "F12", "Alt+."),
+        ("find_references", "Shift+F12", "Shift+F12"),
+        ("navigate_back", "Alt+Left", "Alt+Left"),
+        ("navigate_forward", "Alt+Right", "Alt+Right"),
+        ("toggle_problems", "Ctrl+Shift+M", "Ctrl+C !"),
+        ("run_task", "Ctrl+Shift+B", "Ctrl+C Ctrl+C"),
+        ("rerun_last_task", "Ctrl+Alt+R", "Ctrl+C Ctrl+R"),
+        ("stop_task", "", ""),
+        ("toggle_tasks", "", ""),
+        ("toggle_tests", "", ""),
+        ("run_all_tests", "", "Ctrl+C Ctrl+T"),
+        ("rerun_failed_tests", "", ""),
+        ("start_debugging", "F5", "F5"),
+        ("stop_debugging", "Shift+F5", "Shift+F5"),
+        ("pause_debugging", "", ""),
+        ("step_over", "F10", "F10"),
+        ("step_into", "F11", "F11"),
+        ("step_out", "Shift+F11", "Shift+F11"),
+        ("toggle_breakpoint", "F9", "F9"),
+        ("toggle_debug", "", ""),
+        ("next_change", "Alt+F5", "Alt+F5"),
+        ("previous_change", "Shift+Alt+F5", "Shift+Alt+F5"),
+        ("toggle_source_control", "Ctrl+Shift+G", "Ctrl+X G"),
+        ("compare_with_head", "", ""),
+        ("compare_with_saved", "", ""),
+        ("compare_selection_with_clipboard", "", ""),
+        ("toggle_terminal", "Ctrl+`", "Ctrl+`"),
+        ("new_terminal", "", ""),
+        ("close_terminal", "", ""),
+        ("terminal_copy", "Ctrl+Shift+C", "Ctrl+Shift+C"),
+        ("terminal_paste", "Ctrl+Shift+V", "Ctrl+Shift+V"),
+        ("preferences", "Ctrl+,", "Ctrl+,"),
+        ("command_palette", "Ctrl+Shift+P", "Alt+X"),
+        // Editing commands, mostly useful with Emacs keys
+        ("move_line_start", "", "Ctrl+A"),
+        ("move_line_end", "", "Ctrl+E"),
+        ("forward_char", "", "Ctrl+F"),
+        ("backward_char", "", "Ctrl+B"),
+        ("next_line", "", "Ctrl+N"),
+        ("previous_line", "", "Ctrl+P"),
+        ("set_mark", "", "Ctrl+Space"),
+        ("kill_line", "", "Ctrl+K"),
+        ("kill_region", "", "Ctrl+W"),
+        ("copy_region", "", "Alt+W"),
+        ("yank", "", "Ctrl+Y"),
+        ("yank_pop", "", "Alt+Y"),
+        ("isearch_forward", "", "Ctrl+S"),
+        ("isearch_backward", "", "Ctrl+R"),
+        ("keyboard_quit", "", "Ctrl+G"),
+    ];
+
+    bindings
+        .iter()
+        .map(|(command, default_keys, emacs_keys)| {
+            let keys = match preset {
+                KeymapPreset::Default => default_keys,
+                KeymapPreset::Emacs => emacs_keys,
+            };
+            (command.to_string(), keys.to_string())
+        })
+        .collect()
+}
+
+/**
+ * Returns keybindings of `preset` with the user's customizations applied.
+ *
+ * `[Keybindings]` of config only holds the bindings set by the user, they
+ * replace those of the preset. An empty string unbinds a command.
+ */
+pub fn effective_keybindings(
+    preset: KeymapPreset,
+    user_bindings: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let mut bindings = preset_keybindings(preset);
+    bindings.extend(
+        user_bindings
+            .iter()
+            .map(|(command, keys)| (command.clone(), keys.clone())),
+    );
+
+    bindings
+}
+
+/// Whether `command` can be bound in `[Keybindings]`.
+pub fn is_known_command(command: &str) -> bool {
+    default_keybindings().contains_key(command)
+}
+
+/**
+ * A single key press along with its modifiers, ex: `Ctrl+Shift+O`.
+ *
+ * `key` is a lowercase GDK key name (ex: `o`, `comma`, `f5`, `return`).
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyStroke {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub super_key: bool,
+    pub key: String,
+}
+
+impl KeyStroke {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: normalize_key_name(key),
+            ..Default::default()
+        }
+    }
+
+    /**
+     * Returns the stroke of a key press, `key` being the GDK name of its key.
+     *
+     * Shift is left out for symbols typed with it, ex: GDK reports `!` as
+     * `exclam` with Shift, which is the `!` of keybindings.
+     */
+    pub fn from_key_press(key: &str, ctrl: bool, shift: bool, alt: bool, super_key: bool) -> Self {
+        let mut stroke = Self::new(key);
+        stroke.ctrl = ctrl;
+        stroke.shift = shift && !is_shifted_symbol(&stroke.key);
+        stroke.alt = alt;
+        stroke.super_key = super_key;
+
+        stroke
+    }
+}
+
+impl FromStr for KeyStroke {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(String::from("empty key"));
+        }
+
+        // `Ctrl++` binds the plus key
+        let (modifiers, key) = match s.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => match s.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", s),
+            },
+        };
+
+        if key.is_empty() {
+            return Err(format!("missing key in '{s}'"));
+        }
+
+        let mut stroke = KeyStroke::new(key);
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "primary" => stroke.ctrl = true,
+                "shift" => stroke.shift = true,
+                "alt" | "meta" => stroke.alt = true,
+                "super" | "cmd" => stroke.super_key = true,
+                _ => return Err(format!("unknown modifier '{modifier}' in '{s}'")),
+            }
+        }
+        // `Ctrl+Shift+!` is `Ctrl+!`, see `KeyStroke::from_key_press`
+        if is_shifted_symbol(&stroke.key) {
+            stroke.shift = false;
+        }
+
+        Ok(stroke)
+    }
+}
+
+impl Display for KeyStroke {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.super_key {
+            write!(f, "Super+")?;
+        }
+
+        write!(f, "{}", display_key_name(&self.key))
+    }
+}
+
+/**
+ * Converts user-friendly key names (ex: `,` or `Enter`) to GDK key names.
+ */
+fn normalize_key_name(key: &str) -> String {
+    let name = match key {
+        "," => "comma",
+        "." => "period",
+        "/" => "slash",
+        "\\" => "backslash",
+        ";" => "semicolon",
+        "'" => "apostrophe",
+        "`" => "grave",
+        "-" => "minus",
+        "=" => "equal",
+        "+" => "plus",
+        "[" => "bracketleft",
+        "]" => "bracketright",
+        "!" => "exclam",
+        "@" => "at",
+        "#" => "numbersign",
+        "$" => "dollar",
+        "%" => "percent",
+        "^" => "asciicircum",
+        "&" => "ampersand",
+        "*" => "asterisk",
+        "(" => "parenleft",
+        ")" => "parenright",
+        "_" => "underscore",
+        ":" => "colon",
+        "\"" => "quotedbl",
+        "<" => "less",
+        ">" => "greater",
+        "?" => "question",
+        "{" => "braceleft",
+        "}" => "braceright",
+        "|" => "bar",
+        "~" => "asciitilde",
+        " " => "space",
+        key if key.eq_ignore_ascii_case("enter") => "return",
+        key if key.eq_ignore_ascii_case("esc") => "escape",
+        key if key.eq_ignore_ascii_case("del") => "delete",
+        key if key.eq_ignore_ascii_case("pgup") => "page_up",
+        key if key.eq_ignore_ascii_case("pgdown") => "page_down",
+        key => key,
+    };
+
+    name.to_lowercase()
+}
+
+// Symbols typed with Shift on a US layout, their GDK key names already tell it
+fn is_shifted_symbol(key: &str) -> bool {
+    matches!(
+        key,
+        "exclam"
+            | "at"
+            | "numbersign"
+            | "dollar"
+            | "percent"
+            | "asciicircum"
+            | "ampersand"
+            | "asterisk"
+            | "parenleft"
+            | "parenright"
+            | "underscore"
+            | "plus"
+            | "colon"
+            | "quotedbl"
+            | "less"
+            | "greater"
+            | "question"
+            | "braceleft"
+            | "braceright"
+            | "bar"
+            | "asciitilde"
+    )
+}
+
+fn display_key_name(key: &str) -> String {
+    let name = match key {
+        "comma" => ",",
+        "period" => ".",
+        "slash" => "/",
+        "backslash" => "\\",
+        "semicolon" => ";",
+        "apostrophe" => "'",
+        "grave" => "`",
+        "minus" => "-",
+        "equal" => "=",
+        "plus" => "+",
+        "bracketleft" => "[",
+        "bracketright" => "]",
+        "exclam" => "!",
+        "at" => "@",
+        "numbersign" => "#",
+        "dollar" => "$",
+        "percent" => "%",
+        "asciicircum" => "^",
+        "ampersand" => "&",
+        "asterisk" => "*",
+        "parenleft" => "(",
+        "parenright" => ")",
+        "underscore" => "_",
+        "colon" => ":",
+        "quotedbl" => "\"",
+        "less" => "<",
+        "greater" => ">",
+        "question" => "?",
+        "braceleft" => "{",
+        "braceright" => "}",
+        "bar" => "|",
+        "asciitilde" => "~",
+        key => key,
+    };
+
+    // Single letters and function keys are shown in uppercase, ex: `Ctrl+S` or `F5`
+    let is_function_key =
+        name.len() > 1 && name.starts_with('f') && name[1..].chars().all(|c| c.is_ascii_digit());
+    if name.chars().count() == 1 || is_function_key {
+        return name.to_uppercase();
+    }
+
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/**
+ * Parses a key sequence, ex: `Ctrl+S` or `Ctrl+K Ctrl+C` for a two-key chord.
+ *
+ * An empty string yields an empty sequence, which means the command is unbound.
+ */
+pub fn parse_key_sequence(s: &str) -> Result<Vec<KeyStroke>, String> {
+    let strokes = s
+        .split_whitespace()
+        .map(KeyStroke::from_str)
+        .collect::<Result<Vec<KeyStroke>, String>>()?;
+
+    if strokes.len() > MAX_SEQUENCE_LENGTH {
+        return Err(format!(
+            "'{s}' has more than {MAX_SEQUENCE_LENGTH} keys, only two-key chords are supported"
+        ));
+    }
+
+    Ok(strokes)
+}
+
+pub fn key_sequence_to_string(strokes: &[KeyStroke]) -> String {
+    strokes
+        .iter()
+        .map(KeyStroke::to_string)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/**
+ * Checks keybindings for invalid key sequences and conflicts.
+ *
+ * A conflict is two commands bound to the same keys, or a command bound to
+ * the first key of another command's chord. Returns (command, message) pairs
+ * and reports both commands of a conflict.
+ */
+pub fn check_keybindings(bindings: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut problems = vec![];
+    let mut parsed = vec![];
+
+    for (command, keys) in bindings {
+        match parse_key_sequence(keys) {
+            Ok(strokes) if strokes.is_empty() => {}
+            Ok(strokes) => parsed.push((command, strokes)),
+            Err(message) => problems.push((command.clone(), message)),
+        }
+    }
+
+    for (command, strokes) in parsed.iter() {
+        for (other_command, other_strokes) in parsed.iter() {
+            if command == other_command {
+                continue;
+            }
+
+            if strokes == other_strokes {
+                problems.push((
+                    command.to_string(),
+                    format!(
+                        "'{}' is also bound to '{other_command}'",
+                        key_sequence_to_string(strokes)
+                    ),
+                ));
+            } else if other_strokes.starts_with(strokes) || strokes.starts_with(other_strokes) {
+                problems.push((
+                    command.to_string(),
+                    format!(
+                        "'{}' conflicts with '{}' bound to '{other_command}'",
+                        key_sequence_to_string(strokes),
+                        key_sequence_to_string(other_strokes)
+                    ),
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapMatch {
+    // Keys are bound to a command
+    Command(String),
+    // Keys are the start of a chord, wait for the next key
+    Pending,
+    // Keys aren't bound to anything
+    None,
+}
+
+/**
+ * Resolves key presses to command names.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyStroke>, String)>,
+}
+
+impl Keymap {
+    /**
+     * Builds a keymap from command to key sequence pairs.
+     *
+     * Invalid key sequences are skipped, use `check_keybindings` to report them.
+     */
+    pub fn from_bindings(bindings: &BTreeMap<String, String>) -> Self {
+        let bindings = bindings
+            .iter()
+            .filter_map(|(command, keys)| {
+                parse_key_sequence(keys)
+                    .ok()
+                    .filter(|strokes| !strokes.is_empty())
+                    .map(|strokes| (strokes, command.clone()))
+            })
+            .collect();
+
+        Self { bindings }
+    }
+
+    /**
+     * Looks up the keys pressed so far, `keys` has the previous keys of a chord
+     * followed by the latest one.
+     */
+    pub fn lookup(&self, keys: &[KeyStroke]) -> KeymapMatch {
+        if let Some((_, command)) = self.bindings.iter().find(|(strokes, _)| strokes == keys) {
+            return KeymapMatch::Command(command.clone());
+        }
+
+        if self
+            .bindings
+            .iter()
+            .any(|(strokes, _)| strokes.len() > keys.len() && strokes.starts_with(keys))
+        {
+            return KeymapMatch::Pending;
+        }
+
+        KeymapMatch::None
+    }
+
+    /// Returns the key sequence bound to `command`, ex: `Ctrl+S`.
+    pub fn keys_for(&self, command: &str) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, c)| c == command)
+            .map(|(strokes, _)| key_sequence_to_string(strokes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        check_keybindings, default_keybindings, effective_keybindings, parse_key_sequence,
+        preset_keybindings, KeyStroke, Keymap, KeymapMatch, KeymapPreset,
+    };
+
+    fn bindings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(command, keys)| (command.to_string(), keys.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_key_stroke_test() {
+        let stroke = "Ctrl+Shift+O".parse::<KeyStroke>().unwrap();
+        assert!(stroke.ctrl && stroke.shift && !stroke.alt);
+        assert_eq!(stroke.key, "o");
+        assert_eq!(stroke.to_string(), "Ctrl+Shift+O");
+
+        assert_eq!("Ctrl+,".parse::<KeyStroke>().unwrap().key, "comma");
+        assert_eq!("ctrl+,".parse::<KeyStroke>().unwrap().to_string(), "Ctrl+,");
+        assert_eq!("Ctrl++".parse::<KeyStroke>().unwrap().key, "plus");
+        assert_eq!("F5".parse::<KeyStroke>().unwrap().to_string(), "F5");
+        assert_eq!(
+            "Alt+Enter".parse::<KeyStroke>().unwrap().to_string(),
+            "Alt+Return"
+        );
+
+        assert!("Hyper+S".parse::<KeyStroke>().is_err());
+        assert!("Ctrl+".parse::<KeyStroke>().is_err());
+    }
+
+    #[test]
+    fn parse_key_sequence_test() {
+        let strokes = parse_key_sequence("Ctrl+K Ctrl+C").unwrap();
+        assert_eq!(strokes.len(), 2);
+        assert_eq!(strokes[1], "Ctrl+C".parse().unwrap());
+
+        assert!(parse_key_sequence("").unwrap().is_empty());
+        assert!(parse_key_sequence("Ctrl+K Ctrl+C Ctrl+D").is_err());
+    }
+
+    #[test]
+    fn keymap_lookup_chords() {
+        let keymap = Keymap::from_bindings(&bindings(&[
+            ("save_file", "Ctrl+S"),
+            ("comment", "Ctrl+K Ctrl+C"),
+            ("unbound", ""),
+        ]));
+
+        let ctrl_s = "Ctrl+S".parse::<KeyStroke>().unwrap();
+        let ctrl_k = "Ctrl+K".parse::<KeyStroke>().unwrap();
+        let ctrl_c = "Ctrl+C".parse::<KeyStroke>().unwrap();
+
+        assert_eq!(
+            keymap.lookup(&[ctrl_s]),
+            KeymapMatch::Command("save_file".into())
+        );
+        assert_eq!(
+            keymap.lookup(std::slice::from_ref(&ctrl_k)),
+            KeymapMatch::Pending
+        );
+        assert_eq!(
+            keymap.lookup(&[ctrl_k.clone(), ctrl_c.clone()]),
+            KeymapMatch::Command("comment".into())
+        );
+        assert_eq!(keymap.lookup(&[ctrl_k.clone(), ctrl_k]), KeymapMatch::None);
+        assert_eq!(keymap.lookup(&[ctrl_c]), KeymapMatch::None);
+
+        assert_eq!(keymap.keys_for("comment").as_deref(), Some("Ctrl+K Ctrl+C"));
+        assert_eq!(keymap.keys_for("unbound"), None);
+    }
+
+    #[test]
+    fn keymap_lookup_shifted_symbols() {
+        let keymap = Keymap::from_bindings(&preset_keybindings(KeymapPreset::Emacs));
+
+        // GDK reports `!` as `exclam` with Shift
+        let ctrl_c = KeyStroke::from_key_press("c", true, false, false, false);
+        let exclam = KeyStroke::from_key_press("exclam", false, true, false, false);
+        assert_eq!(exclam.to_string(), "!");
+        assert_eq!(
+            keymap.lookup(&[ctrl_c, exclam]),
+            KeymapMatch::Command("toggle_problems".into())
+        );
+
+        // `+` is typed with Shift on a US layout only
+        let keymap = Keymap::from_bindings(&bindings(&[("zoom_in", "Ctrl++")]));
+        for shift in [false, true] {
+            let ctrl_plus = KeyStroke::from_key_press("plus", true, shift, false, false);
+            assert_eq!(
+                keymap.lookup(&[ctrl_plus]),
+                KeymapMatch::Command("zoom_in".into())
+            );
+        }
+
+        assert_eq!(
+            "Ctrl+Shift+?".parse::<KeyStroke>().unwrap(),
+            "Ctrl+?".parse::<KeyStroke>().unwrap()
+        );
+        // Letters keep Shift
+        let ctrl_shift_m = KeyStroke::from_key_press("m", true, true, false, false);
+        assert_eq!(ctrl_shift_m.to_string(), "Ctrl+Shift+M");
+    }
+
+    #[test]
+    fn check_keybindings_test() {
+        assert!(check_keybindings(&default_keybindings()).is_empty());
+
+        let problems = check_keybindings(&bindings(&[
+            ("save_file", "Ctrl+S"),
+            ("save_all", "ctrl+s"),
+            ("comment", "Ctrl+K Ctrl+C"),
+            ("kill", "Ctrl+K"),
+            ("broken", "Ctrl+Foo+S"),
+            ("unbound", ""),
+        ]));
+        let commands: Vec<&str> = problems.iter().map(|(c, _)| c.as_str()).collect();
+
+        assert_eq!(
+            commands,
+            vec!["broken", "comment", "kill", "save_all", "save_file"]
+        );
+    }
+
+    #[test]
+    fn keymap_presets_test() {
+        for preset in KeymapPreset::ALL {
+            assert!(check_keybindings(&preset_keybindings(preset)).is_empty());
+        }
+
+        // User bindings replace those of the preset, even default keys
+        let user_bindings = bindings(&[
+            ("find_in_files", "Ctrl+Alt+F"),
+            ("find", "Ctrl+F"),
+            ("forward_char", ""),
+        ]);
+        let bindings = effective_keybindings(KeymapPreset::Emacs, &user_bindings);
+
+        assert_eq!(bindings["save_file"], "Ctrl+X Ctrl+S");
+        assert_eq!(bindings["isearch_forward"], "Ctrl+S");
+        assert_eq!(bindings["find_in_files"], "Ctrl+Alt+F");
+        assert_eq!(bindings["find"], "Ctrl+F");
+        // Unbound, `Ctrl+F` doesn't conflict with `find`
+        assert_eq!(bindings["forward_char"], "");
+        assert!(check_keybindings(&bindings).is_empty());
+    }
+}
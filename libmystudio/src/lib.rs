@@ -1,6 +1,24 @@
+pub mod compare;
+pub mod completion;
+pub mod debugger;
+pub mod diagnostics;
+pub mod diff;
+pub mod emacs;
 pub mod encoding;
+pub mod format;
 pub mod fs;
+pub mod fuzzy;
+pub mod git;
+pub mod keymap;
+pub mod lsp;
+pub mod navigation;
 pub mod notebook;
+pub mod outline;
+pub mod symbols;
+pub mod tasks;
+pub mod terminal;
+pub mod test_explorer;
 pub mod tree;
+pub mod vim;
 pub mod workspace;
 pub mod app_config;
\ No newline at end of file
@@ -0,0 +1,482 @@
+use std::{
+    collections::HashMap,
+    io::BufReader,
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+
+use super::{
+    error::LspError,
+    jsonrpc::{read_message, write_message, Message, ResponseError, METHOD_NOT_FOUND},
+    path_to_uri,
+    types::{CompletionItem, Diagnostic, DocumentSymbol, Hover, Location, LspEvent, Position},
+    uri_to_path, LanguageServerConfig,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+// Time given to a server to exit after `exit`, it is killed afterwards
+const EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+type PendingRequests = Mutex<HashMap<u64, mpsc::Sender<Result<Value, LspError>>>>;
+
+/**
+ * Writing half of a server connection, shared with the thread reading
+ * server messages.
+ */
+struct Connection {
+    stdin: Mutex<ChildStdin>,
+    pending: PendingRequests,
+    // Set under `pending` lock once the server stopped sending messages
+    closed: AtomicBool,
+}
+
+impl Connection {
+    fn send(&self, message: &Message) -> Result<(), LspError> {
+        let mut stdin = self.stdin.lock().unwrap();
+        write_message(&mut *stdin, message).map_err(|_| LspError::Exited)
+    }
+}
+
+/**
+A running language server, spoken to over stdio.
+
+Requests block until the server answers or the timeout passes. Notifications
+sent by the server (ex: diagnostics) are passed to `on_event` from a
+background thread.
+*/
+pub struct LspClient {
+    language: String,
+    process: Mutex<Child>,
+    connection: Arc<Connection>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    // `ServerCapabilities` from the `initialize` response
+    capabilities: Value,
+    // Open documents and their version
+    documents: Mutex<HashMap<String, i32>>,
+}
+
+impl LspClient {
+    /**
+     * Spawns the server configured for `language` and initializes it for a
+     * workspace at `root_path`.
+     */
+    pub fn start<F>(
+        language: &str,
+        config: &LanguageServerConfig,
+        root_path: &str,
+        on_event: F,
+    ) -> Result<LspClient, LspError>
+    where
+        F: Fn(LspEvent) + Send + 'static,
+    {
+        let mut command = Command::new(&config.command);
+        // Files opened without a workspace have no root
+        if !root_path.is_empty() {
+            command.current_dir(root_path);
+        }
+
+        let mut process = command
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| {
+                LspError::Io(format!("unable to run '{}': {error}", config.command))
+            })?;
+
+        let connection = Arc::new(Connection {
+            stdin: Mutex::new(process.stdin.take().unwrap()),
+            pending: Mutex::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+        });
+
+        let stdout = process.stdout.take().unwrap();
+        let reader_connection = connection.clone();
+        let reader_language = language.to_string();
+        thread::spawn(move || {
+            read_messages(stdout, &reader_connection, &on_event);
+            on_event(LspEvent::Exited(reader_language));
+        });
+
+        let mut client = LspClient {
+            language: language.to_string(),
+            process: Mutex::new(process),
+            connection,
+            next_id: AtomicU64::new(1),
+            timeout: DEFAULT_TIMEOUT,
+            capabilities: Value::Null,
+            documents: Mutex::new(HashMap::new()),
+        };
+
+        let result = client.request("initialize", initialize_params(root_path));
+        let result = match result {
+            Ok(result) => result,
+            Err(error) => {
+                client.kill();
+                return Err(error);
+            }
+        };
+        client.capabilities = result["capabilities"].clone();
+        client.notify("initialized", json!({}))?;
+
+        Ok(client)
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Checks a `ServerCapabilities` field, ex: `hoverProvider`.
+    pub fn supports(&self, capability: &str) -> bool {
+        match self.capabilities.get(capability) {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+            Some(_) => true,
+        }
+    }
+
+    /**
+     * Sends a request and waits for its result.
+     */
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, LspError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+
+        {
+            let mut pending = self.connection.pending.lock().unwrap();
+            if self.connection.closed.load(Ordering::SeqCst) {
+                return Err(LspError::Exited);
+            }
+            pending.insert(id, tx);
+        }
+        self.connection
+            .send(&Message::request(id, method, params))?;
+
+        match rx.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.connection.pending.lock().unwrap().remove(&id);
+                self.notify("$/cancelRequest", json!({ "id": id })).ok();
+                Err(LspError::Timeout(method.to_string()))
+            }
+            // Reader thread stopped
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(LspError::Exited),
+        }
+    }
+
+    pub fn notify(&self, method: &str, params: Value) -> Result<(), LspError> {
+        self.connection.send(&Message::notification(method, params))
+    }
+
+    pub fn did_open(&self, path: &str, language_id: &str, text: &str) -> Result<(), LspError> {
+        let uri = path_to_uri(path);
+
+        // Already open, ex: the file was opened again after a reload
+        if self.documents.lock().unwrap().contains_key(&uri) {
+            return self.did_change(path, text);
+        }
+        self.documents.lock().unwrap().insert(uri.clone(), 1);
+
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": language_id,
+                    "version": 1,
+                    "text": text,
+                }
+            }),
+        )
+    }
+
+    /// Sends the whole new text of a document.
+    pub fn did_change(&self, path: &str, text: &str) -> Result<(), LspError> {
+        let uri = path_to_uri(path);
+
+        let version = {
+            let mut documents = self.documents.lock().unwrap();
+            let Some(version) = documents.get_mut(&uri) else {
+                return Ok(());
+            };
+            *version += 1;
+            *version
+        };
+
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        )
+    }
+
+    pub fn did_save(&self, path: &str) -> Result<(), LspError> {
+        let uri = path_to_uri(path);
+        if !self.documents.lock().unwrap().contains_key(&uri) {
+            return Ok(());
+        }
+
+        self.notify(
+            "textDocument/didSave",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+    }
+
+    pub fn did_close(&self, path: &str) -> Result<(), LspError> {
+        let uri = path_to_uri(path);
+        if self.documents.lock().unwrap().remove(&uri).is_none() {
+            return Ok(());
+        }
+
+        self.notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": uri } }),
+        )
+    }
+
+    pub fn hover(&self, path: &str, position: Position) -> Result<Option<Hover>, LspError> {
+        if !self.supports("hoverProvider") {
+            return Ok(None);
+        }
+
+        let result = self.request("textDocument/hover", position_params(path, position))?;
+        Ok(Hover::from_value(result))
+    }
+
+    pub fn completion(
+        &self,
+        path: &str,
+        position: Position,
+    ) -> Result<Vec<CompletionItem>, LspError> {
+        if !self.supports("completionProvider") {
+            return Ok(vec![]);
+        }
+
+        let result = self.request("textDocument/completion", position_params(path, position))?;
+        Ok(CompletionItem::list_from_value(result))
+    }
+
+    pub fn definition(&self, path: &str, position: Position) -> Result<Vec<Location>, LspError> {
+        if !self.supports("definitionProvider") {
+            return Ok(vec![]);
+        }
+
+        let result = self.request("textDocument/definition", position_params(path, position))?;
+        Ok(Location::list_from_value(result))
+    }
+
+    pub fn references(
+        &self,
+        path: &str,
+        position: Position,
+        include_declaration: bool,
+    ) -> Result<Vec<Location>, LspError> {
+        if !self.supports("referencesProvider") {
+            return Ok(vec![]);
+        }
+
+        let mut params = position_params(path, position);
+        params["context"] = json!({ "includeDeclaration": include_declaration });
+
+        let result = self.request("textDocument/references", params)?;
+        Ok(Location::list_from_value(result))
+    }
+
+    pub fn document_symbols(&self, path: &str) -> Result<Vec<DocumentSymbol>, LspError> {
+        if !self.supports("documentSymbolProvider") {
+            return Ok(vec![]);
+        }
+
+        let result = self.request(
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        )?;
+        Ok(DocumentSymbol::list_from_value(result))
+    }
+
+    /**
+     * Asks the server to exit, it is killed if it doesn't.
+     */
+    pub fn shutdown(&self) -> Result<(), LspError> {
+        let result = self
+            .request("shutdown", Value::Null)
+            .and_then(|_| self.notify("exit", Value::Null));
+
+        let deadline = Instant::now() + EXIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if self.has_exited() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        self.kill();
+        result
+    }
+
+    /// The server is still connected, it may have exited otherwise.
+    pub fn is_running(&self) -> bool {
+        !self.connection.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn has_exited(&self) -> bool {
+        let mut process = self.process.lock().unwrap();
+        !matches!(process.try_wait(), Ok(None))
+    }
+
+    fn kill(&self) {
+        let mut process = self.process.lock().unwrap();
+        process.kill().ok();
+        process.wait().ok();
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        if !self.has_exited() {
+            self.kill();
+        }
+    }
+}
+
+fn read_messages<F>(stdout: ChildStdout, connection: &Connection, on_event: &F)
+where
+    F: Fn(LspEvent),
+{
+    let mut reader = BufReader::new(stdout);
+
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(error) => {
+                eprintln!("Unable to read language server message: {error}");
+                break;
+            }
+        };
+
+        match message {
+            Message::Response { id, result } => {
+                let sender = id
+                    .as_u64()
+                    .and_then(|id| connection.pending.lock().unwrap().remove(&id));
+
+                if let Some(sender) = sender {
+                    let result = result.map_err(|error| LspError::Server {
+                        code: error.code,
+                        message: error.message,
+                    });
+                    sender.send(result).ok();
+                }
+            }
+            Message::Notification { method, params } => {
+                if let Some(event) = event_from_notification(&method, params) {
+                    on_event(event);
+                }
+            }
+            Message::Request { id, method, params } => {
+                let result = respond_to_request(&method, &params);
+                connection.send(&Message::Response { id, result }).ok();
+            }
+        }
+    }
+
+    // Fail requests still waiting for an answer
+    let mut pending = connection.pending.lock().unwrap();
+    connection.closed.store(true, Ordering::SeqCst);
+    pending.clear();
+}
+
+fn event_from_notification(method: &str, params: Value) -> Option<LspEvent> {
+    match method {
+        "textDocument/publishDiagnostics" => {
+            let path = uri_to_path(params["uri"].as_str()?)?;
+            let diagnostics =
+                serde_json::from_value::<Vec<Diagnostic>>(params["diagnostics"].clone())
+                    .unwrap_or_default();
+
+            Some(LspEvent::Diagnostics { path, diagnostics })
+        }
+        "window/showMessage" => Some(LspEvent::ShowMessage(
+            params["message"].as_str()?.to_string(),
+        )),
+        _ => None,
+    }
+}
+
+/**
+ * Answers requests sent by the server, the IDE has no client side settings
+ * or dynamic registrations yet.
+ */
+fn respond_to_request(method: &str, params: &Value) -> Result<Value, ResponseError> {
+    match method {
+        // One (empty) setting per requested item
+        "workspace/configuration" => {
+            let items = params["items"].as_array().map_or(0, Vec::len);
+            Ok(Value::Array(vec![Value::Null; items]))
+        }
+        "client/registerCapability"
+        | "client/unregisterCapability"
+        | "window/workDoneProgress/create" => Ok(Value::Null),
+        _ => Err(ResponseError {
+            code: METHOD_NOT_FOUND,
+            message: format!("unsupported request '{method}'"),
+        }),
+    }
+}
+
+fn initialize_params(root_path: &str) -> Value {
+    let (root_uri, workspace_folders) = if root_path.is_empty() {
+        (Value::Null, Value::Null)
+    } else {
+        let uri = path_to_uri(root_path);
+        let name = std::path::Path::new(root_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        (json!(uri), json!([{ "uri": uri, "name": name }]))
+    };
+
+    json!({
+        "processId": std::process::id(),
+        "clientInfo": { "name": "MyStudio IDE", "version": env!("CARGO_PKG_VERSION") },
+        "rootUri": root_uri,
+        "workspaceFolders": workspace_folders,
+        "capabilities": {
+            "textDocument": {
+                "synchronization": { "didSave": true },
+                "hover": { "contentFormat": ["markdown", "plaintext"] },
+                "completion": {
+                    "completionItem": { "documentationFormat": ["markdown", "plaintext"] }
+                },
+                "definition": { "linkSupport": true },
+                "references": {},
+                "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+                "publishDiagnostics": {},
+            },
+            "workspace": { "configuration": true, "workspaceFolders": true },
+        },
+    })
+}
+
+fn position_params(path: &str, position: Position) -> Value {
+    json!({
+        "textDocument": { "uri": path_to_uri(path) },
+        "position": position,
+    })
+}
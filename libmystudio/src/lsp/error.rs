@@ -0,0 +1,42 @@
+use std::fmt::Display;
+
+/// A failed exchange with a language server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LspError {
+    // Server could not be spawned, or reading/writing its pipes failed
+    Io(String),
+    // Message is not valid JSON-RPC
+    Protocol(String),
+    // Server answered a request with an error
+    Server { code: i64, message: String },
+    // No response to a request within the timeout
+    Timeout(String),
+    // Server process is gone
+    Exited,
+}
+
+impl Display for LspError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LspError::Io(message) => write!(f, "I/O error: {message}"),
+            LspError::Protocol(message) => write!(f, "protocol error: {message}"),
+            LspError::Server { code, message } => write!(f, "server error {code}: {message}"),
+            LspError::Timeout(method) => write!(f, "request '{method}' timed out"),
+            LspError::Exited => write!(f, "language server exited"),
+        }
+    }
+}
+
+impl std::error::Error for LspError {}
+
+impl From<std::io::Error> for LspError {
+    fn from(error: std::io::Error) -> Self {
+        LspError::Io(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for LspError {
+    fn from(error: serde_json::Error) -> Self {
+        LspError::Protocol(error.to_string())
+    }
+}
@@ -0,0 +1,221 @@
+// JSON-RPC 2.0 messages framed by `Content-Length` headers, as sent over stdio by LSP
+
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Map, Value};
+
+use super::error::LspError;
+
+// Error codes defined by JSON-RPC
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Request {
+        id: Value,
+        method: String,
+        params: Value,
+    },
+    Notification {
+        method: String,
+        params: Value,
+    },
+    Response {
+        id: Value,
+        result: Result<Value, ResponseError>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl Message {
+    pub fn request(id: u64, method: &str, params: Value) -> Self {
+        Message::Request {
+            id: Value::from(id),
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    pub fn notification(method: &str, params: Value) -> Self {
+        Message::Notification {
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("jsonrpc".into(), json!("2.0"));
+
+        match self {
+            Message::Request { id, method, params } => {
+                object.insert("id".into(), id.clone());
+                object.insert("method".into(), json!(method));
+                object.insert("params".into(), params.clone());
+            }
+            Message::Notification { method, params } => {
+                object.insert("method".into(), json!(method));
+                object.insert("params".into(), params.clone());
+            }
+            Message::Response { id, result } => {
+                object.insert("id".into(), id.clone());
+                match result {
+                    Ok(result) => object.insert("result".into(), result.clone()),
+                    Err(error) => object.insert(
+                        "error".into(),
+                        json!({ "code": error.code, "message": error.message }),
+                    ),
+                };
+            }
+        }
+
+        Value::Object(object)
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, LspError> {
+        let Value::Object(mut object) = value else {
+            return Err(LspError::Protocol(String::from("message is not an object")));
+        };
+
+        let id = object.remove("id");
+        let params = object.remove("params").unwrap_or(Value::Null);
+
+        if let Some(method) = object.get("method").and_then(Value::as_str) {
+            let method = method.to_string();
+            return Ok(match id {
+                Some(id) => Message::Request { id, method, params },
+                None => Message::Notification { method, params },
+            });
+        }
+
+        let id = id.ok_or_else(|| LspError::Protocol(String::from("response without id")))?;
+        let result = match object.remove("error") {
+            Some(error) => Err(ResponseError {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            }),
+            None => Ok(object.remove("result").unwrap_or(Value::Null)),
+        };
+
+        Ok(Message::Response { id, result })
+    }
+}
+
+/**
+ * Reads the next message, returns `None` once the stream is closed.
+ */
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Message>, LspError> {
+    let Some(content) = read_frame(reader)? else {
+        return Ok(None);
+    };
+
+    let value = serde_json::from_slice::<Value>(&content)?;
+    Message::from_value(value).map(Some)
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Message) -> Result<(), LspError> {
+    write_frame(writer, &message.to_value().to_string())
+}
+
+/**
+ * Reads the content of the next message framed by headers, returns `None`
+ * once the stream is closed.
+ *
+ * Also used by the Debug Adapter Protocol, which frames messages the same way.
+ */
+pub fn read_frame(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>, LspError> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        // Headers end with an empty line
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length
+        .ok_or_else(|| LspError::Protocol(String::from("missing Content-Length header")))?;
+
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+
+    Ok(Some(content))
+}
+
+pub fn write_frame(writer: &mut impl Write, content: &str) -> Result<(), LspError> {
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use serde_json::json;
+
+    use super::{read_message, write_message, Message, ResponseError};
+
+    #[test]
+    fn message_round_trip_test() {
+        let messages = [
+            Message::request(1, "initialize", json!({ "rootUri": null })),
+            Message::notification("initialized", json!({})),
+            Message::Response {
+                id: json!(2),
+                result: Ok(json!(["ünïcode"])),
+            },
+            Message::Response {
+                id: json!("3"),
+                result: Err(ResponseError {
+                    code: -32601,
+                    message: String::from("unknown method"),
+                }),
+            },
+        ];
+
+        let mut stream = vec![];
+        for message in messages.iter() {
+            write_message(&mut stream, message).unwrap();
+        }
+
+        let mut reader = BufReader::new(stream.as_slice());
+        for message in messages {
+            assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        }
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn read_message_headers_test() {
+        let content = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let stream = format!(
+            "content-length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{content}",
+            content.len()
+        );
+
+        let message = read_message(&mut BufReader::new(stream.as_bytes())).unwrap();
+        assert_eq!(message, Some(Message::notification("exit", json!(null))));
+
+        let missing_length = "Content-Type: text/plain\r\n\r\n{}";
+        assert!(read_message(&mut BufReader::new(missing_length.as_bytes())).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+// Language Server Protocol client, servers are configured per language in
+// `[LanguageServers]` of config.toml and started when a file of their language is opened
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use self::{client::LspClient, error::LspError, types::LspEvent};
+
+pub mod client;
+pub mod error;
+pub mod jsonrpc;
+pub mod types;
+pub mod worker;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct LanguageServerConfig {
+    pub enabled: bool,
+    // Program to run, looked up in `PATH`
+    pub command: String,
+    pub args: Vec<String>,
+    // File extensions handled by the server, without the dot
+    pub extensions: Vec<String>,
+}
+
+impl Default for LanguageServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            command: String::new(),
+            args: vec![],
+            extensions: vec![],
+        }
+    }
+}
+
+impl LanguageServerConfig {
+    fn new(command: &str, args: &[&str], extensions: &[&str]) -> Self {
+        Self {
+            enabled: true,
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+        }
+    }
+}
+
+/// Servers known out of the box, keyed by language.
+pub fn default_language_servers() -> BTreeMap<String, LanguageServerConfig> {
+    BTreeMap::from([
+        (
+            String::from("rust"),
+            LanguageServerConfig::new("rust-analyzer", &[], &["rs"]),
+        ),
+        (
+            String::from("python"),
+            LanguageServerConfig::new("pyright-langserver", &["--stdio"], &["py"]),
+        ),
+        (
+            String::from("typescript"),
+            LanguageServerConfig::new(
+                "typescript-language-server",
+                &["--stdio"],
+                &["ts", "tsx", "js", "jsx"],
+            ),
+        ),
+    ])
+}
+
+/**
+ * Returns the `languageId` of a document sent to servers, which is more
+ * specific than the language of the server for some extensions.
+ */
+pub fn language_id_for_path(path: &str, language: &str) -> String {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    let language_id = match extension {
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" => "javascript",
+        "jsx" => "javascriptreact",
+        _ => language,
+    };
+
+    language_id.to_string()
+}
+
+/**
+ * Converts an absolute path to a `file://` URI.
+ */
+pub fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::from("file://");
+
+    // Windows paths start with a drive letter, ex: `C:/`
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    uri
+}
+
+/**
+ * Converts a `file://` URI to a path, returns `None` for other schemes.
+ */
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let encoded = uri.strip_prefix("file://")?;
+
+    let mut bytes = vec![];
+    let mut input = encoded.bytes();
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let hex = [input.next()?, input.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+
+    // Drop the slash before a Windows drive letter
+    let is_drive_path = path.len() > 2 && path.as_bytes()[2] == b':';
+    if is_drive_path {
+        return Some(path[1..].to_string());
+    }
+
+    Some(path)
+}
+
+/**
+Starts and keeps track of language servers of a workspace.
+
+Events of all servers are passed to `on_event` from a background thread.
+*/
+pub struct LspManager {
+    root_path: String,
+    servers: BTreeMap<String, LanguageServerConfig>,
+    clients: HashMap<String, Arc<LspClient>>,
+    // Languages whose server failed to start, they aren't retried
+    failed: HashSet<String>,
+    events: mpsc::Sender<LspEvent>,
+}
+
+impl LspManager {
+    pub fn new<F>(
+        root_path: &str,
+        servers: BTreeMap<String, LanguageServerConfig>,
+        on_event: F,
+    ) -> Self
+    where
+        F: Fn(LspEvent) + Send + 'static,
+    {
+        let (events, events_rx) = mpsc::channel();
+
+        // Ends once the manager and all its clients are dropped
+        thread::spawn(move || {
+            for event in events_rx {
+                on_event(event);
+            }
+        });
+
+        Self {
+            root_path: root_path.to_string(),
+            servers,
+            clients: HashMap::new(),
+            failed: HashSet::new(),
+            events,
+        }
+    }
+
+    /// Returns the language whose server handles a file.
+    pub fn language_for_path(&self, path: &str) -> Option<&str> {
+        let extension = Path::new(path).extension()?.to_str()?;
+
+        self.servers
+            .iter()
+            .find(|(_, config)| {
+                config.enabled && config.extensions.iter().any(|ext| ext == extension)
+            })
+            .map(|(language, _)| language.as_str())
+    }
+
+    /**
+     * Returns the server handling a file, starting it if needed.
+     *
+     * Returns `Ok(None)` when no server is configured for the file, or when
+     * starting it failed before.
+     */
+    pub fn client_for_path(&mut self, path: &str) -> Result<Option<Arc<LspClient>>, LspError> {
+        let Some(language) = self.language_for_path(path).map(String::from) else {
+            return Ok(None);
+        };
+
+        if let Some(client) = self.clients.get(&language) {
+            return Ok(Some(client.clone()));
+        }
+        if self.failed.contains(&language) {
+            return Ok(None);
+        }
+
+        let events = self.events.clone();
+        let result = LspClient::start(
+            &language,
+            &self.servers[&language],
+            &self.root_path,
+            move |event| {
+                events.send(event).ok();
+            },
+        );
+
+        match result {
+            Ok(client) => {
+                let client = Arc::new(client);
+                self.clients.insert(language, client.clone());
+                Ok(Some(client))
+            }
+            Err(error) => {
+                self.failed.insert(language);
+                Err(error)
+            }
+        }
+    }
+
+    /// Returns the running server of a language.
+    pub fn client(&self, language: &str) -> Option<Arc<LspClient>> {
+        self.clients.get(language).cloned()
+    }
+
+    /// Returns the server handling a file if it is already running.
+    pub fn running_client(&self, path: &str) -> Option<Arc<LspClient>> {
+        self.client(self.language_for_path(path)?)
+    }
+
+    /// Passes an event to `on_event` along with those of servers.
+    pub fn send_event(&self, event: LspEvent) {
+        self.events.send(event).ok();
+    }
+
+    pub(crate) fn event_sender(&self) -> mpsc::Sender<LspEvent> {
+        self.events.clone()
+    }
+
+    /**
+     * Forgets the server of a language after it exited, it is not restarted
+     * automatically.
+     */
+    pub fn remove(&mut self, language: &str) {
+        self.clients.remove(language);
+        self.failed.insert(language.to_string());
+    }
+
+    pub fn shutdown_all(&mut self) {
+        for (_, client) in self.clients.drain() {
+            if let Err(error) = client.shutdown() {
+                eprintln!("Unable to shut down {} server: {error}", client.language());
+            }
+        }
+    }
+}
+
+impl Drop for LspManager {
+    fn drop(&mut self) {
+        self.shutdown_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        default_language_servers, language_id_for_path, path_to_uri, uri_to_path, LspManager,
+    };
+
+    #[test]
+    fn uri_test() {
+        assert_eq!(
+            path_to_uri("/home/me/a b#1.rs"),
+            "file:///home/me/a%20b%231.rs"
+        );
+        assert_eq!(path_to_uri("C:\\src\\main.rs"), "file:///C:/src/main.rs");
+
+        assert_eq!(
+            uri_to_path("file:///home/me/a%20b%231.rs").as_deref(),
+            Some("/home/me/a b#1.rs")
+        );
+        assert_eq!(
+            uri_to_path("file:///C:/src/main.rs").as_deref(),
+            Some("C:/src/main.rs")
+        );
+        assert_eq!(
+            uri_to_path(&path_to_uri("/tmp/ünï.rs")).as_deref(),
+            Some("/tmp/ünï.rs")
+        );
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn language_for_path_test() {
+        let mut servers = default_language_servers();
+        servers.get_mut("python").unwrap().enabled = false;
+        let manager = LspManager::new("/tmp", servers, |_| {});
+
+        assert_eq!(manager.language_for_path("/src/main.rs"), Some("rust"));
+        assert_eq!(
+            manager.language_for_path("/src/app.tsx"),
+            Some("typescript")
+        );
+        assert_eq!(manager.language_for_path("/src/app.py"), None);
+        assert_eq!(manager.language_for_path("/src/Makefile"), None);
+
+        assert_eq!(
+            language_id_for_path("/src/app.tsx", "typescript"),
+            "typescriptreact"
+        );
+        assert_eq!(language_id_for_path("/src/main.rs", "rust"), "rust");
+    }
+}
@@ -0,0 +1,385 @@
+// Subset of LSP structures used by the IDE
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::{error::LspError, uri_to_path};
+
+/**
+ * Zero-based position in a document.
+ *
+ * `character` counts UTF-16 code units, which is what servers expect unless
+ * told otherwise.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Position {
+    /// Builds a position from a column counted in chars, like GTK does.
+    pub fn from_char_column(line: u32, line_text: &str, column: usize) -> Self {
+        let character = line_text
+            .chars()
+            .take(column)
+            .map(char::len_utf16)
+            .sum::<usize>();
+
+        Self {
+            line,
+            character: character as u32,
+        }
+    }
+
+    /// Returns the column of this position in chars.
+    pub fn char_column(&self, line_text: &str) -> usize {
+        let mut units = 0;
+
+        line_text
+            .chars()
+            .take_while(|c| {
+                units += c.len_utf16();
+                units <= self.character as usize
+            })
+            .count()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "u8")]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+impl TryFrom<u8> for DiagnosticSeverity {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, String> {
+        match value {
+            1 => Ok(DiagnosticSeverity::Error),
+            2 => Ok(DiagnosticSeverity::Warning),
+            3 => Ok(DiagnosticSeverity::Information),
+            4 => Ok(DiagnosticSeverity::Hint),
+            _ => Err(format!("invalid diagnostic severity {value}")),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    // Servers may leave it out, it is then shown as an error
+    pub severity: Option<DiagnosticSeverity>,
+    pub message: String,
+    pub source: Option<String>,
+    pub code: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hover {
+    // Markdown or plain text
+    pub contents: String,
+    pub range: Option<Range>,
+}
+
+impl Hover {
+    pub(crate) fn from_value(value: Value) -> Option<Self> {
+        let contents = markup_text(value.get("contents")?);
+        if contents.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            contents,
+            range: value
+                .get("range")
+                .and_then(|range| serde_json::from_value(range.clone()).ok()),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionItem {
+    pub label: String,
+    // `CompletionItemKind` number, ex: 3 for functions
+    pub kind: Option<u32>,
+    pub detail: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_markup")]
+    pub documentation: Option<String>,
+    pub insert_text: Option<String>,
+    pub filter_text: Option<String>,
+    pub sort_text: Option<String>,
+}
+
+impl CompletionItem {
+    /// Text inserted when the item is accepted.
+    pub fn text(&self) -> &str {
+        self.insert_text.as_deref().unwrap_or(&self.label)
+    }
+
+    /// Parses a `CompletionItem[]` or `CompletionList` result.
+    pub(crate) fn list_from_value(value: Value) -> Vec<Self> {
+        let items = match value {
+            Value::Object(mut list) => list.remove("items").unwrap_or_default(),
+            items => items,
+        };
+
+        serde_json::from_value::<Vec<Value>>(items)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect()
+    }
+}
+
+/// A range in a file, ex: where a symbol is defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: String,
+    pub range: Range,
+}
+
+impl Location {
+    /// Parses a `Location`, `Location[]` or `LocationLink[]` result.
+    pub(crate) fn list_from_value(value: Value) -> Vec<Self> {
+        let values = match value {
+            Value::Array(values) => values,
+            Value::Null => vec![],
+            value => vec![value],
+        };
+
+        values
+            .iter()
+            .filter_map(|value| {
+                // LocationLink points at the symbol name with `targetSelectionRange`
+                let (uri, range) = match value.get("targetUri") {
+                    Some(uri) => (uri, value.get("targetSelectionRange")?),
+                    None => (value.get("uri")?, value.get("range")?),
+                };
+
+                Some(Self {
+                    path: uri_to_path(uri.as_str()?)?,
+                    range: serde_json::from_value(range.clone()).ok()?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A symbol of a document, ex: a struct with its fields as children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub detail: Option<String>,
+    // `SymbolKind` number, ex: 12 for functions
+    pub kind: u32,
+    // Whole definition, `selection_range` is the name in it
+    pub range: Range,
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+impl DocumentSymbol {
+    /// Parses a `DocumentSymbol[]` or `SymbolInformation[]` result, the latter is flat.
+    pub(crate) fn list_from_value(value: Value) -> Vec<Self> {
+        match value {
+            Value::Array(values) => values.iter().filter_map(Self::from_value).collect(),
+            _ => vec![],
+        }
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let range_at = |key: &str, value: &Value| -> Option<Range> {
+            serde_json::from_value(value.get(key)?.clone()).ok()
+        };
+
+        // SymbolInformation only has the location of the whole definition
+        let (range, selection_range) = match value.get("location") {
+            Some(location) => {
+                let range = range_at("range", location)?;
+                (range, range)
+            }
+            None => {
+                let range = range_at("range", value)?;
+                (range, range_at("selectionRange", value).unwrap_or(range))
+            }
+        };
+
+        Some(Self {
+            name: value.get("name")?.as_str()?.to_string(),
+            detail: value
+                .get("detail")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            kind: value.get("kind")?.as_u64()? as u32,
+            range,
+            selection_range,
+            children: value
+                .get("children")
+                .and_then(Value::as_array)
+                .map(|children| children.iter().filter_map(Self::from_value).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Something the UI should know about, sent by a server on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LspEvent {
+    // Replaces all diagnostics of a file
+    Diagnostics {
+        path: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    // `window/showMessage`
+    ShowMessage(String),
+    // Server of a language stopped, ex: it crashed
+    Exited(String),
+    // Answer to a request sent with `LspWorker::request`, by request id
+    Response {
+        id: u64,
+        result: Result<LspResponse, LspError>,
+    },
+}
+
+/// A request about a document, see `LspWorker::request`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LspRequest {
+    Hover(Position),
+    Completion(Position),
+    Definition(Position),
+    // References of the symbol at a position, its declaration included
+    References(Position),
+    DocumentSymbols,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LspResponse {
+    Hover(Option<Hover>),
+    Completion(Vec<CompletionItem>),
+    Definition(Vec<Location>),
+    References(Vec<Location>),
+    DocumentSymbols(Vec<DocumentSymbol>),
+    // No running server handles the document, or its server can't answer
+    Unsupported,
+}
+
+/**
+ * Returns the text of a `MarkupContent`, `MarkedString` or an array of
+ * `MarkedString`s.
+ */
+pub(crate) fn markup_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(markup_text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        Value::Object(object) => {
+            let text = object
+                .get("value")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            // MarkedString with a language is a code block
+            match object.get("language").and_then(Value::as_str) {
+                Some(language) => format!("```{language}\n{text}\n```"),
+                None => text.to_string(),
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+fn deserialize_markup<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.map(|value| markup_text(&value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{markup_text, CompletionItem, DocumentSymbol, Hover, Location, Position};
+
+    #[test]
+    fn position_utf16_test() {
+        let line = "let ü = \"😀\";";
+
+        let position = Position::from_char_column(2, line, 11);
+        assert_eq!(position.character, 12);
+        assert_eq!(position.char_column(line), 11);
+        assert_eq!(Position::from_char_column(0, line, 4).char_column(line), 4);
+    }
+
+    #[test]
+    fn markup_text_test() {
+        assert_eq!(markup_text(&json!("plain")), "plain");
+        assert_eq!(
+            markup_text(&json!({ "kind": "markdown", "value": "**bold**" })),
+            "**bold**"
+        );
+        assert_eq!(
+            markup_text(&json!([{ "language": "rust", "value": "fn a()" }, "Docs"])),
+            "```rust\nfn a()\n```\n\nDocs"
+        );
+
+        assert_eq!(Hover::from_value(json!({ "contents": [] })), None);
+    }
+
+    #[test]
+    fn parse_results_test() {
+        let items = CompletionItem::list_from_value(json!({
+            "isIncomplete": false,
+            "items": [
+                { "label": "len", "kind": 2, "documentation": { "kind": "markdown", "value": "Length" } },
+                { "label": "push", "insertText": "push()" }
+            ]
+        }));
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].documentation.as_deref(), Some("Length"));
+        assert_eq!(items[1].text(), "push()");
+
+        let range =
+            json!({ "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 5 } });
+        let locations =
+            Location::list_from_value(json!({ "uri": "file:///tmp/a.rs", "range": range }));
+        assert_eq!(locations[0].path, "/tmp/a.rs");
+        assert_eq!(locations[0].range.start.character, 2);
+        assert!(Location::list_from_value(json!(null)).is_empty());
+
+        let symbols = DocumentSymbol::list_from_value(json!([{
+            "name": "Editor",
+            "kind": 23,
+            "range": range,
+            "selectionRange": range,
+            "children": [{ "name": "inner", "detail": "View", "kind": 8, "range": range }]
+        }]));
+        assert_eq!(symbols[0].children[0].detail.as_deref(), Some("View"));
+        assert_eq!(symbols[0].children[0].selection_range, symbols[0].range);
+
+        let symbols = DocumentSymbol::list_from_value(json!([{
+            "name": "main",
+            "kind": 12,
+            "location": { "uri": "file:///tmp/a.rs", "range": range }
+        }]));
+        assert_eq!(symbols[0].range.start.line, 1);
+        assert!(symbols[0].children.is_empty());
+    }
+}
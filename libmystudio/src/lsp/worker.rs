@@ -0,0 +1,169 @@
+use std::{sync::mpsc, thread};
+
+use super::{
+    client::LspClient,
+    error::LspError,
+    language_id_for_path,
+    types::{LspEvent, LspRequest, LspResponse},
+    LspManager,
+};
+
+type Job = Box<dyn FnOnce(&mut LspManager) + Send>;
+
+/**
+Runs an `LspManager` on a background thread, so that starting servers and
+waiting for their answers never blocks the caller.
+
+Documents are opened, changed and closed in the order they were sent, and
+before requests sent after them. Answers are passed to `on_event` of the
+manager as `LspEvent::Response`. Servers are shut down once the worker is
+dropped.
+*/
+pub struct LspWorker {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl LspWorker {
+    pub fn new(mut manager: LspManager) -> Self {
+        let (jobs, jobs_rx) = mpsc::channel::<Job>();
+
+        thread::spawn(move || {
+            for job in jobs_rx {
+                job(&mut manager);
+            }
+        });
+
+        Self { jobs }
+    }
+
+    /**
+     * Opens a document on the server of its language, the server is started
+     * first if needed.
+     */
+    pub fn did_open(&self, path: &str, text: String) {
+        let path = path.to_string();
+
+        self.run(move |manager| match manager.client_for_path(&path) {
+            Ok(Some(client)) => {
+                let language_id = language_id_for_path(&path, client.language());
+                report(client.did_open(&path, &language_id, &text));
+            }
+            Ok(None) => {}
+            Err(error) => manager.send_event(LspEvent::ShowMessage(format!(
+                "Unable to start language server: {error}"
+            ))),
+        });
+    }
+
+    /// Sends the whole new text of a document.
+    pub fn did_change(&self, path: &str, text: String) {
+        self.with_client(path, move |client, path| client.did_change(path, &text));
+    }
+
+    pub fn did_save(&self, path: &str) {
+        self.with_client(path, |client, path| client.did_save(path));
+    }
+
+    pub fn did_close(&self, path: &str) {
+        self.with_client(path, |client, path| client.did_close(path));
+    }
+
+    /**
+     * Sends a request about a document to its running server.
+     *
+     * Its answer is sent as `LspEvent::Response` with `id`, waiting for it
+     * doesn't delay the jobs sent afterwards.
+     */
+    pub fn request(&self, id: u64, path: &str, request: LspRequest) {
+        let path = path.to_string();
+
+        self.run(move |manager| {
+            let events = manager.event_sender();
+            let client = manager.running_client(&path);
+
+            thread::spawn(move || {
+                let result = match client {
+                    Some(client) => send_request(&client, &path, request),
+                    None => Ok(LspResponse::Unsupported),
+                };
+                events.send(LspEvent::Response { id, result }).ok();
+            });
+        });
+    }
+
+    /**
+     * Forgets the server of a language after an `LspEvent::Exited`, unless it
+     * was shut down on purpose. Crashes are reported with
+     * `LspEvent::ShowMessage`.
+     */
+    pub fn server_exited(&self, language: &str) {
+        let language = language.to_string();
+
+        self.run(move |manager| {
+            let crashed = manager
+                .client(&language)
+                .is_some_and(|client| !client.is_running());
+
+            if crashed {
+                manager.remove(&language);
+                manager.send_event(LspEvent::ShowMessage(format!(
+                    "Language server for {language} exited"
+                )));
+            }
+        });
+    }
+
+    fn with_client<F>(&self, path: &str, notify: F)
+    where
+        F: FnOnce(&LspClient, &str) -> Result<(), LspError> + Send + 'static,
+    {
+        let path = path.to_string();
+
+        self.run(move |manager| {
+            if let Some(client) = manager.running_client(&path) {
+                report(notify(&client, &path));
+            }
+        });
+    }
+
+    fn run<F>(&self, job: F)
+    where
+        F: FnOnce(&mut LspManager) + Send + 'static,
+    {
+        self.jobs.send(Box::new(job)).ok();
+    }
+}
+
+fn send_request(
+    client: &LspClient,
+    path: &str,
+    request: LspRequest,
+) -> Result<LspResponse, LspError> {
+    let response = match request {
+        LspRequest::Hover(position) => LspResponse::Hover(client.hover(path, position)?),
+        LspRequest::Completion(position) => {
+            LspResponse::Completion(client.completion(path, position)?)
+        }
+        LspRequest::Definition(position) => {
+            LspResponse::Definition(client.definition(path, position)?)
+        }
+        LspRequest::References(_) if !client.supports("referencesProvider") => {
+            LspResponse::Unsupported
+        }
+        LspRequest::References(position) => {
+            LspResponse::References(client.references(path, position, true)?)
+        }
+        LspRequest::DocumentSymbols if !client.supports("documentSymbolProvider") => {
+            LspResponse::Unsupported
+        }
+        LspRequest::DocumentSymbols => LspResponse::DocumentSymbols(client.document_symbols(path)?),
+    };
+
+    Ok(response)
+}
+
+fn report(result: Result<(), LspError>) {
+    if let Err(error) = result {
+        eprintln!("Unable to sync document with language server: {error}");
+    }
+}
@@ -0,0 +1,152 @@
+// Back and forward history of cursor locations, like a web browser
+
+use std::collections::VecDeque;
+
+// Oldest locations are dropped past this
+const DEFAULT_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: String,
+    // One-based, `column` is a byte offset in the line like `jump_to_line_with_editor`
+    pub line: i32,
+    pub column: i32,
+}
+
+impl Location {
+    pub fn new(path: &str, line: i32, column: i32) -> Self {
+        Self {
+            path: path.to_string(),
+            line,
+            column,
+        }
+    }
+}
+
+/**
+Locations left by jumps, ex: go to definition.
+
+The location before each jump is recorded, going back returns to it and
+going forward returns to where the cursor was when going back.
+*/
+#[derive(Debug, Clone)]
+pub struct NavigationHistory {
+    back: VecDeque<Location>,
+    forward: Vec<Location>,
+    capacity: usize,
+}
+
+impl Default for NavigationHistory {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl NavigationHistory {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            back: VecDeque::new(),
+            forward: vec![],
+            capacity: capacity.max(1),
+        }
+    }
+
+    /**
+     * Records the location left by a jump, the forward history is dropped
+     * like in a browser.
+     */
+    pub fn record(&mut self, location: Location) {
+        self.forward.clear();
+
+        if self.back.back() == Some(&location) {
+            return;
+        }
+        if self.back.len() == self.capacity {
+            self.back.pop_front();
+        }
+        self.back.push_back(location);
+    }
+
+    /**
+     * Returns the location to go back to from `current`, which can be
+     * returned to by going forward.
+     */
+    pub fn back(&mut self, current: Location) -> Option<Location> {
+        // Nothing to go back to from where the jump was recorded
+        while self.back.back() == Some(&current) {
+            self.back.pop_back();
+        }
+
+        let location = self.back.pop_back()?;
+        self.forward.push(current);
+        Some(location)
+    }
+
+    /// Opposite of `back`.
+    pub fn forward(&mut self, current: Location) -> Option<Location> {
+        while self.forward.last() == Some(&current) {
+            self.forward.pop();
+        }
+
+        let location = self.forward.pop()?;
+        self.back.push_back(current);
+        Some(location)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.back.clear();
+        self.forward.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Location, NavigationHistory};
+
+    fn at(line: i32) -> Location {
+        Location::new("/workspace/src/main.rs", line, 1)
+    }
+
+    #[test]
+    fn back_and_forward_test() {
+        let mut history = NavigationHistory::default();
+        history.record(at(1));
+        history.record(at(10));
+        // Jumps from the same place are recorded once
+        history.record(at(10));
+
+        assert_eq!(history.back(at(20)), Some(at(10)));
+        assert_eq!(history.back(at(10)), Some(at(1)));
+        assert_eq!(history.back(at(1)), None);
+
+        assert_eq!(history.forward(at(1)), Some(at(10)));
+        assert_eq!(history.forward(at(10)), Some(at(20)));
+        assert!(!history.can_go_forward());
+
+        // A new jump drops the forward history
+        history.back(at(20));
+        history.record(at(15));
+        assert_eq!(history.forward(at(30)), None);
+        assert_eq!(history.back(at(30)), Some(at(15)));
+    }
+
+    #[test]
+    fn capacity_test() {
+        let mut history = NavigationHistory::with_capacity(2);
+        history.record(at(1));
+        history.record(at(2));
+        history.record(at(3));
+
+        assert_eq!(history.back(at(4)), Some(at(3)));
+        assert_eq!(history.back(at(3)), Some(at(2)));
+        assert_eq!(history.back(at(2)), None);
+    }
+}
@@ -13,9 +13,16 @@ impl NotebookTabCache {
         cache.push(tab);
     }
 
+    /**
+     * Forgets the tab of the notebook page at `index` when it has one, pages
+     * after it move back by one, ex: after closing a diff page.
+     */
     pub fn remove(index: u32) {
         let mut cache = NOTEBOOK_TABS_CACHE.write();
-        cache.swap_remove(index as usize);
+        cache.retain(|tab| tab.position != index);
+        for tab in cache.iter_mut().filter(|tab| tab.position > index) {
+            tab.position -= 1;
+        }
     }
 
     pub fn find_by_path(file_path: String) -> Option<NotebookTabCache> {
@@ -38,6 +45,10 @@ impl NotebookTabCache {
         NOTEBOOK_TABS_CACHE.write().clear();
     }
 
+    pub fn all() -> Vec<NotebookTabCache> {
+        NOTEBOOK_TABS_CACHE.read().clone()
+    }
+
     pub fn is_empty() -> bool {
         NOTEBOOK_TABS_CACHE.read().iter().len() == 0
     }
@@ -85,5 +96,21 @@ mod tests {
 
         // verify cache is none
         assert!(found_cache.is_none());
+
+        // tabs after a removed page move back, page 1 has no tab
+        let second_cache = NotebookTabCache {
+            file_path: "/tmp/2".to_string(),
+            position: 2,
+            ..get_mock_cache()
+        };
+        NotebookTabCache::insert(mock_cache.clone());
+        NotebookTabCache::insert(second_cache.clone());
+        NotebookTabCache::remove(1);
+        assert_eq!(locate_cache_item().map(|tab| tab.position), Some(0));
+        NotebookTabCache::remove(0);
+        let found_cache = NotebookTabCache::find_by_path(second_cache.file_path);
+        assert_eq!(found_cache.map(|tab| tab.position), Some(0));
+        NotebookTabCache::remove(0);
+        assert!(NotebookTabCache::is_empty());
     }
 }
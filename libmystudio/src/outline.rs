@@ -0,0 +1,594 @@
+// Outline of a document, its definitions as a tree (ex: methods inside impl
+// blocks), from a language server or built-in parsers
+
+use std::path::Path;
+
+use crate::{
+    lsp::types::DocumentSymbol,
+    symbols::{definition_in_line, extract_symbols, tokens, SymbolKind, RUST_KEYWORDS},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineItem {
+    pub name: String,
+    pub kind: SymbolKind,
+    // Zero-based, `column` is the byte offset of the name in its line
+    pub line: u32,
+    pub column: u32,
+    // Last line of the definition, ex: its closing brace
+    pub end_line: u32,
+    pub children: Vec<OutlineItem>,
+}
+
+impl OutlineItem {
+    fn new(name: &str, kind: SymbolKind, line: u32, column: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            line,
+            column,
+            end_line: line,
+            children: vec![],
+        }
+    }
+}
+
+/**
+ * Returns the outline of a file with the built-in parsers, the language comes
+ * from the extension of `path`.
+ *
+ * Rust and Markdown are nested, other languages known to `symbols` are flat.
+ */
+pub fn outline_for_path(path: &str, text: &str) -> Vec<OutlineItem> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "rs" => rust_outline(text),
+        "md" | "markdown" => markdown_outline(text),
+        _ => extract_symbols(path, text)
+            .into_iter()
+            .map(|symbol| OutlineItem::new(&symbol.name, symbol.kind, symbol.line, symbol.column))
+            .collect(),
+    }
+}
+
+/**
+ * Converts symbols of a language server, `text` is the document they were
+ * asked for.
+ */
+pub fn outline_from_lsp(symbols: &[DocumentSymbol], text: &str) -> Vec<OutlineItem> {
+    let lines: Vec<&str> = text.lines().collect();
+
+    let mut items: Vec<OutlineItem> = symbols
+        .iter()
+        .map(|symbol| {
+            let start = symbol.selection_range.start;
+            let line = lines.get(start.line as usize).copied().unwrap_or_default();
+            // Servers count columns in UTF-16
+            let column = line
+                .char_indices()
+                .nth(start.char_column(line))
+                .map_or(line.len(), |(index, _)| index);
+
+            OutlineItem {
+                name: symbol.name.clone(),
+                kind: SymbolKind::from_lsp(symbol.kind),
+                line: start.line,
+                column: column as u32,
+                end_line: symbol.range.end.line.max(start.line),
+                children: outline_from_lsp(&symbol.children, text),
+            }
+        })
+        .collect();
+
+    items.sort_by_key(|item| item.line);
+    items
+}
+
+/**
+ * Returns indices of the innermost item around a line and of its parents,
+ * outermost first. Empty when the line is outside of all items.
+ */
+pub fn item_path_at_line(items: &[OutlineItem], line: u32) -> Vec<usize> {
+    let mut path = vec![];
+    let mut items = items;
+
+    while let Some((index, item)) = items
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, item)| item.line <= line && line <= item.end_line)
+    {
+        path.push(index);
+        items = &item.children;
+    }
+
+    path
+}
+
+// Items whose definition may still be going on, with the level they were found at
+#[derive(Default)]
+struct OpenItems {
+    roots: Vec<OutlineItem>,
+    open: Vec<(OutlineItem, usize)>,
+}
+
+impl OpenItems {
+    fn push(&mut self, item: OutlineItem, level: usize) {
+        self.open.push((item, level));
+    }
+
+    fn parent_kind(&self) -> Option<SymbolKind> {
+        self.open.last().map(|(item, _)| item.kind)
+    }
+
+    // Ends items found at `level` or deeper, on `end_line` or their own line
+    fn close(&mut self, level: usize, end_line: Option<u32>) {
+        while self
+            .open
+            .last()
+            .is_some_and(|(_, item_level)| *item_level >= level)
+        {
+            let (mut item, _) = self.open.pop().unwrap();
+            item.end_line = end_line.unwrap_or(item.line).max(item.line);
+
+            match self.open.last_mut() {
+                Some((parent, _)) => parent.children.push(item),
+                None => self.roots.push(item),
+            }
+        }
+    }
+
+    fn finish(mut self, last_line: u32) -> Vec<OutlineItem> {
+        self.close(0, Some(last_line));
+        self.roots
+    }
+}
+
+/**
+ * Outline of Rust code, items are nested by braces (ex: methods in impl
+ * blocks). Braces in comments and strings are skipped.
+ */
+pub fn rust_outline(text: &str) -> Vec<OutlineItem> {
+    let mut items = OpenItems::default();
+    let mut scanner = RustScanner::default();
+    let mut depth: usize = 0;
+    let mut last_line = 0;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line_number = line_number as u32;
+        let code = scanner.code(line);
+        last_line = line_number;
+
+        if let Some(mut item) = rust_definition(&code, line_number) {
+            // Siblings without a body end here, ex: `struct Marker;`
+            items.close(depth, None);
+
+            let in_type = matches!(
+                items.parent_kind(),
+                Some(SymbolKind::Impl | SymbolKind::Trait)
+            );
+            if item.kind == SymbolKind::Function && in_type {
+                item.kind = SymbolKind::Method;
+            }
+            items.push(item, depth);
+        }
+
+        for c in code.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    items.close(depth, Some(line_number));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    items.finish(last_line)
+}
+
+fn rust_definition(code: &str, line: u32) -> Option<OutlineItem> {
+    if let Some((name, kind, column)) = definition_in_line(code, RUST_KEYWORDS) {
+        return Some(OutlineItem::new(name, kind, line, column as u32));
+    }
+
+    // `impl Display for Editor`, generics of the impl are left out
+    let (column, _) = tokens(code)
+        .into_iter()
+        .find(|(_, token)| !["unsafe", "default"].contains(token))
+        .filter(|(_, token)| *token == "impl" || token.starts_with("impl<"))?;
+
+    let mut rest = &code[column + "impl".len()..];
+    if rest.starts_with('<') {
+        let mut nesting = 0;
+        let end = rest.char_indices().find_map(|(index, c)| {
+            match c {
+                '<' => nesting += 1,
+                '>' => nesting -= 1,
+                _ => {}
+            }
+            (nesting == 0).then_some(index + 1)
+        })?;
+        rest = &rest[end..];
+    }
+    let header = rest.split('{').next().unwrap_or_default();
+    let header = header.split(" where").next().unwrap_or_default();
+
+    let name = header.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name = if name.is_empty() {
+        String::from("impl")
+    } else {
+        format!("impl {name}")
+    };
+
+    Some(OutlineItem::new(
+        &name,
+        SymbolKind::Impl,
+        line,
+        column as u32,
+    ))
+}
+
+// Blanks out comments and string contents of Rust code, line by line
+#[derive(Default)]
+struct RustScanner {
+    block_comment_depth: usize,
+    in_string: bool,
+    // Number of `#` of the raw string being scanned, ex: 1 for `r#"..."#`
+    raw_string_hashes: Option<usize>,
+}
+
+impl RustScanner {
+    // Byte offsets are kept, skipped chars become spaces
+    fn code(&mut self, line: &str) -> String {
+        let mut code = String::with_capacity(line.len());
+        let blank = |code: &mut String, c: char| code.extend((0..c.len_utf8()).map(|_| ' '));
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let next = chars.peek().copied();
+
+            if self.block_comment_depth > 0 {
+                match (c, next) {
+                    ('*', Some('/')) => self.block_comment_depth -= 1,
+                    ('/', Some('*')) => self.block_comment_depth += 1,
+                    _ => {
+                        blank(&mut code, c);
+                        continue;
+                    }
+                }
+                chars.next();
+                code.push_str("  ");
+                continue;
+            }
+
+            if let Some(hashes) = self.raw_string_hashes {
+                let closes =
+                    c == '"' && chars.clone().take_while(|next| *next == '#').count() >= hashes;
+                if closes {
+                    code.push(c);
+                    for _ in 0..hashes {
+                        chars.next();
+                        code.push('#');
+                    }
+                    self.raw_string_hashes = None;
+                } else {
+                    blank(&mut code, c);
+                }
+                continue;
+            }
+
+            if self.in_string {
+                match c {
+                    '\\' => {
+                        blank(&mut code, c);
+                        if let Some(escaped) = chars.next() {
+                            blank(&mut code, escaped);
+                        }
+                    }
+                    '"' => {
+                        self.in_string = false;
+                        code.push(c);
+                    }
+                    c => blank(&mut code, c),
+                }
+                continue;
+            }
+
+            match (c, next) {
+                ('/', Some('/')) => break,
+                ('/', Some('*')) => {
+                    chars.next();
+                    self.block_comment_depth += 1;
+                    code.push_str("  ");
+                }
+                ('"', _) => {
+                    self.in_string = true;
+                    code.push(c);
+                }
+                // Char literal, not a lifetime like `'a`
+                ('\'', Some(literal)) => {
+                    let mut after = chars.clone();
+                    after.next();
+                    let is_char_literal = literal == '\\' || after.peek() == Some(&'\'');
+                    code.push(c);
+
+                    if is_char_literal {
+                        while let Some(c) = chars.next() {
+                            if c == '\'' {
+                                code.push(c);
+                                break;
+                            }
+                            blank(&mut code, c);
+                            if c == '\\' {
+                                if let Some(escaped) = chars.next() {
+                                    blank(&mut code, escaped);
+                                }
+                            }
+                        }
+                    }
+                }
+                // Raw string, not the end of a name like `for`
+                ('r', Some('#' | '"'))
+                    if !code.ends_with(|c: char| c.is_alphanumeric() || c == '_') =>
+                {
+                    code.push(c);
+
+                    let hashes = chars.clone().take_while(|next| *next == '#').count();
+                    if chars.clone().nth(hashes) == Some('"') {
+                        // Hashes and the opening quote
+                        code.extend(chars.by_ref().take(hashes + 1));
+                        self.raw_string_hashes = Some(hashes);
+                    }
+                }
+                (c, _) => code.push(c),
+            }
+        }
+
+        code
+    }
+}
+
+/**
+ * Outline of a Markdown document, sections are nested by heading level.
+ * Headings in code blocks are skipped.
+ */
+pub fn markdown_outline(text: &str) -> Vec<OutlineItem> {
+    let mut items = OpenItems::default();
+    // Marker of the open code block, ex: "```"
+    let mut fence: Option<&str> = None;
+    // Paragraph line which may be a heading underlined by the next one
+    let mut previous: Option<(u32, usize, &str)> = None;
+    let mut last_line = 0;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line_number = line_number as u32;
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        last_line = line_number;
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if indent < 4 && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+            fence = Some(&trimmed[..3]);
+            previous = None;
+            continue;
+        }
+
+        let heading = if indent < 4 {
+            atx_heading(trimmed)
+                .map(|(level, name, offset)| (level, name, line_number, indent + offset))
+                .or_else(|| {
+                    let (previous_line, previous_indent, previous_text) = previous?;
+                    let level = setext_level(trimmed.trim_end())?;
+                    Some((level, previous_text.trim(), previous_line, previous_indent))
+                })
+        } else {
+            None
+        };
+
+        match heading {
+            Some((level, name, heading_line, column)) => {
+                items.close(level, Some(heading_line.saturating_sub(1)));
+                items.push(
+                    OutlineItem::new(name, SymbolKind::Heading, heading_line, column as u32),
+                    level,
+                );
+                previous = None;
+            }
+            None if trimmed.is_empty() => previous = None,
+            None => previous = Some((line_number, indent, trimmed)),
+        }
+    }
+
+    items.finish(last_line)
+}
+
+// Returns level, text and its offset of a `## Heading` line
+fn atx_heading(line: &str) -> Option<(usize, &str, usize)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    // Closing hashes are optional, ex: `## Heading ##`
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    let offset = level + (rest.len() - rest.trim_start().len());
+    Some((level, text, offset))
+}
+
+// Level of a heading underlined with `===` or `---`
+fn setext_level(line: &str) -> Option<usize> {
+    if !line.is_empty() && line.chars().all(|c| c == '=') {
+        Some(1)
+    } else if line.len() >= 2 && line.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{lsp::types::DocumentSymbol, symbols::SymbolKind};
+
+    use super::{
+        item_path_at_line, markdown_outline, outline_for_path, outline_from_lsp, rust_outline,
+        OutlineItem,
+    };
+
+    // (depth, name, kind, line, end line) of items in tree order
+    fn flatten(items: &[OutlineItem]) -> Vec<(usize, String, SymbolKind, u32, u32)> {
+        fn walk(
+            items: &[OutlineItem],
+            depth: usize,
+            out: &mut Vec<(usize, String, SymbolKind, u32, u32)>,
+        ) {
+            for item in items {
+                out.push((
+                    depth,
+                    item.name.clone(),
+                    item.kind,
+                    item.line,
+                    item.end_line,
+                ));
+                walk(&item.children, depth + 1, out);
+            }
+        }
+
+        let mut out = vec![];
+        walk(items, 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn rust_outline_test() {
+        let text = r##"pub struct Marker;
+
+/* fn hidden() { */
+impl<T: Clone> Display for Editor<T> where T: Debug {
+    fn fmt(&self) -> String {
+        let brace = '{';
+        format!("}} {}", brace)
+    }
+}
+
+pub trait Save {
+    fn save(&self);
+    fn path(&self) -> &'static str {
+        "{"
+    }
+}
+
+mod tests {
+    const JSON: &str = r#"{"a":{"b":"}"}}"#;
+    fn helper() {}
+}
+"##;
+
+        assert_eq!(
+            flatten(&rust_outline(text)),
+            [
+                (0, String::from("Marker"), SymbolKind::Struct, 0, 0),
+                (
+                    0,
+                    String::from("impl Display for Editor<T>"),
+                    SymbolKind::Impl,
+                    3,
+                    8
+                ),
+                (1, String::from("fmt"), SymbolKind::Method, 4, 7),
+                (0, String::from("Save"), SymbolKind::Trait, 10, 15),
+                (1, String::from("save"), SymbolKind::Method, 11, 11),
+                (1, String::from("path"), SymbolKind::Method, 12, 14),
+                (0, String::from("tests"), SymbolKind::Module, 17, 20),
+                (1, String::from("JSON"), SymbolKind::Constant, 18, 18),
+                (1, String::from("helper"), SymbolKind::Function, 19, 19),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_outline_test() {
+        let text = "\
+# MyStudio IDE
+
+## Building
+```sh
+# not a heading
+```
+
+Usage
+-----
+### Keys ###
+
+# License
+";
+
+        assert_eq!(
+            flatten(&markdown_outline(text)),
+            [
+                (0, String::from("MyStudio IDE"), SymbolKind::Heading, 0, 10),
+                (1, String::from("Building"), SymbolKind::Heading, 2, 6),
+                (1, String::from("Usage"), SymbolKind::Heading, 7, 10),
+                (2, String::from("Keys"), SymbolKind::Heading, 9, 10),
+                (0, String::from("License"), SymbolKind::Heading, 11, 11),
+            ]
+        );
+        assert_eq!(markdown_outline(text)[0].column, 2);
+    }
+
+    #[test]
+    fn item_path_at_line_test() {
+        let items = outline_for_path(
+            "lib.rs",
+            "impl Editor {\n    fn new() {\n    }\n}\n\nfn main() {}\n",
+        );
+
+        assert_eq!(item_path_at_line(&items, 2), [0, 0]);
+        assert_eq!(item_path_at_line(&items, 3), [0]);
+        assert_eq!(item_path_at_line(&items, 4), Vec::<usize>::new());
+        assert_eq!(item_path_at_line(&items, 5), [1]);
+    }
+
+    #[test]
+    fn outline_from_lsp_test() {
+        let symbols = DocumentSymbol::list_from_value(json!([{
+            "name": "Größe",
+            "kind": 23,
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 2, "character": 1 } },
+            "selectionRange": { "start": { "line": 0, "character": 7 }, "end": { "line": 0, "character": 12 } },
+            "children": [{
+                "name": "wert",
+                "kind": 8,
+                "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 13 } }
+            }]
+        }]));
+
+        let items = outline_from_lsp(&symbols, "struct Größe {\n    wert: u8,\n}\n");
+        assert_eq!(
+            flatten(&items),
+            [
+                (0, String::from("Größe"), SymbolKind::Struct, 0, 2),
+                (1, String::from("wert"), SymbolKind::Field, 1, 1),
+            ]
+        );
+        assert_eq!(items[0].column, 7);
+    }
+}
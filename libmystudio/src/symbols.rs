@@ -0,0 +1,682 @@
+// Ctags-like symbol index, definitions are found by scanning source files for
+// declaration keywords. Used when no language server can answer.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use jwalk::WalkDir;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    completion::is_word_char,
+    fs::{get_cache_dir, is_path_excluded},
+    fuzzy::{fuzzy_match, FuzzyMatch},
+};
+
+// Bigger files are likely generated, they are not indexed
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+// Bumped when the layout of saved indexes or the parsers change
+const CACHE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Enum,
+    Trait,
+    Type,
+    Module,
+    Constant,
+    Macro,
+    Class,
+    Interface,
+    // Top level `const` of JS/TS, ex: `const handler = () => {}`
+    Variable,
+    Method,
+    Field,
+    Impl,
+    // Markdown section
+    Heading,
+    Other,
+}
+
+const ALL_KINDS: &[SymbolKind] = &[
+    SymbolKind::Function,
+    SymbolKind::Struct,
+    SymbolKind::Enum,
+    SymbolKind::Trait,
+    SymbolKind::Type,
+    SymbolKind::Module,
+    SymbolKind::Constant,
+    SymbolKind::Macro,
+    SymbolKind::Class,
+    SymbolKind::Interface,
+    SymbolKind::Variable,
+    SymbolKind::Method,
+    SymbolKind::Field,
+    SymbolKind::Impl,
+    SymbolKind::Heading,
+    SymbolKind::Other,
+];
+
+impl SymbolKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Trait => "trait",
+            SymbolKind::Type => "type",
+            SymbolKind::Module => "module",
+            SymbolKind::Constant => "constant",
+            SymbolKind::Macro => "macro",
+            SymbolKind::Class => "class",
+            SymbolKind::Interface => "interface",
+            SymbolKind::Variable => "variable",
+            SymbolKind::Method => "method",
+            SymbolKind::Field => "field",
+            SymbolKind::Impl => "impl",
+            SymbolKind::Heading => "heading",
+            SymbolKind::Other => "symbol",
+        }
+    }
+
+    /// Converts a LSP `SymbolKind` number.
+    pub fn from_lsp(kind: u32) -> Self {
+        match kind {
+            2..=4 => SymbolKind::Module,
+            5 => SymbolKind::Class,
+            6 | 9 => SymbolKind::Method,
+            7 | 8 | 22 => SymbolKind::Field,
+            10 => SymbolKind::Enum,
+            // Traits are interfaces to rust-analyzer
+            11 => SymbolKind::Interface,
+            12 => SymbolKind::Function,
+            13 => SymbolKind::Variable,
+            14 => SymbolKind::Constant,
+            23 => SymbolKind::Struct,
+            26 => SymbolKind::Type,
+            _ => SymbolKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // Saved indexes group symbols by path
+    #[serde(skip)]
+    pub path: String,
+    // Zero-based, `column` is a byte offset in the line
+    pub line: u32,
+    pub column: u32,
+}
+
+pub(crate) const RUST_KEYWORDS: &[(&str, SymbolKind)] = &[
+    ("fn", SymbolKind::Function),
+    ("struct", SymbolKind::Struct),
+    ("union", SymbolKind::Struct),
+    ("enum", SymbolKind::Enum),
+    ("trait", SymbolKind::Trait),
+    ("type", SymbolKind::Type),
+    ("mod", SymbolKind::Module),
+    ("const", SymbolKind::Constant),
+    ("static", SymbolKind::Constant),
+    ("macro_rules!", SymbolKind::Macro),
+];
+
+const PYTHON_KEYWORDS: &[(&str, SymbolKind)] =
+    &[("def", SymbolKind::Function), ("class", SymbolKind::Class)];
+
+const JAVASCRIPT_KEYWORDS: &[(&str, SymbolKind)] = &[
+    ("function", SymbolKind::Function),
+    ("function*", SymbolKind::Function),
+    ("class", SymbolKind::Class),
+    ("interface", SymbolKind::Interface),
+    ("type", SymbolKind::Type),
+    ("enum", SymbolKind::Enum),
+    ("namespace", SymbolKind::Module),
+    ("const", SymbolKind::Variable),
+];
+
+// Words allowed before a keyword, ex: `pub` in `pub fn`
+const MODIFIERS: &[&str] = &[
+    "pub", "async", "unsafe", "extern", "\"C\"", "default", "export", "declare", "abstract",
+];
+
+fn keywords_for_path(path: &str) -> &'static [(&'static str, SymbolKind)] {
+    let extension = Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "rs" => RUST_KEYWORDS,
+        "py" | "pyi" => PYTHON_KEYWORDS,
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => JAVASCRIPT_KEYWORDS,
+        _ => &[],
+    }
+}
+
+/// Tells whether definitions can be found in a file, going by its extension.
+pub fn is_indexed(path: &str) -> bool {
+    !keywords_for_path(path).is_empty()
+}
+
+/**
+ * Returns definitions found in the text of a file, the language comes from
+ * the extension of `path`.
+ */
+pub fn extract_symbols(path: &str, text: &str) -> Vec<Symbol> {
+    let keywords = keywords_for_path(path);
+    if keywords.is_empty() {
+        return vec![];
+    }
+
+    text.lines()
+        .enumerate()
+        .filter_map(|(line_number, line)| {
+            let (name, kind, column) = definition_in_line(line, keywords)?;
+            Some(Symbol {
+                name: name.to_string(),
+                kind,
+                path: path.to_string(),
+                line: line_number as u32,
+                column: column as u32,
+            })
+        })
+        .collect()
+}
+
+// Returns the name, kind and byte column of the definition starting a line
+pub(crate) fn definition_in_line<'a>(
+    line: &'a str,
+    keywords: &[(&str, SymbolKind)],
+) -> Option<(&'a str, SymbolKind, usize)> {
+    let tokens = tokens(line);
+    let is_indented = line.starts_with(char::is_whitespace);
+
+    for (index, (_, token)) in tokens.iter().enumerate() {
+        if MODIFIERS.contains(token) || token.starts_with("pub(") {
+            continue;
+        }
+
+        let (_, kind) = keywords.iter().find(|(keyword, _)| keyword == token)?;
+        let (column, next) = tokens.get(index + 1)?;
+
+        // Keyword used as a modifier, ex: `const` in `const fn`
+        if MODIFIERS.contains(next) || keywords.iter().any(|(keyword, _)| keyword == next) {
+            continue;
+        }
+        if *kind == SymbolKind::Variable && is_indented {
+            return None;
+        }
+
+        let name_length = next
+            .char_indices()
+            .find(|(_, c)| !is_word_char(*c))
+            .map_or(next.len(), |(index, _)| index);
+        let name = &next[..name_length];
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        return Some((name, *kind, *column));
+    }
+
+    None
+}
+
+// Words of a line separated by whitespace, with their byte offsets
+pub(crate) fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+
+    for (index, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (Some(token_start), true) => {
+                tokens.push((token_start, &line[token_start..index]));
+                start = None;
+            }
+            (None, false) => start = Some(index),
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+/**
+ * Returns the identifier around a byte column of a line, ex: `open` in
+ * `self.open(path)` for any column from `o` to just after `n`.
+ */
+pub fn word_at(line: &str, column: usize) -> Option<&str> {
+    let column = column.min(line.len());
+    if !line.is_char_boundary(column) {
+        return None;
+    }
+
+    let start = line[..column]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(column, |(index, _)| index);
+    let end = line[column..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(line.len(), |(index, _)| column + index);
+
+    let word = &line[start..end];
+    (!word.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit())).then_some(word)
+}
+
+// Symbols of an indexed file along with its modification time when read
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IndexedFile {
+    modified: Option<SystemTime>,
+    symbols: Vec<Symbol>,
+}
+
+// Layout of the cache file written by `SymbolIndex::save`
+#[derive(Serialize, Deserialize)]
+struct IndexCache {
+    version: u32,
+    root: String,
+    files: BTreeMap<String, IndexedFile>,
+}
+
+/**
+Definitions of the files of a workspace, by file path.
+
+The index is kept between sessions with `save` and `load`, `refresh` only
+reads files modified since they were indexed.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    files: BTreeMap<String, IndexedFile>,
+}
+
+impl SymbolIndex {
+    /**
+     * Indexes files under `root`, skipping those matching `exclude` patterns
+     * (see `fs::is_path_excluded`).
+     */
+    pub fn build(root: &str, exclude: &[String]) -> Self {
+        let mut index = SymbolIndex::default();
+        index.refresh(root, exclude);
+        index
+    }
+
+    /**
+     * Brings the index up to date with files under `root`, ex: after they
+     * were changed outside of the editor.
+     *
+     * Returns `false` when nothing changed.
+     */
+    pub fn refresh(&mut self, root: &str, exclude: &[String]) -> bool {
+        let mut changed = false;
+        let mut found_paths = BTreeSet::new();
+
+        for entry in WalkDir::new(root)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+        {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            let relative_path = path.strip_prefix(root).unwrap_or(&path);
+            if is_path_excluded(relative_path, exclude) {
+                continue;
+            }
+
+            let path_str = path.to_string_lossy().to_string();
+            if !is_indexed(&path_str) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.len() > MAX_FILE_SIZE {
+                continue;
+            }
+
+            let modified = metadata.modified().ok();
+            found_paths.insert(path_str.clone());
+            let is_unchanged = self
+                .files
+                .get(&path_str)
+                .is_some_and(|file| modified.is_some() && file.modified == modified);
+            if is_unchanged {
+                continue;
+            }
+
+            // Files which are not UTF-8 are indexed without symbols
+            let text = fs::read_to_string(&path).unwrap_or_default();
+            self.set_file(&path_str, &text, modified);
+            changed = true;
+        }
+
+        let previous_len = self.files.len();
+        self.files.retain(|path, _| found_paths.contains(path));
+
+        changed || self.files.len() != previous_len
+    }
+
+    /// Replaces definitions of a file, ex: after it is saved.
+    pub fn update_file(&mut self, path: &str, text: &str) {
+        if !is_indexed(path) {
+            return;
+        }
+
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        self.set_file(path, text, modified);
+    }
+
+    fn set_file(&mut self, path: &str, text: &str, modified: Option<SystemTime>) {
+        let symbols = extract_symbols(path, text);
+        self.files
+            .insert(path.to_string(), IndexedFile { modified, symbols });
+    }
+
+    pub fn remove_file(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.files.values().flat_map(|file| file.symbols.iter())
+    }
+
+    /// Returns definitions named `name`, ordered by path and line.
+    pub fn definitions(&self, name: &str) -> Vec<&Symbol> {
+        self.symbols()
+            .filter(|symbol| symbol.name == name)
+            .collect()
+    }
+
+    /**
+     * Returns up to `limit` definitions whose name fuzzy matches `query`, best
+     * matches first.
+     *
+     * A leading keyword followed by a space keeps definitions of its kind, ex:
+     * `fn save` or `struct Work`.
+     */
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(&Symbol, FuzzyMatch)> {
+        let (kinds, pattern) = parse_query(query);
+
+        let mut matches: Vec<_> = self
+            .symbols()
+            .filter(|symbol| kinds.is_empty() || kinds.contains(&symbol.kind))
+            .filter_map(|symbol| fuzzy_match(pattern, &symbol.name).map(|m| (symbol, m)))
+            .collect();
+        // Shorter names are closer to the query for equal scores
+        matches.sort_by(|(symbol_a, match_a), (symbol_b, match_b)| {
+            match_b
+                .score
+                .cmp(&match_a.score)
+                .then(symbol_a.name.len().cmp(&symbol_b.name.len()))
+                .then(symbol_a.name.cmp(&symbol_b.name))
+        });
+        matches.truncate(limit);
+
+        matches
+    }
+
+    /// Number of definitions in the index.
+    pub fn len(&self) -> usize {
+        self.files.values().map(|file| file.symbols.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * Reads an index written by `save` for the workspace at `root`.
+     *
+     * Returns `None` if the file is missing, unreadable or from another
+     * version of the index.
+     */
+    pub fn load(cache_path: &Path, root: &str) -> Option<Self> {
+        let content = fs::read(cache_path).ok()?;
+        let cache: IndexCache = serde_json::from_slice(&content).ok()?;
+        if cache.version != CACHE_VERSION || cache.root != root {
+            return None;
+        }
+
+        let mut files = cache.files;
+        // Paths are left out of the file, see `Symbol`
+        for (path, file) in files.iter_mut() {
+            for symbol in file.symbols.iter_mut() {
+                symbol.path = path.clone();
+            }
+        }
+
+        Some(Self { files })
+    }
+
+    pub fn save(&self, cache_path: &Path, root: &str) -> io::Result<()> {
+        let cache = IndexCache {
+            version: CACHE_VERSION,
+            root: root.to_string(),
+            files: self.files.clone(),
+        };
+        let content = serde_json::to_vec(&cache)?;
+
+        if let Some(parent) = cache_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Readers never see a partly written file
+        let temp_path = cache_path.with_extension("tmp");
+        fs::write(&temp_path, content)?;
+        fs::rename(temp_path, cache_path)
+    }
+}
+
+/// Where the index of the workspace at `root` is kept between sessions.
+pub fn cache_path(root: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    root.hash(&mut hasher);
+
+    get_cache_dir()
+        .join("symbols")
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+// Splits a leading kind keyword from a search query, ex: `fn` in `fn open`
+fn parse_query(query: &str) -> (Vec<SymbolKind>, &str) {
+    let query = query.trim_start();
+    let Some((keyword, pattern)) = query.split_once(char::is_whitespace) else {
+        return (vec![], query);
+    };
+
+    let kinds: Vec<SymbolKind> = [RUST_KEYWORDS, PYTHON_KEYWORDS, JAVASCRIPT_KEYWORDS]
+        .iter()
+        .flat_map(|keywords| keywords.iter())
+        .filter(|(other, _)| *other == keyword)
+        .map(|(_, kind)| *kind)
+        .chain(
+            ALL_KINDS
+                .iter()
+                .copied()
+                .filter(|kind| kind.name() == keyword),
+        )
+        .collect();
+
+    if kinds.is_empty() {
+        (vec![], query)
+    } else {
+        (kinds, pattern.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        time::{Duration, SystemTime},
+    };
+
+    use tempfile::tempdir;
+
+    use super::{extract_symbols, word_at, SymbolIndex, SymbolKind};
+
+    #[test]
+    fn extract_symbols_test() {
+        let text = "\
+// fn commented_out() {}
+pub(crate) struct Editor<T> {
+    inner: T,
+}
+
+impl Editor {
+    pub const fn new() -> Self {}
+    async unsafe fn open(&self) {}
+}
+
+macro_rules! log {}
+";
+        let symbols: Vec<_> = extract_symbols("src/editor.rs", text)
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.line, symbol.column))
+            .collect();
+
+        assert_eq!(
+            symbols,
+            [
+                (String::from("Editor"), SymbolKind::Struct, 1, 18),
+                (String::from("new"), SymbolKind::Function, 6, 17),
+                (String::from("open"), SymbolKind::Function, 7, 20),
+                (String::from("log"), SymbolKind::Macro, 10, 13),
+            ]
+        );
+
+        let text =
+            "export default function render() {}\nconst handler = () => {\n  const local = 1;\n};";
+        let names: Vec<_> = extract_symbols("app.tsx", text)
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect();
+        // Constants inside functions are left out
+        assert_eq!(names, ["render", "handler"]);
+
+        assert!(extract_symbols("notes.txt", "fn main() {}").is_empty());
+    }
+
+    #[test]
+    fn word_at_test() {
+        let line = "    self.open_file(path);";
+
+        assert_eq!(word_at(line, 9), Some("open_file"));
+        assert_eq!(word_at(line, 13), Some("open_file"));
+        // Right after the word
+        assert_eq!(word_at(line, 18), Some("open_file"));
+        assert_eq!(word_at(line, 2), None);
+        assert_eq!(word_at("let n = 42;", 9), None);
+    }
+
+    #[test]
+    fn symbol_index_test() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "pub fn open() {}\n").unwrap();
+        fs::write(dir.path().join("src/app.py"), "def open():\n    pass\n").unwrap();
+        fs::write(dir.path().join("target/gen.rs"), "fn open() {}\n").unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+
+        let mut index = SymbolIndex::build(&root, &[String::from("target")]);
+        let paths: Vec<_> = index
+            .definitions("open")
+            .iter()
+            .map(|symbol| symbol.path.strip_prefix(&root).unwrap().to_string())
+            .collect();
+        assert_eq!(paths, ["/src/app.py", "/src/lib.rs"]);
+
+        let lib_path = dir.path().join("src/lib.rs").to_string_lossy().to_string();
+        index.update_file(&lib_path, "pub fn close() {}\n");
+        assert_eq!(index.definitions("open").len(), 1);
+        assert_eq!(index.definitions("close")[0].line, 0);
+
+        index.remove_file(&lib_path);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn refresh_and_cache_test() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let lib_path = dir.path().join("lib.rs");
+        let main_path = dir.path().join("main.rs");
+        fs::write(&lib_path, "pub fn open() {}\n").unwrap();
+        fs::write(&main_path, "fn main() {}\n").unwrap();
+
+        let mut index = SymbolIndex::build(&root, &[]);
+        assert!(!index.refresh(&root, &[]));
+
+        let cache_path = dir.path().join("cache/symbols.json");
+        index.save(&cache_path, &root).unwrap();
+        // Saved for another workspace
+        assert!(SymbolIndex::load(&cache_path, "/elsewhere").is_none());
+
+        // Changed while the IDE was closed, the modification time is set as
+        // writes may happen within the same tick
+        fs::write(&lib_path, "pub fn close() {}\n").unwrap();
+        File::options()
+            .write(true)
+            .open(&lib_path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        fs::remove_file(&main_path).unwrap();
+
+        let mut index = SymbolIndex::load(&cache_path, &root).unwrap();
+        assert_eq!(
+            index.definitions("open")[0].path,
+            lib_path.to_string_lossy()
+        );
+        assert!(index.refresh(&root, &[]));
+        assert!(index.definitions("open").is_empty());
+        assert_eq!(index.definitions("close").len(), 1);
+        assert!(index.definitions("main").is_empty());
+    }
+
+    #[test]
+    fn search_test() {
+        let mut index = SymbolIndex::default();
+        index.update_file(
+            "/workspace/src/fs.rs",
+            "pub fn save_file_changes() {}\npub struct SaveDialog;\nfn set_file() {}\n",
+        );
+        index.update_file("/workspace/src/workspace.rs", "pub struct Workspace {}\n");
+
+        let names = |query: &str| -> Vec<String> {
+            index
+                .search(query, 10)
+                .into_iter()
+                .map(|(symbol, _)| symbol.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names("sfc"), ["save_file_changes"]);
+        assert_eq!(names("save"), ["SaveDialog", "save_file_changes"]);
+        // Kind keywords filter results
+        assert_eq!(names("fn sa"), ["save_file_changes"]);
+        assert_eq!(names("struct "), ["Workspace", "SaveDialog"]);
+        // Without a following space a keyword is part of the name
+        assert_eq!(names("struct"), Vec::<String>::new());
+        assert_eq!(index.search("", 1).len(), 1);
+    }
+}
@@ -0,0 +1,184 @@
+// Parsing of `cargo --message-format=json` output
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::diagnostics::{Diagnostic, Severity, TextPosition, TextRange};
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<RustcMessage>,
+    // Of `compiler-artifact` messages of binaries and tests
+    executable: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RustcMessage {
+    message: String,
+    code: Option<RustcCode>,
+    level: String,
+    #[serde(default)]
+    spans: Vec<RustcSpan>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RustcCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RustcSpan {
+    file_name: String,
+    // One-based, columns count chars
+    line_start: u32,
+    line_end: u32,
+    column_start: u32,
+    column_end: u32,
+    is_primary: bool,
+}
+
+/// A line of cargo output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CargoLine {
+    // Not a JSON message, ex: output of tests or of the program run
+    Text(String),
+    // A compiler message, `rendered` is the text printed by rustc without JSON
+    Message {
+        rendered: String,
+        diagnostic: Option<Diagnostic>,
+    },
+    // Path of a built binary or test executable
+    Artifact(String),
+    // Other messages, ex: built libraries
+    Ignored,
+}
+
+/**
+ * Parses a line printed by cargo running in `root`, relative paths of
+ * compiler messages are resolved against it.
+ */
+pub fn parse_line(line: &str, root: &str) -> CargoLine {
+    if !line.starts_with('{') {
+        return CargoLine::Text(line.to_string());
+    }
+    let Ok(message) = serde_json::from_str::<CargoMessage>(line) else {
+        // Ex: JSON printed by a test
+        return CargoLine::Text(line.to_string());
+    };
+
+    match (message.reason.as_str(), message.message) {
+        ("compiler-message", Some(message)) => CargoLine::Message {
+            rendered: message
+                .rendered
+                .clone()
+                .unwrap_or_else(|| format!("{}: {}", message.level, message.message)),
+            diagnostic: to_diagnostic(&message, root),
+        },
+        ("compiler-artifact", _) => match message.executable {
+            Some(executable) => CargoLine::Artifact(executable),
+            None => CargoLine::Ignored,
+        },
+        _ => CargoLine::Ignored,
+    }
+}
+
+// Messages without a primary span, ex: `aborting due to 2 previous errors`,
+// have no diagnostic
+fn to_diagnostic(message: &RustcMessage, root: &str) -> Option<Diagnostic> {
+    let span = message.spans.iter().find(|span| span.is_primary)?;
+
+    let severity = match message.level.as_str() {
+        "warning" => Severity::Warning,
+        "note" | "failure-note" => Severity::Info,
+        "help" => Severity::Hint,
+        _ => Severity::Error,
+    };
+
+    // Clippy lints are named `clippy::lint_name`
+    let (source, message_text) = match &message.code {
+        Some(code) if code.code.starts_with("clippy::") => {
+            ("clippy", format!("{} ({})", message.message, code.code))
+        }
+        Some(code) => ("rustc", format!("{} [{}]", message.message, code.code)),
+        None => ("rustc", message.message.clone()),
+    };
+
+    let position = |line: u32, column: u32| TextPosition {
+        line: line.saturating_sub(1),
+        column: column.saturating_sub(1),
+    };
+
+    Some(Diagnostic {
+        path: Path::new(root)
+            .join(&span.file_name)
+            .to_string_lossy()
+            .to_string(),
+        range: TextRange {
+            start: position(span.line_start, span.column_start),
+            end: position(span.line_end, span.column_end),
+        },
+        severity,
+        message: message_text,
+        source: Some(source.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::{Severity, TextPosition};
+
+    use super::{parse_line, CargoLine};
+
+    #[test]
+    fn parse_line_test() {
+        let line = r#"{"reason":"compiler-message","package_id":"app 0.1.0","manifest_path":"/ws/app/Cargo.toml","target":{"name":"app"},"message":{"message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"app/src/main.rs","byte_start":16,"byte_end":17,"line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"text":[],"label":null}],"children":[],"rendered":"warning: unused variable: `x`\n --> app/src/main.rs:2:9\n"}}"#;
+
+        let CargoLine::Message {
+            rendered,
+            diagnostic: Some(diagnostic),
+        } = parse_line(line, "/ws")
+        else {
+            panic!("expected a compiler message");
+        };
+        assert!(rendered.starts_with("warning: unused variable"));
+        assert_eq!(diagnostic.path, "/ws/app/src/main.rs");
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.range.start, TextPosition { line: 1, column: 8 });
+        assert_eq!(diagnostic.range.end, TextPosition { line: 1, column: 9 });
+        assert_eq!(
+            diagnostic.message,
+            "unused variable: `x` [unused_variables]"
+        );
+        assert_eq!(diagnostic.source.as_deref(), Some("rustc"));
+
+        let summary = r#"{"reason":"compiler-message","message":{"message":"aborting due to 1 previous error","code":null,"level":"error","spans":[],"children":[],"rendered":"error: aborting due to 1 previous error\n"}}"#;
+        assert!(matches!(
+            parse_line(summary, "/ws"),
+            CargoLine::Message {
+                diagnostic: None,
+                ..
+            }
+        ));
+
+        let artifact =
+            r#"{"reason":"compiler-artifact","package_id":"app 0.1.0","executable":null}"#;
+        assert_eq!(parse_line(artifact, "/ws"), CargoLine::Ignored);
+        let executable = r#"{"reason":"compiler-artifact","package_id":"app 0.1.0","executable":"/ws/target/debug/app"}"#;
+        assert_eq!(
+            parse_line(executable, "/ws"),
+            CargoLine::Artifact(String::from("/ws/target/debug/app"))
+        );
+
+        assert_eq!(
+            parse_line("test tests::it_works ... ok", "/ws"),
+            CargoLine::Text(String::from("test tests::it_works ... ok"))
+        );
+        assert_eq!(
+            parse_line(r#"{"name": "not cargo"}"#, "/ws"),
+            CargoLine::Text(String::from(r#"{"name": "not cargo"}"#))
+        );
+    }
+}
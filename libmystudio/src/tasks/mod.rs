@@ -0,0 +1,456 @@
+// Tasks of a workspace: commands found in `Cargo.toml`, `Makefile` and
+// `package.json`, plus user tasks configured in `[Tasks]` of config.toml or
+// workspace settings.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{navigation::Location, terminal::resolve_link};
+
+pub mod cargo;
+pub mod runner;
+
+// Flag added to cargo commands so that compiler messages can be parsed
+const CARGO_MESSAGE_FORMAT: &str = "--message-format=json";
+
+const MAKEFILE_NAMES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// How output of a task is turned into diagnostics.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProblemMatcher {
+    // Output is only shown
+    #[default]
+    None,
+    // JSON messages of `cargo --message-format=json`
+    Cargo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskSource {
+    Cargo,
+    Make,
+    Npm,
+    User,
+}
+
+impl TaskSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskSource::Cargo => "cargo",
+            TaskSource::Make => "make",
+            TaskSource::Npm => "npm",
+            TaskSource::User => "user",
+        }
+    }
+}
+
+/// A user task, ex: `[Tasks.lint]` with `command = "cargo"`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TaskConfig {
+    // Program to run, looked up in `PATH`
+    pub command: String,
+    pub args: Vec<String>,
+    // Directory relative to the workspace, the workspace itself when empty
+    pub cwd: String,
+    pub problem_matcher: ProblemMatcher,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    // Shown in the task list, ex: `cargo: test -p libmystudio`
+    pub label: String,
+    pub source: TaskSource,
+    pub command: String,
+    pub args: Vec<String>,
+    // Absolute directory the command runs in
+    pub cwd: String,
+    pub problem_matcher: ProblemMatcher,
+}
+
+impl Task {
+    /// Returns the command as typed in a shell, ex: `cargo build --workspace`.
+    pub fn command_line(&self) -> String {
+        [self.command.as_str()]
+            .into_iter()
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub(crate) fn cargo(root: &Path, args: &[&str]) -> Self {
+        Self {
+            label: format!("cargo: {}", args.join(" ")),
+            source: TaskSource::Cargo,
+            command: String::from("cargo"),
+            args: args
+                .iter()
+                .take(1)
+                .chain([&CARGO_MESSAGE_FORMAT])
+                .chain(args.iter().skip(1))
+                .map(|arg| arg.to_string())
+                .collect(),
+            cwd: root.to_string_lossy().to_string(),
+            problem_matcher: ProblemMatcher::Cargo,
+        }
+    }
+}
+
+/**
+ * Returns tasks of the workspace at `root`: cargo, make and npm tasks found
+ * in its top directory followed by `user_tasks`.
+ */
+pub fn discover_tasks(root: &str, user_tasks: &BTreeMap<String, TaskConfig>) -> Vec<Task> {
+    let root = Path::new(root);
+    let mut tasks = vec![];
+
+    if !root.as_os_str().is_empty() {
+        tasks.extend(cargo_tasks(root));
+        tasks.extend(make_tasks(root));
+        tasks.extend(npm_tasks(root));
+    }
+
+    for (name, config) in user_tasks.iter() {
+        if config.command.trim().is_empty() {
+            continue;
+        }
+        let cwd = match config.cwd.trim() {
+            "" => root.to_path_buf(),
+            cwd => root.join(cwd),
+        };
+
+        tasks.push(Task {
+            label: name.clone(),
+            source: TaskSource::User,
+            command: config.command.clone(),
+            args: config.args.clone(),
+            cwd: cwd.to_string_lossy().to_string(),
+            problem_matcher: config.problem_matcher,
+        });
+    }
+
+    tasks
+}
+
+// A package of a cargo workspace
+pub(crate) struct CargoPackage {
+    pub name: String,
+    pub dir: PathBuf,
+    pub has_binary: bool,
+}
+
+fn read_cargo_package(dir: &Path) -> Option<CargoPackage> {
+    let manifest = read_toml(&dir.join("Cargo.toml"))?;
+    let name = manifest.get("package")?.get("name")?.as_str()?.to_string();
+    let has_binary = manifest.get("bin").is_some()
+        || dir.join("src/main.rs").is_file()
+        || dir.join("src/bin").is_dir();
+
+    Some(CargoPackage {
+        name,
+        dir: dir.to_path_buf(),
+        has_binary,
+    })
+}
+
+/**
+ * Returns packages of the cargo project at `root`, and whether it is a
+ * workspace with members.
+ */
+pub(crate) fn cargo_packages(root: &Path) -> (Vec<CargoPackage>, bool) {
+    let Some(manifest) = read_toml(&root.join("Cargo.toml")) else {
+        return (vec![], false);
+    };
+
+    let members: Vec<PathBuf> = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(toml::Value::as_array)
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(toml::Value::as_str)
+                .flat_map(|member| expand_member(root, member))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let packages = read_cargo_package(root)
+        .into_iter()
+        .chain(members.iter().filter_map(|dir| read_cargo_package(dir)))
+        .collect();
+    (packages, !members.is_empty())
+}
+
+fn cargo_tasks(root: &Path) -> Vec<Task> {
+    if !root.join("Cargo.toml").is_file() {
+        return vec![];
+    }
+
+    let (packages, is_workspace) = cargo_packages(root);
+    if !is_workspace {
+        // A single package
+        let mut tasks = vec![
+            Task::cargo(root, &["build"]),
+            Task::cargo(root, &["test"]),
+            Task::cargo(root, &["clippy"]),
+        ];
+        if packages.first().is_some_and(|package| package.has_binary) {
+            tasks.push(Task::cargo(root, &["run"]));
+        }
+        return tasks;
+    }
+
+    let mut tasks = vec![
+        Task::cargo(root, &["build", "--workspace"]),
+        Task::cargo(root, &["test", "--workspace"]),
+        Task::cargo(root, &["clippy", "--workspace"]),
+    ];
+
+    for package in packages {
+        let name = package.name.as_str();
+        tasks.push(Task::cargo(root, &["build", "-p", name]));
+        tasks.push(Task::cargo(root, &["test", "-p", name]));
+        tasks.push(Task::cargo(root, &["clippy", "-p", name]));
+        if package.has_binary {
+            tasks.push(Task::cargo(root, &["run", "-p", name]));
+        }
+    }
+
+    tasks
+}
+
+// Directories of a `workspace.members` entry, `dir/*` globs are expanded
+fn expand_member(root: &Path, member: &str) -> Vec<PathBuf> {
+    let Some(parent) = member.strip_suffix("/*") else {
+        return vec![root.join(member)];
+    };
+
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root.join(parent))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.join("Cargo.toml").is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn make_tasks(root: &Path) -> Vec<Task> {
+    let Some(makefile) = MAKEFILE_NAMES
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+    else {
+        return vec![];
+    };
+    let Ok(content) = fs::read_to_string(makefile) else {
+        return vec![];
+    };
+
+    make_targets(&content)
+        .into_iter()
+        .map(|target| Task {
+            label: format!("make: {target}"),
+            source: TaskSource::Make,
+            command: String::from("make"),
+            args: vec![target],
+            cwd: root.to_string_lossy().to_string(),
+            problem_matcher: ProblemMatcher::None,
+        })
+        .collect()
+}
+
+/**
+ * Returns explicit targets of a makefile in the order they are defined,
+ * special (`.PHONY`) and pattern (`%.o`) targets are skipped.
+ */
+pub fn make_targets(content: &str) -> Vec<String> {
+    let mut targets: Vec<String> = vec![];
+
+    for line in content.lines() {
+        // Recipes are indented, comments and directives have no `:`
+        if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+            continue;
+        }
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // Variable assignments, ex: `CC := gcc` or `A = b:c`
+        if rest.starts_with('=') || rest.starts_with(":=") || names.contains('=') {
+            continue;
+        }
+
+        for name in names.split_whitespace() {
+            let is_special = name.starts_with('.') || name.contains(['%', '$']);
+            if !is_special && !targets.iter().any(|target| target == name) {
+                targets.push(name.to_string());
+            }
+        }
+    }
+
+    targets
+}
+
+fn npm_tasks(root: &Path) -> Vec<Task> {
+    let Ok(content) = fs::read_to_string(root.join("package.json")) else {
+        return vec![];
+    };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return vec![];
+    };
+    let Some(scripts) = package
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+    else {
+        return vec![];
+    };
+
+    scripts
+        .keys()
+        .map(|script| Task {
+            label: format!("npm: {script}"),
+            source: TaskSource::Npm,
+            command: String::from("npm"),
+            args: vec![String::from("run"), script.clone()],
+            cwd: root.to_string_lossy().to_string(),
+            problem_matcher: ProblemMatcher::None,
+        })
+        .collect()
+}
+
+fn read_toml(path: &Path) -> Option<toml::Value> {
+    let content = fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/**
+ * Returns the first `path:line:col` location in a line of task output which
+ * points to an existing file, ex: `--> src/main.rs:4:9` printed by rustc.
+ */
+pub fn find_location(text: &str, root: &str) -> Option<Location> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| "\"'`()[]{}<>,;".contains(c)))
+        .map(|word| word.trim_end_matches(['.', ':']))
+        .filter(|word| word.contains(':'))
+        .find_map(|word| resolve_link(word, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs};
+
+    use tempfile::tempdir;
+
+    use super::{discover_tasks, find_location, make_targets, ProblemMatcher, TaskConfig};
+
+    #[test]
+    fn discover_tasks_test() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\", \"crates/*\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("app/src")).unwrap();
+        fs::write(root.join("app/Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        fs::write(root.join("app/src/main.rs"), "fn main() {}\n").unwrap();
+        fs::create_dir_all(root.join("crates/core")).unwrap();
+        fs::write(
+            root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("Makefile"), "all: build\n\tcargo build\n").unwrap();
+        fs::write(
+            root.join("package.json"),
+            r#"{"scripts": {"lint": "eslint ."}}"#,
+        )
+        .unwrap();
+
+        let user_tasks = BTreeMap::from([(
+            String::from("docs"),
+            TaskConfig {
+                command: String::from("cargo"),
+                args: vec![String::from("doc")],
+                ..Default::default()
+            },
+        )]);
+        let root_path = root.to_string_lossy().to_string();
+        let tasks = discover_tasks(&root_path, &user_tasks);
+        let labels: Vec<&str> = tasks.iter().map(|task| task.label.as_str()).collect();
+
+        assert_eq!(
+            labels,
+            [
+                "cargo: build --workspace",
+                "cargo: test --workspace",
+                "cargo: clippy --workspace",
+                "cargo: build -p app",
+                "cargo: test -p app",
+                "cargo: clippy -p app",
+                "cargo: run -p app",
+                "cargo: build -p core",
+                "cargo: test -p core",
+                "cargo: clippy -p core",
+                "make: all",
+                "npm: lint",
+                "docs",
+            ]
+        );
+
+        assert_eq!(
+            tasks[4].command_line(),
+            "cargo test --message-format=json -p app"
+        );
+        assert_eq!(tasks[4].problem_matcher, ProblemMatcher::Cargo);
+        assert_eq!(tasks[11].command_line(), "npm run lint");
+        assert_eq!(tasks[12].cwd, root_path);
+    }
+
+    #[test]
+    fn make_targets_test() {
+        let makefile = "\
+CC := gcc
+FLAGS = -O2
+.PHONY: all clean
+all: app docs
+app: main.o
+\t$(CC) -o app main.o
+%.o: %.c
+\t$(CC) -c $<
+# comment: not a target
+clean install:
+\trm -f app
+all: extra
+";
+        assert_eq!(make_targets(makefile), ["all", "app", "clean", "install"]);
+    }
+
+    #[test]
+    fn find_location_test() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+
+        let location = find_location("   --> src/main.rs:4:9", &root).unwrap();
+        assert_eq!((location.line, location.column), (4, 9));
+
+        let location = find_location("thread 'main' panicked at src/main.rs:10:5:", &root).unwrap();
+        assert_eq!((location.line, location.column), (10, 5));
+
+        assert!(find_location("Compiling app v0.1.0 (/tmp/app)", &root).is_none());
+        assert!(find_location("error: src/missing.rs:1:1", &root).is_none());
+    }
+}
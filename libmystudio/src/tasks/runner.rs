@@ -0,0 +1,261 @@
+// Running tasks in the background with their output streamed line by line
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::diagnostics::Diagnostic;
+
+use super::{
+    cargo::{parse_line, CargoLine},
+    ProblemMatcher, Task,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    Succeeded,
+    // Exit code, `None` when the task was killed by a signal
+    Failed(Option<i32>),
+    Stopped,
+}
+
+impl Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskStatus::Succeeded => write!(f, "succeeded"),
+            TaskStatus::Failed(Some(code)) => write!(f, "failed with exit code {code}"),
+            TaskStatus::Failed(None) => write!(f, "failed"),
+            TaskStatus::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// Sent by a running task, tagged with the id it was started with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskEvent {
+    // A line to show, without its line break
+    Output(u64, String),
+    // Found by the problem matcher of the task
+    Diagnostic(u64, Diagnostic),
+    // Executable built by a cargo task
+    Artifact(u64, String),
+    Finished(u64, TaskStatus),
+}
+
+/**
+A task started with `TaskRun::start`.
+
+Lines of stdout and stderr are passed to `on_event` from a background thread,
+in the order they are read.
+*/
+pub struct TaskRun {
+    id: u64,
+    process: Arc<Mutex<Child>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl TaskRun {
+    pub fn start<F>(id: u64, task: &Task, on_event: F) -> io::Result<TaskRun>
+    where
+        F: Fn(TaskEvent) + Send + 'static,
+    {
+        let mut process = Command::new(&task.command)
+            .args(&task.args)
+            .current_dir(&task.cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| {
+                io::Error::new(
+                    error.kind(),
+                    format!("unable to run '{}': {error}", task.command),
+                )
+            })?;
+
+        let (tx, rx) = mpsc::channel();
+        read_lines(process.stdout.take().unwrap(), tx.clone());
+        read_lines(process.stderr.take().unwrap(), tx);
+
+        let process = Arc::new(Mutex::new(process));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_process = process.clone();
+        let thread_stopped = stopped.clone();
+        let matcher = task.problem_matcher;
+        let cwd = task.cwd.clone();
+        thread::spawn(move || {
+            // Ends once both pipes are closed
+            for line in rx {
+                for event in match_line(id, matcher, &line, &cwd) {
+                    on_event(event);
+                }
+            }
+
+            let status = thread_process.lock().unwrap().wait();
+            let status = match status {
+                _ if thread_stopped.load(Ordering::SeqCst) => TaskStatus::Stopped,
+                Ok(status) if status.success() => TaskStatus::Succeeded,
+                Ok(status) => TaskStatus::Failed(status.code()),
+                Err(_) => TaskStatus::Failed(None),
+            };
+            on_event(TaskEvent::Finished(id, status));
+        });
+
+        Ok(TaskRun {
+            id,
+            process,
+            stopped,
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Kills the task, it then finishes with `TaskStatus::Stopped`.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.process.lock().unwrap().kill().ok();
+    }
+}
+
+// Sends lines of `pipe` to `tx` until it is closed, invalid UTF-8 is replaced
+fn read_lines(pipe: impl Read + Send + 'static, tx: mpsc::Sender<String>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buffer = vec![];
+
+        while let Ok(read) = reader.read_until(b'\n', &mut buffer) {
+            if read == 0 {
+                break;
+            }
+
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches(['\n', '\r']).to_string();
+            if tx.send(line).is_err() {
+                break;
+            }
+            buffer.clear();
+        }
+    });
+}
+
+// Events of a line of output
+fn match_line(id: u64, matcher: ProblemMatcher, line: &str, cwd: &str) -> Vec<TaskEvent> {
+    if matcher == ProblemMatcher::None {
+        return vec![TaskEvent::Output(id, line.to_string())];
+    }
+
+    match parse_line(line, cwd) {
+        CargoLine::Text(text) => vec![TaskEvent::Output(id, text)],
+        CargoLine::Message {
+            rendered,
+            diagnostic,
+        } => rendered
+            .trim_end()
+            .lines()
+            .map(|line| TaskEvent::Output(id, line.to_string()))
+            .chain(diagnostic.map(|diagnostic| TaskEvent::Diagnostic(id, diagnostic)))
+            .collect(),
+        CargoLine::Artifact(executable) => vec![TaskEvent::Artifact(id, executable)],
+        CargoLine::Ignored => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    use crate::tasks::{ProblemMatcher, Task, TaskSource};
+
+    use super::{TaskEvent, TaskRun, TaskStatus};
+
+    fn shell_task(script: &str, problem_matcher: ProblemMatcher) -> Task {
+        Task {
+            label: String::from("test"),
+            source: TaskSource::User,
+            command: String::from("sh"),
+            args: vec![String::from("-c"), script.to_string()],
+            cwd: std::env::temp_dir().to_string_lossy().to_string(),
+            problem_matcher,
+        }
+    }
+
+    // Events of a run until it finishes
+    fn collect_events(rx: &mpsc::Receiver<TaskEvent>) -> Vec<TaskEvent> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut events = vec![];
+
+        while let Ok(event) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            let finished = matches!(event, TaskEvent::Finished(..));
+            events.push(event);
+            if finished {
+                break;
+            }
+        }
+
+        events
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn task_run_test() {
+        let message = r#"{"reason":"compiler-message","message":{"message":"oops","code":null,"level":"error","spans":[{"file_name":"src/lib.rs","line_start":1,"line_end":1,"column_start":1,"column_end":2,"is_primary":true}],"rendered":"error: oops\n --> src/lib.rs:1:1\n"}}"#;
+        let task = shell_task(
+            &format!("echo 'Compiling' >&2; printf '%s\\n' '{message}'; exit 3"),
+            ProblemMatcher::Cargo,
+        );
+
+        let (tx, rx) = mpsc::channel();
+        let run = TaskRun::start(7, &task, move |event| tx.send(event).unwrap()).unwrap();
+        assert_eq!(run.id(), 7);
+
+        let events = collect_events(&rx);
+        assert!(events.contains(&TaskEvent::Output(7, String::from("Compiling"))));
+        assert!(events.contains(&TaskEvent::Output(7, String::from("error: oops"))));
+        assert!(events.contains(&TaskEvent::Output(7, String::from(" --> src/lib.rs:1:1"))));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, TaskEvent::Diagnostic(7, d) if d.message == "oops")));
+        assert_eq!(
+            events.last(),
+            Some(&TaskEvent::Finished(7, TaskStatus::Failed(Some(3))))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn task_stop_test() {
+        let task = shell_task("echo started; exec sleep 30", ProblemMatcher::None);
+
+        let (tx, rx) = mpsc::channel();
+        let run = TaskRun::start(1, &task, move |event| tx.send(event).unwrap()).unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(10)),
+            Ok(TaskEvent::Output(1, String::from("started")))
+        );
+
+        run.stop();
+        assert_eq!(
+            collect_events(&rx),
+            [TaskEvent::Finished(1, TaskStatus::Stopped)]
+        );
+
+        let missing = Task {
+            command: String::from("mystudio-missing-command"),
+            ..task
+        };
+        assert!(TaskRun::start(2, &missing, |_| {}).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+// Settings of the integrated terminal and `path:line:col` links in its output
+
+use std::{env, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::navigation::Location;
+
+/**
+ * Regex (PCRE2 syntax, used by VTE) of links to files in terminal output, ex:
+ * `src/main.rs:10:5` printed by cargo.
+ */
+pub const LINK_PATTERN: &str = r"(?:[\w.~-]*/)*[\w.-]*\w\.\w+:\d+(?::\d+)?";
+
+// Used when neither `Terminal.shell` nor `$SHELL` is set
+const FALLBACK_SHELL: &str = "/bin/sh";
+
+/// How several terminals are shown in the panel.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalLayout {
+    #[default]
+    Tabs,
+    // Side by side
+    Split,
+}
+
+impl TerminalLayout {
+    pub const ALL: [TerminalLayout; 2] = [TerminalLayout::Tabs, TerminalLayout::Split];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerminalLayout::Tabs => "tabs",
+            TerminalLayout::Split => "split",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct TerminalConfig {
+    // Program started in new terminals, `$SHELL` when empty
+    pub shell: String,
+    pub shell_args: Vec<String>,
+    pub layout: TerminalLayout,
+    // Editor font is used when empty
+    pub font_family: String,
+    // Editor font size is used when 0
+    pub font_size: u32,
+    pub scrollback_lines: u32,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            shell: String::new(),
+            shell_args: vec![],
+            layout: TerminalLayout::Tabs,
+            font_family: String::new(),
+            font_size: 0,
+            scrollback_lines: 10_000,
+        }
+    }
+}
+
+impl TerminalConfig {
+    /// Returns the program and arguments started in new terminals.
+    pub fn shell_command(&self) -> Vec<String> {
+        let shell = match self.shell.trim() {
+            "" => env::var("SHELL")
+                .ok()
+                .filter(|shell| !shell.trim().is_empty())
+                .unwrap_or_else(|| String::from(FALLBACK_SHELL)),
+            shell => shell.to_string(),
+        };
+
+        [shell]
+            .into_iter()
+            .chain(self.shell_args.iter().cloned())
+            .collect()
+    }
+}
+
+/**
+ * Returns the location of a link matched by `LINK_PATTERN`, relative paths
+ * are resolved against `root`.
+ *
+ * Returns `None` when the file doesn't exist.
+ */
+pub fn resolve_link(text: &str, root: &str) -> Option<Location> {
+    let (rest, last_number) = text.trim().rsplit_once(':')?;
+    let last_number: i32 = last_number.parse().ok()?;
+
+    let (path, line, column) = match rest.rsplit_once(':') {
+        Some((path, line)) if line.parse::<i32>().is_ok() => {
+            (path, line.parse().unwrap(), last_number)
+        }
+        _ => (rest, last_number, 1),
+    };
+
+    let path = Path::new(root).join(path);
+    if !path.is_file() {
+        return None;
+    }
+
+    Some(Location::new(
+        &path.to_string_lossy(),
+        line.max(1),
+        column.max(1),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{resolve_link, TerminalConfig};
+
+    #[test]
+    fn resolve_link_test() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let main_path = dir.path().join("src/main.rs").to_string_lossy().to_string();
+
+        let location = resolve_link("src/main.rs:10:5", &root).unwrap();
+        assert_eq!(
+            (location.path.as_str(), location.line, location.column),
+            (main_path.as_str(), 10, 5)
+        );
+
+        let location = resolve_link("src/main.rs:3", &root).unwrap();
+        assert_eq!((location.line, location.column), (3, 1));
+
+        // Absolute paths are kept
+        let location = resolve_link(&format!("{main_path}:2:1"), "/elsewhere").unwrap();
+        assert_eq!(location.path, main_path);
+
+        assert!(resolve_link("src/missing.rs:1:1", &root).is_none());
+        assert!(resolve_link("src/main.rs", &root).is_none());
+    }
+
+    #[test]
+    fn shell_command_test() {
+        let config = TerminalConfig {
+            shell: String::from("bash"),
+            shell_args: vec![String::from("--login")],
+            ..Default::default()
+        };
+        assert_eq!(config.shell_command(), ["bash", "--login"]);
+
+        assert!(!TerminalConfig::default().shell_command()[0].is_empty());
+    }
+}
@@ -0,0 +1,1001 @@
+// Tests of cargo workspaces: listing them with `cargo test -- --list`, finding
+// `#[test]` functions in files, the `cargo test` command running some of them
+// and parsing of its output
+
+use std::{fs, path::Path};
+
+use jwalk::WalkDir;
+
+use crate::{
+    outline::{rust_outline, OutlineItem},
+    symbols::SymbolKind,
+    tasks::{cargo_packages, CargoPackage, Task},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestStatus {
+    #[default]
+    NotRun,
+    // Part of a run which has not reported it yet
+    Queued,
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// Cargo target a test is compiled into.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TestTarget {
+    Lib,
+    // Name of the binary, ex: `app` for `src/main.rs`
+    Bin(String),
+    // Name of the integration test, ex: `api` for `tests/api.rs`
+    Test(String),
+}
+
+impl TestTarget {
+    // Arguments of `cargo test` selecting the target
+    pub(crate) fn cargo_args(&self) -> Vec<String> {
+        match self {
+            TestTarget::Lib => vec![String::from("--lib")],
+            TestTarget::Bin(name) => vec![String::from("--bin"), name.clone()],
+            TestTarget::Test(name) => vec![String::from("--test"), name.clone()],
+        }
+    }
+
+    /**
+     * Name of the test executable without its hash, as printed by
+     * `cargo test`, ex: `my_crate` for the lib of `my-crate`.
+     */
+    pub fn binary_name(&self, crate_name: &str) -> String {
+        match self {
+            TestTarget::Lib => crate_name,
+            TestTarget::Bin(name) | TestTarget::Test(name) => name,
+        }
+        .replace('-', "_")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    // Package the test belongs to
+    pub crate_name: String,
+    pub target: TestTarget,
+    // Name given by the test harness, ex: `parser::tests::parse_empty`
+    pub path: String,
+    pub file: String,
+    // Zero-based line of the `fn`
+    pub line: u32,
+    // Marked with `#[ignore]`
+    pub ignored: bool,
+}
+
+impl TestCase {
+    /// Modules of the test, ex: `["parser", "tests"]`.
+    pub fn modules(&self) -> Vec<&str> {
+        let mut modules: Vec<&str> = self.path.split("::").collect();
+        modules.pop();
+        modules
+    }
+
+    /// Name of the test function.
+    pub fn name(&self) -> &str {
+        self.path.rsplit("::").next().unwrap_or_default()
+    }
+
+    fn binary_name(&self) -> String {
+        self.target.binary_name(&self.crate_name)
+    }
+}
+
+/// Tests run by `test_task`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestScope {
+    All,
+    Crate(String),
+    // Tests of a module and its submodules
+    Module {
+        crate_name: String,
+        target: TestTarget,
+        path: String,
+    },
+    Tests(Vec<TestCase>),
+}
+
+impl TestScope {
+    /// Checks if running the scope runs `test`.
+    pub fn contains(&self, test: &TestCase) -> bool {
+        match self {
+            TestScope::All => true,
+            TestScope::Crate(crate_name) => test.crate_name == *crate_name,
+            TestScope::Module {
+                crate_name,
+                target,
+                path,
+            } => {
+                test.crate_name == *crate_name
+                    && test.target == *target
+                    && test.path.starts_with(&format!("{path}::"))
+            }
+            TestScope::Tests(tests) => tests.iter().any(|scope_test| {
+                scope_test.crate_name == test.crate_name
+                    && scope_test.target == test.target
+                    && scope_test.path == test.path
+            }),
+        }
+    }
+}
+
+/**
+ * Returns tests found in files of the cargo project at `root`, sorted by
+ * crate, target and file. Their module paths are guessed from file paths,
+ * see `merge_listed_tests` for those built by cargo.
+ *
+ * Tests are `fn`s marked with `#[test]` or an attribute ending with `::test`
+ * (ex: `#[tokio::test]`) in modules of `src`, `src/bin` and `tests`.
+ * Methods and functions nested in functions are not tests.
+ */
+pub fn discover_tests(root: &str) -> Vec<TestCase> {
+    let (packages, _) = cargo_packages(Path::new(root));
+    let mut tests = vec![];
+
+    for package in packages {
+        let files = rust_files(&package.dir.join("src"))
+            .into_iter()
+            .chain(rust_files(&package.dir.join("tests")));
+
+        for file in files {
+            let Some((target, modules)) = file_target(&package, &file) else {
+                continue;
+            };
+            let Ok(text) = fs::read_to_string(&file) else {
+                continue;
+            };
+            tests.extend(file_tests(&package.name, &target, &modules, &file, &text));
+        }
+    }
+
+    tests
+}
+
+/**
+ * Returns tests of a file of the cargo project at `root` from its current
+ * text, ex: of an editor with unsaved changes.
+ */
+pub fn tests_in_file(root: &str, file: &str, text: &str) -> Vec<TestCase> {
+    let file = Path::new(file);
+    let (packages, _) = cargo_packages(Path::new(root));
+
+    // Members can be nested in the directory of the root package
+    let package = packages
+        .iter()
+        .filter(|package| file.starts_with(&package.dir))
+        .max_by_key(|package| package.dir.components().count());
+
+    package
+        .and_then(|package| {
+            let (target, modules) = file_target(package, file)?;
+            Some(file_tests(&package.name, &target, &modules, file, text))
+        })
+        .unwrap_or_default()
+}
+
+// Target of a file of `package` and its module in the target, `None` for
+// files outside of targets
+fn file_target(package: &CargoPackage, file: &Path) -> Option<(TestTarget, Vec<String>)> {
+    let components = |dir: &str| -> Option<Vec<String>> {
+        let relative_path = file.strip_prefix(package.dir.join(dir)).ok()?;
+        Some(
+            relative_path
+                .iter()
+                .map(|component| component.to_string_lossy().to_string())
+                .collect(),
+        )
+    };
+
+    if let Some(components) = components("src") {
+        let has_lib = package.dir.join("src/lib.rs").is_file();
+
+        return Some(match components.as_slice() {
+            [bin, rest @ ..] if bin == "bin" && !rest.is_empty() => {
+                // `src/bin/tool.rs` or `src/bin/tool/main.rs`
+                let name = rest[0].trim_end_matches(".rs").to_string();
+                (TestTarget::Bin(name), module_path(&rest[1..]))
+            }
+            [main] if main == "main.rs" => (TestTarget::Bin(package.name.clone()), vec![]),
+            _ if has_lib => (TestTarget::Lib, module_path(&components)),
+            _ => (
+                TestTarget::Bin(package.name.clone()),
+                module_path(&components),
+            ),
+        });
+    }
+
+    // Files in subdirectories are modules shared by tests, except
+    // `tests/api/main.rs`
+    let components = components("tests")?;
+    let name = match components.as_slice() {
+        [file_name] => file_name.trim_end_matches(".rs"),
+        [dir, main] if main == "main.rs" => dir.as_str(),
+        _ => return None,
+    };
+    Some((TestTarget::Test(name.to_string()), vec![]))
+}
+
+// `.rs` files in `dir` and its subdirectories, sorted
+fn rust_files(dir: &Path) -> Vec<std::path::PathBuf> {
+    if !dir.is_dir() {
+        return vec![];
+    }
+
+    WalkDir::new(dir)
+        .sort(true)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rs"))
+        .collect()
+}
+
+// Module of a file from its path in the crate root directory, ex: `a/b.rs`
+// is `a::b`, `a/mod.rs` is `a` and `lib.rs` is the crate itself
+fn module_path(components: &[String]) -> Vec<String> {
+    let mut modules: Vec<String> = components
+        .iter()
+        .map(|component| component.trim_end_matches(".rs").to_string())
+        .collect();
+
+    match modules.last().map(String::as_str) {
+        Some("mod") => {
+            modules.pop();
+        }
+        Some("lib" | "main") if modules.len() == 1 => {
+            modules.pop();
+        }
+        _ => {}
+    }
+    modules
+}
+
+fn file_tests(
+    crate_name: &str,
+    target: &TestTarget,
+    modules: &[String],
+    file: &Path,
+    text: &str,
+) -> Vec<TestCase> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut tests = vec![];
+
+    for (path, item) in test_functions(&rust_outline(text), modules.to_vec()) {
+        let attributes = attributes_above(&lines, item.line);
+        if !attributes.iter().any(|name| is_test_attribute(name)) {
+            continue;
+        }
+
+        tests.push(TestCase {
+            crate_name: crate_name.to_string(),
+            target: target.clone(),
+            path,
+            file: file.to_string_lossy().to_string(),
+            line: item.line,
+            ignored: attributes.iter().any(|name| name == "ignore"),
+        });
+    }
+
+    tests
+}
+
+// Functions of modules with their path, ex: `tests::parse`
+fn test_functions(items: &[OutlineItem], modules: Vec<String>) -> Vec<(String, &OutlineItem)> {
+    let mut functions = vec![];
+
+    for item in items {
+        match item.kind {
+            SymbolKind::Function => {
+                let path = modules
+                    .iter()
+                    .map(String::as_str)
+                    .chain([item.name.as_str()])
+                    .collect::<Vec<_>>()
+                    .join("::");
+                functions.push((path, item));
+            }
+            SymbolKind::Module => {
+                let mut modules = modules.clone();
+                modules.push(item.name.clone());
+                functions.extend(test_functions(&item.children, modules));
+            }
+            _ => {}
+        }
+    }
+
+    functions
+}
+
+// Names of attributes in the lines right above `line`, ex: `tokio::test` for
+// `#[tokio::test(flavor = "multi_thread")]`, doc comments are skipped
+fn attributes_above(lines: &[&str], line: u32) -> Vec<String> {
+    lines[..(line as usize).min(lines.len())]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("#[") || line.starts_with("//"))
+        .filter_map(|line| line.strip_prefix("#["))
+        .map(|attribute| {
+            attribute
+                .split(['(', ']', ' ', '='])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
+fn is_test_attribute(name: &str) -> bool {
+    name == "test" || name.ends_with("::test")
+}
+
+/**
+ * Returns the `cargo test` task of the workspace at `root` running `scope`.
+ *
+ * Output of passing tests is shown as well, see `TestOutputParser`.
+ */
+pub fn test_task(root: &str, scope: &TestScope) -> Task {
+    let root = Path::new(root);
+    let mut args: Vec<String> = vec![String::from("test")];
+    let mut test_args: Vec<String> = vec![];
+
+    let label = match scope {
+        TestScope::All => {
+            args.push(String::from("--workspace"));
+            String::from("cargo: test --workspace")
+        }
+        TestScope::Crate(crate_name) => {
+            args.extend([String::from("-p"), crate_name.clone()]);
+            format!("cargo: test -p {crate_name}")
+        }
+        TestScope::Module {
+            crate_name,
+            target,
+            path,
+        } => {
+            args.extend([String::from("-p"), crate_name.clone()]);
+            args.extend(target.cargo_args());
+            test_args.push(format!("{path}::"));
+            format!("test: {crate_name} {path}")
+        }
+        TestScope::Tests(tests) => {
+            let mut targets: Vec<(&str, &TestTarget)> = tests
+                .iter()
+                .map(|test| (test.crate_name.as_str(), &test.target))
+                .collect();
+            targets.sort();
+            targets.dedup();
+
+            // Targets can be selected for a single crate only
+            if let [(crate_name, target)] = targets.as_slice() {
+                args.extend([String::from("-p"), crate_name.to_string()]);
+                args.extend(target.cargo_args());
+            } else {
+                let mut crate_names: Vec<&str> =
+                    targets.iter().map(|(crate_name, _)| *crate_name).collect();
+                crate_names.dedup();
+                for crate_name in crate_names {
+                    args.extend([String::from("-p"), crate_name.to_string()]);
+                }
+            }
+
+            test_args.extend(tests.iter().map(|test| test.path.clone()));
+            test_args.push(String::from("--exact"));
+            // Running a single test asks for it, even when ignored
+            if tests.len() == 1 {
+                test_args.push(String::from("--include-ignored"));
+            }
+
+            match tests.as_slice() {
+                [test] => format!("test: {} {}", test.crate_name, test.path),
+                _ => format!("test: {} tests", tests.len()),
+            }
+        }
+    };
+
+    args.push(String::from("--"));
+    args.extend(test_args);
+    args.push(String::from("--show-output"));
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    Task {
+        label,
+        ..Task::cargo(root, &args)
+    }
+}
+
+/**
+ * Returns the `cargo test` task listing tests of the workspace at `root`
+ * without running them, see `TestListParser`.
+ */
+pub fn list_tests_task(root: &str) -> Task {
+    Task {
+        label: String::from("cargo: test --workspace -- --list"),
+        ..Task::cargo(
+            Path::new(root),
+            &["test", "--workspace", "--", "--list", "--format=terse"],
+        )
+    }
+}
+
+/// A test printed by `list_tests_task`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedTest {
+    // Executable which printed it, see `TestTarget::binary_name`
+    pub binary_name: String,
+    // Root file of its target in the package, ex: `src/lib.rs`
+    pub source: String,
+    pub path: String,
+}
+
+impl ListedTest {
+    fn matches(&self, test: &TestCase) -> bool {
+        self.path == test.path && self.binary_name == test.binary_name()
+    }
+}
+
+/**
+Reads tests listed by `cargo test -- --list --format=terse` line by line.
+
+Each test binary prints its tests as `tests::parse: test` after a `Running`
+line naming the binary and its root file. Doc tests and benchmarks are
+skipped.
+*/
+#[derive(Debug, Default)]
+pub struct TestListParser {
+    // Binary name and root file of the binary listing tests
+    binary: Option<(String, String)>,
+}
+
+impl TestListParser {
+    pub fn parse_line(&mut self, line: &str) -> Option<ListedTest> {
+        let trimmed = line.trim();
+
+        if let Some(running) = trimmed.strip_prefix("Running ") {
+            self.binary = running_binary_name(running).map(|binary_name| {
+                // `unittests src/lib.rs (target/debug/deps/app-0f1e2d3c)`
+                let source = running
+                    .trim_start_matches("unittests ")
+                    .split(" (")
+                    .next()
+                    .filter(|source| source.ends_with(".rs"))
+                    .unwrap_or_default();
+                (binary_name, source.to_string())
+            });
+            return None;
+        }
+        if trimmed.starts_with("Doc-tests ") {
+            self.binary = None;
+            return None;
+        }
+
+        let (binary_name, source) = self.binary.clone()?;
+        let path = trimmed.strip_suffix(": test")?;
+
+        Some(ListedTest {
+            binary_name,
+            source,
+            path: path.to_string(),
+        })
+    }
+}
+
+/**
+ * Returns the tests listed by cargo, with their location in `scanned`
+ * tests found in files by `discover_tests`.
+ *
+ * Scanned tests cargo doesn't list are dropped, ex: in modules turned off by
+ * `#[cfg]`. Listed tests missing in files, ex: made by macros, point to the
+ * root file of their target.
+ */
+pub fn merge_listed_tests(
+    root: &str,
+    scanned: &[TestCase],
+    listed: &[ListedTest],
+) -> Vec<TestCase> {
+    let (packages, _) = cargo_packages(Path::new(root));
+
+    let mut tests: Vec<TestCase> = scanned
+        .iter()
+        .filter(|test| listed.iter().any(|listed_test| listed_test.matches(test)))
+        .cloned()
+        .collect();
+    for listed_test in listed {
+        if !scanned.iter().any(|test| listed_test.matches(test)) {
+            tests.extend(locate_listed_test(&packages, listed_test));
+        }
+    }
+
+    // Next to the other tests of their target
+    let mut targets: Vec<(String, TestTarget)> = vec![];
+    for test in &tests {
+        let target = (test.crate_name.clone(), test.target.clone());
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    tests.sort_by_key(|test| {
+        targets.iter().position(|(crate_name, target)| {
+            *crate_name == test.crate_name && *target == test.target
+        })
+    });
+
+    tests
+}
+
+// Finds the package and target of a listed test from its root file, the
+// test is at the top of that file
+fn locate_listed_test(packages: &[CargoPackage], listed_test: &ListedTest) -> Option<TestCase> {
+    if listed_test.source.is_empty() {
+        return None;
+    }
+
+    packages.iter().find_map(|package| {
+        let file = package.dir.join(&listed_test.source);
+        if !file.is_file() {
+            return None;
+        }
+        let (target, _) = file_target(package, &file)?;
+        if target.binary_name(&package.name) != listed_test.binary_name {
+            return None;
+        }
+
+        Some(TestCase {
+            crate_name: package.name.clone(),
+            target,
+            path: listed_test.path.clone(),
+            file: file.to_string_lossy().to_string(),
+            line: 0,
+            ignored: false,
+        })
+    })
+}
+
+/// Found by `TestOutputParser` in a line of `cargo test` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestUpdate {
+    Result {
+        // Executable which printed it, see `TestTarget::binary_name`
+        binary_name: String,
+        path: String,
+        status: TestStatus,
+    },
+    // Captured stdout of a test, printed after all of its binary ran
+    Output {
+        binary_name: String,
+        path: String,
+        output: String,
+    },
+}
+
+/**
+Reads results of `cargo test` line by line.
+
+Results are printed by each test binary as `test tests::parse ... ok`, after
+a `Running` line naming the binary. Captured output of tests follows under
+`---- tests::parse stdout ----` headers. Doc tests are skipped.
+*/
+#[derive(Debug, Default)]
+pub struct TestOutputParser {
+    binary_name: Option<String>,
+    // Test whose output is read and the lines read so far
+    capture: Option<(String, Vec<String>)>,
+}
+
+impl TestOutputParser {
+    pub fn parse_line(&mut self, line: &str) -> Vec<TestUpdate> {
+        let trimmed = line.trim();
+        let mut updates = vec![];
+
+        let ends_capture = (trimmed.starts_with("---- ") && trimmed.ends_with(" ----"))
+            || trimmed == "failures:"
+            || trimmed == "successes:"
+            || trimmed.starts_with("test result:")
+            || trimmed.starts_with("Running ")
+            || trimmed.starts_with("Doc-tests ");
+        if ends_capture {
+            updates.extend(self.finish_capture());
+        } else if let Some((_, lines)) = self.capture.as_mut() {
+            lines.push(line.to_string());
+            return updates;
+        }
+
+        if let Some(running) = trimmed.strip_prefix("Running ") {
+            self.binary_name = running_binary_name(running);
+        } else if trimmed.starts_with("Doc-tests ") {
+            self.binary_name = None;
+        }
+
+        let Some(binary_name) = self.binary_name.clone() else {
+            return updates;
+        };
+
+        if let Some(path) = trimmed
+            .strip_prefix("---- ")
+            .and_then(|header| header.strip_suffix(" stdout ----"))
+        {
+            self.capture = Some((path.to_string(), vec![]));
+        } else if let Some(result) = trimmed.strip_prefix("test ") {
+            let Some((path, outcome)) = result.rsplit_once(" ... ") else {
+                return updates;
+            };
+            let status = match outcome {
+                "ok" => TestStatus::Passed,
+                "FAILED" => TestStatus::Failed,
+                _ if outcome.starts_with("ignored") => TestStatus::Ignored,
+                _ => return updates,
+            };
+            let path = path.trim_end_matches(" - should panic");
+            updates.push(TestUpdate::Result {
+                binary_name,
+                path: path.to_string(),
+                status,
+            });
+        }
+
+        updates
+    }
+
+    fn finish_capture(&mut self) -> Option<TestUpdate> {
+        let (path, mut lines) = self.capture.take()?;
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        Some(TestUpdate::Output {
+            binary_name: self.binary_name.clone().unwrap_or_default(),
+            path,
+            output: lines.join("\n"),
+        })
+    }
+}
+
+// Binary name of a `Running` line of `cargo test`, ex: `app` for
+// `Running unittests src/lib.rs (target/debug/deps/app-0f1e2d3c)`
+fn running_binary_name(running: &str) -> Option<String> {
+    running
+        .rsplit(['/', '\\'])
+        .next()
+        .map(|file_name| file_name.trim_end_matches(')'))
+        .map(|file_name| file_name.trim_end_matches(".exe"))
+        .map(|file_name| match file_name.rsplit_once('-') {
+            Some((name, _hash)) => name.to_string(),
+            None => file_name.to_string(),
+        })
+}
+
+/**
+ * Checks if `update` is about `test`.
+ */
+pub fn update_matches(update: &TestUpdate, test: &TestCase) -> bool {
+    let (TestUpdate::Result {
+        binary_name, path, ..
+    }
+    | TestUpdate::Output {
+        binary_name, path, ..
+    }) = update;
+
+    *path == test.path && *binary_name == test.binary_name()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{
+        discover_tests, merge_listed_tests, test_task, tests_in_file, update_matches, ListedTest,
+        TestListParser, TestOutputParser, TestScope, TestStatus, TestTarget, TestUpdate,
+    };
+
+    #[test]
+    fn discover_tests_test() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"core\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("core/src/parser")).unwrap();
+        fs::create_dir_all(root.join("core/tests/common")).unwrap();
+        fs::write(
+            root.join("core/Cargo.toml"),
+            "[package]\nname = \"my-core\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("core/src/lib.rs"),
+            "\
+mod parser;
+
+pub fn add() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn adds() {}
+
+    /// Slow
+    #[test]
+    #[ignore = \"slow\"]
+    fn adds_many() {}
+
+    fn helper() {}
+}
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("core/src/parser/mod.rs"),
+            "\
+struct Parser;
+
+impl Parser {
+    #[test]
+    fn not_a_test() {}
+}
+
+#[tokio::test(flavor = \"multi_thread\")]
+async fn parses() {}
+",
+        )
+        .unwrap();
+        fs::write(
+            root.join("core/tests/api.rs"),
+            "#[test]\nfn api_works() {}\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("core/tests/common/mod.rs"),
+            "#[test]\nfn shared() {}\n",
+        )
+        .unwrap();
+
+        let tests = discover_tests(&root.to_string_lossy());
+        let found: Vec<(&TestTarget, &str, u32, bool)> = tests
+            .iter()
+            .map(|test| (&test.target, test.path.as_str(), test.line, test.ignored))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (&TestTarget::Lib, "tests::adds", 7, false),
+                (&TestTarget::Lib, "tests::adds_many", 12, true),
+                (&TestTarget::Lib, "parser::parses", 8, false),
+                (
+                    &TestTarget::Test(String::from("api")),
+                    "api_works",
+                    1,
+                    false
+                ),
+            ]
+        );
+        assert_eq!(tests[0].crate_name, "my-core");
+        assert_eq!(tests[0].modules(), ["tests"]);
+        assert_eq!(tests[0].name(), "adds");
+        assert!(tests[2].file.ends_with("mod.rs"));
+
+        let file = root.join("core/tests/api.rs").to_string_lossy().to_string();
+        let edited = tests_in_file(
+            &root.to_string_lossy(),
+            &file,
+            "#[test]\nfn api_works() {}\n\n#[test]\nfn api_fails() {}\n",
+        );
+        let paths: Vec<(&str, u32)> = edited
+            .iter()
+            .map(|test| (test.path.as_str(), test.line))
+            .collect();
+        assert_eq!(paths, [("api_works", 1), ("api_fails", 4)]);
+        assert_eq!(edited[1].target, TestTarget::Test(String::from("api")));
+    }
+
+    #[test]
+    fn merge_listed_tests_test() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"my-core\"\n").unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "\
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn adds() {}
+}
+
+#[cfg(feature = \"off\")]
+mod off {
+    #[test]
+    fn skipped() {}
+}
+",
+        )
+        .unwrap();
+        fs::write(root.join("tests/api.rs"), "#[test]\nfn api_works() {}\n").unwrap();
+
+        let output = "\
+   Compiling my-core v0.1.0 (/ws)
+     Running unittests src/lib.rs (target/debug/deps/my_core-0f1e2d3c4b5a6978)
+tests::adds: test
+tests::generated: test
+     Running tests/api.rs (target/debug/deps/api-1a2b3c4d5e6f7081)
+api_works: test
+   Doc-tests my_core
+src/lib.rs - add (line 3): test
+";
+        let mut parser = TestListParser::default();
+        let listed: Vec<ListedTest> = output
+            .lines()
+            .filter_map(|line| parser.parse_line(line))
+            .collect();
+        assert_eq!(
+            listed[2],
+            ListedTest {
+                binary_name: String::from("api"),
+                source: String::from("tests/api.rs"),
+                path: String::from("api_works"),
+            }
+        );
+        assert_eq!(listed.len(), 3);
+
+        let root = root.to_string_lossy();
+        let tests = merge_listed_tests(&root, &discover_tests(&root), &listed);
+        let found: Vec<(&TestTarget, &str, u32)> = tests
+            .iter()
+            .map(|test| (&test.target, test.path.as_str(), test.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (&TestTarget::Lib, "tests::adds", 3),
+                (&TestTarget::Lib, "tests::generated", 0),
+                (&TestTarget::Test(String::from("api")), "api_works", 1),
+            ]
+        );
+        assert!(tests[1].file.ends_with("lib.rs"));
+        assert_eq!(tests[1].crate_name, "my-core");
+    }
+
+    #[test]
+    fn test_task_test() {
+        let test = |path: &str, target: TestTarget| super::TestCase {
+            crate_name: String::from("app"),
+            target,
+            path: path.to_string(),
+            file: String::from("/ws/app/src/lib.rs"),
+            line: 0,
+            ignored: false,
+        };
+
+        let task = test_task("/ws", &TestScope::All);
+        assert_eq!(
+            task.command_line(),
+            "cargo test --message-format=json --workspace -- --show-output"
+        );
+        assert_eq!(task.cwd, "/ws");
+
+        let module = TestScope::Module {
+            crate_name: String::from("app"),
+            target: TestTarget::Lib,
+            path: String::from("parser"),
+        };
+        assert_eq!(
+            test_task("/ws", &module).command_line(),
+            "cargo test --message-format=json -p app --lib -- parser:: --show-output"
+        );
+        assert!(module.contains(&test("parser::tests::parses", TestTarget::Lib)));
+        assert!(!module.contains(&test("parsers::parses", TestTarget::Lib)));
+
+        let single = test_task(
+            "/ws",
+            &TestScope::Tests(vec![test(
+                "api_works",
+                TestTarget::Test(String::from("api")),
+            )]),
+        );
+        assert_eq!(single.label, "test: app api_works");
+        assert_eq!(
+            single.command_line(),
+            "cargo test --message-format=json -p app --test api -- api_works --exact --include-ignored --show-output"
+        );
+
+        let failed = TestScope::Tests(vec![
+            test("tests::a", TestTarget::Lib),
+            test("b", TestTarget::Test(String::from("api"))),
+        ]);
+        assert_eq!(
+            test_task("/ws", &failed).command_line(),
+            "cargo test --message-format=json -p app -- tests::a b --exact --show-output"
+        );
+        assert!(failed.contains(&test("b", TestTarget::Test(String::from("api")))));
+        assert!(!failed.contains(&test("b", TestTarget::Lib)));
+    }
+
+    #[test]
+    fn test_output_parser_test() {
+        let output = "\
+     Running unittests src/lib.rs (target/debug/deps/my_core-0f1e2d3c4b5a6978)
+
+running 3 tests
+test tests::adds ... ok
+test tests::adds_many ... ignored, slow
+test tests::panics - should panic ... FAILED
+
+successes:
+
+---- tests::adds stdout ----
+adding
+
+successes:
+    tests::adds
+
+failures:
+
+---- tests::panics stdout ----
+thread 'tests::panics' panicked at src/lib.rs:20:5:
+boom
+
+
+failures:
+    tests::panics
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+
+   Doc-tests my_core
+
+test src/lib.rs - add (line 3) ... ok
+";
+        let mut parser = TestOutputParser::default();
+        let updates: Vec<TestUpdate> = output
+            .lines()
+            .flat_map(|line| parser.parse_line(line))
+            .collect();
+
+        let result = |path: &str, status| TestUpdate::Result {
+            binary_name: String::from("my_core"),
+            path: path.to_string(),
+            status,
+        };
+        let captured = |path: &str, output: &str| TestUpdate::Output {
+            binary_name: String::from("my_core"),
+            path: path.to_string(),
+            output: output.to_string(),
+        };
+        assert_eq!(
+            updates,
+            [
+                result("tests::adds", TestStatus::Passed),
+                result("tests::adds_many", TestStatus::Ignored),
+                result("tests::panics", TestStatus::Failed),
+                captured("tests::adds", "adding"),
+                captured(
+                    "tests::panics",
+                    "thread 'tests::panics' panicked at src/lib.rs:20:5:\nboom"
+                ),
+            ]
+        );
+
+        let test = super::TestCase {
+            crate_name: String::from("my-core"),
+            target: TestTarget::Lib,
+            path: String::from("tests::adds"),
+            file: String::new(),
+            line: 0,
+            ignored: false,
+        };
+        assert!(update_matches(&updates[0], &test));
+        assert!(!update_matches(&updates[1], &test));
+    }
+}
@@ -1,9 +1,9 @@
 use gtk::{
-    prelude::ObjectExt, traits::TreeModelExt, CellRenderer, CellRendererPixbuf, CellRendererText,
-    TreeIter, TreeModel, TreeViewColumn,
+    glib, prelude::ObjectExt, traits::TreeModelExt, CellRenderer, CellRendererPixbuf,
+    CellRendererText, TreeIter, TreeModel, TreeViewColumn,
 };
 
-use super::tree_model::{RootTreeModel, TreeNodeType};
+use super::tree_model::{RootTreeModel, TreeNodeGitStatus, TreeNodeType};
 
 pub fn set_cell_data(
     _: &TreeViewColumn,
@@ -16,10 +16,25 @@ pub fn set_cell_data(
         .get::<RootTreeModel>()
         .unwrap();
 
-    // Set the text
+    // Set the text, colored by git status
     let filename = tree_model.property_value("file-name");
     if cell.is::<CellRendererText>() {
-        cell.set_property("text", filename.clone());
+        let name = filename
+            .get::<Option<String>>()
+            .unwrap()
+            .unwrap_or_default();
+        let item_type = tree_model
+            .property_value("item-type")
+            .get::<TreeNodeType>()
+            .unwrap();
+        let git_status = tree_model
+            .property_value("git-status")
+            .get::<TreeNodeGitStatus>()
+            .unwrap();
+        cell.set_property(
+            "markup",
+            get_git_status_markup(&name, item_type, git_status),
+        );
     }
 
     // Set icon
@@ -42,6 +57,39 @@ pub fn set_cell_data(
     }
 }
 
+/**
+ * Returns Pango markup of `name` colored by `git_status` and followed by a badge,
+ * ex: `M` for modified files. Directories get a dot instead of a letter.
+ */
+pub fn get_git_status_markup(
+    name: &str,
+    item_type: TreeNodeType,
+    git_status: TreeNodeGitStatus,
+) -> String {
+    let name = glib::markup_escape_text(name);
+
+    let (color, badge) = match git_status {
+        TreeNodeGitStatus::Clean => return name.to_string(),
+        TreeNodeGitStatus::Modified => ("#c4a000", "M"),
+        TreeNodeGitStatus::Added => ("#4e9a06", "A"),
+        TreeNodeGitStatus::Untracked => ("#4e9a06", "U"),
+        TreeNodeGitStatus::Deleted => ("#cc0000", "D"),
+        TreeNodeGitStatus::Conflicted => ("#cc0000", "C"),
+        // Dimmed, without badge
+        TreeNodeGitStatus::Ignored => ("#888a85", ""),
+    };
+    let badge = match item_type {
+        TreeNodeType::Directory | TreeNodeType::Workspace if !badge.is_empty() => "\u{2022}",
+        _ => badge,
+    };
+
+    if badge.is_empty() {
+        format!("<span foreground=\"{color}\">{name}</span>")
+    } else {
+        format!("<span foreground=\"{color}\">{name}  <small><b>{badge}</b></small></span>")
+    }
+}
+
 pub fn get_icon_for_name(filename: &str, icon_type: TreeNodeType) -> String {
     if icon_type == TreeNodeType::Directory {
         return "folder".to_owned();
@@ -67,7 +115,7 @@ fn get_icon_name(filename: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::tree::tree_cell::get_icon_for_name;
+    use crate::tree::tree_cell::{get_git_status_markup, get_icon_for_name};
 
     fn test_icon_name_mime<'a>(
         name: &'a str,
@@ -83,6 +131,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn git_status_markup() {
+        use crate::tree::tree_model::{TreeNodeGitStatus, TreeNodeType};
+
+        assert_eq!(
+            get_git_status_markup("a&b.rs", TreeNodeType::File, TreeNodeGitStatus::Clean),
+            "a&amp;b.rs"
+        );
+        assert_eq!(
+            get_git_status_markup("main.rs", TreeNodeType::File, TreeNodeGitStatus::Modified),
+            "<span foreground=\"#c4a000\">main.rs  <small><b>M</b></small></span>"
+        );
+        assert_eq!(
+            get_git_status_markup("src", TreeNodeType::Directory, TreeNodeGitStatus::Added),
+            "<span foreground=\"#4e9a06\">src  <small><b>\u{2022}</b></small></span>"
+        );
+        assert_eq!(
+            get_git_status_markup(
+                "target",
+                TreeNodeType::Directory,
+                TreeNodeGitStatus::Ignored
+            ),
+            "<span foreground=\"#888a85\">target</span>"
+        );
+    }
+
     /**
      * Note: This test needs to be run on Linux and Windows for 100% assurance.
      */
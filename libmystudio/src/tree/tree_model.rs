@@ -4,13 +4,17 @@ use gtk::{
     TreeIter, TreeStore,
 };
 
-use std::cell::RefCell;
+use std::{cell::RefCell, path::Path};
 
 use gtk::glib;
 
 use jwalk::DirEntry;
 
-use crate::{fs::read_dir_recursive, workspace::Workspace};
+use crate::{
+    fs::read_dir_recursive,
+    git::status::{FileStatus, GitStatus},
+    workspace::Workspace,
+};
 pub struct TreeInfo {
     pub value: String,
     pub iter: TreeIter,
@@ -32,6 +36,39 @@ impl Default for TreeNodeType {
     }
 }
 
+#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(i32)]
+#[enum_type(name = "TreeNodeGitStatus")]
+pub enum TreeNodeGitStatus {
+    Clean = 0,
+    Modified = 1,
+    Added = 2,
+    Deleted = 3,
+    Untracked = 4,
+    Ignored = 5,
+    Conflicted = 6,
+}
+
+impl Default for TreeNodeGitStatus {
+    fn default() -> Self {
+        TreeNodeGitStatus::Clean
+    }
+}
+
+impl From<Option<FileStatus>> for TreeNodeGitStatus {
+    fn from(status: Option<FileStatus>) -> Self {
+        match status {
+            None => TreeNodeGitStatus::Clean,
+            Some(FileStatus::Modified) => TreeNodeGitStatus::Modified,
+            Some(FileStatus::Added) => TreeNodeGitStatus::Added,
+            Some(FileStatus::Deleted) => TreeNodeGitStatus::Deleted,
+            Some(FileStatus::Untracked) => TreeNodeGitStatus::Untracked,
+            Some(FileStatus::Ignored) => TreeNodeGitStatus::Ignored,
+            Some(FileStatus::Conflicted) => TreeNodeGitStatus::Conflicted,
+        }
+    }
+}
+
 mod imp {
     use std::cell::Cell;
 
@@ -47,6 +84,7 @@ mod imp {
         file_name: RefCell<Option<String>>,
         abs_path: RefCell<Option<String>>,
         item_type: Cell<Option<TreeNodeType>>,
+        git_status: Cell<TreeNodeGitStatus>,
     }
 
     #[glib::object_subclass]
@@ -86,6 +124,14 @@ mod imp {
                         TreeNodeType::Unknown as i32,
                         glib::ParamFlags::READWRITE,
                     ),
+                    glib::ParamSpecEnum::new(
+                        "git-status",
+                        "Git status of node",
+                        "git-status",
+                        imp::TreeNodeGitStatus::static_type(),
+                        TreeNodeGitStatus::Clean as i32,
+                        glib::ParamFlags::READWRITE,
+                    ),
                 ]
             });
 
@@ -118,6 +164,12 @@ mod imp {
                         .expect("type conformity checked by `Object::set_property`");
                     self.item_type.replace(Some(item_type));
                 }
+                "git-status" => {
+                    let git_status = value
+                        .get()
+                        .expect("type conformity checked by `Object::set_property`");
+                    self.git_status.replace(git_status);
+                }
                 e => {
                     println!("requested set-property for unknown property: '{e}'");
                 }
@@ -129,6 +181,7 @@ mod imp {
                 "file-name" => self.file_name.borrow().to_value(),
                 "abs-path" => self.abs_path.borrow().to_value(),
                 "item-type" => self.item_type.get().unwrap_or_default().to_value(),
+                "git-status" => self.git_status.get().to_value(),
                 e => {
                     println!("requested unknown property: '{e}'");
                     e.to_value()
@@ -173,6 +226,7 @@ impl RootTreeModel {
     ) {
         // Remove and return first item of files
         let root_dir = files.remove(0);
+        let git_status = GitStatus::current();
 
         // Custom Model
         let tree_model_struct = RootTreeModel::new();
@@ -182,6 +236,10 @@ impl RootTreeModel {
             &root_dir.parent_path().as_os_str().to_str().unwrap(),
         );
         tree_model_struct.set_property("item-type", &TreeNodeType::Workspace);
+        tree_model_struct.set_property(
+            "git-status",
+            TreeNodeGitStatus::from(git_status.status_of(Path::new(&Workspace::get_path()))),
+        );
 
         let root_iter = if let Some(iter) = selected_iter {
             iter.to_owned()
@@ -220,6 +278,10 @@ impl RootTreeModel {
             tree_model_struct.set_property("file-name", &entry_file_str);
             tree_model_struct.set_property("abs-path", &entry_path_str);
             tree_model_struct.set_property("item-type", &item_type);
+            tree_model_struct.set_property(
+                "git-status",
+                TreeNodeGitStatus::from(git_status.status_of(&entry_path)),
+            );
 
             let m_iter =
                 store.insert_with_values(Some(parent_iter), None, &[(0, &tree_model_struct)]);
@@ -252,6 +314,39 @@ impl RootTreeModel {
         });
     }
 
+    /**
+     * Updates git status of every node from `GitStatus::current()`.
+     *
+     * Only rows whose status changed are redrawn.
+     */
+    pub fn apply_git_status(store: &TreeStore) {
+        let git_status = GitStatus::current();
+        let workspace_path = Workspace::get_path();
+
+        store.foreach(|model, path, iter| {
+            let node = model.value(iter, 0).get::<RootTreeModel>().unwrap();
+            let item_type = node
+                .property_value("item-type")
+                .get::<TreeNodeType>()
+                .unwrap();
+            let abs_path = match item_type {
+                TreeNodeType::Workspace => Some(workspace_path.clone()),
+                // Filler rows have no path
+                _ => node.property::<Option<String>>("abs-path"),
+            };
+
+            if let Some(abs_path) = abs_path {
+                let status = TreeNodeGitStatus::from(git_status.status_of(Path::new(&abs_path)));
+                if node.property::<TreeNodeGitStatus>("git-status") != status {
+                    node.set_property("git-status", status);
+                    model.row_changed(path, iter);
+                }
+            }
+
+            false
+        });
+    }
+
     pub fn update_tree_model(tree: &gtk::TreeView) {
         tree.set_model(Some(&RootTreeModel::build_tree_model()));
         // Expand root node and select it
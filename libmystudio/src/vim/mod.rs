@@ -0,0 +1,1201 @@
+use std::fmt::Display;
+
+use self::{
+    motions::{
+        change_word_end, clamp_to_char, first_non_blank, line_count, line_end, line_of, line_start,
+        offset_of_line, Motion, MotionKind,
+    },
+    registers::{Register, Registers},
+};
+
+pub mod motions;
+pub mod registers;
+
+/**
+ * Text buffer edited by Vim emulation, offsets are char offsets.
+ */
+pub trait VimBuffer {
+    fn text(&self) -> String;
+    fn cursor(&self) -> usize;
+    fn set_cursor(&mut self, offset: usize);
+    // Selects text between `anchor` and `cursor` and moves the cursor
+    fn set_selection(&mut self, anchor: usize, cursor: usize);
+    fn delete(&mut self, start: usize, end: usize);
+    fn insert(&mut self, offset: usize, text: &str);
+    fn undo(&mut self);
+    fn redo(&mut self);
+    // Edits between these calls are undone at once
+    fn begin_change(&mut self) {}
+    fn end_change(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimMode {
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+    // Typing a `:` command
+    CommandLine,
+}
+
+impl Display for VimMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+            VimMode::Visual => "VISUAL",
+            VimMode::VisualLine => "VISUAL LINE",
+            VimMode::CommandLine => "COMMAND",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimKey {
+    Char(char),
+    // Char pressed with Ctrl, ex: `Ctrl('r')`
+    Ctrl(char),
+    Escape,
+    Enter,
+    Backspace,
+}
+
+/**
+ * `:` commands which need to be handled by the application.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    // :w
+    Write,
+    // :q
+    Quit,
+    // :wq, :x
+    WriteQuit,
+    // :{line}, 1-based
+    GotoLine(usize),
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VimResponse {
+    // Key was handled and shouldn't reach the editor
+    pub consumed: bool,
+    pub command: Option<ExCommand>,
+}
+
+impl VimResponse {
+    fn consumed() -> Self {
+        Self {
+            consumed: true,
+            command: None,
+        }
+    }
+
+    fn ignored() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Motion(Motion),
+    // Doubled operator, ex: `dd`
+    Line,
+    // Visual mode selection
+    Selection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Insert,
+    Append,
+    InsertAtLineStart,
+    AppendAtLineEnd,
+    OpenBelow,
+    OpenAbove,
+    PutAfter,
+    PutBefore,
+    Undo,
+    Redo,
+    Visual,
+    VisualLine,
+    Repeat,
+    CommandLine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandKind {
+    Move(Motion),
+    Operate(Operator, Target),
+    Act(Action),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Command {
+    register: Option<char>,
+    count: Option<usize>,
+    kind: CommandKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Parsed<T> {
+    Incomplete,
+    Invalid,
+    Complete(T),
+}
+
+/**
+ * Modal editing state shared by all editors.
+ *
+ * Keys are fed one at a time with `handle_key`. In insert mode keys are not
+ * consumed so the editor inserts them as usual.
+ */
+#[derive(Debug)]
+pub struct Vim {
+    mode: VimMode,
+    // Keys of the command being typed, ex: `"a2d`
+    pending: Vec<VimKey>,
+    command_line: String,
+    registers: Registers,
+    // Keys of the last change, replayed by `.`
+    last_change: Vec<VimKey>,
+    // Keys of a change which is still in insert mode
+    insert_change: Option<Vec<VimKey>>,
+    visual_anchor: usize,
+    visual_cursor: usize,
+    // Insert mode keys are applied by Vim itself while repeating a change
+    replaying: bool,
+}
+
+impl Default for Vim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vim {
+    pub fn new() -> Self {
+        Self {
+            mode: VimMode::Normal,
+            pending: vec![],
+            command_line: String::new(),
+            registers: Registers::default(),
+            last_change: vec![],
+            insert_change: None,
+            visual_anchor: 0,
+            visual_cursor: 0,
+            replaying: false,
+        }
+    }
+
+    pub fn mode(&self) -> VimMode {
+        self.mode
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Text shown in the status bar, ex: `-- INSERT --` or `:w`.
+    pub fn status(&self) -> String {
+        match self.mode {
+            VimMode::CommandLine => format!(":{}", self.command_line),
+            mode => format!("-- {mode} --"),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: VimKey, buffer: &mut dyn VimBuffer) -> VimResponse {
+        match self.mode {
+            VimMode::Insert => self.handle_insert_key(key, buffer),
+            VimMode::CommandLine => self.handle_command_line_key(key),
+            VimMode::Normal | VimMode::Visual | VimMode::VisualLine => {
+                self.handle_command_key(key, buffer)
+            }
+        }
+    }
+
+    fn handle_insert_key(&mut self, key: VimKey, buffer: &mut dyn VimBuffer) -> VimResponse {
+        match key {
+            VimKey::Escape => {
+                self.mode = VimMode::Normal;
+
+                // Cursor moves back onto the last inserted char
+                let text = chars(buffer);
+                let cursor = buffer.cursor();
+                if cursor > line_start(&text, cursor) {
+                    buffer.set_cursor(cursor - 1);
+                }
+
+                if let Some(mut keys) = self.insert_change.take() {
+                    keys.push(VimKey::Escape);
+                    self.last_change = keys;
+                }
+
+                VimResponse::consumed()
+            }
+            // Editor shortcuts, ex: Ctrl+V
+            VimKey::Ctrl(_) => VimResponse::ignored(),
+            key => {
+                if let Some(keys) = self.insert_change.as_mut() {
+                    keys.push(key);
+                }
+
+                if !self.replaying {
+                    return VimResponse::ignored();
+                }
+
+                let cursor = buffer.cursor();
+                match key {
+                    VimKey::Char(c) => {
+                        buffer.insert(cursor, &c.to_string());
+                        buffer.set_cursor(cursor + 1);
+                    }
+                    VimKey::Enter => {
+                        buffer.insert(cursor, "\n");
+                        buffer.set_cursor(cursor + 1);
+                    }
+                    VimKey::Backspace if cursor > 0 => {
+                        buffer.delete(cursor - 1, cursor);
+                        buffer.set_cursor(cursor - 1);
+                    }
+                    _ => {}
+                }
+
+                VimResponse::consumed()
+            }
+        }
+    }
+
+    fn handle_command_line_key(&mut self, key: VimKey) -> VimResponse {
+        match key {
+            VimKey::Char(c) => self.command_line.push(c),
+            VimKey::Backspace => {
+                if self.command_line.pop().is_none() {
+                    self.mode = VimMode::Normal;
+                }
+            }
+            VimKey::Escape => {
+                self.mode = VimMode::Normal;
+                self.command_line.clear();
+            }
+            VimKey::Enter => {
+                self.mode = VimMode::Normal;
+                let command_line = std::mem::take(&mut self.command_line);
+
+                return VimResponse {
+                    consumed: true,
+                    command: parse_ex_command(&command_line),
+                };
+            }
+            VimKey::Ctrl(_) => {}
+        }
+
+        VimResponse::consumed()
+    }
+
+    fn handle_command_key(&mut self, key: VimKey, buffer: &mut dyn VimBuffer) -> VimResponse {
+        let visual = self.is_visual();
+
+        if key == VimKey::Escape {
+            self.pending.clear();
+            if visual {
+                self.exit_visual(buffer);
+            }
+            return VimResponse::consumed();
+        }
+
+        // Leave editor shortcuts alone, except for redo
+        if matches!(key, VimKey::Ctrl(c) if c != 'r') {
+            return VimResponse::ignored();
+        }
+
+        self.pending.push(key);
+        match parse_command(&self.pending, visual) {
+            Parsed::Incomplete => {}
+            Parsed::Invalid => self.pending.clear(),
+            Parsed::Complete(command) => {
+                let keys = std::mem::take(&mut self.pending);
+                self.execute(command, keys, buffer);
+            }
+        }
+
+        VimResponse::consumed()
+    }
+
+    fn is_visual(&self) -> bool {
+        matches!(self.mode, VimMode::Visual | VimMode::VisualLine)
+    }
+
+    fn execute(&mut self, command: Command, keys: Vec<VimKey>, buffer: &mut dyn VimBuffer) {
+        let text = chars(buffer);
+        let visual = self.is_visual();
+        let cursor = if visual {
+            self.visual_cursor
+        } else {
+            buffer.cursor()
+        };
+
+        match command.kind {
+            CommandKind::Move(motion) => {
+                let Some(target) = motion.target(&text, cursor, command.count) else {
+                    return;
+                };
+
+                if visual {
+                    self.visual_cursor = clamp_to_char(&text, target);
+                    self.update_selection(&text, buffer);
+                } else {
+                    buffer.set_cursor(clamp_to_char(&text, target));
+                }
+            }
+            CommandKind::Operate(operator, target) => {
+                let Some((start, end, linewise)) =
+                    self.operator_range(&text, cursor, operator, target, command.count)
+                else {
+                    return;
+                };
+
+                if visual {
+                    self.mode = VimMode::Normal;
+                }
+
+                let register = Register {
+                    text: text[start..end].iter().collect::<String>()
+                        + if linewise { "\n" } else { "" },
+                    linewise,
+                };
+                self.registers
+                    .store(command.register, register, operator == Operator::Yank);
+
+                self.operate(
+                    operator, &text, cursor, start, end, linewise, visual, buffer,
+                );
+
+                // Visual mode changes are not repeated
+                if visual {
+                    return;
+                }
+                match operator {
+                    Operator::Delete => self.last_change = keys,
+                    Operator::Change => self.insert_change = Some(keys),
+                    Operator::Yank => {}
+                }
+            }
+            CommandKind::Act(action) => self.act(action, command, keys, &text, cursor, buffer),
+        }
+    }
+
+    /**
+     * Returns the range affected by an operator, linewise ranges span whole
+     * lines without the last line break.
+     */
+    fn operator_range(
+        &self,
+        text: &[char],
+        cursor: usize,
+        operator: Operator,
+        target: Target,
+        count: Option<usize>,
+    ) -> Option<(usize, usize, bool)> {
+        match target {
+            Target::Selection => {
+                let start = self.visual_anchor.min(self.visual_cursor);
+                let end = self.visual_anchor.max(self.visual_cursor);
+
+                if self.mode == VimMode::VisualLine {
+                    Some((line_start(text, start), line_end(text, end), true))
+                } else {
+                    Some((start, (end + 1).min(text.len()), false))
+                }
+            }
+            Target::Line => {
+                let last_line =
+                    (line_of(text, cursor) + count.unwrap_or(1) - 1).min(line_count(text) - 1);
+                let end = line_end(text, offset_of_line(text, last_line));
+
+                Some((line_start(text, cursor), end, true))
+            }
+            Target::Motion(Motion::WordForward)
+                if operator == Operator::Change
+                    && text.get(cursor).is_some_and(|c| !c.is_whitespace()) =>
+            {
+                // `cw` changes up to the end of word, like `ce`
+                let end = change_word_end(text, cursor, count.unwrap_or(1));
+                Some((cursor, (end + 1).min(text.len()), false))
+            }
+            Target::Motion(motion) => {
+                let mut target = motion.target(text, cursor, count)?;
+                let (start, end) = (cursor.min(target), cursor.max(target));
+
+                match motion.kind() {
+                    MotionKind::Linewise => {
+                        Some((line_start(text, start), line_end(text, end), true))
+                    }
+                    MotionKind::Inclusive => Some((start, (end + 1).min(text.len()), false)),
+                    MotionKind::Exclusive => {
+                        // `dw` on the last word of a line stops at the line end
+                        if motion == Motion::WordForward && target > line_end(text, cursor) {
+                            target = line_end(text, cursor).max(cursor);
+                        }
+                        Some((cursor.min(target), cursor.max(target), false))
+                    }
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn operate(
+        &mut self,
+        operator: Operator,
+        text: &[char],
+        cursor: usize,
+        start: usize,
+        end: usize,
+        linewise: bool,
+        visual: bool,
+        buffer: &mut dyn VimBuffer,
+    ) {
+        match operator {
+            Operator::Yank => {
+                if visual || !linewise {
+                    buffer.set_cursor(start);
+                } else {
+                    buffer.set_cursor(cursor);
+                }
+            }
+            Operator::Delete => {
+                buffer.begin_change();
+                if linewise {
+                    // Take the line break after the lines, or before them on the last line
+                    let (delete_start, delete_end) = if end < text.len() {
+                        (start, end + 1)
+                    } else {
+                        (start.saturating_sub(1), end)
+                    };
+                    buffer.delete(delete_start, delete_end);
+
+                    let text = chars(buffer);
+                    buffer.set_cursor(first_non_blank(&text, delete_start.min(text.len())));
+                } else {
+                    buffer.delete(start, end);
+
+                    let text = chars(buffer);
+                    buffer.set_cursor(clamp_to_char(&text, start));
+                }
+                buffer.end_change();
+            }
+            Operator::Change => {
+                // Linewise change keeps an empty line to type in
+                buffer.begin_change();
+                buffer.delete(start, end);
+                buffer.set_cursor(start);
+                buffer.end_change();
+
+                self.mode = VimMode::Insert;
+            }
+        }
+    }
+
+    fn act(
+        &mut self,
+        action: Action,
+        command: Command,
+        keys: Vec<VimKey>,
+        text: &[char],
+        cursor: usize,
+        buffer: &mut dyn VimBuffer,
+    ) {
+        let count = command.count.unwrap_or(1);
+
+        match action {
+            Action::Insert
+            | Action::Append
+            | Action::InsertAtLineStart
+            | Action::AppendAtLineEnd
+            | Action::OpenBelow
+            | Action::OpenAbove => {
+                match action {
+                    Action::Append if cursor < line_end(text, cursor) => {
+                        buffer.set_cursor(cursor + 1)
+                    }
+                    Action::InsertAtLineStart => buffer.set_cursor(first_non_blank(text, cursor)),
+                    Action::AppendAtLineEnd => buffer.set_cursor(line_end(text, cursor)),
+                    Action::OpenBelow => {
+                        let end = line_end(text, cursor);
+                        buffer.insert(end, "\n");
+                        buffer.set_cursor(end + 1);
+                    }
+                    Action::OpenAbove => {
+                        let start = line_start(text, cursor);
+                        buffer.insert(start, "\n");
+                        buffer.set_cursor(start);
+                    }
+                    _ => {}
+                }
+
+                self.mode = VimMode::Insert;
+                self.insert_change = Some(keys);
+            }
+            Action::PutAfter | Action::PutBefore => {
+                let Some(register) = self.registers.get(command.register).cloned() else {
+                    return;
+                };
+
+                buffer.begin_change();
+                put(
+                    &register,
+                    count,
+                    action == Action::PutAfter,
+                    text,
+                    cursor,
+                    buffer,
+                );
+                buffer.end_change();
+
+                self.last_change = keys;
+            }
+            Action::Undo | Action::Redo => {
+                for _ in 0..count {
+                    if action == Action::Undo {
+                        buffer.undo();
+                    } else {
+                        buffer.redo();
+                    }
+                }
+
+                let text = chars(buffer);
+                buffer.set_cursor(clamp_to_char(&text, buffer.cursor()));
+            }
+            Action::Visual | Action::VisualLine => {
+                let mode = if action == Action::Visual {
+                    VimMode::Visual
+                } else {
+                    VimMode::VisualLine
+                };
+
+                if self.mode == mode {
+                    self.exit_visual(buffer);
+                    return;
+                }
+
+                if !self.is_visual() {
+                    self.visual_anchor = cursor;
+                    self.visual_cursor = cursor;
+                }
+                self.mode = mode;
+                self.update_selection(text, buffer);
+            }
+            Action::Repeat => {
+                if self.last_change.is_empty() {
+                    return;
+                }
+
+                let keys = self.last_change.clone();
+                self.replaying = true;
+                buffer.begin_change();
+                for _ in 0..count {
+                    for key in keys.iter() {
+                        self.handle_key(*key, buffer);
+                    }
+                }
+                buffer.end_change();
+                self.replaying = false;
+            }
+            Action::CommandLine => {
+                self.mode = VimMode::CommandLine;
+                self.command_line.clear();
+            }
+        }
+    }
+
+    fn update_selection(&self, text: &[char], buffer: &mut dyn VimBuffer) {
+        let start = self.visual_anchor.min(self.visual_cursor);
+        let end = self.visual_anchor.max(self.visual_cursor);
+
+        let (start, end) = if self.mode == VimMode::VisualLine {
+            (
+                line_start(text, start),
+                (line_end(text, end) + 1).min(text.len()),
+            )
+        } else {
+            (start, (end + 1).min(text.len()))
+        };
+
+        if self.visual_cursor >= self.visual_anchor {
+            buffer.set_selection(start, end);
+        } else {
+            buffer.set_selection(end, start);
+        }
+    }
+
+    fn exit_visual(&mut self, buffer: &mut dyn VimBuffer) {
+        self.mode = VimMode::Normal;
+        buffer.set_cursor(self.visual_cursor);
+    }
+}
+
+fn chars(buffer: &dyn VimBuffer) -> Vec<char> {
+    buffer.text().chars().collect()
+}
+
+fn put(
+    register: &Register,
+    count: usize,
+    after: bool,
+    text: &[char],
+    cursor: usize,
+    buffer: &mut dyn VimBuffer,
+) {
+    let content = register.text.repeat(count);
+
+    if register.linewise {
+        let line_offset = if !after {
+            let start = line_start(text, cursor);
+            buffer.insert(start, &content);
+            start
+        } else if line_end(text, cursor) < text.len() {
+            let start = line_end(text, cursor) + 1;
+            buffer.insert(start, &content);
+            start
+        } else {
+            // Last line has no line break to put after
+            let end = line_end(text, cursor);
+            buffer.insert(end, &format!("\n{}", content.trim_end_matches('\n')));
+            end + 1
+        };
+
+        let text = chars(buffer);
+        buffer.set_cursor(first_non_blank(&text, line_offset));
+    } else {
+        let offset = if after && cursor < line_end(text, cursor) {
+            cursor + 1
+        } else {
+            cursor
+        };
+        buffer.insert(offset, &content);
+        buffer.set_cursor(offset + content.chars().count().saturating_sub(1));
+    }
+}
+
+fn parse_count(keys: &[VimKey], index: &mut usize) -> Option<usize> {
+    let mut count: Option<usize> = None;
+
+    while let Some(VimKey::Char(c)) = keys.get(*index) {
+        let Some(digit) = c.to_digit(10) else {
+            break;
+        };
+        // `0` alone is a motion
+        if digit == 0 && count.is_none() {
+            break;
+        }
+
+        count = Some(
+            count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit as usize),
+        );
+        *index += 1;
+    }
+
+    count
+}
+
+fn parse_motion(keys: &[VimKey]) -> Parsed<Motion> {
+    let Some(key) = keys.first() else {
+        return Parsed::Incomplete;
+    };
+
+    let motion = match key {
+        VimKey::Char('h') | VimKey::Backspace => Motion::Left,
+        VimKey::Char('l') | VimKey::Char(' ') => Motion::Right,
+        VimKey::Char('k') => Motion::Up,
+        VimKey::Char('j') | VimKey::Enter => Motion::Down,
+        VimKey::Char('w') => Motion::WordForward,
+        VimKey::Char('b') => Motion::WordBackward,
+        VimKey::Char('e') => Motion::WordEnd,
+        VimKey::Char('0') => Motion::LineStart,
+        VimKey::Char('^') => Motion::FirstNonBlank,
+        VimKey::Char('$') => Motion::LineEnd,
+        VimKey::Char('G') => Motion::LastLine,
+        VimKey::Char('g') => match keys.get(1) {
+            None => return Parsed::Incomplete,
+            Some(VimKey::Char('g')) => Motion::FirstLine,
+            Some(_) => return Parsed::Invalid,
+        },
+        VimKey::Char(c @ ('f' | 't')) => match keys.get(1) {
+            None => return Parsed::Incomplete,
+            Some(VimKey::Char(target)) if *c == 'f' => Motion::FindChar(*target),
+            Some(VimKey::Char(target)) => Motion::TillChar(*target),
+            Some(_) => return Parsed::Invalid,
+        },
+        _ => return Parsed::Invalid,
+    };
+
+    // Keys after a complete motion don't belong to it
+    let length = match motion {
+        Motion::FirstLine | Motion::FindChar(_) | Motion::TillChar(_) => 2,
+        _ => 1,
+    };
+    if keys.len() > length {
+        return Parsed::Invalid;
+    }
+
+    Parsed::Complete(motion)
+}
+
+/**
+ * Parses a normal or visual mode command, ex: `"a3dw`.
+ */
+fn parse_command(keys: &[VimKey], visual: bool) -> Parsed<Command> {
+    let mut index = 0;
+
+    let register = match keys.first() {
+        Some(VimKey::Char('"')) => match keys.get(1) {
+            None => return Parsed::Incomplete,
+            Some(VimKey::Char(name)) if Registers::is_valid_name(*name) => {
+                index = 2;
+                Some(*name)
+            }
+            Some(_) => return Parsed::Invalid,
+        },
+        _ => None,
+    };
+
+    let count = parse_count(keys, &mut index);
+    let Some(key) = keys.get(index) else {
+        return Parsed::Incomplete;
+    };
+
+    let command = |kind| {
+        Parsed::Complete(Command {
+            register,
+            count,
+            kind,
+        })
+    };
+
+    let operator = match key {
+        VimKey::Char('d') => Some(Operator::Delete),
+        VimKey::Char('c') => Some(Operator::Change),
+        VimKey::Char('y') => Some(Operator::Yank),
+        _ => None,
+    };
+
+    if visual {
+        let target = Target::Selection;
+        return match key {
+            _ if operator.is_some() => command(CommandKind::Operate(operator.unwrap(), target)),
+            VimKey::Char('x') => command(CommandKind::Operate(Operator::Delete, target)),
+            VimKey::Char('s') => command(CommandKind::Operate(Operator::Change, target)),
+            VimKey::Char('v') => command(CommandKind::Act(Action::Visual)),
+            VimKey::Char('V') => command(CommandKind::Act(Action::VisualLine)),
+            _ => match parse_motion(&keys[index..]) {
+                Parsed::Complete(motion) => command(CommandKind::Move(motion)),
+                Parsed::Incomplete => Parsed::Incomplete,
+                Parsed::Invalid => Parsed::Invalid,
+            },
+        };
+    }
+
+    if let Some(operator) = operator {
+        index += 1;
+        let motion_count = parse_count(keys, &mut index);
+        let count = match (count, motion_count) {
+            (None, None) => None,
+            (count, motion_count) => Some(count.unwrap_or(1) * motion_count.unwrap_or(1)),
+        };
+
+        let Some(motion_key) = keys.get(index) else {
+            return Parsed::Incomplete;
+        };
+
+        let target = if motion_key == key {
+            if keys.len() > index + 1 {
+                return Parsed::Invalid;
+            }
+            Target::Line
+        } else {
+            match parse_motion(&keys[index..]) {
+                Parsed::Complete(motion) => Target::Motion(motion),
+                Parsed::Incomplete => return Parsed::Incomplete,
+                Parsed::Invalid => return Parsed::Invalid,
+            }
+        };
+
+        return Parsed::Complete(Command {
+            register,
+            count,
+            kind: CommandKind::Operate(operator, target),
+        });
+    }
+
+    let kind = match key {
+        VimKey::Char('x') => CommandKind::Operate(Operator::Delete, Target::Motion(Motion::Right)),
+        VimKey::Char('X') => CommandKind::Operate(Operator::Delete, Target::Motion(Motion::Left)),
+        VimKey::Char('s') => CommandKind::Operate(Operator::Change, Target::Motion(Motion::Right)),
+        VimKey::Char('D') => {
+            CommandKind::Operate(Operator::Delete, Target::Motion(Motion::LineEnd))
+        }
+        VimKey::Char('C') => {
+            CommandKind::Operate(Operator::Change, Target::Motion(Motion::LineEnd))
+        }
+        VimKey::Char('Y') => CommandKind::Operate(Operator::Yank, Target::Line),
+        VimKey::Char('i') => CommandKind::Act(Action::Insert),
+        VimKey::Char('a') => CommandKind::Act(Action::Append),
+        VimKey::Char('I') => CommandKind::Act(Action::InsertAtLineStart),
+        VimKey::Char('A') => CommandKind::Act(Action::AppendAtLineEnd),
+        VimKey::Char('o') => CommandKind::Act(Action::OpenBelow),
+        VimKey::Char('O') => CommandKind::Act(Action::OpenAbove),
+        VimKey::Char('p') => CommandKind::Act(Action::PutAfter),
+        VimKey::Char('P') => CommandKind::Act(Action::PutBefore),
+        VimKey::Char('u') => CommandKind::Act(Action::Undo),
+        VimKey::Ctrl('r') => CommandKind::Act(Action::Redo),
+        VimKey::Char('v') => CommandKind::Act(Action::Visual),
+        VimKey::Char('V') => CommandKind::Act(Action::VisualLine),
+        VimKey::Char('.') => CommandKind::Act(Action::Repeat),
+        VimKey::Char(':') => CommandKind::Act(Action::CommandLine),
+        _ => match parse_motion(&keys[index..]) {
+            Parsed::Complete(motion) => CommandKind::Move(motion),
+            Parsed::Incomplete => return Parsed::Incomplete,
+            Parsed::Invalid => return Parsed::Invalid,
+        },
+    };
+
+    if keys.len() > index + 1 && !matches!(kind, CommandKind::Move(_)) {
+        return Parsed::Invalid;
+    }
+
+    command(kind)
+}
+
+fn parse_ex_command(command_line: &str) -> Option<ExCommand> {
+    let command = command_line.trim();
+
+    match command {
+        "" => None,
+        "w" | "write" => Some(ExCommand::Write),
+        "q" | "q!" | "quit" => Some(ExCommand::Quit),
+        "wq" | "x" => Some(ExCommand::WriteQuit),
+        _ => match command.parse::<usize>() {
+            Ok(line) => Some(ExCommand::GotoLine(line.max(1))),
+            Err(_) => Some(ExCommand::Unknown(command.to_string())),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExCommand, Vim, VimBuffer, VimKey, VimMode};
+
+    /// Buffer with a simple undo stack, mimics a GTK text buffer.
+    #[derive(Default)]
+    struct StringBuffer {
+        text: Vec<char>,
+        cursor: usize,
+        selection: Option<(usize, usize)>,
+        undo_stack: Vec<(Vec<char>, usize)>,
+        redo_stack: Vec<(Vec<char>, usize)>,
+        change_depth: usize,
+    }
+
+    impl StringBuffer {
+        fn new(text: &str) -> Self {
+            Self {
+                text: text.chars().collect(),
+                ..Default::default()
+            }
+        }
+
+        fn snapshot(&mut self) {
+            if self.change_depth == 0 {
+                self.undo_stack.push((self.text.clone(), self.cursor));
+                self.redo_stack.clear();
+            }
+        }
+
+        fn selected_text(&self) -> String {
+            let (a, b) = self.selection.unwrap();
+            self.text[a.min(b)..a.max(b)].iter().collect()
+        }
+    }
+
+    impl VimBuffer for StringBuffer {
+        fn text(&self) -> String {
+            self.text.iter().collect()
+        }
+
+        fn cursor(&self) -> usize {
+            self.cursor
+        }
+
+        fn set_cursor(&mut self, offset: usize) {
+            self.cursor = offset.min(self.text.len());
+            self.selection = None;
+        }
+
+        fn set_selection(&mut self, anchor: usize, cursor: usize) {
+            self.selection = Some((anchor, cursor));
+            self.cursor = cursor;
+        }
+
+        fn delete(&mut self, start: usize, end: usize) {
+            self.snapshot();
+            self.text.drain(start..end);
+        }
+
+        fn insert(&mut self, offset: usize, text: &str) {
+            self.snapshot();
+            for (i, c) in text.chars().enumerate() {
+                self.text.insert(offset + i, c);
+            }
+        }
+
+        fn undo(&mut self) {
+            if let Some((text, cursor)) = self.undo_stack.pop() {
+                self.redo_stack.push((self.text.clone(), self.cursor));
+                self.text = text;
+                self.cursor = cursor;
+            }
+        }
+
+        fn redo(&mut self) {
+            if let Some((text, cursor)) = self.redo_stack.pop() {
+                self.undo_stack.push((self.text.clone(), self.cursor));
+                self.text = text;
+                self.cursor = cursor;
+            }
+        }
+
+        fn begin_change(&mut self) {
+            self.snapshot();
+            self.change_depth += 1;
+        }
+
+        fn end_change(&mut self) {
+            self.change_depth -= 1;
+        }
+    }
+
+    /**
+     * Feeds keys like `dw` or `cwfoo<Esc>`, typing unconsumed keys like an editor would.
+     */
+    fn feed(vim: &mut Vim, buffer: &mut StringBuffer, keys: &str) -> Option<ExCommand> {
+        let mut command = None;
+        let mut rest = keys;
+
+        while let Some(c) = rest.chars().next() {
+            let (key, length) = [
+                ("<Esc>", VimKey::Escape),
+                ("<CR>", VimKey::Enter),
+                ("<BS>", VimKey::Backspace),
+                ("<C-r>", VimKey::Ctrl('r')),
+            ]
+            .iter()
+            .find(|(name, _)| rest.starts_with(name))
+            .map_or((VimKey::Char(c), c.len_utf8()), |(name, key)| {
+                (*key, name.len())
+            });
+            rest = &rest[length..];
+
+            let response = vim.handle_key(key, buffer);
+            if response.command.is_some() {
+                command = response.command;
+            }
+
+            if !response.consumed {
+                if let VimKey::Char(c) = key {
+                    buffer.insert(buffer.cursor, &c.to_string());
+                    buffer.cursor += 1;
+                }
+            }
+        }
+
+        command
+    }
+
+    fn run(text: &str, keys: &str) -> StringBuffer {
+        let mut vim = Vim::new();
+        let mut buffer = StringBuffer::new(text);
+        feed(&mut vim, &mut buffer, keys);
+
+        buffer
+    }
+
+    fn text_of(buffer: &StringBuffer) -> String {
+        buffer.text()
+    }
+
+    #[test]
+    fn operators_with_motions() {
+        assert_eq!(text_of(&run("foo bar baz", "dw")), "bar baz");
+        assert_eq!(text_of(&run("foo bar baz", "d2w")), "baz");
+        assert_eq!(text_of(&run("foo bar baz", "2dw")), "baz");
+        assert_eq!(text_of(&run("foo bar baz", "wde")), "foo  baz");
+        assert_eq!(text_of(&run("foo bar baz", "wd$")), "foo ");
+        assert_eq!(text_of(&run("foo bar baz", "$d0")), "z");
+        assert_eq!(text_of(&run("foo(a, b)", "dt,")), ", b)");
+        assert_eq!(text_of(&run("foo(a, b)", "df,")), " b)");
+        assert_eq!(text_of(&run("foo bar", "wdb")), "bar");
+        // Doesn't delete past the end of line
+        assert_eq!(text_of(&run("foo bar\nbaz", "wdw")), "foo \nbaz");
+        assert_eq!(text_of(&run("foo bar", "xx")), "o bar");
+        assert_eq!(text_of(&run("foo bar", "wD")), "foo ");
+    }
+
+    #[test]
+    fn linewise_operators() {
+        assert_eq!(text_of(&run("one\ntwo\nthree", "dd")), "two\nthree");
+        assert_eq!(text_of(&run("one\ntwo\nthree", "2dd")), "three");
+        assert_eq!(text_of(&run("one\ntwo\nthree", "Gdd")), "one\ntwo");
+        assert_eq!(text_of(&run("one\ntwo\nthree", "jdk")), "three");
+        assert_eq!(text_of(&run("one\ntwo\nthree", "dG")), "");
+        assert_eq!(text_of(&run("one\ntwo\nthree", "Gdgg")), "");
+        assert_eq!(
+            text_of(&run("one\ntwo\nthree", "yyp")),
+            "one\none\ntwo\nthree"
+        );
+        assert_eq!(text_of(&run("one\ntwo", "jyyP")), "one\ntwo\ntwo");
+        assert_eq!(text_of(&run("one\ntwo", "jyyp")), "one\ntwo\ntwo");
+        assert_eq!(text_of(&run("one\ntwo", "ccnew<Esc>")), "new\ntwo");
+    }
+
+    #[test]
+    fn change_and_insert() {
+        let buffer = run("foo bar", "cwbaz<Esc>");
+        assert_eq!(text_of(&buffer), "baz bar");
+        assert_eq!(buffer.cursor, 2);
+
+        assert_eq!(text_of(&run("foo bar", "wcbqux<Esc>")), "quxbar");
+        assert_eq!(text_of(&run("foo", "A bar<Esc>")), "foo bar");
+        assert_eq!(text_of(&run("  foo", "$I- <Esc>")), "  - foo");
+        assert_eq!(text_of(&run("foo", "ox<Esc>")), "foo\nx");
+        assert_eq!(text_of(&run("foo", "Ox<Esc>")), "x\nfoo");
+        assert_eq!(text_of(&run("foo", "ax<Esc>")), "fxoo");
+    }
+
+    #[test]
+    fn counts_and_motions() {
+        let buffer = run("one two three four", "3w");
+        assert_eq!(buffer.cursor, 14);
+
+        let buffer = run("a\nb\nc\nd", "2j");
+        assert_eq!(buffer.cursor, 4);
+
+        let buffer = run("a\nb\nc\nd", "G2gg");
+        assert_eq!(buffer.cursor, 2);
+
+        // Normal mode cursor stays on a char
+        let buffer = run("foo\nbar", "$");
+        assert_eq!(buffer.cursor, 2);
+    }
+
+    #[test]
+    fn registers_and_put() {
+        let mut vim = Vim::new();
+        let mut buffer = StringBuffer::new("foo bar");
+
+        feed(&mut vim, &mut buffer, "\"ayw");
+        assert_eq!(vim.registers().get(Some('a')).unwrap().text, "foo ");
+
+        feed(&mut vim, &mut buffer, "wdw");
+        assert_eq!(vim.registers().get(None).unwrap().text, "bar");
+        // Only unnamed yanks go to register `0`
+        assert!(vim.registers().get(Some('0')).is_none());
+
+        feed(&mut vim, &mut buffer, "0\"aP");
+        assert_eq!(text_of(&buffer), "foo foo ");
+
+        feed(&mut vim, &mut buffer, "$p");
+        assert_eq!(text_of(&buffer), "foo foo bar");
+    }
+
+    #[test]
+    fn repeat_last_change() {
+        assert_eq!(text_of(&run("a b c d", "dw..")), "d");
+        assert_eq!(text_of(&run("a b c d", "dw2.")), "d");
+        assert_eq!(text_of(&run("foo foo", "cwbar<Esc>w.")), "bar bar");
+        assert_eq!(text_of(&run("one\ntwo\nthree", "dd.")), "three");
+        assert_eq!(text_of(&run("x", "A!<Esc>.")), "x!!");
+    }
+
+    #[test]
+    fn visual_modes() {
+        let mut vim = Vim::new();
+        let mut buffer = StringBuffer::new("foo bar\nbaz");
+
+        feed(&mut vim, &mut buffer, "ve");
+        assert_eq!(vim.mode(), VimMode::Visual);
+        assert_eq!(buffer.selected_text(), "foo");
+
+        feed(&mut vim, &mut buffer, "y");
+        assert_eq!(vim.mode(), VimMode::Normal);
+        assert_eq!(vim.registers().get(None).unwrap().text, "foo");
+
+        assert_eq!(text_of(&run("foo bar\nbazbaz", "wvjd")), "foo z");
+        assert_eq!(text_of(&run("foo bar\nbaz", "jVd")), "foo bar");
+        assert_eq!(text_of(&run("foo bar\nbaz", "Vjcnew<Esc>")), "new");
+
+        let mut vim = Vim::new();
+        let mut buffer = StringBuffer::new("foo bar");
+        feed(&mut vim, &mut buffer, "vl<Esc>");
+        assert_eq!(vim.mode(), VimMode::Normal);
+        assert_eq!(buffer.selection, None);
+        assert_eq!(buffer.cursor, 1);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut vim = Vim::new();
+        let mut buffer = StringBuffer::new("one two");
+
+        feed(&mut vim, &mut buffer, "dwdw");
+        assert_eq!(text_of(&buffer), "");
+
+        feed(&mut vim, &mut buffer, "u");
+        assert_eq!(text_of(&buffer), "two");
+
+        feed(&mut vim, &mut buffer, "u<C-r>");
+        assert_eq!(text_of(&buffer), "two");
+    }
+
+    #[test]
+    fn command_line() {
+        let mut vim = Vim::new();
+        let mut buffer = StringBuffer::new("foo");
+
+        assert_eq!(
+            feed(&mut vim, &mut buffer, ":w<CR>"),
+            Some(ExCommand::Write)
+        );
+        assert_eq!(
+            feed(&mut vim, &mut buffer, ":wq<CR>"),
+            Some(ExCommand::WriteQuit)
+        );
+        assert_eq!(feed(&mut vim, &mut buffer, ":q<CR>"), Some(ExCommand::Quit));
+        assert_eq!(
+            feed(&mut vim, &mut buffer, ":42<CR>"),
+            Some(ExCommand::GotoLine(42))
+        );
+
+        feed(&mut vim, &mut buffer, ":set");
+        assert_eq!(vim.mode(), VimMode::CommandLine);
+        assert_eq!(vim.status(), ":set");
+
+        feed(&mut vim, &mut buffer, "<Esc>");
+        assert_eq!(vim.mode(), VimMode::Normal);
+        assert_eq!(vim.status(), "-- NORMAL --");
+        assert_eq!(text_of(&buffer), "foo");
+    }
+}
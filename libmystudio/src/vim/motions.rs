@@ -0,0 +1,334 @@
+// Cursor motions used by Vim emulation, offsets are char offsets into the text
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    // h, l
+    Left,
+    Right,
+    // k, j
+    Up,
+    Down,
+    // w, b, e
+    WordForward,
+    WordBackward,
+    WordEnd,
+    // 0, ^, $
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    // gg, G
+    FirstLine,
+    LastLine,
+    // f{char}, t{char}
+    FindChar(char),
+    TillChar(char),
+}
+
+/**
+ * How an operator (ex: `d`) treats the text between cursor and motion target.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionKind {
+    // Target char is not included, ex: `dw`
+    Exclusive,
+    // Target char is included, ex: `de`
+    Inclusive,
+    // Whole lines are affected, ex: `dj`
+    Linewise,
+}
+
+impl Motion {
+    pub fn kind(&self) -> MotionKind {
+        match self {
+            Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine => {
+                MotionKind::Linewise
+            }
+            Motion::WordEnd | Motion::FindChar(_) | Motion::TillChar(_) => MotionKind::Inclusive,
+            _ => MotionKind::Exclusive,
+        }
+    }
+
+    /**
+     * Returns where a motion from `cursor` lands, or `None` if it can't move
+     * (ex: `f` without a match).
+     *
+     * `count` is `None` when no count was typed, which matters for `gg` and `G`.
+     */
+    pub fn target(&self, text: &[char], cursor: usize, count: Option<usize>) -> Option<usize> {
+        let n = count.unwrap_or(1).max(1);
+        let cursor = cursor.min(text.len());
+        let last_line = line_count(text) - 1;
+
+        match self {
+            Motion::Left => Some(cursor.saturating_sub(n).max(line_start(text, cursor))),
+            Motion::Right => Some((cursor + n).min(line_end(text, cursor))),
+            Motion::Up => {
+                let line = line_of(text, cursor);
+                if line == 0 {
+                    return None;
+                }
+                Some(same_column(text, cursor, line.saturating_sub(n)))
+            }
+            Motion::Down => {
+                let line = line_of(text, cursor);
+                if line == last_line {
+                    return None;
+                }
+                Some(same_column(text, cursor, (line + n).min(last_line)))
+            }
+            Motion::WordForward => Some((0..n).fold(cursor, |pos, _| next_word_start(text, pos))),
+            Motion::WordBackward => Some((0..n).fold(cursor, |pos, _| prev_word_start(text, pos))),
+            Motion::WordEnd => Some((0..n).fold(cursor, |pos, _| word_end(text, pos))),
+            Motion::LineStart => Some(line_start(text, cursor)),
+            Motion::FirstNonBlank => Some(first_non_blank(text, cursor)),
+            Motion::LineEnd => {
+                let line = (line_of(text, cursor) + n - 1).min(last_line);
+                Some(line_end(text, offset_of_line(text, line)))
+            }
+            Motion::FirstLine => {
+                let line = count.map_or(0, |c| c.saturating_sub(1)).min(last_line);
+                Some(first_non_blank(text, offset_of_line(text, line)))
+            }
+            Motion::LastLine => {
+                let line = count.map_or(last_line, |c| c.saturating_sub(1).min(last_line));
+                Some(first_non_blank(text, offset_of_line(text, line)))
+            }
+            Motion::FindChar(c) => find_in_line(text, cursor, *c, n),
+            Motion::TillChar(c) => find_in_line(text, cursor, *c, n).map(|pos| pos - 1),
+        }
+    }
+}
+
+/// Offset of the first char of line containing `pos`.
+pub fn line_start(text: &[char], pos: usize) -> usize {
+    text[..pos.min(text.len())]
+        .iter()
+        .rposition(|c| *c == '\n')
+        .map_or(0, |i| i + 1)
+}
+
+/// Offset of the line break (or end of text) of line containing `pos`.
+pub fn line_end(text: &[char], pos: usize) -> usize {
+    let pos = pos.min(text.len());
+    text[pos..]
+        .iter()
+        .position(|c| *c == '\n')
+        .map_or(text.len(), |i| pos + i)
+}
+
+/// 0-based line number of `pos`.
+pub fn line_of(text: &[char], pos: usize) -> usize {
+    text[..pos.min(text.len())]
+        .iter()
+        .filter(|c| **c == '\n')
+        .count()
+}
+
+pub fn line_count(text: &[char]) -> usize {
+    text.iter().filter(|c| **c == '\n').count() + 1
+}
+
+/// Offset of the first char of a 0-based line.
+pub fn offset_of_line(text: &[char], line: usize) -> usize {
+    if line == 0 {
+        return 0;
+    }
+
+    text.iter()
+        .enumerate()
+        .filter(|(_, c)| **c == '\n')
+        .nth(line - 1)
+        .map_or(text.len(), |(i, _)| i + 1)
+}
+
+/// Offset of the first non-whitespace char of line containing `pos`.
+pub fn first_non_blank(text: &[char], pos: usize) -> usize {
+    let start = line_start(text, pos);
+    let end = line_end(text, pos);
+
+    (start..end)
+        .find(|i| !text[*i].is_whitespace())
+        .unwrap_or(start)
+}
+
+/**
+ * Keeps the cursor on a char in normal mode, it can't sit on a line break
+ * unless the line is empty.
+ */
+pub fn clamp_to_char(text: &[char], pos: usize) -> usize {
+    let pos = pos.min(text.len());
+    let start = line_start(text, pos);
+    let end = line_end(text, pos);
+
+    if pos >= end && end > start {
+        end - 1
+    } else {
+        pos
+    }
+}
+
+/**
+ * End of the word `cw` changes, which unlike `e` stays on the current word
+ * when the cursor is at its last char.
+ */
+pub fn change_word_end(text: &[char], cursor: usize, count: usize) -> usize {
+    let mut pos = cursor;
+
+    for i in 0..count.max(1) {
+        let at_word_end =
+            pos + 1 >= text.len() || char_class(text[pos + 1]) != char_class(text[pos]);
+        if i > 0 || !at_word_end {
+            pos = word_end(text, pos);
+        }
+    }
+
+    pos
+}
+
+fn same_column(text: &[char], cursor: usize, line: usize) -> usize {
+    let column = cursor - line_start(text, cursor);
+    let start = offset_of_line(text, line);
+
+    (start + column).min(line_end(text, start))
+}
+
+// 0: whitespace, 1: word chars, 2: punctuation
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+fn next_word_start(text: &[char], pos: usize) -> usize {
+    let len = text.len();
+    if pos >= len {
+        return len;
+    }
+
+    let mut i = pos;
+    let class = char_class(text[i]);
+    if class != 0 {
+        while i < len && char_class(text[i]) == class {
+            i += 1;
+        }
+    }
+    while i < len && char_class(text[i]) == 0 {
+        i += 1;
+    }
+
+    i
+}
+
+fn prev_word_start(text: &[char], pos: usize) -> usize {
+    if pos == 0 || text.is_empty() {
+        return 0;
+    }
+
+    let mut i = pos.min(text.len()) - 1;
+    while i > 0 && char_class(text[i]) == 0 {
+        i -= 1;
+    }
+
+    let class = char_class(text[i]);
+    while i > 0 && class != 0 && char_class(text[i - 1]) == class {
+        i -= 1;
+    }
+
+    i
+}
+
+fn word_end(text: &[char], pos: usize) -> usize {
+    let len = text.len();
+    if len == 0 {
+        return 0;
+    }
+
+    let mut i = pos + 1;
+    while i < len && char_class(text[i]) == 0 {
+        i += 1;
+    }
+    if i >= len {
+        return len - 1;
+    }
+
+    let class = char_class(text[i]);
+    while i + 1 < len && char_class(text[i + 1]) == class {
+        i += 1;
+    }
+
+    i
+}
+
+fn find_in_line(text: &[char], cursor: usize, c: char, count: usize) -> Option<usize> {
+    let end = line_end(text, cursor);
+
+    (cursor + 1..end).filter(|i| text[*i] == c).nth(count - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{change_word_end, clamp_to_char, Motion};
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn word_motions_test() {
+        let text = chars("let foo_bar = baz(1);\nnext");
+
+        assert_eq!(Motion::WordForward.target(&text, 0, None), Some(4));
+        assert_eq!(Motion::WordForward.target(&text, 4, Some(2)), Some(14));
+        // Punctuation is a word of its own
+        assert_eq!(Motion::WordForward.target(&text, 14, None), Some(17));
+        // Crosses line breaks
+        assert_eq!(Motion::WordForward.target(&text, 19, None), Some(22));
+
+        assert_eq!(Motion::WordEnd.target(&text, 0, None), Some(2));
+        assert_eq!(Motion::WordEnd.target(&text, 2, None), Some(10));
+        assert_eq!(Motion::WordBackward.target(&text, 14, None), Some(12));
+        assert_eq!(Motion::WordBackward.target(&text, 22, Some(2)), Some(18));
+
+        assert_eq!(change_word_end(&text, 4, 1), 10);
+        assert_eq!(change_word_end(&text, 10, 1), 10);
+    }
+
+    #[test]
+    fn line_motions_test() {
+        let text = chars("  first\nsecond line\n\nlast");
+
+        assert_eq!(Motion::LineStart.target(&text, 5, None), Some(0));
+        assert_eq!(Motion::FirstNonBlank.target(&text, 5, None), Some(2));
+        assert_eq!(Motion::LineEnd.target(&text, 0, None), Some(7));
+        assert_eq!(Motion::LineEnd.target(&text, 0, Some(2)), Some(19));
+        assert_eq!(clamp_to_char(&text, 7), 6);
+        assert_eq!(clamp_to_char(&text, 20), 20);
+
+        assert_eq!(Motion::Down.target(&text, 5, None), Some(13));
+        // Column is limited by shorter lines
+        assert_eq!(Motion::Down.target(&text, 13, None), Some(20));
+        assert_eq!(Motion::Up.target(&text, 5, None), None);
+
+        assert_eq!(Motion::FirstLine.target(&text, 22, None), Some(2));
+        assert_eq!(Motion::LastLine.target(&text, 0, None), Some(21));
+        assert_eq!(Motion::LastLine.target(&text, 0, Some(2)), Some(8));
+    }
+
+    #[test]
+    fn find_motions_test() {
+        let text = chars("a(b, c), d\ne");
+
+        assert_eq!(Motion::FindChar(',').target(&text, 0, None), Some(3));
+        assert_eq!(Motion::FindChar(',').target(&text, 0, Some(2)), Some(7));
+        assert_eq!(Motion::TillChar(')').target(&text, 0, None), Some(5));
+        // Doesn't search past the line
+        assert_eq!(Motion::FindChar('e').target(&text, 0, None), None);
+
+        assert_eq!(Motion::Left.target(&text, 1, Some(5)), Some(0));
+        assert_eq!(Motion::Right.target(&text, 8, Some(5)), Some(10));
+    }
+}
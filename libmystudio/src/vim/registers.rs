@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+// Register used when no register is given
+const UNNAMED_REGISTER: char = '"';
+// Register holding the last yank
+const YANK_REGISTER: char = '0';
+// Text stored here is discarded
+const BLACK_HOLE_REGISTER: char = '_';
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    // Put as whole lines, ex: after `yy`
+    pub linewise: bool,
+}
+
+/**
+ * Named (`a`-`z`) and unnamed registers.
+ *
+ * Storing to `A`-`Z` appends to the matching lowercase register.
+ */
+#[derive(Debug, Default)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphanumeric() || name == UNNAMED_REGISTER || name == BLACK_HOLE_REGISTER
+    }
+
+    pub fn get(&self, name: Option<char>) -> Option<&Register> {
+        let name = name.unwrap_or(UNNAMED_REGISTER).to_ascii_lowercase();
+        self.registers.get(&name)
+    }
+
+    /**
+     * Stores yanked or deleted text in register `name`, the unnamed register
+     * always receives it too.
+     */
+    pub fn store(&mut self, name: Option<char>, register: Register, is_yank: bool) {
+        let register = match name {
+            Some(BLACK_HOLE_REGISTER) => return,
+            Some(name) if name.is_ascii_uppercase() => {
+                let entry = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                entry.text.push_str(&register.text);
+                entry.linewise |= register.linewise;
+                entry.clone()
+            }
+            Some(name) if name != UNNAMED_REGISTER => {
+                self.registers.insert(name, register.clone());
+                register
+            }
+            _ => {
+                if is_yank {
+                    self.registers.insert(YANK_REGISTER, register.clone());
+                }
+                register
+            }
+        };
+
+        self.registers.insert(UNNAMED_REGISTER, register);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Register, Registers};
+
+    fn register(text: &str) -> Register {
+        Register {
+            text: text.to_string(),
+            linewise: false,
+        }
+    }
+
+    #[test]
+    fn registers_test() {
+        let mut registers = Registers::default();
+
+        registers.store(None, register("foo"), true);
+        registers.store(Some('a'), register("bar"), true);
+        registers.store(Some('A'), register("baz"), false);
+        registers.store(Some('_'), register("ignored"), false);
+
+        assert_eq!(registers.get(Some('0')).unwrap().text, "foo");
+        assert_eq!(registers.get(Some('a')).unwrap().text, "barbaz");
+        assert_eq!(registers.get(None).unwrap().text, "barbaz");
+        assert!(registers.get(Some('b')).is_none());
+    }
+}
@@ -12,6 +12,9 @@ use grep::regex::RegexMatcher;
 use grep::searcher::{BinaryDetection, SearcherBuilder};
 use jwalk::WalkDir;
 
+use crate::app_config::AppConfig;
+use crate::fs::is_path_excluded;
+
 // Holds reference to Workspace
 #[dynamic]
 static WORKSPACE_PATH: ArcSwap<Workspace> = ArcSwap::new(Arc::new(Workspace::new()));
@@ -78,6 +81,7 @@ impl Workspace {
             return Ok(vec![]);
         }
         let workspace_path = WORKSPACE_PATH.load().dir_path.clone();
+        let exclude_patterns = AppConfig::current().Search.exclude.clone();
         let mut matches: Vec<SearchResult> = vec![];
 
         let matcher = RegexMatcher::new_line_matcher(&pattern)?;
@@ -86,7 +90,7 @@ impl Workspace {
             .line_number(true)
             .build();
 
-        for result in WalkDir::new(workspace_path) {
+        for result in WalkDir::new(&workspace_path) {
             let dent = match result {
                 Ok(dent) => dent,
                 Err(err) => {
@@ -98,6 +102,14 @@ impl Workspace {
                 continue;
             }
 
+            let dent_path = dent.path();
+            let relative_path = dent_path
+                .strip_prefix(&workspace_path)
+                .unwrap_or(&dent_path);
+            if is_path_excluded(relative_path, &exclude_patterns) {
+                continue;
+            }
+
             // println!("searching for {} in: {:?}", pattern, dent.path());
             let result = searcher.search_path(
                 &matcher,
@@ -0,0 +1,242 @@
+// Runs DapClient against the scripted adapter in `support/fake_dap_adapter.rs`
+
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use libmystudio::debugger::{
+    client::DapClient,
+    error::DapError,
+    types::{DapEvent, Variable},
+    worker::{DapRequest, DapResponse, DapWorker},
+    DebugAdapterConfig,
+};
+use serde_json::{json, Value};
+
+const FILE_PATH: &str = "/workspace/src/main.rs";
+
+fn start(script: Value) -> (DapClient, Receiver<DapEvent>) {
+    let config = DebugAdapterConfig {
+        command: env!("CARGO_BIN_EXE_fake-dap-adapter").to_string(),
+        args: vec![script.to_string()],
+    };
+    let (tx, rx) = mpsc::channel();
+
+    let client = DapClient::start("fake", &config, "", move |event| {
+        tx.send(event).ok();
+    })
+    .expect("Unable to start fake adapter");
+
+    (client, rx)
+}
+
+// Commands and arguments received by the fake adapter so far
+fn received(client: &DapClient) -> Vec<(String, Value)> {
+    let body = client.request("fakeReceived", Value::Null).unwrap();
+
+    body["requests"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["command"].as_str().unwrap().to_string(),
+                r["arguments"].clone(),
+            )
+        })
+        .collect()
+}
+
+fn launch_script() -> Value {
+    json!({
+        "responses": {
+            "initialize": { "supportsConfigurationDoneRequest": true },
+            "setBreakpoints": { "breakpoints": [{ "verified": true, "line": 4 }] },
+        },
+        "send": {
+            "launch": [{ "event": "initialized", "body": {} }],
+            "configurationDone": [
+                { "event": "stopped", "body": { "reason": "breakpoint", "threadId": 1 } },
+            ],
+        },
+        "respond_after": { "launch": "configurationDone" },
+    })
+}
+
+#[test]
+fn launch_sequence_test() {
+    let (client, events) = start(launch_script());
+    assert!(client.supports("supportsConfigurationDoneRequest"));
+
+    let breakpoints = BTreeMap::from([(String::from(FILE_PATH), vec![3])]);
+    let states = client
+        .launch(
+            json!({ "program": "/workspace/target/debug/app" }),
+            &breakpoints,
+        )
+        .unwrap();
+    assert!(states[FILE_PATH][0].verified);
+    assert_eq!(states[FILE_PATH][0].line, Some(4));
+
+    let commands: Vec<String> = received(&client).into_iter().map(|(c, _)| c).collect();
+    assert_eq!(
+        commands,
+        [
+            "initialize",
+            "launch",
+            "setBreakpoints",
+            "configurationDone"
+        ]
+    );
+
+    let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(
+        event,
+        DapEvent::Stopped {
+            reason: String::from("breakpoint"),
+            thread_id: Some(1),
+            description: None,
+            all_threads_stopped: false,
+        }
+    );
+}
+
+#[test]
+fn failed_launch_test() {
+    let (client, _events) = start(json!({
+        "errors": { "launch": "program not found" },
+    }));
+
+    let error = client.launch(json!({}), &BTreeMap::new()).unwrap_err();
+    assert_eq!(
+        error,
+        DapError::Adapter {
+            command: String::from("launch"),
+            message: String::from("program not found"),
+        }
+    );
+}
+
+#[test]
+fn inspect_test() {
+    let (client, _events) = start(json!({
+        "responses": {
+            "threads": { "threads": [{ "id": 1, "name": "main" }] },
+            "stackTrace": {
+                "stackFrames": [{
+                    "id": 10,
+                    "name": "app::main",
+                    "source": { "path": FILE_PATH },
+                    "line": 4,
+                    "column": 5,
+                }],
+            },
+            "scopes": { "scopes": [{ "name": "Locals", "variablesReference": 20 }] },
+            "variables": {
+                "variables": [
+                    { "name": "count", "value": "3", "type": "i32", "variablesReference": 0 },
+                ],
+            },
+        },
+        "errors": { "evaluate": "use of undeclared identifier 'missing'" },
+    }));
+
+    assert_eq!(client.threads().unwrap()[0].name, "main");
+
+    let frames = client.stack_trace(1).unwrap();
+    assert_eq!(frames[0].path.as_deref(), Some(FILE_PATH));
+    assert_eq!(frames[0].line, 4);
+
+    let scopes = client.scopes(frames[0].id).unwrap();
+    assert_eq!(scopes[0].variables_reference, 20);
+    assert_eq!(
+        client.variables(20).unwrap(),
+        [Variable {
+            name: String::from("count"),
+            value: String::from("3"),
+            type_name: Some(String::from("i32")),
+            variables_reference: 0,
+        }]
+    );
+
+    let error = client.evaluate("missing", Some(10)).unwrap_err();
+    assert!(matches!(error, DapError::Adapter { command, .. } if command == "evaluate"));
+
+    let received = received(&client);
+    assert_eq!(received[2].0, "stackTrace");
+    assert_eq!(received[2].1["threadId"], 1);
+    assert_eq!(received[5].1["frameId"], 10);
+}
+
+#[test]
+fn disconnect_test() {
+    let (client, events) = start(json!({}));
+
+    client.disconnect().unwrap();
+    assert!(client.has_exited());
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        DapEvent::AdapterExited
+    );
+    assert_eq!(client.threads().unwrap_err(), DapError::Exited);
+}
+
+#[test]
+fn worker_test() {
+    let config = DebugAdapterConfig {
+        command: env!("CARGO_BIN_EXE_fake-dap-adapter").to_string(),
+        args: vec![launch_script().to_string()],
+    };
+    let (tx, events) = mpsc::channel();
+    let worker = DapWorker::start("fake", &config, "", move |event| {
+        tx.send(event).ok();
+    });
+
+    worker.request(
+        1,
+        DapRequest::Launch {
+            arguments: json!({ "program": "/workspace/target/debug/app" }),
+            breakpoints: BTreeMap::from([(String::from(FILE_PATH), vec![3])]),
+        },
+    );
+    worker.request(2, DapRequest::Scopes(10));
+
+    // Stopped at the breakpoint, then both answers in order
+    let mut responses = vec![];
+    while responses.len() < 2 {
+        match events.recv_timeout(Duration::from_secs(5)).unwrap() {
+            DapEvent::Response { id, result } => responses.push((id, result)),
+            DapEvent::Stopped { .. } => {}
+            event => panic!("Unexpected event {event:?}"),
+        }
+    }
+    assert_eq!(responses[0].0, 1);
+    assert!(
+        matches!(&responses[0].1, Ok(DapResponse::Launched(states)) if states[FILE_PATH][0].verified)
+    );
+    assert_eq!(responses[1], (2, Ok(DapResponse::Scopes(vec![]))));
+
+    drop(worker);
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)).unwrap(),
+        DapEvent::AdapterExited
+    );
+}
+
+#[test]
+fn worker_start_failed_test() {
+    let config = DebugAdapterConfig {
+        command: String::from("/nonexistent/adapter"),
+        args: vec![],
+    };
+    let (tx, events) = mpsc::channel();
+    let worker = DapWorker::start("fake", &config, "", move |event| {
+        tx.send(event).ok();
+    });
+    worker.request(1, DapRequest::Pause);
+
+    let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(matches!(event, DapEvent::StartFailed(DapError::Io(_))));
+}
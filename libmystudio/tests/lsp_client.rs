@@ -0,0 +1,374 @@
+// Runs LspClient against the scripted server in `support/fake_lsp_server.rs`
+
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use libmystudio::lsp::{
+    client::LspClient,
+    error::LspError,
+    path_to_uri,
+    types::{DiagnosticSeverity, LspEvent, LspRequest, LspResponse, Position},
+    worker::LspWorker,
+    LanguageServerConfig, LspManager,
+};
+use serde_json::{json, Value};
+
+const FILE_PATH: &str = "/workspace/src/main.rs";
+
+fn start(script: Value) -> (LspClient, Receiver<LspEvent>) {
+    let config = LanguageServerConfig {
+        command: env!("CARGO_BIN_EXE_fake-lsp-server").to_string(),
+        args: vec![script.to_string()],
+        ..LanguageServerConfig::default()
+    };
+    let (tx, rx) = mpsc::channel();
+
+    let client = LspClient::start("rust", &config, "", move |event| {
+        tx.send(event).ok();
+    })
+    .expect("Unable to start fake server");
+
+    (client, rx)
+}
+
+fn all_capabilities() -> Value {
+    json!({
+        "initialize": {
+            "capabilities": {
+                "hoverProvider": true,
+                "completionProvider": { "triggerCharacters": ["."] },
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "documentSymbolProvider": true,
+            }
+        }
+    })
+}
+
+// Methods and params received by the fake server so far
+fn received(client: &LspClient) -> Vec<(String, Value)> {
+    let received = client.request("fake/received", Value::Null).unwrap();
+
+    received
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| {
+            (
+                m["method"].as_str().unwrap().to_string(),
+                m["params"].clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn initialize_and_shutdown_test() {
+    let (client, _events) = start(json!({ "responses": all_capabilities() }));
+
+    assert!(client.supports("hoverProvider"));
+    assert!(client.supports("completionProvider"));
+    assert!(!client.supports("renameProvider"));
+
+    let received = received(&client);
+    assert_eq!(received[0].0, "initialize");
+    assert!(received[0].1["processId"].is_u64());
+    assert_eq!(received[1].0, "initialized");
+
+    client.shutdown().unwrap();
+    assert!(client.has_exited());
+}
+
+#[test]
+fn document_sync_test() {
+    let (client, _events) = start(json!({}));
+
+    client.did_open(FILE_PATH, "rust", "fn main() {}").unwrap();
+    client.did_change(FILE_PATH, "fn main() { }").unwrap();
+    client.did_save(FILE_PATH).unwrap();
+    client.did_close(FILE_PATH).unwrap();
+    // Closed documents are not synced
+    client.did_change(FILE_PATH, "ignored").unwrap();
+
+    let received: Vec<_> = received(&client).into_iter().skip(2).collect();
+    let methods: Vec<_> = received.iter().map(|(method, _)| method.as_str()).collect();
+    assert_eq!(
+        methods,
+        [
+            "textDocument/didOpen",
+            "textDocument/didChange",
+            "textDocument/didSave",
+            "textDocument/didClose"
+        ]
+    );
+
+    let opened = &received[0].1["textDocument"];
+    assert_eq!(opened["uri"], path_to_uri(FILE_PATH));
+    assert_eq!(opened["languageId"], "rust");
+    assert_eq!(opened["version"], 1);
+
+    let changed = &received[1].1;
+    assert_eq!(changed["textDocument"]["version"], 2);
+    assert_eq!(changed["contentChanges"][0]["text"], "fn main() { }");
+}
+
+#[test]
+fn diagnostics_event_test() {
+    let diagnostics = json!({
+        "method": "textDocument/publishDiagnostics",
+        "params": {
+            "uri": path_to_uri(FILE_PATH),
+            "diagnostics": [{
+                "range": {
+                    "start": { "line": 0, "character": 3 },
+                    "end": { "line": 0, "character": 7 }
+                },
+                "severity": 2,
+                "source": "rustc",
+                "message": "function `main` is never used"
+            }]
+        }
+    });
+    let (client, events) = start(json!({
+        "send": { "textDocument/didOpen": [diagnostics] }
+    }));
+
+    client.did_open(FILE_PATH, "rust", "fn main() {}").unwrap();
+
+    let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+    let LspEvent::Diagnostics { path, diagnostics } = event else {
+        panic!("Expected diagnostics, got {event:?}");
+    };
+    assert_eq!(path, FILE_PATH);
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::Warning));
+    assert_eq!(diagnostics[0].range.start.character, 3);
+    assert_eq!(diagnostics[0].source.as_deref(), Some("rustc"));
+}
+
+#[test]
+fn language_features_test() {
+    let mut responses = all_capabilities();
+    responses["textDocument/hover"] = json!({
+        "contents": { "kind": "markdown", "value": "```rust\nfn main()\n```" }
+    });
+    responses["textDocument/completion"] = json!([
+        { "label": "println!", "kind": 3, "detail": "macro" },
+        { "label": "print!", "kind": 3 }
+    ]);
+    responses["textDocument/definition"] = json!([{
+        "targetUri": path_to_uri("/workspace/src/lib.rs"),
+        "targetRange": {
+            "start": { "line": 4, "character": 0 },
+            "end": { "line": 9, "character": 1 }
+        },
+        "targetSelectionRange": {
+            "start": { "line": 4, "character": 7 },
+            "end": { "line": 4, "character": 11 }
+        }
+    }]);
+    responses["textDocument/references"] = json!([
+        {
+            "uri": path_to_uri(FILE_PATH),
+            "range": {
+                "start": { "line": 2, "character": 4 },
+                "end": { "line": 2, "character": 8 }
+            }
+        },
+        {
+            "uri": path_to_uri("/workspace/src/lib.rs"),
+            "range": {
+                "start": { "line": 4, "character": 7 },
+                "end": { "line": 4, "character": 11 }
+            }
+        }
+    ]);
+    responses["textDocument/documentSymbol"] = json!([{
+        "name": "main",
+        "kind": 12,
+        "range": {
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 3, "character": 1 }
+        },
+        "selectionRange": {
+            "start": { "line": 0, "character": 3 },
+            "end": { "line": 0, "character": 7 }
+        }
+    }]);
+    let (client, _events) = start(json!({ "responses": responses }));
+    let position = Position {
+        line: 0,
+        character: 4,
+    };
+
+    let hover = client.hover(FILE_PATH, position).unwrap().unwrap();
+    assert_eq!(hover.contents, "```rust\nfn main()\n```");
+
+    let items = client.completion(FILE_PATH, position).unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].detail.as_deref(), Some("macro"));
+
+    let locations = client.definition(FILE_PATH, position).unwrap();
+    assert_eq!(locations[0].path, "/workspace/src/lib.rs");
+    assert_eq!(locations[0].range.start.line, 4);
+    assert_eq!(locations[0].range.start.character, 7);
+
+    let locations = client.references(FILE_PATH, position, true).unwrap();
+    assert_eq!(locations.len(), 2);
+    assert_eq!(locations[0].path, FILE_PATH);
+    assert_eq!(locations[1].range.start.line, 4);
+
+    let symbols = client.document_symbols(FILE_PATH).unwrap();
+    assert_eq!(symbols[0].name, "main");
+    assert_eq!(symbols[0].range.end.line, 3);
+
+    let requests = received(&client);
+    assert_eq!(requests[2].0, "textDocument/hover");
+    assert_eq!(requests[2].1["position"]["character"], 4);
+    assert_eq!(requests[5].0, "textDocument/references");
+    assert_eq!(requests[5].1["context"]["includeDeclaration"], true);
+}
+
+#[test]
+fn unsupported_features_are_not_requested_test() {
+    let (client, _events) = start(json!({}));
+
+    assert_eq!(client.hover(FILE_PATH, Position::default()), Ok(None));
+    assert_eq!(
+        client.definition(FILE_PATH, Position::default()),
+        Ok(vec![])
+    );
+    assert_eq!(
+        client.references(FILE_PATH, Position::default(), false),
+        Ok(vec![])
+    );
+    assert_eq!(received(&client).len(), 2);
+}
+
+#[test]
+fn request_errors_test() {
+    let mut script = json!({
+        "responses": all_capabilities(),
+        "errors": { "textDocument/definition": { "code": -32603, "message": "no crate graph" } },
+        "delays": { "textDocument/hover": 1000 }
+    });
+    script["responses"]["textDocument/hover"] = json!({ "contents": "late" });
+    let (mut client, _events) = start(script);
+    client.set_timeout(Duration::from_millis(100));
+
+    assert_eq!(
+        client.definition(FILE_PATH, Position::default()),
+        Err(LspError::Server {
+            code: -32603,
+            message: String::from("no crate graph")
+        })
+    );
+    assert_eq!(
+        client.hover(FILE_PATH, Position::default()),
+        Err(LspError::Timeout(String::from("textDocument/hover")))
+    );
+
+    // Late answer of the timed out request is dropped
+    client.set_timeout(Duration::from_secs(5));
+    let requests = received(&client);
+    assert_eq!(requests.last().unwrap().0, "$/cancelRequest");
+}
+
+#[test]
+fn server_requests_test() {
+    let configuration_request = json!({
+        "id": "config-1",
+        "method": "workspace/configuration",
+        "params": { "items": [{ "section": "rust-analyzer" }, { "section": "files" }] }
+    });
+    let (client, _events) = start(json!({
+        "send": { "initialized": [configuration_request] }
+    }));
+
+    // Client answers from its reader thread, maybe after `fake/received` was sent
+    let response = (0..50)
+        .find_map(|_| {
+            let response = received(&client)
+                .into_iter()
+                .find(|(method, _)| method == "response");
+            if response.is_none() {
+                thread::sleep(Duration::from_millis(20));
+            }
+            response
+        })
+        .map(|(_, response)| response)
+        .expect("No response to workspace/configuration");
+    assert_eq!(response["id"], "config-1");
+    assert_eq!(response["result"], json!([null, null]));
+}
+
+#[test]
+fn server_crash_test() {
+    let (client, events) = start(json!({
+        "responses": all_capabilities(),
+        "crash_on": ["textDocument/completion"]
+    }));
+
+    assert_eq!(
+        client.completion(FILE_PATH, Position::default()),
+        Err(LspError::Exited)
+    );
+    assert_eq!(
+        events.recv_timeout(Duration::from_secs(5)),
+        Ok(LspEvent::Exited(String::from("rust")))
+    );
+    assert!(!client.is_running());
+    assert_eq!(
+        client.request("fake/received", Value::Null),
+        Err(LspError::Exited)
+    );
+}
+
+#[test]
+fn worker_test() {
+    let mut responses = all_capabilities();
+    responses["textDocument/hover"] = json!({ "contents": "fn main()" });
+    let script = json!({ "responses": responses });
+    let config = LanguageServerConfig {
+        command: env!("CARGO_BIN_EXE_fake-lsp-server").to_string(),
+        args: vec![script.to_string()],
+        extensions: vec![String::from("rs")],
+        ..LanguageServerConfig::default()
+    };
+    let (tx, events) = mpsc::channel();
+    let manager = LspManager::new(
+        "",
+        BTreeMap::from([(String::from("rust"), config)]),
+        move |event| {
+            tx.send(event).ok();
+        },
+    );
+
+    // Calls return right away, the server is started on the worker thread
+    let worker = LspWorker::new(manager);
+    worker.did_open(FILE_PATH, String::from("fn main() {}"));
+    worker.request(1, FILE_PATH, LspRequest::Hover(Position::default()));
+    worker.request(2, FILE_PATH, LspRequest::DocumentSymbols);
+    worker.request(3, "/workspace/README.md", LspRequest::DocumentSymbols);
+
+    let mut responses = vec![];
+    while responses.len() < 3 {
+        match events.recv_timeout(Duration::from_secs(5)) {
+            Ok(LspEvent::Response { id, result }) => responses.push((id, result)),
+            Ok(_) => {}
+            Err(error) => panic!("No response: {error}"),
+        }
+    }
+    responses.sort_by_key(|(id, _)| *id);
+
+    let Ok(LspResponse::Hover(Some(hover))) = &responses[0].1 else {
+        panic!("Unexpected hover response {:?}", responses[0]);
+    };
+    assert_eq!(hover.contents, "fn main()");
+    assert_eq!(responses[1].1, Ok(LspResponse::DocumentSymbols(vec![])));
+    // No server for markdown files
+    assert_eq!(responses[2].1, Ok(LspResponse::Unsupported));
+}
@@ -0,0 +1,99 @@
+/*
+A debug adapter that follows a JSON script given as first argument:
+
+{
+    // Body of each response, `null` when missing
+    "responses": { "initialize": { "supportsConfigurationDoneRequest": true } },
+    // Requests failing with an error message instead
+    "errors": { "evaluate": "no such variable" },
+    // Events sent after receiving a command, ex: `initialized` after `launch`
+    "send": { "launch": [{ "event": "initialized", "body": {} }] },
+    // Requests answered only once another command is received
+    "respond_after": { "launch": "configurationDone" }
+}
+
+Every request received is recorded, the `fakeReceived` request returns them
+as `{ "command", "arguments" }` objects. The adapter exits after `disconnect`.
+*/
+
+use std::{
+    collections::HashMap,
+    io::{stdin, stdout, BufReader},
+};
+
+use libmystudio::debugger::protocol::{read_message, write_message, DapMessage};
+use serde_json::{json, Value};
+
+fn main() {
+    let script = std::env::args()
+        .nth(1)
+        .map(|script| serde_json::from_str::<Value>(&script).expect("Invalid script"))
+        .unwrap_or(Value::Null);
+
+    let mut reader = BufReader::new(stdin());
+    let mut writer = stdout();
+    let mut seq = 0;
+    let mut received = vec![];
+    // Command answered later to its `seq`, by the command it waits for
+    let mut deferred: HashMap<String, (String, u64)> = HashMap::new();
+
+    let mut send = |message: DapMessage| {
+        seq += 1;
+        write_message(&mut writer, seq, &message).unwrap();
+    };
+    let response = |script: &Value, request_seq: u64, command: &str, received: &[Value]| {
+        let (success, message, body) = if command == "fakeReceived" {
+            (true, None, json!({ "requests": received }))
+        } else if let Some(error) = script["errors"][command].as_str() {
+            (false, Some(error.to_string()), Value::Null)
+        } else {
+            (true, None, script["responses"][command].clone())
+        };
+
+        DapMessage::Response {
+            request_seq,
+            success,
+            command: command.to_string(),
+            message,
+            body,
+        }
+    };
+
+    while let Ok(Some((request_seq, message))) = read_message(&mut reader) {
+        let DapMessage::Request { command, arguments } = message else {
+            continue;
+        };
+
+        match script["respond_after"][&command].as_str() {
+            Some(after) => {
+                deferred.insert(after.to_string(), (command.clone(), request_seq));
+            }
+            None => send(response(&script, request_seq, &command, &received)),
+        }
+
+        if let Some(events) = script["send"][&command].as_array() {
+            for event in events {
+                send(DapMessage::event(
+                    event["event"].as_str().expect("Invalid event"),
+                    event["body"].clone(),
+                ));
+            }
+        }
+
+        if let Some((deferred_command, deferred_seq)) = deferred.remove(&command) {
+            send(response(
+                &script,
+                deferred_seq,
+                &deferred_command,
+                &received,
+            ));
+        }
+
+        if command == "disconnect" {
+            break;
+        }
+        if command != "fakeReceived" {
+            received.push(json!({ "command": command, "arguments": arguments }));
+        }
+    }
+}
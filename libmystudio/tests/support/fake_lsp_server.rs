@@ -0,0 +1,90 @@
+/*
+A language server that follows a JSON script given as first argument:
+
+{
+    // Result of each request method, `null` when missing
+    "responses": { "initialize": { "capabilities": { "hoverProvider": true } } },
+    // Requests answered with an error instead
+    "errors": { "textDocument/definition": { "code": -32603, "message": "failed" } },
+    // Messages sent after receiving a method, ex: diagnostics after `didOpen`
+    "send": { "textDocument/didOpen": [{ "method": "window/showMessage", "params": {} }] },
+    // Requests answered after a delay, in milliseconds
+    "delays": { "textDocument/hover": 500 },
+    // Methods making the server exit right away, as if it crashed
+    "crash_on": ["textDocument/completion"]
+}
+
+Every message received is recorded, the `fake/received` request returns
+them as `{ "method", "params" }` objects (responses have a `response` method).
+*/
+
+use std::{
+    io::{stdin, stdout, BufReader},
+    process::exit,
+    thread,
+    time::Duration,
+};
+
+use libmystudio::lsp::jsonrpc::{read_message, write_message, Message, ResponseError};
+use serde_json::{json, Value};
+
+fn main() {
+    let script = std::env::args()
+        .nth(1)
+        .map(|script| serde_json::from_str::<Value>(&script).expect("Invalid script"))
+        .unwrap_or(Value::Null);
+
+    let mut reader = BufReader::new(stdin());
+    let mut writer = stdout();
+    let mut received = vec![];
+
+    while let Ok(Some(message)) = read_message(&mut reader) {
+        let (method, params) = match &message {
+            Message::Request { method, params, .. } => (method.clone(), params.clone()),
+            Message::Notification { method, params } => (method.clone(), params.clone()),
+            Message::Response { id, result } => (
+                String::from("response"),
+                json!({ "id": id, "result": result.clone().unwrap_or(Value::Null) }),
+            ),
+        };
+
+        if script["crash_on"]
+            .as_array()
+            .is_some_and(|methods| methods.contains(&json!(method)))
+        {
+            exit(1);
+        }
+
+        if let Message::Request { id, .. } = message {
+            if let Some(delay) = script["delays"][&method].as_u64() {
+                thread::sleep(Duration::from_millis(delay));
+            }
+
+            let result = if method == "fake/received" {
+                Ok(Value::Array(received.clone()))
+            } else if let Some(error) = script["errors"].get(&method) {
+                Err(ResponseError {
+                    code: error["code"].as_i64().unwrap_or(-32603),
+                    message: error["message"].as_str().unwrap_or_default().to_string(),
+                })
+            } else {
+                Ok(script["responses"][&method].clone())
+            };
+            write_message(&mut writer, &Message::Response { id, result }).unwrap();
+        }
+
+        if let Some(messages) = script["send"][&method].as_array() {
+            for message in messages {
+                let message = Message::from_value(message.clone()).expect("Invalid message");
+                write_message(&mut writer, &message).unwrap();
+            }
+        }
+
+        if method == "exit" {
+            break;
+        }
+        if method != "fake/received" {
+            received.push(json!({ "method": method, "params": params }));
+        }
+    }
+}
@@ -0,0 +1,387 @@
+// Registry of actions that can be run from keybindings, buttons and the command palette
+
+use libmystudio::workspace::Workspace;
+
+use crate::ui::{
+    action_row::handler::{
+        on_open_dir_clicked, on_open_file_clicked, on_preferences_clicked, on_save_changes_clicked,
+    },
+    features,
+    notebook::{completion, diff_view, emacs, format, git_gutter, lsp, navigation},
+    statusbar::goto_line::show_goto_dialog,
+};
+
+pub struct Command {
+    // Used as key in `[Keybindings]` section of config, ex: `save_file`
+    pub id: &'static str,
+    // Shown in command palette
+    pub title: &'static str,
+    pub handler: fn(),
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        id: "open_workspace",
+        title: "Open Workspace",
+        handler: on_open_dir_clicked,
+    },
+    Command {
+        id: "open_file",
+        title: "Open File",
+        handler: on_open_file_clicked,
+    },
+    Command {
+        id: "save_file",
+        title: "Save File",
+        handler: on_save_changes_clicked,
+    },
+    Command {
+        id: "goto_line",
+        title: "Go to Line",
+        handler: goto_line,
+    },
+    Command {
+        id: "find",
+        title: "Find",
+        handler: show_find_bar,
+    },
+    Command {
+        id: "replace",
+        title: "Replace",
+        handler: show_replace_bar,
+    },
+    Command {
+        id: "find_next",
+        title: "Find Next",
+        handler: features::find_bar::find_next,
+    },
+    Command {
+        id: "find_previous",
+        title: "Find Previous",
+        handler: features::find_bar::find_previous,
+    },
+    Command {
+        id: "show_hover",
+        title: "Show Hover Information",
+        handler: lsp::show_hover,
+    },
+    Command {
+        id: "show_completion",
+        title: "Show Completions",
+        handler: completion::show,
+    },
+    Command {
+        id: "format_document",
+        title: "Format Document",
+        handler: format::format_document,
+    },
+    Command {
+        id: "go_to_definition",
+        title: "Go to Definition",
+        handler: navigation::go_to_definition,
+    },
+    Command {
+        id: "find_references",
+        title: "Find References",
+        handler: navigation::find_references,
+    },
+    Command {
+        id: "navigate_back",
+        title: "Go Back",
+        handler: navigation::go_back,
+    },
+    Command {
+        id: "navigate_forward",
+        title: "Go Forward",
+        handler: navigation::go_forward,
+    },
+    Command {
+        id: "toggle_problems",
+        title: "Toggle Problems Panel",
+        handler: features::problems::toggle,
+    },
+    Command {
+        id: "run_task",
+        title: "Run Task",
+        handler: features::tasks::show_dialog,
+    },
+    Command {
+        id: "rerun_last_task",
+        title: "Rerun Last Task",
+        handler: features::tasks::rerun_last_task,
+    },
+    Command {
+        id: "stop_task",
+        title: "Stop Task",
+        handler: features::tasks::stop_task,
+    },
+    Command {
+        id: "toggle_tasks",
+        title: "Toggle Tasks Panel",
+        handler: features::tasks::toggle,
+    },
+    Command {
+        id: "toggle_tests",
+        title: "Toggle Tests Panel",
+        handler: features::test_explorer::toggle,
+    },
+    Command {
+        id: "run_all_tests",
+        title: "Run All Tests",
+        handler: features::test_explorer::run_all_tests,
+    },
+    Command {
+        id: "rerun_failed_tests",
+        title: "Rerun Failed Tests",
+        handler: features::test_explorer::rerun_failed_tests,
+    },
+    Command {
+        id: "start_debugging",
+        title: "Start or Continue Debugging",
+        handler: features::debugger::start_debugging,
+    },
+    Command {
+        id: "stop_debugging",
+        title: "Stop Debugging",
+        handler: features::debugger::stop_debugging,
+    },
+    Command {
+        id: "pause_debugging",
+        title: "Pause Debugging",
+        handler: features::debugger::pause_debugging,
+    },
+    Command {
+        id: "step_over",
+        title: "Step Over",
+        handler: features::debugger::step_over,
+    },
+    Command {
+        id: "step_into",
+        title: "Step Into",
+        handler: features::debugger::step_into,
+    },
+    Command {
+        id: "step_out",
+        title: "Step Out",
+        handler: features::debugger::step_out,
+    },
+    Command {
+        id: "toggle_breakpoint",
+        title: "Toggle Breakpoint",
+        handler: features::debugger::toggle_breakpoint,
+    },
+    Command {
+        id: "toggle_debug",
+        title: "Toggle Debug Panel",
+        handler: features::debugger::toggle,
+    },
+    Command {
+        id: "next_change",
+        title: "Go to Next Change",
+        handler: git_gutter::next_change,
+    },
+    Command {
+        id: "previous_change",
+        title: "Go to Previous Change",
+        handler: git_gutter::previous_change,
+    },
+    Command {
+        id: "toggle_source_control",
+        title: "Toggle Source Control",
+        handler: features::source_control::toggle,
+    },
+    Command {
+        id: "compare_with_head",
+        title: "Compare with HEAD",
+        handler: diff_view::compare_with_head,
+    },
+    Command {
+        id: "compare_with_saved",
+        title: "Compare with Saved",
+        handler: diff_view::compare_with_saved,
+    },
+    Command {
+        id: "compare_selection_with_clipboard",
+        title: "Compare Selection with Clipboard",
+        handler: diff_view::compare_selection_with_clipboard,
+    },
+    Command {
+        id: "toggle_terminal",
+        title: "Toggle Terminal",
+        handler: features::terminal::toggle,
+    },
+    Command {
+        id: "new_terminal",
+        title: "New Terminal",
+        handler: features::terminal::new_terminal,
+    },
+    Command {
+        id: "close_terminal",
+        title: "Close Terminal",
+        handler: features::terminal::close_terminal,
+    },
+    Command {
+        id: "terminal_copy",
+        title: "Copy in Terminal",
+        handler: features::terminal::copy,
+    },
+    Command {
+        id: "terminal_paste",
+        title: "Paste in Terminal",
+        handler: features::terminal::paste,
+    },
+    Command {
+        id: "find_in_files",
+        title: "Find in Files",
+        handler: find_in_files,
+    },
+    Command {
+        id: "symbol_search",
+        title: "Go to Symbol in Workspace",
+        handler: features::symbol_search::show_dialog,
+    },
+    Command {
+        id: "preferences",
+        title: "Preferences",
+        handler: on_preferences_clicked,
+    },
+    Command {
+        id: "command_palette",
+        title: "Show Command Palette",
+        handler: features::command_palette::show_dialog,
+    },
+    Command {
+        id: "move_line_start",
+        title: "Move to Line Start",
+        handler: emacs::move_line_start,
+    },
+    Command {
+        id: "move_line_end",
+        title: "Move to Line End",
+        handler: emacs::move_line_end,
+    },
+    Command {
+        id: "forward_char",
+        title: "Forward Char",
+        handler: emacs::forward_char,
+    },
+    Command {
+        id: "backward_char",
+        title: "Backward Char",
+        handler: emacs::backward_char,
+    },
+    Command {
+        id: "next_line",
+        title: "Next Line",
+        handler: emacs::next_line,
+    },
+    Command {
+        id: "previous_line",
+        title: "Previous Line",
+        handler: emacs::previous_line,
+    },
+    Command {
+        id: "set_mark",
+        title: "Set Mark",
+        handler: emacs::set_mark,
+    },
+    Command {
+        id: "kill_line",
+        title: "Kill Line",
+        handler: emacs::kill_line,
+    },
+    Command {
+        id: "kill_region",
+        title: "Kill Region",
+        handler: emacs::kill_region,
+    },
+    Command {
+        id: "copy_region",
+        title: "Copy Region",
+        handler: emacs::copy_region,
+    },
+    Command {
+        id: "yank",
+        title: "Yank",
+        handler: emacs::yank,
+    },
+    Command {
+        id: "yank_pop",
+        title: "Yank Pop",
+        handler: emacs::yank_pop,
+    },
+    Command {
+        id: "isearch_forward",
+        title: "Incremental Search Forward",
+        handler: emacs::isearch_forward,
+    },
+    Command {
+        id: "isearch_backward",
+        title: "Incremental Search Backward",
+        handler: emacs::isearch_backward,
+    },
+    Command {
+        id: "keyboard_quit",
+        title: "Keyboard Quit",
+        handler: emacs::keyboard_quit,
+    },
+];
+
+pub fn all() -> &'static [Command] {
+    COMMANDS
+}
+
+pub fn find(id: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.id == id)
+}
+
+/**
+ * Runs a command by its id.
+ */
+pub fn run(id: &str) {
+    match find(id) {
+        Some(command) => (command.handler)(),
+        None => eprintln!("Unknown command '{id}'"),
+    }
+}
+
+fn goto_line() {
+    if Workspace::get_open_file_path().is_some() {
+        show_goto_dialog();
+    }
+}
+
+fn show_find_bar() {
+    features::find_bar::show(false);
+}
+
+fn show_replace_bar() {
+    features::find_bar::show(true);
+}
+
+fn find_in_files() {
+    if !Workspace::get_path().is_empty() {
+        features::find_in_files::show_dialog();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libmystudio::keymap::{preset_keybindings, KeymapPreset};
+
+    use super::{all, find};
+
+    #[test]
+    fn keymap_presets_use_known_commands() {
+        for preset in KeymapPreset::ALL {
+            for command_id in preset_keybindings(preset).keys() {
+                assert!(find(command_id).is_some(), "unknown command {command_id}");
+            }
+        }
+
+        // Ids are unique
+        for command in all() {
+            assert_eq!(all().iter().filter(|c| c.id == command.id).count(), 1);
+        }
+    }
+}
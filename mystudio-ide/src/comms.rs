@@ -1,8 +1,14 @@
 use std::cell::RefCell;
 
 use gtk::glib::{self, Receiver, Sender};
+use libmystudio::app_config::layered::LayeredAppConfig;
+use libmystudio::debugger::types::DapEvent;
+use libmystudio::git::error::GitError;
+use libmystudio::git::status::GitStatus;
+use libmystudio::lsp::types::LspEvent;
+use libmystudio::symbols::SymbolIndex;
+use libmystudio::tasks::runner::TaskEvent;
 use libmystudio::tree::tree_model::RootTreeModel;
-use libmystudio::workspace::Workspace;
 
 use crate::ui;
 use crate::ui::notebook::{editor::Editor, handler::handle_notebook_event};
@@ -21,6 +27,26 @@ pub enum CommEvents {
     UpdateRootTextViewContent(Option<String>, Option<String>),
     // Save Changes
     SaveEditorChanges(),
+    // config.toml or workspace settings were modified on disk
+    AppConfigChanged(LayeredAppConfig),
+    // Sent by a language server, ex: new diagnostics
+    LanguageServerEvent(LspEvent),
+    // Symbol index of a workspace was refreshed on a background thread
+    SymbolIndexUpdated(String, SymbolIndex),
+    // Output of a running task, see `TaskRun`
+    TaskEvent(TaskEvent),
+    // Output of `cargo test -- --list` run by the Tests panel
+    TestListEvent(TaskEvent),
+    // Sent by the debug adapter of a session, by session id
+    DebugEvent(u64, DapEvent),
+    // Git status of the workspace was read again on a background thread
+    GitStatusChanged(GitStatus),
+    // Committed text of an open file was read, by read id, see `git_gutter`
+    GitHeadLoaded(u64, String, Option<String>),
+    // Committed text of a file to compare with was read, see `diff_view`
+    CompareHeadRead(String, Result<Option<String>, GitError>),
+    // Output of git commands of the Source Control panel, by command id
+    SourceControlOutput(u64, ui::features::source_control::GitOutput),
 }
 
 thread_local! { static G_COMMS_SENDER: RefCell<Option<Sender<CommEvents>>> = RefCell::new(None) }
@@ -40,6 +66,10 @@ impl Comms {
         rx.attach(None, move |msg| {
             match msg {
                 CommEvents::UpdateRootTree() => {
+                    // Workspace settings may differ
+                    ui::app_config::workspace_changed();
+                    ui::w_explorer::refresh_git_status();
+
                     G_TREE.with(|tree| {
                         RootTreeModel::update_tree_model(&tree.borrow().clone().unwrap());
                         // Reset UI
@@ -62,31 +92,39 @@ impl Comms {
                     Editor::new().set_text(file_path, content, false);
                 }
                 CommEvents::SaveEditorChanges() => {
-                    let file_absolute_path = Workspace::get_open_file_path();
-                    match file_absolute_path {
-                        Some(file_abs_path) => {
-                            // Get text from editor
-                            let text_buffer = Editor::buffer_from_path(file_abs_path.clone())
-                                .expect("Unable to find editor for open file");
-
-                            // Show message in Status bar
-                            match ui::action_row::handler::save_file_changes(
-                                text_buffer,
-                                file_abs_path.clone(),
-                            ) {
-                                Ok(_) => {
-                                    let message = format!("Saved changes to '{}'", &file_abs_path);
-                                    ui::statusbar::message::show_message(message);
-                                }
-                                Err(error_message) => {
-                                    ui::statusbar::message::show_message(error_message);
-                                }
-                            }
-                        }
-                        None => {
-                            eprintln!("Unable to write Workspace#open_file_path");
-                        }
-                    }
+                    ui::action_row::handler::save_open_file();
+                }
+                CommEvents::AppConfigChanged(layered_config) => {
+                    ui::app_config::reload(layered_config);
+                }
+                CommEvents::LanguageServerEvent(event) => {
+                    ui::notebook::lsp::handle_event(event);
+                }
+                CommEvents::SymbolIndexUpdated(root, index) => {
+                    ui::features::symbol_search::index_updated(root, index);
+                }
+                CommEvents::TaskEvent(event) => {
+                    ui::features::test_explorer::handle_task_event(&event);
+                    ui::features::debugger::handle_task_event(&event);
+                    ui::features::tasks::handle_event(event);
+                }
+                CommEvents::TestListEvent(event) => {
+                    ui::features::test_explorer::handle_list_event(event);
+                }
+                CommEvents::DebugEvent(id, event) => {
+                    ui::features::debugger::handle_event(id, event);
+                }
+                CommEvents::GitStatusChanged(status) => {
+                    ui::w_explorer::git_status_changed(status);
+                }
+                CommEvents::GitHeadLoaded(load, file_path, head) => {
+                    ui::notebook::git_gutter::head_loaded(load, &file_path, head);
+                }
+                CommEvents::CompareHeadRead(file_path, head) => {
+                    ui::notebook::diff_view::head_read(file_path, head);
+                }
+                CommEvents::SourceControlOutput(id, output) => {
+                    ui::features::source_control::handle_output(id, output);
                 }
             }
             // Don't forget to include this!
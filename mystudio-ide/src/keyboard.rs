@@ -2,87 +2,124 @@
 
 use std::cell::RefCell;
 
-use crate::ui::{
-    action_row::handler::{on_open_dir_clicked, on_save_changes_clicked},
-    features,
-    statusbar::goto_line::show_goto_dialog,
+use crate::{
+    commands,
+    ui::{
+        features::terminal::{self, TERMINAL_COMMANDS},
+        notebook::editor::{Editor, EDITOR_COMMANDS},
+        statusbar::message::show_message,
+    },
 };
 
-use gtk::prelude::{AccelGroupExtManual, GtkWindowExt};
-use gtk::{gdk, AccelFlags, ApplicationWindow};
+use gtk::prelude::{GtkWindowExt, ObjectExt, WidgetExt};
+use gtk::{gdk, ApplicationWindow, Editable, TextView};
 
-use libmystudio::workspace::Workspace;
+use libmystudio::{
+    app_config::AppConfig,
+    keymap::{key_sequence_to_string, KeyStroke, Keymap, KeymapMatch},
+};
 
-thread_local! {static KEY_EVENT_TRACKER : RefCell<Vec<gdk::EventKey>> = RefCell::new(Vec::new())}
+thread_local! {static G_KEYMAP : RefCell<Keymap> = RefCell::new(Keymap::default())}
+// Keys pressed so far of an unfinished chord
+thread_local! {static KEY_EVENT_TRACKER : RefCell<Vec<KeyStroke>> = RefCell::new(Vec::new())}
 
 pub fn listen_for_events(window: &ApplicationWindow) {
-    // "Open Workspace" Keyboard shortcut
-    let (accel_key, accel_mods) = gtk::accelerator_parse("<Ctrl><Shift>O");
-    let accel_group = gtk::AccelGroup::new();
-
-    accel_group.connect_accel_group(
-        accel_key,
-        accel_mods,
-        AccelFlags::VISIBLE,
-        move |_, _, _, _| {
-            on_open_dir_clicked();
-            true
-        },
-    );
-
-    window.add_accel_group(&accel_group);
-
-    // "Save Changes" Keyboard shortcut
-    let (accel_key, accel_mods) = gtk::accelerator_parse("<Ctrl>S");
-    let accel_group = gtk::AccelGroup::new();
-
-    accel_group.connect_accel_group(
-        accel_key,
-        accel_mods,
-        AccelFlags::VISIBLE,
-        move |_, _, _, _| {
-            on_save_changes_clicked();
-            true
-        },
-    );
-
-    window.add_accel_group(&accel_group);
-
-    // Goto Line
-
-    let (accel_key, accel_mods) = gtk::accelerator_parse("<Ctrl>G");
-    let accel_group = gtk::AccelGroup::new();
-
-    accel_group.connect_accel_group(
-        accel_key,
-        accel_mods,
-        AccelFlags::VISIBLE,
-        move |_, _, _, _| {
-            if Workspace::get_open_file_path().is_some() {
-                show_goto_dialog();
+    window.connect_key_press_event(move |window, event| {
+        let Some(key_stroke) = key_stroke_from_event(event) else {
+            return gtk::Inhibit(false);
+        };
+
+        // Keys go to the shell, except for commands handling terminals
+        if terminal::has_focus() {
+            KEY_EVENT_TRACKER.with(|tracker| tracker.borrow_mut().clear());
+            return match G_KEYMAP.with(|keymap| keymap.borrow().lookup(&[key_stroke])) {
+                KeymapMatch::Command(command) if TERMINAL_COMMANDS.contains(&command.as_str()) => {
+                    commands::run(&command);
+                    gtk::Inhibit(true)
+                }
+                _ => gtk::Inhibit(false),
+            };
+        }
+
+        let mut keys =
+            KEY_EVENT_TRACKER.with(|tracker| tracker.borrow_mut().drain(..).collect::<Vec<_>>());
+        let is_chord = !keys.is_empty();
+        keys.push(key_stroke);
+
+        // Text fields other than the editor handle their keys first, ex: `Ctrl+C`
+        // copies in the find bar even if it starts a chord
+        let editor_focused = Editor::has_focus();
+        if !is_chord
+            && !editor_focused
+            && text_field_has_focus(window)
+            && window.propagate_key_event(event)
+        {
+            return gtk::Inhibit(true);
+        }
+
+        match G_KEYMAP.with(|keymap| keymap.borrow().lookup(&keys)) {
+            KeymapMatch::Command(command)
+                if !editor_focused && EDITOR_COMMANDS.contains(&command.as_str()) =>
+            {
+                gtk::Inhibit(false)
             }
-            true
-        },
-    );
-
-    window.add_accel_group(&accel_group);
+            KeymapMatch::Command(command) => {
+                commands::run(&command);
+                gtk::Inhibit(true)
+            }
+            KeymapMatch::Pending => {
+                show_message(format!(
+                    "({}) was pressed. Waiting for second key of chord...",
+                    key_sequence_to_string(&keys)
+                ));
+                KEY_EVENT_TRACKER.with(|tracker| *tracker.borrow_mut() = keys);
+                gtk::Inhibit(true)
+            }
+            KeymapMatch::None if is_chord => {
+                show_message(format!(
+                    "The key combination ({}) is not a command.",
+                    key_sequence_to_string(&keys)
+                ));
+                gtk::Inhibit(true)
+            }
+            KeymapMatch::None => gtk::Inhibit(false),
+        }
+    });
+}
 
-    // Find in Files
+/// Returns keys bound to a given command, ex: `Ctrl+S`.
+pub fn keys_for_command(command_id: &str) -> Option<String> {
+    G_KEYMAP.with(|keymap| keymap.borrow().keys_for(command_id))
+}
 
-    let (accel_key, accel_mods) = gtk::accelerator_parse("<Ctrl><Shift>F");
-    let accel_group = gtk::AccelGroup::new();
+/**
+ * Rebuilds keymap from the keymap preset and `[Keybindings]` section of a given config.
+ */
+pub fn reload_keymap(config: &AppConfig) {
+    G_KEYMAP.with(|keymap| *keymap.borrow_mut() = Keymap::from_bindings(&config.keybindings()));
+    KEY_EVENT_TRACKER.with(|tracker| tracker.borrow_mut().clear());
+}
 
-    accel_group.connect_accel_group(
-        accel_key,
-        accel_mods,
-        AccelFlags::VISIBLE,
-        move |_, _, _, _| {
-            if !Workspace::get_path().is_empty() {
-                features::find_in_files::show_dialog();
-            }
-            true
-        },
-    );
+fn text_field_has_focus(window: &ApplicationWindow) -> bool {
+    window
+        .focused_widget()
+        .is_some_and(|widget| widget.is::<Editable>() || widget.is::<TextView>())
+}
 
-    window.add_accel_group(&accel_group);
+fn key_stroke_from_event(event: &gdk::EventKey) -> Option<KeyStroke> {
+    // Wait for the actual key when only a modifier is pressed
+    if event.is_modifier() {
+        return None;
+    }
+
+    let key_name = event.keyval().to_lower().name()?;
+    let state = event.state();
+
+    Some(KeyStroke::from_key_press(
+        &key_name,
+        state.contains(gdk::ModifierType::CONTROL_MASK),
+        state.contains(gdk::ModifierType::SHIFT_MASK),
+        state.contains(gdk::ModifierType::MOD1_MASK),
+        state.contains(gdk::ModifierType::SUPER_MASK),
+    ))
 }
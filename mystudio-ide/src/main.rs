@@ -12,12 +12,13 @@ use gtk::{
     Application, ApplicationWindow, Builder, StyleContext,
 };
 use libmystudio::{
-    app_config::{AppConfigProvider, DefaultAppConfigProvider},
+    app_config::layered::LayeredAppConfig,
     workspace::Workspace,
 };
 
 use crate::comms::Comms;
 
+mod commands;
 pub mod comms;
 mod keyboard;
 mod ui;
@@ -42,18 +43,8 @@ fn build_ui(app: &Application) {
         assert!(window.borrow().as_ref().is_some());
         window.borrow().as_ref().unwrap().set_application(Some(app));
 
-        // Set window geometry
-        let app_config = DefaultAppConfigProvider::get_config();
-        window
-            .borrow()
-            .as_ref()
-            .unwrap()
-            .set_width_request(app_config.General.application_width);
-        window
-            .borrow()
-            .as_ref()
-            .unwrap()
-            .set_height_request(app_config.General.application_height);
+        // Load config
+        let app_config_errors = LayeredAppConfig::recompute();
 
         // Init styling
         let css_provider = gtk::CssProvider::new();
@@ -73,10 +64,14 @@ fn build_ui(app: &Application) {
         let (tx, rx) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
         Comms::init(tx, rx);
 
+        // Apply config & reload it on change
+        ui::app_config::init();
+
         // Actions buttons menu
         ui::action_row::setup_actions(&builder);
 
-        // Tree
+        // Tree and the panels next to it
+        ui::side_panel::init(&builder);
         ui::w_explorer::init(&builder);
         // Notebook
         ui::notebook::init(&builder);
@@ -87,10 +82,26 @@ fn build_ui(app: &Application) {
         // Find in files
         ui::features::find_in_files::init(&builder);
 
+        // Find and replace in open file
+        ui::features::find_bar::init(&builder);
+
+        // Definitions of the active file
+        ui::features::outline::init(&builder);
+
+        // Definitions of the whole workspace
+        ui::features::symbol_search::init(&builder);
+
+        // Tool panels below the editor
+        ui::bottom_panel::init(&builder);
+        ui::features::problems::init();
+
         // Keyboard events
         crate::keyboard::listen_for_events(&window.borrow().clone().unwrap());
 
         window.borrow().clone().unwrap().show_all();
+
+        // Report problems in config file
+        ui::app_config::show_problems_dialog(&app_config_errors);
     });
 }
 
@@ -1,11 +1,14 @@
 use gtk::{
-    prelude::{FileExt, TextBufferExt},
+    prelude::{FileExt, ObjectExt, TextBufferExt},
     traits::{FileChooserExt, NativeDialogExt},
     TextBuffer,
 };
-use libmystudio::{fs, workspace::Workspace};
+use libmystudio::{fs, tree::tree_model::RootTreeModel, workspace::Workspace};
 
-use crate::comms::{CommEvents, Comms};
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{features, notebook, notebook::editor::Editor, statusbar, w_explorer},
+};
 
 pub fn save_file_changes(
     text_buffer: TextBuffer,
@@ -16,7 +19,49 @@ pub fn save_file_changes(
         .unwrap();
     let content = content_gstring.as_str();
 
-    fs::save_file_changes(file_absolute_path, content)
+    fs::save_file_changes(file_absolute_path.clone(), content)?;
+    notebook::lsp::document_saved(&file_absolute_path);
+    features::symbol_search::document_saved(&file_absolute_path, content);
+
+    Ok(())
+}
+
+/**
+ * Formats the open file if enabled then saves it, the result is shown in the
+ * status bar. Returns whether the changes were saved.
+ */
+pub fn save_open_file() -> bool {
+    let Some(file_abs_path) = Workspace::get_open_file_path() else {
+        eprintln!("Unable to write Workspace#open_file_path");
+        return false;
+    };
+    let Some(text_buffer) = Editor::buffer_from_path(file_abs_path.clone()) else {
+        eprintln!("Unable to find editor for open file");
+        return false;
+    };
+
+    // Changes are saved even if the formatter fails
+    let format_result = notebook::format::format_on_save(&text_buffer, &file_abs_path);
+
+    // Show message in Status bar
+    match save_file_changes(text_buffer, file_abs_path.clone()) {
+        Ok(_) => {
+            let message = match format_result {
+                Ok(_) => format!("Saved changes to '{}'", &file_abs_path),
+                Err(error_message) => format!(
+                    "Saved changes to '{}' without formatting. {error_message}",
+                    &file_abs_path
+                ),
+            };
+            statusbar::message::show_message(message);
+            w_explorer::refresh_git_status();
+            true
+        }
+        Err(error_message) => {
+            statusbar::message::show_message(error_message);
+            false
+        }
+    }
 }
 
 pub fn on_open_dir_clicked() {
@@ -47,7 +92,33 @@ pub fn on_open_dir_clicked() {
     chooser.hide();
 }
 
+pub fn on_open_file_clicked() {
+    let tx = Comms::sender();
+
+    let chooser = gtk::FileChooserNative::builder()
+        .action(gtk::FileChooserAction::Open)
+        .title("Open File")
+        .show_hidden(false)
+        .build();
+
+    if let gtk::ResponseType::Accept = chooser.run() {
+        let chosen_file = chooser.file().unwrap();
+        let file_path_buf = chosen_file.path().unwrap();
+
+        // Open it like a file clicked in the tree view
+        let tree_model = RootTreeModel::default();
+        tree_model.set_property("abs-path", file_path_buf.to_str().unwrap());
+        tx.send(CommEvents::RootTreeItemClicked(Some(tree_model))).ok();
+    };
+
+    chooser.hide();
+}
+
 pub fn on_save_changes_clicked() {
     let tx = Comms::sender();
     tx.send(CommEvents::SaveEditorChanges()).ok();
 }
+
+pub fn on_preferences_clicked() {
+    features::preferences::show_dialog();
+}
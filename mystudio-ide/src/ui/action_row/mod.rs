@@ -1,6 +1,6 @@
 use gtk::{prelude::BuilderExtManual, traits::WidgetExt, Button};
 
-use self::handler::{on_open_dir_clicked, on_save_changes_clicked};
+use crate::commands;
 
 pub mod handler;
 
@@ -10,7 +10,7 @@ pub fn setup_actions(builder: &gtk::Builder) {
         .expect("Unable to find button_open_workspace");
 
     open_dir_btn.connect_button_release_event(move |_btn, _y| {
-        on_open_dir_clicked();
+        commands::run("open_workspace");
         gtk::Inhibit(true)
     });
 
@@ -19,11 +19,20 @@ pub fn setup_actions(builder: &gtk::Builder) {
         .expect("button_save_changes");
 
     save_changes_btn.connect_button_release_event(move |_btn, _y| {
-        on_save_changes_clicked();
+        commands::run("save_file");
         // Note: Fixes an issue where button has focus on hover after first use
         gtk::Inhibit(false)
     });
 
+    let preferences_btn: Button = builder
+        .object("button_preferences")
+        .expect("Unable to find button_preferences");
+
+    preferences_btn.connect_button_release_event(move |_btn, _y| {
+        commands::run("preferences");
+        gtk::Inhibit(false)
+    });
+
     // FIXME: remove these and work it out in Glade
     open_dir_btn.set_sensitive(true);
     save_changes_btn.set_sensitive(true);
@@ -0,0 +1,176 @@
+use std::cell::RefCell;
+
+use gtk::{
+    gdk::Screen,
+    prelude::{CssProviderExt, DialogExtManual},
+    traits::{GtkWindowExt, MessageDialogExt, WidgetExt},
+    ButtonsType, CssProvider, DialogFlags, MessageDialog, MessageType, StyleContext,
+};
+use libmystudio::{
+    app_config::{
+        error::{AppConfigError, AppConfigErrorKind},
+        layered::LayeredAppConfig,
+        watcher::AppConfigWatcher,
+        AppConfig,
+    },
+    notebook::cache::NotebookTabCache,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{
+        self,
+        notebook::editor::{Editor, EDITOR_STYLE_CLASS},
+        statusbar::message::show_message,
+    },
+};
+
+// Separate provider for editor font so it can be replaced at runtime
+thread_local! { static G_EDITOR_CSS_PROVIDER: RefCell<Option<CssProvider>> = RefCell::new(None) }
+
+pub fn init() {
+    let css_provider = CssProvider::new();
+    let screen = Screen::default().expect("Unable to find screen for css_provider");
+    StyleContext::add_provider_for_screen(
+        &screen,
+        &css_provider,
+        gtk::STYLE_PROVIDER_PRIORITY_USER,
+    );
+    G_EDITOR_CSS_PROVIDER.with(|p| *p.borrow_mut() = Some(css_provider));
+
+    apply(&AppConfig::current());
+
+    // Reload config file on change
+    let tx = Comms::sender();
+    AppConfigWatcher::watch(move |layered_config| {
+        tx.send(CommEvents::AppConfigChanged(layered_config)).ok();
+    });
+}
+
+/**
+ * Applies a config reloaded from disk and reports its problems in the status bar.
+ */
+pub fn reload(layered_config: LayeredAppConfig) {
+    let errors = layered_config.errors().to_vec();
+    for error in errors.iter() {
+        eprintln!("{error}");
+    }
+
+    // Keep the current config until the file can be parsed again
+    if let Some(error) = errors
+        .iter()
+        .find(|e| matches!(e.kind, AppConfigErrorKind::Syntax | AppConfigErrorKind::Io))
+    {
+        show_message(format!("{error} (changes not applied)"));
+        return;
+    }
+
+    let previous = AppConfig::current();
+    LayeredAppConfig::replace(layered_config);
+    let config = AppConfig::current();
+    apply(&config);
+
+    // Running servers were started with the previous commands
+    if config.LanguageServers != previous.LanguageServers {
+        ui::notebook::lsp::servers_changed();
+    }
+
+    if errors.is_empty() {
+        show_message("Reloaded config".into());
+    } else {
+        show_errors_message(&errors);
+    }
+}
+
+/**
+ * Loads and applies the config of newly opened workspace, its settings file
+ * replaces the one of the previous workspace.
+ */
+pub fn workspace_changed() {
+    let errors = LayeredAppConfig::recompute();
+    apply(&AppConfig::current());
+
+    if !errors.is_empty() {
+        show_errors_message(&errors);
+    }
+}
+
+fn show_errors_message(errors: &[AppConfigError]) {
+    match errors.first() {
+        Some(error) if errors.len() > 1 => {
+            show_message(format!("{error} (and {} more)", errors.len() - 1))
+        }
+        Some(error) => show_message(error.to_string()),
+        None => {}
+    }
+}
+
+/**
+ * Shows problems found in config file in a dialog.
+ */
+pub fn show_problems_dialog(errors: &[AppConfigError]) {
+    if errors.is_empty() {
+        return;
+    }
+
+    let window = ui::main_window();
+    let message_type = if errors.iter().all(AppConfigError::is_warning) {
+        MessageType::Warning
+    } else {
+        MessageType::Error
+    };
+
+    let dialog = MessageDialog::new(
+        Some(&window),
+        DialogFlags::MODAL | DialogFlags::DESTROY_WITH_PARENT,
+        message_type,
+        ButtonsType::Ok,
+        "Problems found in config files",
+    );
+
+    let details = errors
+        .iter()
+        .map(AppConfigError::to_string)
+        .collect::<Vec<String>>()
+        .join("\n");
+    dialog.set_secondary_text(Some(&format!(
+        "Default values are used for the affected settings.\n\n{details}"
+    )));
+
+    dialog.run();
+    dialog.close();
+}
+
+/**
+ * Applies a given AppConfig to the running UI.
+ */
+pub fn apply(config: &AppConfig) {
+    // Window geometry
+    let window = ui::main_window();
+    window.set_width_request(config.General.application_width);
+    window.set_height_request(config.General.application_height);
+
+    // Editor font
+    let css = format!(
+        "textview.{} {{ font-family: \"{}\"; font-size: {}pt; }}",
+        EDITOR_STYLE_CLASS, config.Editor.font_family, config.Editor.font_size
+    );
+    G_EDITOR_CSS_PROVIDER.with(|p| {
+        if let Some(provider) = p.borrow().as_ref() {
+            provider.load_from_data(css.as_bytes()).ok();
+        }
+    });
+
+    // Keybindings
+    crate::keyboard::reload_keymap(config);
+
+    // Open editors
+    for tab in NotebookTabCache::all() {
+        if let Some(view) = Editor::from_path(tab.file_path) {
+            Editor::apply_config(&view, config);
+        }
+    }
+
+    // Open terminals
+    crate::ui::features::terminal::apply_config(config);
+}
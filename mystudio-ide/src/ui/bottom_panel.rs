@@ -0,0 +1,70 @@
+// Notebook below the editor holding tool panels, ex: Problems
+
+use std::cell::RefCell;
+
+use gtk::{
+    prelude::{BuilderExtManual, IsA, NotebookExtManual},
+    traits::WidgetExt,
+    Builder, Label, Notebook, Widget,
+};
+
+thread_local! { static G_BOTTOM_PANEL: RefCell<Option<Notebook>> = RefCell::new(None) }
+
+pub fn init(builder: &Builder) {
+    G_BOTTOM_PANEL.with(|panel| {
+        *panel.borrow_mut() = builder.object("bottom_panel_notebook");
+        assert!(panel.borrow().is_some());
+    });
+}
+
+fn get() -> Notebook {
+    G_BOTTOM_PANEL.with(|panel| {
+        panel
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| panic!("{}", "Unable to find bottom panel"))
+    })
+}
+
+/**
+ * Adds a page to the panel, the panel stays hidden until a page is shown.
+ */
+pub fn add_page(page: &impl IsA<Widget>, title: &str) {
+    get().append_page(page, Some(&Label::new(Some(title))));
+}
+
+/**
+ * Shows the panel with `page` selected.
+ */
+pub fn show_page(page: &impl IsA<Widget>) {
+    let panel = get();
+
+    // Panel and its pages are `no-show-all` until first shown
+    page.show_all();
+    panel.show();
+    panel.set_current_page(panel.page_num(page));
+}
+
+/**
+ * Shows `page`, or hides the panel when `page` is already showing.
+ */
+pub fn toggle_page(page: &impl IsA<Widget>) {
+    let panel = get();
+
+    if panel.is_visible() && panel.current_page() == panel.page_num(page) {
+        panel.hide();
+    } else {
+        show_page(page);
+    }
+}
+
+/**
+ * Hides the panel when `page` is the one showing.
+ */
+pub fn hide_page(page: &impl IsA<Widget>) {
+    let panel = get();
+
+    if panel.current_page() == panel.page_num(page) {
+        panel.hide();
+    }
+}
@@ -0,0 +1,189 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{
+    gdk::keys::constants as key_constants,
+    glib,
+    prelude::{DialogExt, EntryExt, SearchEntryExt},
+    traits::{
+        BoxExt, ContainerExt, GtkWindowExt, LabelExt, ListBoxExt, ListBoxRowExt, StyleContextExt,
+        WidgetExt,
+    },
+    Adjustment, Box, Dialog, Label, ListBox, ListBoxRow, ScrolledWindow, SearchEntry,
+    SelectionMode,
+};
+use libmystudio::fuzzy::fuzzy_match;
+
+use crate::{commands, keyboard::keys_for_command, ui};
+
+/**
+ * Shows a searchable list of commands along with their keybindings.
+ *
+ * Commands are filtered with a fuzzy match on their title, `Enter` runs the
+ * selected one.
+ */
+pub fn show_dialog() {
+    let window = ui::main_window();
+
+    let dialog = Dialog::builder()
+        .title("Command Palette")
+        .transient_for(&window)
+        .modal(true)
+        .destroy_with_parent(true)
+        .default_width(480)
+        .default_height(360)
+        .build();
+
+    let input = SearchEntry::new();
+    input.set_placeholder_text(Some("Type a command"));
+    input.set_margin(6);
+
+    let listbox = ListBox::new();
+    listbox.set_selection_mode(SelectionMode::Browse);
+    listbox.set_placeholder(Some(&Label::new(Some("No matching commands"))));
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.set_vexpand(true);
+    scrolled_window.add(&listbox);
+
+    let content_area = dialog.content_area();
+    content_area.pack_start(&input, false, true, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+
+    // Ids of commands in the order they are listed
+    let listed_commands: Rc<RefCell<Vec<&'static str>>> = Rc::new(RefCell::new(vec![]));
+    update_list(&listbox, "", &listed_commands);
+
+    let listbox_clone = listbox.clone();
+    let listed_commands_clone = listed_commands.clone();
+    input.connect_search_changed(move |input| {
+        update_list(&listbox_clone, &input.text(), &listed_commands_clone);
+    });
+
+    let dialog_clone = dialog.clone();
+    let listbox_clone = listbox.clone();
+    let listed_commands_clone = listed_commands.clone();
+    input.connect_activate(move |_| {
+        if let Some(row) = listbox_clone.selected_row() {
+            run_listed_command(&dialog_clone, row.index(), &listed_commands_clone);
+        }
+    });
+
+    let dialog_clone = dialog.clone();
+    listbox.connect_row_activated(move |_, row| {
+        run_listed_command(&dialog_clone, row.index(), &listed_commands);
+    });
+
+    // Keep focus in the input while moving through the list
+    let listbox_clone = listbox.clone();
+    dialog.connect_key_press_event(move |dialog, event| {
+        let keyval = event.keyval();
+
+        if keyval == key_constants::Escape {
+            dialog.close();
+            return gtk::Inhibit(true);
+        }
+
+        let step = if keyval == key_constants::Down {
+            1
+        } else if keyval == key_constants::Up {
+            -1
+        } else {
+            return gtk::Inhibit(false);
+        };
+
+        let selected_index = listbox_clone.selected_row().map_or(0, |row| row.index());
+        if let Some(row) = listbox_clone.row_at_index(selected_index + step) {
+            listbox_clone.select_row(Some(&row));
+        }
+
+        gtk::Inhibit(true)
+    });
+
+    dialog.show_all();
+    input.grab_focus();
+}
+
+fn run_listed_command(dialog: &Dialog, index: i32, listed_commands: &Rc<RefCell<Vec<&str>>>) {
+    let command_id = listed_commands.borrow().get(index as usize).copied();
+
+    // Close first, commands may open dialogs of their own
+    dialog.close();
+
+    if let Some(command_id) = command_id {
+        commands::run(command_id);
+    }
+}
+
+fn update_list(listbox: &ListBox, query: &str, listed_commands: &Rc<RefCell<Vec<&'static str>>>) {
+    for child in listbox.children() {
+        listbox.remove(&child);
+    }
+
+    let mut matches: Vec<_> = commands::all()
+        .iter()
+        .filter(|command| command.id != "command_palette")
+        .filter_map(|command| fuzzy_match(query, command.title).map(|m| (command, m)))
+        .collect();
+    // Stable sort keeps registry order for equal scores
+    matches.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+
+    let mut listed_commands = listed_commands.borrow_mut();
+    listed_commands.clear();
+
+    for (command, fuzzy_match) in matches {
+        let row = ListBoxRow::new();
+        let row_box = Box::new(gtk::Orientation::Horizontal, 12);
+        row_box.set_margin(6);
+
+        let title = Label::new(None);
+        title.set_markup(&highlight_positions(command.title, &fuzzy_match.positions));
+        title.set_xalign(0f32);
+        row_box.pack_start(&title, true, true, 0);
+
+        if let Some(keys) = keys_for_command(command.id) {
+            let keys_label = Label::new(Some(&keys));
+            keys_label.style_context().add_class("dim-label");
+            row_box.pack_end(&keys_label, false, false, 0);
+        }
+
+        row.add(&row_box);
+        row.show_all();
+        listbox.add(&row);
+        listed_commands.push(command.id);
+    }
+
+    if let Some(first_row) = listbox.row_at_index(0) {
+        listbox.select_row(Some(&first_row));
+    }
+}
+
+/**
+ * Returns Pango markup of `text` with chars at `positions` in bold.
+ */
+pub fn highlight_positions(text: &str, positions: &[usize]) -> String {
+    text.chars()
+        .enumerate()
+        .map(|(index, c)| {
+            let escaped = glib::markup_escape_text(&c.to_string()).to_string();
+            if positions.contains(&index) {
+                format!("<b>{escaped}</b>")
+            } else {
+                escaped
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::highlight_positions;
+
+    #[test]
+    fn highlight_positions_test() {
+        assert_eq!(
+            highlight_positions("Go to <Line>", &[0, 7]),
+            "<b>G</b>o to &lt;<b>L</b>ine&gt;"
+        );
+    }
+}
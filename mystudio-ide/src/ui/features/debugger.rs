@@ -0,0 +1,1354 @@
+// Debug panel with the call stack, variables, watch expressions and output of
+// the program being debugged, plus breakpoints in the editor gutter. Adapters
+// run on a `DapWorker`, their answers arrive as `CommEvents::DebugEvent`
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::Duration,
+};
+
+use gtk::{
+    gdk::{keys::constants as key_constants, RGBA},
+    glib::{self, Type},
+    pango::EllipsizeMode,
+    prelude::{Cast, DialogExt, EntryExt, SearchEntryExt, TreeModelExt, TreeStoreExtManual},
+    traits::{
+        BoxExt, ButtonExt, CellLayoutExt, CellRendererTextExt, ContainerExt, GtkListStoreExt,
+        GtkListStoreExtManual, GtkWindowExt, LabelExt, ListBoxExt, ListBoxRowExt, PanedExt,
+        StyleContextExt, TextBufferExt, TextViewExt, TreeSelectionExt, TreeStoreExt,
+        TreeViewColumnExt, TreeViewExt, WidgetExt,
+    },
+    Adjustment, Button, CellRendererText, Dialog, Entry, IconSize, Label, ListBox, ListBoxRow,
+    ListStore, Orientation, Paned, ReliefStyle, ScrolledWindow, SearchEntry, SelectionMode,
+    TextView, TreeIter, TreePath, TreeStore, TreeView, TreeViewColumn,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    debugger::{
+        discover_launch_configs,
+        error::DapError,
+        test_launch_config,
+        types::{DapEvent, StackFrame, Variable},
+        worker::{DapRequest, DapResponse, DapWorker},
+        LaunchConfig,
+    },
+    fuzzy::fuzzy_match,
+    notebook::cache::NotebookTabCache,
+    tasks::runner::{TaskEvent, TaskStatus},
+    test_explorer::TestCase,
+    workspace::Workspace,
+};
+use sourceview4::{
+    traits::{BufferExt, MarkAttributesExt, ViewExt},
+    Buffer, MarkAttributes, View,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{
+        self, bottom_panel,
+        notebook::editor::{open_editor_at, Editor},
+        statusbar::message::show_message,
+    },
+};
+
+use super::{command_palette::highlight_positions, tasks};
+
+// Breakpoint lines are read back from gutter marks once typing pauses
+const MARKS_SYNC_DELAY: Duration = Duration::from_millis(500);
+
+// Mark categories, above diagnostics, see `diagnostics::priority`
+const BREAKPOINT_CATEGORY: &str = "breakpoint";
+const CURRENT_LINE_CATEGORY: &str = "debug-current-line";
+// Gutter actions of tests, clicking their lines doesn't toggle breakpoints
+const TEST_RUN_CATEGORY: &str = "test-run";
+
+// Columns of the variables and watch stores
+const COLUMN_NAME: u32 = 0;
+const COLUMN_VALUE: u32 = 1;
+const COLUMN_TYPE: u32 = 2;
+// `variablesReference` of the row, non-zero when it has children
+const COLUMN_REFERENCE: u32 = 3;
+// Children were fetched from the adapter
+const COLUMN_LOADED: u32 = 4;
+
+// Columns of the call stack store
+const COLUMN_FRAME_NAME: u32 = 0;
+const COLUMN_FRAME_LOCATION: u32 = 1;
+
+type ResponseCallback = Box<dyn FnOnce(Result<DapResponse, DapError>)>;
+
+struct Session {
+    worker: DapWorker,
+    // Thread which stopped, `None` while the program runs
+    thread_id: Option<i64>,
+    // Incremented each time the program runs, answers about an older pause
+    // are dropped
+    runs: u64,
+    frames: Vec<StackFrame>,
+    // Frame shown in the variables and watch views
+    frame_id: Option<i64>,
+}
+
+// Launch waiting for its program to be built
+struct PendingLaunch {
+    task_id: u64,
+    name: String,
+    config: LaunchConfig,
+    // Reported by the build task
+    program: Option<String>,
+}
+
+#[derive(Clone)]
+struct DebugPage {
+    page: gtk::Box,
+    label: Label,
+    stack_view: TreeView,
+    stack_store: ListStore,
+    variables_view: TreeView,
+    variables_store: TreeStore,
+    watch_view: TreeView,
+    watch_store: TreeStore,
+    console_view: TextView,
+}
+
+thread_local! { static G_DEBUG_PAGE: RefCell<Option<DebugPage>> = RefCell::new(None) }
+thread_local! { static G_SESSION: RefCell<Option<Session>> = RefCell::new(None) }
+// Events of older sessions are dropped
+thread_local! { static G_SESSION_ID: Cell<u64> = Cell::new(0) }
+thread_local! { static G_PENDING_LAUNCH: RefCell<Option<PendingLaunch>> = RefCell::new(None) }
+// Requests of the current session waiting for their answer, by request id
+thread_local! { static G_PENDING_REQUESTS: RefCell<HashMap<u64, ResponseCallback>> = RefCell::new(HashMap::new()) }
+thread_local! { static G_NEXT_REQUEST_ID: Cell<u64> = Cell::new(1) }
+// Incremented on each refresh of watch values, older answers are dropped
+thread_local! { static G_WATCHES_REFRESH: Cell<u64> = Cell::new(0) }
+// One-based lines of breakpoints, by file
+thread_local! { static G_BREAKPOINTS: RefCell<BTreeMap<String, BTreeSet<u32>>> = RefCell::new(BTreeMap::new()) }
+// Line the program is paused at, one-based
+thread_local! { static G_CURRENT_LINE: RefCell<Option<(String, u32)>> = RefCell::new(None) }
+thread_local! { static G_WATCHES: RefCell<Vec<String>> = RefCell::new(vec![]) }
+
+/**
+ * Shows the Debug panel, or hides it when it is already showing.
+ */
+pub fn toggle() {
+    let page = debug_page();
+    bottom_panel::toggle_page(&page.page);
+}
+
+/**
+ * Continues the paused program, or asks for a launch configuration when
+ * nothing is being debugged.
+ */
+pub fn start_debugging() {
+    let paused = G_SESSION.with(|session| {
+        session
+            .borrow()
+            .as_ref()
+            .map(|session| session.thread_id.is_some())
+    });
+
+    match paused {
+        Some(true) => continue_debugging(),
+        Some(false) => show_message(String::from("The program is already running")),
+        None => show_dialog(),
+    }
+}
+
+pub fn continue_debugging() {
+    step(DapRequest::Continue);
+}
+
+pub fn step_over() {
+    step(DapRequest::Next);
+}
+
+pub fn step_into() {
+    step(DapRequest::StepIn);
+}
+
+pub fn step_out() {
+    step(DapRequest::StepOut);
+}
+
+/// Pauses the running program.
+pub fn pause_debugging() {
+    let Some(thread_id) =
+        G_SESSION.with(|session| session.borrow().as_ref().map(|session| session.thread_id))
+    else {
+        show_message(String::from("Nothing is being debugged"));
+        return;
+    };
+    if thread_id.is_some() {
+        return;
+    }
+
+    request(DapRequest::Pause, |result| {
+        if let Err(error) = result {
+            show_message(format!("Unable to pause: {}", error_message(&error)));
+        }
+    });
+}
+
+/**
+ * Ends the debugging session, the program is terminated. A build waiting to
+ * be debugged is stopped.
+ */
+pub fn stop_debugging() {
+    if G_PENDING_LAUNCH
+        .with(|pending| pending.borrow_mut().take())
+        .is_some()
+    {
+        tasks::stop_task();
+    }
+    end_session("Debugging stopped");
+}
+
+/**
+ * Toggles a breakpoint on the line of the cursor in the active editor.
+ */
+pub fn toggle_breakpoint() {
+    let (Some(file_path), Some(view)) = (Workspace::get_open_file_path(), Editor::active()) else {
+        return;
+    };
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+
+    let line = buffer.iter_at_offset(buffer.cursor_position()).line();
+    toggle_breakpoint_at(&file_path, line as u32 + 1);
+}
+
+/**
+ * Debugs a single test, its test binary is built first.
+ */
+pub fn debug_test(test: &TestCase) {
+    start_launch(&format!("test: {}", test.path), test_launch_config(test));
+}
+
+/**
+ * Stops debugging and forgets breakpoints of the previous workspace.
+ */
+pub fn workspace_changed() {
+    G_PENDING_LAUNCH.with(|pending| pending.borrow_mut().take());
+    end_session("");
+    G_BREAKPOINTS.with(|breakpoints| breakpoints.borrow_mut().clear());
+}
+
+/**
+ * Shows what the adapter of the current session reports.
+ */
+pub fn handle_event(id: u64, event: DapEvent) {
+    if G_SESSION_ID.with(|session_id| session_id.get()) != id {
+        return;
+    }
+
+    match event {
+        DapEvent::Stopped {
+            reason,
+            thread_id,
+            description,
+            ..
+        } => stopped(thread_id, description.unwrap_or(reason)),
+        DapEvent::Continued { .. } => set_running(),
+        DapEvent::Output { category, output } => {
+            if category != "telemetry" {
+                append_console(&output);
+            }
+        }
+        DapEvent::Exited(code) => append_console(&format!("Program exited with code {code}\n")),
+        DapEvent::Terminated | DapEvent::AdapterExited => end_session("Debugging ended"),
+        DapEvent::StartFailed(error) => {
+            end_session("Not debugging");
+            show_message(format!("Unable to start debug adapter: {error}"));
+        }
+        DapEvent::Response { id, result } => {
+            let on_response = G_PENDING_REQUESTS.with(|requests| requests.borrow_mut().remove(&id));
+            if let Some(on_response) = on_response {
+                on_response(result);
+            }
+        }
+    }
+}
+
+/**
+ * Launches the program of a pending launch once its build task succeeded.
+ */
+pub fn handle_task_event(event: &TaskEvent) {
+    let (TaskEvent::Output(id, _)
+    | TaskEvent::Diagnostic(id, _)
+    | TaskEvent::Artifact(id, _)
+    | TaskEvent::Finished(id, _)) = event;
+    let is_build = G_PENDING_LAUNCH.with(|pending| {
+        pending
+            .borrow()
+            .as_ref()
+            .is_some_and(|pending| pending.task_id == *id)
+    });
+    if !is_build {
+        return;
+    }
+
+    match event {
+        TaskEvent::Artifact(_, path) => G_PENDING_LAUNCH.with(|pending| {
+            if let Some(pending) = pending.borrow_mut().as_mut() {
+                pending.program = Some(path.clone());
+            }
+        }),
+        TaskEvent::Finished(_, status) => {
+            let Some(pending) = G_PENDING_LAUNCH.with(|pending| pending.borrow_mut().take()) else {
+                return;
+            };
+
+            match (status, pending.program) {
+                (TaskStatus::Succeeded, Some(program)) => {
+                    launch(&pending.name, &pending.config, Some(program))
+                }
+                (TaskStatus::Succeeded, None) => {
+                    show_message(format!("No executable was built for '{}'", pending.name))
+                }
+                (status, _) => {
+                    debug_page().label.set_text("Not debugging");
+                    show_message(format!("Build of '{}' {status}", pending.name));
+                }
+            }
+        }
+        TaskEvent::Output(..) | TaskEvent::Diagnostic(..) => {}
+    }
+}
+
+/**
+ * Shows breakpoints of a new editor in its gutter, clicking the gutter
+ * toggles them.
+ */
+pub fn attach(view: &View, file_path: &str) {
+    let Some(buffer) = view
+        .buffer()
+        .and_then(|buffer| buffer.downcast::<Buffer>().ok())
+    else {
+        return;
+    };
+
+    let attributes = MarkAttributes::new();
+    attributes.set_icon_name("media-record-symbolic");
+    attributes.connect_query_tooltip_text(|_, _| String::from("Breakpoint"));
+    view.set_mark_attributes(BREAKPOINT_CATEGORY, &attributes, 4);
+
+    let attributes = MarkAttributes::new();
+    attributes.set_icon_name("go-next-symbolic");
+    attributes.set_background(&RGBA::new(0.96, 0.83, 0.25, 0.35));
+    view.set_mark_attributes(CURRENT_LINE_CATEGORY, &attributes, 5);
+
+    let file_path_clone = file_path.to_string();
+    view.connect_line_mark_activated(move |view, iter, _event| {
+        let has_test_mark = view
+            .buffer()
+            .and_then(|buffer| buffer.downcast::<Buffer>().ok())
+            .is_some_and(|buffer| {
+                !buffer
+                    .source_marks_at_line(iter.line(), Some(TEST_RUN_CATEGORY))
+                    .is_empty()
+            });
+        if !has_test_mark {
+            toggle_breakpoint_at(&file_path_clone, iter.line() as u32 + 1);
+        }
+    });
+
+    // Marks follow edits, breakpoints move with them
+    let pending = Rc::new(Cell::new(false));
+    let file_path_clone = file_path.to_string();
+    buffer.connect_changed(move |buffer| {
+        if pending.replace(true) {
+            return;
+        }
+
+        let pending = pending.clone();
+        let buffer = buffer.clone();
+        let file_path = file_path_clone.clone();
+        glib::timeout_add_local_once(MARKS_SYNC_DELAY, move || {
+            pending.set(false);
+            sync_breakpoint_lines(&buffer, &file_path);
+        });
+    });
+
+    show_breakpoints(&buffer, file_path);
+    show_current_line(file_path);
+}
+
+fn toggle_breakpoint_at(file_path: &str, line: u32) {
+    let lines = G_BREAKPOINTS.with(|breakpoints| {
+        let mut breakpoints = breakpoints.borrow_mut();
+        let lines = breakpoints.entry(file_path.to_string()).or_default();
+        if !lines.remove(&line) {
+            lines.insert(line);
+        }
+        lines.clone()
+    });
+
+    if let Some(buffer) = source_buffer(file_path) {
+        show_breakpoints(&buffer, file_path);
+    }
+    send_breakpoints(file_path, &lines);
+}
+
+fn show_breakpoints(buffer: &Buffer, file_path: &str) {
+    let (start, end) = (buffer.start_iter(), buffer.end_iter());
+    buffer.remove_source_marks(&start, &end, Some(BREAKPOINT_CATEGORY));
+
+    for line in breakpoint_lines(file_path) {
+        buffer.create_source_mark(
+            None,
+            BREAKPOINT_CATEGORY,
+            &buffer.iter_at_line(line as i32 - 1),
+        );
+    }
+}
+
+fn sync_breakpoint_lines(buffer: &Buffer, file_path: &str) {
+    let previous = breakpoint_lines(file_path);
+    if previous.is_empty() {
+        return;
+    }
+
+    // Lines of a deleted block end up on the same line
+    let lines: BTreeSet<u32> = (0..buffer.line_count())
+        .filter(|line| {
+            !buffer
+                .source_marks_at_line(*line, Some(BREAKPOINT_CATEGORY))
+                .is_empty()
+        })
+        .map(|line| line as u32 + 1)
+        .collect();
+    if lines == previous {
+        return;
+    }
+
+    G_BREAKPOINTS.with(|breakpoints| {
+        breakpoints
+            .borrow_mut()
+            .insert(file_path.to_string(), lines.clone())
+    });
+    send_breakpoints(file_path, &lines);
+}
+
+fn breakpoint_lines(file_path: &str) -> BTreeSet<u32> {
+    G_BREAKPOINTS.with(|breakpoints| {
+        breakpoints
+            .borrow()
+            .get(file_path)
+            .cloned()
+            .unwrap_or_default()
+    })
+}
+
+// Updates breakpoints of a file in the running session
+fn send_breakpoints(file_path: &str, lines: &BTreeSet<u32>) {
+    let breakpoints = DapRequest::SetBreakpoints {
+        path: file_path.to_string(),
+        lines: lines.iter().copied().collect(),
+    };
+
+    request(breakpoints, |result| {
+        if let Err(error) = result {
+            show_message(format!(
+                "Unable to set breakpoints: {}",
+                error_message(&error)
+            ));
+        }
+    });
+}
+
+/**
+ * Shows a searchable list of launch configurations, `Enter` debugs the
+ * selected one.
+ */
+fn show_dialog() {
+    let root = Workspace::get_path();
+    let configs = discover_launch_configs(&root, &AppConfig::current().Launch);
+    if configs.is_empty() {
+        show_message(String::from(
+            "No launch configurations found, add them to `[Launch]` of workspace settings",
+        ));
+        return;
+    }
+
+    let window = ui::main_window();
+
+    let dialog = Dialog::builder()
+        .title("Start Debugging")
+        .transient_for(&window)
+        .modal(true)
+        .destroy_with_parent(true)
+        .default_width(640)
+        .default_height(420)
+        .build();
+
+    let input = SearchEntry::new();
+    input.set_placeholder_text(Some("Type a launch configuration, ex: cargo: app"));
+    input.set_margin(6);
+
+    let listbox = ListBox::new();
+    listbox.set_selection_mode(SelectionMode::Browse);
+    listbox.set_placeholder(Some(&Label::new(Some("No matching configurations"))));
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.set_vexpand(true);
+    scrolled_window.add(&listbox);
+
+    let content_area = dialog.content_area();
+    content_area.pack_start(&input, false, true, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+
+    let configs = Rc::new(configs);
+    // Configurations in the order they are listed
+    let listed_configs: Rc<RefCell<Vec<(String, LaunchConfig)>>> = Rc::new(RefCell::new(vec![]));
+    update_list(&listbox, "", &configs, &listed_configs);
+
+    let listbox_clone = listbox.clone();
+    let listed_configs_clone = listed_configs.clone();
+    input.connect_search_changed(move |input| {
+        update_list(
+            &listbox_clone,
+            &input.text(),
+            &configs,
+            &listed_configs_clone,
+        );
+    });
+
+    let dialog_clone = dialog.clone();
+    let listbox_clone = listbox.clone();
+    let listed_configs_clone = listed_configs.clone();
+    input.connect_activate(move |_| {
+        if let Some(row) = listbox_clone.selected_row() {
+            launch_listed_config(&dialog_clone, row.index(), &listed_configs_clone);
+        }
+    });
+
+    let dialog_clone = dialog.clone();
+    listbox.connect_row_activated(move |_, row| {
+        launch_listed_config(&dialog_clone, row.index(), &listed_configs);
+    });
+
+    // Keep focus in the input while moving through the list
+    let listbox_clone = listbox.clone();
+    dialog.connect_key_press_event(move |dialog, event| {
+        let keyval = event.keyval();
+
+        if keyval == key_constants::Escape {
+            dialog.close();
+            return gtk::Inhibit(true);
+        }
+
+        let step = if keyval == key_constants::Down {
+            1
+        } else if keyval == key_constants::Up {
+            -1
+        } else {
+            return gtk::Inhibit(false);
+        };
+
+        let selected_index = listbox_clone.selected_row().map_or(0, |row| row.index());
+        if let Some(row) = listbox_clone.row_at_index(selected_index + step) {
+            listbox_clone.select_row(Some(&row));
+        }
+
+        gtk::Inhibit(true)
+    });
+
+    dialog.show_all();
+    input.grab_focus();
+}
+
+fn launch_listed_config(
+    dialog: &Dialog,
+    index: i32,
+    listed_configs: &Rc<RefCell<Vec<(String, LaunchConfig)>>>,
+) {
+    let config = listed_configs.borrow().get(index as usize).cloned();
+
+    dialog.close();
+
+    if let Some((name, config)) = config {
+        start_launch(&name, config);
+    }
+}
+
+fn update_list(
+    listbox: &ListBox,
+    query: &str,
+    configs: &[(String, LaunchConfig)],
+    listed_configs: &Rc<RefCell<Vec<(String, LaunchConfig)>>>,
+) {
+    for child in listbox.children() {
+        listbox.remove(&child);
+    }
+
+    let mut matches: Vec<_> = configs
+        .iter()
+        .filter_map(|config| fuzzy_match(query, &config.0).map(|m| (config, m)))
+        .collect();
+    matches.sort_by_key(|(_, fuzzy_match)| -fuzzy_match.score);
+
+    let mut listed_configs = listed_configs.borrow_mut();
+    listed_configs.clear();
+
+    for ((name, config), fuzzy_match) in matches {
+        let row = ListBoxRow::new();
+        let row_box = gtk::Box::new(Orientation::Horizontal, 12);
+        row_box.set_margin(6);
+
+        let name_label = Label::new(None);
+        name_label.set_markup(&highlight_positions(name, &fuzzy_match.positions));
+        name_label.set_xalign(0f32);
+        row_box.pack_start(&name_label, false, false, 0);
+
+        let detail = match config.cargo.is_empty() {
+            true => config.program.clone(),
+            false => format!("cargo {}", config.cargo.join(" ")),
+        };
+        let detail_label = Label::new(Some(&format!("{detail} ({})", config.adapter)));
+        detail_label.style_context().add_class("dim-label");
+        detail_label.set_xalign(1f32);
+        detail_label.set_ellipsize(EllipsizeMode::End);
+        row_box.pack_end(&detail_label, true, true, 0);
+
+        row.add(&row_box);
+        row.show_all();
+        listbox.add(&row);
+        listed_configs.push((name.clone(), config.clone()));
+    }
+
+    if let Some(first_row) = listbox.row_at_index(0) {
+        listbox.select_row(Some(&first_row));
+    }
+}
+
+// Builds the program of `config` if needed, then debugs it
+fn start_launch(name: &str, config: LaunchConfig) {
+    let root = Workspace::get_path();
+    if root.is_empty() {
+        show_message(String::from("Open a workspace to debug its programs"));
+        return;
+    }
+    end_session("");
+
+    let Some(task) = config.build_task(name, &root) else {
+        launch(name, &config, None);
+        return;
+    };
+    let Some(task_id) = tasks::run_task(task) else {
+        return;
+    };
+
+    G_PENDING_LAUNCH.with(|pending| {
+        *pending.borrow_mut() = Some(PendingLaunch {
+            task_id,
+            name: name.to_string(),
+            config,
+            program: None,
+        })
+    });
+    debug_page()
+        .label
+        .set_text(&format!("Building '{name}' to debug it..."));
+}
+
+// Starts the adapter of `config` and launches its program with breakpoints set
+fn launch(name: &str, config: &LaunchConfig, program: Option<String>) {
+    let root = Workspace::get_path();
+    let app_config = AppConfig::current();
+    let Some(adapter) = app_config.DebugAdapters.get(&config.adapter) else {
+        show_message(format!(
+            "Unknown debug adapter '{}', add it to `[DebugAdapters]` of config.toml",
+            config.adapter
+        ));
+        return;
+    };
+
+    let id = G_SESSION_ID.with(|session_id| {
+        session_id.set(session_id.get() + 1);
+        session_id.get()
+    });
+    let tx = Comms::sender();
+    let worker = DapWorker::start(&config.adapter, adapter, &root, move |event| {
+        tx.send(CommEvents::DebugEvent(id, event)).ok();
+    });
+
+    let page = debug_page();
+    clear_views(&page);
+    if let Some(buffer) = page.console_view.buffer() {
+        buffer.set_text("");
+    }
+    append_console(&format!("> Debugging '{name}' with {}\n", config.adapter));
+    page.label.set_text(&format!("Running '{name}'"));
+    bottom_panel::show_page(&page.page);
+
+    G_SESSION.with(|session| {
+        *session.borrow_mut() = Some(Session {
+            worker,
+            thread_id: None,
+            runs: 0,
+            frames: vec![],
+            frame_id: None,
+        })
+    });
+
+    let breakpoints: BTreeMap<String, Vec<u32>> = G_BREAKPOINTS.with(|breakpoints| {
+        breakpoints
+            .borrow()
+            .iter()
+            .filter(|(_, lines)| !lines.is_empty())
+            .map(|(path, lines)| (path.clone(), lines.iter().copied().collect()))
+            .collect()
+    });
+    let arguments = config.launch_arguments(name, &root, program.as_deref());
+
+    let name = name.to_string();
+    request(
+        DapRequest::Launch {
+            arguments,
+            breakpoints,
+        },
+        move |result| match result {
+            Ok(DapResponse::Launched(states)) => {
+                let unverified = states
+                    .values()
+                    .flatten()
+                    .filter(|state| !state.verified)
+                    .count();
+                if unverified > 0 {
+                    append_console(&format!(
+                        "{unverified} breakpoint(s) could not be set, ex: on lines without code\n"
+                    ));
+                }
+            }
+            Ok(_) => {}
+            Err(error) => {
+                end_session("Not debugging");
+                show_message(format!(
+                    "Unable to debug '{name}': {}",
+                    error_message(&error)
+                ));
+            }
+        },
+    );
+}
+
+// Disconnects the current session in the background, `message` is shown in
+// the panel
+fn end_session(message: &str) {
+    G_SESSION.with(|session| session.borrow_mut().take());
+    G_PENDING_REQUESTS.with(|requests| requests.borrow_mut().clear());
+    clear_current_line();
+
+    if let Some(page) = G_DEBUG_PAGE.with(|page| page.borrow().clone()) {
+        clear_views(&page);
+        page.label.set_text(message);
+    }
+}
+
+/**
+ * Sends a request to the adapter of the current session, `on_response` is
+ * called once it answers. Nothing is sent without a session.
+ */
+fn request<F>(request: DapRequest, on_response: F)
+where
+    F: FnOnce(Result<DapResponse, DapError>) + 'static,
+{
+    G_SESSION.with(|session| {
+        let session = session.borrow();
+        let Some(session) = session.as_ref() else {
+            return;
+        };
+
+        let id = G_NEXT_REQUEST_ID.with(|next_id| next_id.replace(next_id.get() + 1));
+        G_PENDING_REQUESTS.with(|requests| requests.borrow_mut().insert(id, Box::new(on_response)));
+        session.worker.request(id, request);
+    });
+}
+
+// Number of times the program of the session ran, see `Session::runs`
+fn session_runs() -> Option<u64> {
+    G_SESSION.with(|session| session.borrow().as_ref().map(|session| session.runs))
+}
+
+// Runs a stepping request on the paused thread, the program runs afterwards
+fn step(step_request: fn(i64) -> DapRequest) {
+    let thread_id = G_SESSION.with(|session| session.borrow().as_ref()?.thread_id);
+    let Some(thread_id) = thread_id else {
+        show_message(String::from("The program is not paused"));
+        return;
+    };
+
+    // Running right away, the adapter may report the next stop before
+    // answering
+    set_running();
+    request(step_request(thread_id), move |result| {
+        if let Err(error) = result {
+            show_message(format!("Unable to step: {}", error_message(&error)));
+            stopped(Some(thread_id), String::from("step failed"));
+        }
+    });
+}
+
+fn stopped(thread_id: Option<i64>, reason: String) {
+    let runs = session_runs();
+
+    request(DapRequest::StackTrace(thread_id), move |result| {
+        if session_runs() != runs {
+            return;
+        }
+
+        match result {
+            Ok(DapResponse::StackTrace { thread_id, frames }) => {
+                show_stack(thread_id, frames, &reason)
+            }
+            Ok(_) => {}
+            Err(error) => {
+                append_console(&format!(
+                    "Unable to get the call stack: {}\n",
+                    error_message(&error)
+                ));
+                if let Some(thread_id) = thread_id {
+                    show_stack(thread_id, vec![], &reason);
+                }
+            }
+        }
+    });
+}
+
+fn show_stack(thread_id: i64, frames: Vec<StackFrame>, reason: &str) {
+    G_SESSION.with(|session| {
+        if let Some(session) = session.borrow_mut().as_mut() {
+            session.thread_id = Some(thread_id);
+            session.frames = frames.clone();
+        }
+    });
+
+    let page = debug_page();
+    page.stack_store.clear();
+    for frame in &frames {
+        let location = match &frame.path {
+            Some(path) => format!("{}:{}", path.rsplit('/').next().unwrap_or(path), frame.line),
+            None => String::from("no source"),
+        };
+        page.stack_store.insert_with_values(
+            None,
+            &[
+                (COLUMN_FRAME_NAME, &frame.name),
+                (COLUMN_FRAME_LOCATION, &location),
+            ],
+        );
+    }
+
+    page.label.set_text(&format!("Paused: {reason}"));
+    bottom_panel::show_page(&page.page);
+
+    // Frames without sources are skipped, ex: a crash inside libc
+    let index = frames
+        .iter()
+        .position(|frame| frame.path.is_some())
+        .unwrap_or_default();
+    if let Some(iter) = page
+        .stack_store
+        .iter(&TreePath::from_indicesv(&[index as i32]))
+    {
+        page.stack_view.selection().select_iter(&iter);
+    }
+    select_frame(index);
+}
+
+fn set_running() {
+    G_SESSION.with(|session| {
+        if let Some(session) = session.borrow_mut().as_mut() {
+            session.thread_id = None;
+            session.runs += 1;
+            session.frames.clear();
+            session.frame_id = None;
+        }
+    });
+    clear_current_line();
+
+    let page = debug_page();
+    clear_views(&page);
+    page.label.set_text("Running");
+}
+
+// Shows the source and variables of a frame of the call stack
+fn select_frame(index: usize) {
+    let frame = G_SESSION.with(|session| {
+        let mut session = session.borrow_mut();
+        let session = session.as_mut()?;
+        let frame = session.frames.get(index)?.clone();
+        session.frame_id = Some(frame.id);
+        Some(frame)
+    });
+    let Some(frame) = frame else {
+        return;
+    };
+
+    clear_current_line();
+    if let Some(path) = frame.path {
+        G_CURRENT_LINE
+            .with(|current_line| *current_line.borrow_mut() = Some((path.clone(), frame.line)));
+        show_current_line(&path);
+        open_editor_at(path, frame.line as i32, frame.column.max(1) as i32);
+    }
+
+    load_scopes(frame.id);
+    refresh_watches();
+}
+
+fn show_current_line(file_path: &str) {
+    let Some(line) = G_CURRENT_LINE.with(|current_line| match current_line.borrow().as_ref() {
+        Some((path, line)) if path == file_path => Some(*line),
+        _ => None,
+    }) else {
+        return;
+    };
+
+    if let Some(buffer) = source_buffer(file_path) {
+        buffer.create_source_mark(
+            None,
+            CURRENT_LINE_CATEGORY,
+            &buffer.iter_at_line(line as i32 - 1),
+        );
+    }
+}
+
+fn clear_current_line() {
+    let Some((path, _)) = G_CURRENT_LINE.with(|current_line| current_line.borrow_mut().take())
+    else {
+        return;
+    };
+
+    if let Some(buffer) = source_buffer(&path) {
+        let (start, end) = (buffer.start_iter(), buffer.end_iter());
+        buffer.remove_source_marks(&start, &end, Some(CURRENT_LINE_CATEGORY));
+    }
+}
+
+// Buffer of an open file
+fn source_buffer(file_path: &str) -> Option<Buffer> {
+    NotebookTabCache::find_by_path(file_path.to_string())?;
+    Editor::buffer_from_path(file_path.to_string())
+        .and_then(|buffer| buffer.downcast::<Buffer>().ok())
+}
+
+fn load_scopes(frame_id: i64) {
+    let page = debug_page();
+    page.variables_store.clear();
+
+    request(DapRequest::Scopes(frame_id), move |result| {
+        // Another frame was selected meanwhile
+        let frame_shown = G_SESSION.with(|session| session.borrow().as_ref()?.frame_id);
+        if frame_shown != Some(frame_id) {
+            return;
+        }
+
+        let scopes = match result {
+            Ok(DapResponse::Scopes(scopes)) => scopes,
+            Ok(_) => return,
+            Err(error) => {
+                append_console(&format!(
+                    "Unable to get variables: {}\n",
+                    error_message(&error)
+                ));
+                return;
+            }
+        };
+
+        for scope in scopes {
+            let iter = insert_variable(
+                &page.variables_store,
+                None,
+                &Variable {
+                    name: scope.name,
+                    value: String::new(),
+                    type_name: None,
+                    variables_reference: scope.variables_reference,
+                },
+            );
+            // Expanding loads children, see `load_children`
+            if !scope.expensive {
+                if let Some(path) = page.variables_store.path(&iter) {
+                    page.variables_view.expand_row(&path, false);
+                }
+            }
+        }
+    });
+}
+
+// Shows watch expressions, their values are filled as the adapter answers
+fn refresh_watches() {
+    let page = debug_page();
+    page.watch_store.clear();
+
+    let refresh = G_WATCHES_REFRESH.with(|refresh| refresh.replace(refresh.get() + 1) + 1);
+    let frame_id = G_SESSION.with(|session| session.borrow().as_ref()?.frame_id);
+
+    let watches = G_WATCHES.with(|watches| watches.borrow().clone());
+    for (index, expression) in watches.into_iter().enumerate() {
+        insert_variable(
+            &page.watch_store,
+            None,
+            &Variable {
+                name: expression.clone(),
+                value: String::new(),
+                type_name: None,
+                variables_reference: 0,
+            },
+        );
+        let Some(frame_id) = frame_id else {
+            continue;
+        };
+
+        let store = page.watch_store.clone();
+        let evaluate = DapRequest::Evaluate {
+            expression: expression.clone(),
+            frame_id,
+        };
+        request(evaluate, move |result| {
+            if G_WATCHES_REFRESH.with(|current| current.get()) != refresh {
+                return;
+            }
+            let Some(iter) = store.iter_nth_child(None, index as i32) else {
+                return;
+            };
+
+            let variable = match result {
+                Ok(DapResponse::Evaluated(variable)) => variable,
+                Ok(_) => return,
+                Err(error) => Variable {
+                    name: expression,
+                    value: error_message(&error),
+                    type_name: None,
+                    variables_reference: 0,
+                },
+            };
+            set_variable(&store, &iter, &variable);
+        });
+    }
+}
+
+fn add_watch(expression: &str) {
+    let expression = expression.trim();
+    if expression.is_empty() {
+        return;
+    }
+
+    G_WATCHES.with(|watches| watches.borrow_mut().push(expression.to_string()));
+    refresh_watches();
+}
+
+// Removes the selected watch expression, its children are left alone
+fn remove_selected_watch(page: &DebugPage) {
+    let Some((model, iter)) = page.watch_view.selection().selected() else {
+        return;
+    };
+    let Some(path) = model.path(&iter) else {
+        return;
+    };
+    if path.depth() != 1 {
+        return;
+    }
+
+    let index = path.indices()[0] as usize;
+    G_WATCHES.with(|watches| {
+        let mut watches = watches.borrow_mut();
+        if index < watches.len() {
+            watches.remove(index);
+        }
+    });
+    refresh_watches();
+}
+
+fn insert_variable(store: &TreeStore, parent: Option<&TreeIter>, variable: &Variable) -> TreeIter {
+    let iter = store.insert(parent, None);
+    set_variable(store, &iter, variable);
+    iter
+}
+
+fn set_variable(store: &TreeStore, iter: &TreeIter, variable: &Variable) {
+    store.set(
+        iter,
+        &[
+            (COLUMN_NAME, &variable.name),
+            (COLUMN_VALUE, &variable.value),
+            (COLUMN_TYPE, &variable.type_name.clone().unwrap_or_default()),
+            (COLUMN_REFERENCE, &variable.variables_reference),
+            (COLUMN_LOADED, &false),
+        ],
+    );
+
+    // Placeholder giving the row an expander until children are loaded
+    if variable.variables_reference > 0 {
+        store.insert_with_values(Some(iter), None, &[(COLUMN_NAME, &"Loading...")]);
+    }
+}
+
+// Fetches children of an expanded row on first expansion
+fn load_children(store: &TreeStore, iter: &TreeIter) {
+    let loaded = store
+        .value(iter, COLUMN_LOADED as i32)
+        .get::<bool>()
+        .unwrap_or_default();
+    let reference = store
+        .value(iter, COLUMN_REFERENCE as i32)
+        .get::<i64>()
+        .unwrap_or_default();
+    if loaded || reference == 0 {
+        return;
+    }
+    let Some(path) = store.path(iter) else {
+        return;
+    };
+    store.set(iter, &[(COLUMN_LOADED, &true)]);
+
+    let store = store.clone();
+    request(DapRequest::Variables(reference), move |result| {
+        // The row is gone or shows another variable, ex: after a step
+        let Some(iter) = store.iter(&path) else {
+            return;
+        };
+        let row_reference = store
+            .value(&iter, COLUMN_REFERENCE as i32)
+            .get::<i64>()
+            .unwrap_or_default();
+        if row_reference != reference {
+            return;
+        }
+
+        while let Some(child) = store.iter_children(Some(&iter)) {
+            store.remove(&child);
+        }
+        match result {
+            Ok(DapResponse::Variables(variables)) => {
+                for variable in &variables {
+                    insert_variable(&store, Some(&iter), variable);
+                }
+            }
+            Ok(_) => {}
+            Err(error) => {
+                store.insert_with_values(
+                    Some(&iter),
+                    None,
+                    &[(COLUMN_NAME, &error_message(&error))],
+                );
+            }
+        }
+    });
+}
+
+fn error_message(error: &DapError) -> String {
+    match error {
+        DapError::Adapter { message, .. } => message.clone(),
+        error => error.to_string(),
+    }
+}
+
+fn clear_views(page: &DebugPage) {
+    page.stack_store.clear();
+    page.variables_store.clear();
+    // Watch expressions stay, without values
+    refresh_watches();
+}
+
+// Adds text printed by the program, it ends with its own line break
+fn append_console(text: &str) {
+    let page = debug_page();
+    let Some(buffer) = page.console_view.buffer() else {
+        return;
+    };
+
+    let mut end = buffer.end_iter();
+    buffer.insert(&mut end, text);
+
+    if let Some(end_mark) = buffer.mark("end") {
+        page.console_view.scroll_mark_onscreen(&end_mark);
+    }
+}
+
+fn tool_button(icon_name: &str, tooltip: &str, on_clicked: fn()) -> Button {
+    let button = Button::from_icon_name(Some(icon_name), IconSize::Menu);
+    button.set_relief(ReliefStyle::None);
+    button.set_tooltip_text(Some(tooltip));
+    button.connect_clicked(move |_| on_clicked());
+    button
+}
+
+// Tree of variables with name, value and type columns, children are loaded
+// when a row is expanded
+fn variables_tree() -> (TreeView, TreeStore, ScrolledWindow) {
+    let store = TreeStore::new(&[
+        Type::STRING,
+        Type::STRING,
+        Type::STRING,
+        Type::I64,
+        Type::BOOL,
+    ]);
+    let tree_view = TreeView::with_model(&store);
+
+    for (title, column_index) in [
+        ("Name", COLUMN_NAME),
+        ("Value", COLUMN_VALUE),
+        ("Type", COLUMN_TYPE),
+    ] {
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        cell.set_ellipsize(EllipsizeMode::End);
+        column.set_title(title);
+        column.set_resizable(true);
+        column.set_expand(column_index == COLUMN_VALUE);
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", column_index as i32);
+        tree_view.append_column(&column);
+    }
+
+    let store_clone = store.clone();
+    tree_view.connect_test_expand_row(move |_, iter, _| {
+        load_children(&store_clone, iter);
+        gtk::Inhibit(false)
+    });
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.add(&tree_view);
+
+    (tree_view, store, scrolled_window)
+}
+
+// Page of the bottom panel with the debugging session, added on first use
+fn debug_page() -> DebugPage {
+    if let Some(page) = G_DEBUG_PAGE.with(|page| page.borrow().clone()) {
+        return page;
+    }
+
+    let stack_store = ListStore::new(&[Type::STRING, Type::STRING]);
+    let stack_view = TreeView::with_model(&stack_store);
+    for (title, column_index) in [
+        ("Call Stack", COLUMN_FRAME_NAME),
+        ("", COLUMN_FRAME_LOCATION),
+    ] {
+        let column = TreeViewColumn::new();
+        let cell = CellRendererText::new();
+        cell.set_ellipsize(EllipsizeMode::End);
+        column.set_title(title);
+        column.set_expand(column_index == COLUMN_FRAME_NAME);
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", column_index as i32);
+        stack_view.append_column(&column);
+    }
+    let stack_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    stack_window.add(&stack_view);
+
+    let (variables_view, variables_store, variables_window) = variables_tree();
+    let (watch_view, watch_store, watch_window) = variables_tree();
+    watch_window.set_vexpand(true);
+
+    let watch_entry = Entry::new();
+    watch_entry.set_placeholder_text(Some("Add watch expression"));
+    watch_entry.connect_activate(|entry| {
+        add_watch(&entry.text());
+        entry.set_text("");
+    });
+    let watch_box = gtk::Box::new(Orientation::Vertical, 0);
+    watch_box.pack_start(&watch_window, true, true, 0);
+    watch_box.pack_start(&watch_entry, false, false, 0);
+
+    let console_view = TextView::new();
+    console_view.set_editable(false);
+    console_view.set_cursor_visible(false);
+    console_view.set_monospace(true);
+    console_view.set_left_margin(4);
+    if let Some(buffer) = console_view.buffer() {
+        // Stays at the end, output is scrolled to it
+        buffer.create_mark(Some("end"), &buffer.end_iter(), false);
+    }
+    let console_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    console_window.add(&console_view);
+
+    let watch_paned = Paned::new(Orientation::Horizontal);
+    watch_paned.pack1(&watch_box, true, false);
+    watch_paned.pack2(&console_window, true, false);
+    watch_paned.set_position(280);
+
+    let variables_paned = Paned::new(Orientation::Horizontal);
+    variables_paned.pack1(&variables_window, true, false);
+    variables_paned.pack2(&watch_paned, true, false);
+    variables_paned.set_position(360);
+
+    let paned = Paned::new(Orientation::Horizontal);
+    paned.pack1(&stack_window, true, false);
+    paned.pack2(&variables_paned, true, false);
+    paned.set_position(280);
+
+    let label = Label::new(Some("Not debugging"));
+    label.set_xalign(0.0);
+    label.set_margin(4);
+    label.set_ellipsize(EllipsizeMode::End);
+
+    let toolbar = gtk::Box::new(Orientation::Horizontal, 0);
+    toolbar.pack_start(&label, true, true, 0);
+    for (icon_name, tooltip, on_clicked) in [
+        (
+            "media-playback-stop-symbolic",
+            "Stop",
+            stop_debugging as fn(),
+        ),
+        ("go-up-symbolic", "Step Out", step_out),
+        ("go-down-symbolic", "Step Into", step_into),
+        ("go-jump-symbolic", "Step Over", step_over),
+        ("media-playback-pause-symbolic", "Pause", pause_debugging),
+        (
+            "media-playback-start-symbolic",
+            "Start or Continue",
+            start_debugging,
+        ),
+    ] {
+        toolbar.pack_end(
+            &tool_button(icon_name, tooltip, on_clicked),
+            false,
+            false,
+            0,
+        );
+    }
+
+    let page = gtk::Box::new(Orientation::Vertical, 0);
+    page.pack_start(&toolbar, false, false, 0);
+    page.pack_start(&paned, true, true, 0);
+    bottom_panel::add_page(&page, "Debug");
+
+    let page = DebugPage {
+        page,
+        label,
+        stack_view,
+        stack_store,
+        variables_view,
+        variables_store,
+        watch_view,
+        watch_store,
+        console_view,
+    };
+    G_DEBUG_PAGE.with(|debug_page| *debug_page.borrow_mut() = Some(page.clone()));
+
+    page.stack_view.set_activate_on_single_click(true);
+    page.stack_view.connect_row_activated(|_, path, _| {
+        if let Some(index) = path.indices().first() {
+            select_frame(*index as usize);
+        }
+    });
+    page.watch_view.connect_key_press_event(|_, event| {
+        if event.keyval() != key_constants::Delete {
+            return gtk::Inhibit(false);
+        }
+        remove_selected_watch(&debug_page());
+        gtk::Inhibit(true)
+    });
+
+    page
+}
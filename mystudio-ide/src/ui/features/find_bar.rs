@@ -0,0 +1,506 @@
+use std::cell::RefCell;
+
+use gtk::{
+    gdk::{keys::constants as key_constants, ModifierType},
+    glib::{IsA, Object},
+    prelude::{BuilderExtManual, Cast, EntryExt, SearchEntryExt, TextBufferExt},
+    traits::{
+        ButtonExt, LabelExt, RevealerExt, StyleContextExt, TextViewExt, ToggleButtonExt, WidgetExt,
+    },
+    Builder, Button, Entry, Label, Revealer, SearchEntry, TextBuffer, TextIter, ToggleButton,
+};
+use sourceview4::{
+    traits::{SearchContextExt, SearchSettingsExt},
+    Buffer, SearchContext, SearchSettings, View,
+};
+
+use crate::ui::{notebook::editor::Editor, statusbar::message::show_message};
+
+// Marks around the text searched by "Find in selection"
+const SELECTION_START_MARK: &str = "find_bar_selection_start";
+const SELECTION_END_MARK: &str = "find_bar_selection_end";
+
+#[derive(Clone)]
+struct FindBar {
+    revealer: Revealer,
+    search_entry: SearchEntry,
+    replace_entry: Entry,
+    count_label: Label,
+    case_toggle: ToggleButton,
+    word_toggle: ToggleButton,
+    regex_toggle: ToggleButton,
+    selection_toggle: ToggleButton,
+    replace_button: Button,
+    replace_all_button: Button,
+    // Shared by search contexts of all editors
+    settings: SearchSettings,
+}
+
+thread_local! { static G_FIND_BAR: RefCell<Option<FindBar>> = RefCell::new(None) }
+// Search context for the buffer of the open editor
+thread_local! { static G_SEARCH_CONTEXT: RefCell<Option<SearchContext>> = RefCell::new(None) }
+
+pub fn init(builder: &Builder) {
+    fn object<T: IsA<Object>>(builder: &Builder, id: &str) -> T {
+        builder
+            .object(id)
+            .unwrap_or_else(|| panic!("Unable to find {id}"))
+    }
+
+    let settings = SearchSettings::new();
+    settings.set_wrap_around(true);
+
+    let find_bar = FindBar {
+        revealer: object(builder, "revealer_find_bar"),
+        search_entry: object(builder, "entry_find_bar_search"),
+        replace_entry: object(builder, "entry_find_bar_replace"),
+        count_label: object(builder, "label_find_bar_count"),
+        case_toggle: object(builder, "toggle_find_bar_case"),
+        word_toggle: object(builder, "toggle_find_bar_word"),
+        regex_toggle: object(builder, "toggle_find_bar_regex"),
+        selection_toggle: object(builder, "toggle_find_bar_selection"),
+        replace_button: object(builder, "button_find_bar_replace"),
+        replace_all_button: object(builder, "button_find_bar_replace_all"),
+        settings,
+    };
+
+    // Incremental search
+    find_bar.search_entry.connect_search_changed(|_| {
+        update_settings();
+        find_from_selection_start();
+    });
+    find_bar.search_entry.connect_activate(|_| find_next());
+    find_bar.search_entry.connect_stop_search(|_| hide());
+    find_bar.search_entry.connect_key_press_event(|_, event| {
+        let is_shift_enter = event.keyval() == key_constants::Return
+            && event.state().contains(ModifierType::SHIFT_MASK);
+        if is_shift_enter {
+            find_previous();
+        }
+
+        gtk::Inhibit(is_shift_enter)
+    });
+
+    find_bar.replace_entry.connect_activate(|_| replace());
+    find_bar.replace_entry.connect_key_press_event(|_, event| {
+        if event.keyval() == key_constants::Escape {
+            hide();
+            return gtk::Inhibit(true);
+        }
+        gtk::Inhibit(false)
+    });
+
+    for toggle in [
+        &find_bar.case_toggle,
+        &find_bar.word_toggle,
+        &find_bar.regex_toggle,
+    ] {
+        toggle.connect_toggled(|_| {
+            update_settings();
+            find_from_selection_start();
+        });
+    }
+    find_bar.selection_toggle.connect_toggled(|toggle| {
+        if toggle.is_active() {
+            remember_selection();
+        }
+        update_count();
+    });
+
+    let button = |id: &str| object::<Button>(builder, id);
+    button("button_find_bar_previous").connect_clicked(|_| find_previous());
+    button("button_find_bar_next").connect_clicked(|_| find_next());
+    button("button_find_bar_close").connect_clicked(|_| hide());
+    find_bar.replace_button.connect_clicked(|_| replace());
+    find_bar
+        .replace_all_button
+        .connect_clicked(|_| replace_all());
+
+    G_FIND_BAR.with(|f| *f.borrow_mut() = Some(find_bar));
+}
+
+fn find_bar() -> FindBar {
+    G_FIND_BAR.with(|f| f.borrow().clone().expect("Find bar is not initialized"))
+}
+
+/**
+ * Shows the find bar above the open editor, along with replace fields when
+ * `with_replace` is set.
+ *
+ * A selection on a single line is used as search text, a larger one turns on
+ * "Find in selection".
+ */
+pub fn show(with_replace: bool) {
+    let Some(view) = Editor::active() else {
+        return;
+    };
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+    let find_bar = find_bar();
+
+    if let Some((start, end)) = buffer.selection_bounds() {
+        if start.line() == end.line() {
+            if let Some(text) = buffer.text(&start, &end, true) {
+                find_bar.search_entry.set_text(&text);
+            }
+            find_bar.selection_toggle.set_active(false);
+        } else {
+            find_bar.selection_toggle.set_active(true);
+            remember_selection();
+        }
+    }
+
+    find_bar.replace_entry.set_visible(with_replace);
+    find_bar.replace_button.set_visible(with_replace);
+    find_bar.replace_all_button.set_visible(with_replace);
+    find_bar.revealer.set_reveal_child(true);
+
+    find_bar.search_entry.grab_focus();
+    update_settings();
+    update_count();
+}
+
+pub fn hide() {
+    let find_bar = find_bar();
+    find_bar.revealer.set_reveal_child(false);
+
+    G_SEARCH_CONTEXT.with(|context| {
+        if let Some(context) = context.borrow_mut().take() {
+            context.set_highlight(false);
+        }
+    });
+
+    if let Some(view) = Editor::active() {
+        view.grab_focus();
+    }
+}
+
+pub fn find_next() {
+    find(true);
+}
+
+pub fn find_previous() {
+    find(false);
+}
+
+/**
+ * Returns the search context of the open editor, creating it when the open
+ * editor (or its buffer) changed.
+ */
+fn search_context() -> Option<(View, SearchContext)> {
+    let view = Editor::active()?;
+    let buffer = view.buffer()?.downcast::<Buffer>().ok()?;
+
+    let context = G_SEARCH_CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+
+        let is_current = context
+            .as_ref()
+            .and_then(|c| c.buffer())
+            .is_some_and(|b| b == buffer);
+        if !is_current {
+            if let Some(old_context) = context.take() {
+                old_context.set_highlight(false);
+            }
+
+            let new_context = SearchContext::new(&buffer, Some(&find_bar().settings));
+            new_context.connect_occurrences_count_notify(|_| update_count());
+            *context = Some(new_context);
+        }
+
+        context.clone().unwrap()
+    });
+
+    context.set_highlight(true);
+
+    Some((view, context))
+}
+
+fn update_settings() {
+    let find_bar = find_bar();
+    let settings = &find_bar.settings;
+    let text = find_bar.search_entry.text();
+
+    settings.set_case_sensitive(find_bar.case_toggle.is_active());
+    settings.set_at_word_boundaries(find_bar.word_toggle.is_active());
+    settings.set_regex_enabled(find_bar.regex_toggle.is_active());
+    settings.set_search_text(Some(text.as_str()).filter(|text| !text.is_empty()));
+}
+
+// Typing moves to the first match from where the current match starts
+fn find_from_selection_start() {
+    let Some((view, context)) = search_context() else {
+        return;
+    };
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+
+    let start = match buffer.selection_bounds() {
+        Some((start, _)) => start,
+        None => buffer.iter_at_offset(buffer.cursor_position()),
+    };
+    search_from(&view, &context, &start, true);
+}
+
+fn find(forward: bool) {
+    let Some((view, context)) = search_context() else {
+        return;
+    };
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+
+    let cursor = buffer.iter_at_offset(buffer.cursor_position());
+    let from = match buffer.selection_bounds() {
+        Some((start, end)) => {
+            if forward {
+                end
+            } else {
+                start
+            }
+        }
+        None => cursor,
+    };
+    search_from(&view, &context, &from, forward);
+}
+
+/**
+ * Selects the first match from `iter`, staying inside the remembered range
+ * when searching in selection.
+ */
+fn search_from(view: &View, context: &SearchContext, iter: &TextIter, forward: bool) {
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+
+    let search = |iter: &TextIter| {
+        if forward {
+            context.forward(iter)
+        } else {
+            context.backward(iter)
+        }
+    };
+
+    let mut found = search(iter).map(|(start, end, _)| (start, end));
+
+    if let Some((range_start, range_end)) = selection_range(&buffer) {
+        let in_range = |(start, end): &(TextIter, TextIter)| {
+            start.offset() >= range_start && end.offset() <= range_end
+        };
+
+        // Wrap around inside the range
+        if !found.as_ref().is_some_and(in_range) {
+            let wrap_iter = buffer.iter_at_offset(if forward { range_start } else { range_end });
+            found = search(&wrap_iter)
+                .map(|(start, end, _)| (start, end))
+                .filter(in_range);
+        }
+    }
+
+    if let Some((start, end)) = found {
+        buffer.select_range(&start, &end);
+        if let Some(mark) = buffer.get_insert() {
+            view.scroll_mark_onscreen(&mark);
+        }
+    }
+
+    update_count();
+}
+
+fn update_count() {
+    let Some(find_bar) = G_FIND_BAR.with(|f| f.borrow().clone()) else {
+        return;
+    };
+    let context = G_SEARCH_CONTEXT.with(|context| context.borrow().clone());
+    let style_context = find_bar.search_entry.style_context();
+    style_context.remove_class("error");
+
+    let Some(context) = context.filter(|_| !find_bar.search_entry.text().is_empty()) else {
+        find_bar.count_label.set_text("");
+        return;
+    };
+
+    if context.regex_error().is_some() {
+        style_context.add_class("error");
+        find_bar.count_label.set_text("Invalid regex");
+        return;
+    }
+
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+    let selection = buffer.selection_bounds();
+
+    let label = match selection_range(&buffer) {
+        Some(range) => {
+            let matches = matches_in_range(&context, range);
+            let position = selection.and_then(|(start, end)| {
+                matches
+                    .iter()
+                    .position(|m| *m == (start.offset(), end.offset()))
+            });
+            match_count_label(position.map(|p| p + 1), matches.len())
+        }
+        None => {
+            // Still counting in the background
+            let count = context.occurrences_count();
+            if count < 0 {
+                return;
+            }
+
+            let position = selection
+                .map(|(start, end)| context.occurrence_position(&start, &end))
+                .filter(|position| *position > 0);
+            match_count_label(position.map(|p| p as usize), count as usize)
+        }
+    };
+
+    if label == NO_RESULTS {
+        style_context.add_class("error");
+    }
+    find_bar.count_label.set_text(&label);
+}
+
+const NO_RESULTS: &str = "No results";
+
+/**
+ * Returns the match counter text, ex: `3 of 17`.
+ *
+ * `position` is the 1-based index of the selected match, if any.
+ */
+fn match_count_label(position: Option<usize>, count: usize) -> String {
+    match (position, count) {
+        (_, 0) => NO_RESULTS.to_string(),
+        (Some(position), count) => format!("{position} of {count}"),
+        (None, 1) => String::from("1 match"),
+        (None, count) => format!("{count} matches"),
+    }
+}
+
+fn replace() {
+    let Some((view, context)) = search_context() else {
+        return;
+    };
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+
+    // Replace the selected match, then move on to the next one
+    if let Some((mut start, mut end)) = buffer.selection_bounds() {
+        if context.occurrence_position(&start, &end) > 0 {
+            let replacement = find_bar().replace_entry.text();
+            if let Err(error) = context.replace(&mut start, &mut end, &replacement) {
+                show_message(error.to_string());
+                return;
+            }
+        }
+    }
+
+    let from = buffer.iter_at_offset(buffer.cursor_position());
+    search_from(&view, &context, &from, true);
+}
+
+fn replace_all() {
+    let Some((_, context)) = search_context() else {
+        return;
+    };
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+    let replacement = find_bar().replace_entry.text();
+
+    // Undone at once
+    buffer.begin_user_action();
+    let result = match selection_range(&buffer) {
+        Some(range) => {
+            let matches = matches_in_range(&context, range);
+
+            // Last to first so offsets of earlier matches stay valid
+            matches
+                .iter()
+                .rev()
+                .try_for_each(|(start, end)| {
+                    let mut start = buffer.iter_at_offset(*start);
+                    let mut end = buffer.iter_at_offset(*end);
+                    context.replace(&mut start, &mut end, &replacement)
+                })
+                .map(|_| matches.len() as u32)
+        }
+        None => context.replace_all(&replacement),
+    };
+    buffer.end_user_action();
+
+    match result {
+        Ok(count) => show_message(format!("Replaced {count} occurrence(s)")),
+        Err(error) => show_message(error.to_string()),
+    }
+    update_count();
+}
+
+// Stores the selection of the open editor as the range searched in
+fn remember_selection() {
+    let Some(buffer) = Editor::active().and_then(|view| view.buffer()) else {
+        return;
+    };
+    let Some((start, end)) = buffer.selection_bounds() else {
+        return;
+    };
+
+    for (name, iter) in [(SELECTION_START_MARK, &start), (SELECTION_END_MARK, &end)] {
+        match buffer.mark(name) {
+            Some(mark) => buffer.move_mark(&mark, iter),
+            None => {
+                // Start mark stays before text inserted at the range start
+                buffer.create_mark(Some(name), iter, name == SELECTION_START_MARK);
+            }
+        }
+    }
+}
+
+/**
+ * Returns offsets of the range searched by "Find in selection", if it is on.
+ */
+fn selection_range(buffer: &TextBuffer) -> Option<(i32, i32)> {
+    if !find_bar().selection_toggle.is_active() {
+        return None;
+    }
+
+    let start = buffer.mark(SELECTION_START_MARK)?;
+    let end = buffer.mark(SELECTION_END_MARK)?;
+
+    Some((
+        buffer.iter_at_mark(&start).offset(),
+        buffer.iter_at_mark(&end).offset(),
+    ))
+}
+
+fn matches_in_range(
+    context: &SearchContext,
+    (range_start, range_end): (i32, i32),
+) -> Vec<(i32, i32)> {
+    let buffer = context.buffer().unwrap().upcast::<TextBuffer>();
+    let mut matches = vec![];
+    let mut iter = buffer.iter_at_offset(range_start);
+
+    while let Some((start, end, wrapped)) = context.forward(&iter) {
+        if wrapped || start.offset() < range_start || end.offset() > range_end {
+            break;
+        }
+        matches.push((start.offset(), end.offset()));
+
+        // Empty regex matches would be found again
+        iter = if end.offset() == start.offset() {
+            buffer.iter_at_offset(end.offset() + 1)
+        } else {
+            end
+        };
+        if iter.offset() >= range_end {
+            break;
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::match_count_label;
+
+    #[test]
+    fn match_count_label_test() {
+        assert_eq!(match_count_label(Some(3), 17), "3 of 17");
+        assert_eq!(match_count_label(None, 17), "17 matches");
+        assert_eq!(match_count_label(None, 1), "1 match");
+        assert_eq!(match_count_label(None, 0), "No results");
+    }
+}
@@ -1 +1,16 @@
+pub mod command_palette;
+pub mod debugger;
+pub mod find_bar;
 pub mod find_in_files;
+pub mod outline;
+pub mod preferences;
+pub mod problems;
+pub mod source_control;
+pub mod symbol_search;
+pub mod tasks;
+#[cfg(all(unix, feature = "terminal"))]
+pub mod terminal;
+#[cfg(not(all(unix, feature = "terminal")))]
+#[path = "terminal_unsupported.rs"]
+pub mod terminal;
+pub mod test_explorer;
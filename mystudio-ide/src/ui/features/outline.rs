@@ -0,0 +1,245 @@
+// Outline panel below the workspace explorer, lists definitions of the
+// active file and follows its cursor
+
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+use gtk::{
+    glib::{self, Type},
+    prelude::{BuilderExtManual, TreeModelExt, TreeStoreExtManual, TreeViewExt},
+    traits::{CellLayoutExt, TextBufferExt, TextViewExt, TreeSelectionExt, TreeStoreExt},
+    Builder, CellRendererPixbuf, CellRendererText, TextBuffer, TreeIter, TreePath, TreeStore,
+    TreeView, TreeViewColumn,
+};
+use libmystudio::{
+    lsp::types::{LspRequest, LspResponse},
+    notebook::cache::NotebookTabCache,
+    outline::{item_path_at_line, outline_for_path, outline_from_lsp, OutlineItem},
+    symbols::SymbolKind,
+    workspace::Workspace,
+};
+use sourceview4::View;
+
+use crate::ui::notebook::{
+    editor::{open_editor_for_abs_path, Editor},
+    lsp,
+};
+
+// Outline is rebuilt once typing pauses
+const REFRESH_DELAY: Duration = Duration::from_millis(500);
+
+thread_local! { static G_OUTLINE: RefCell<Option<(TreeView, TreeStore)>> = RefCell::new(None) }
+// Items shown in the tree, to find the row around the cursor
+thread_local! { static G_OUTLINE_ITEMS: RefCell<Vec<OutlineItem>> = RefCell::new(vec![]) }
+thread_local! { static G_REFRESH_PENDING: Cell<bool> = Cell::new(false) }
+// Incremented on each refresh, only the latest one fills the tree
+thread_local! { static G_REFRESH_ID: Cell<u64> = Cell::new(0) }
+
+// Columns of the store
+const COLUMN_ICON: u32 = 0;
+const COLUMN_NAME: u32 = 1;
+// One-based like `open_editor_for_abs_path`
+const COLUMN_LINE: u32 = 2;
+const COLUMN_COLUMN: u32 = 3;
+
+pub fn init(builder: &Builder) {
+    let tree_view: TreeView = builder
+        .object("outline_tree")
+        .expect("Unable to find outline tree");
+
+    let store = TreeStore::new(&[Type::STRING, Type::STRING, Type::I32, Type::I32]);
+    tree_view.set_model(Some(&store));
+
+    let column = TreeViewColumn::new();
+    let cell_icon = CellRendererPixbuf::new();
+    let cell_text = CellRendererText::new();
+    column.pack_start(&cell_icon, false);
+    column.pack_start(&cell_text, true);
+    column.add_attribute(&cell_icon, "icon-name", COLUMN_ICON as i32);
+    column.add_attribute(&cell_text, "text", COLUMN_NAME as i32);
+    tree_view.append_column(&column);
+
+    // Selecting rows to follow the cursor doesn't activate them
+    tree_view.set_activate_on_single_click(true);
+    tree_view.connect_row_activated(|tree_view, path, _column| {
+        let (Some(model), Some(file_path)) = (tree_view.model(), Workspace::get_open_file_path())
+        else {
+            return;
+        };
+        let Some(iter) = model.iter(path) else {
+            return;
+        };
+
+        let line = model.value(&iter, COLUMN_LINE as i32).get::<i32>().unwrap();
+        let column = model
+            .value(&iter, COLUMN_COLUMN as i32)
+            .get::<i32>()
+            .unwrap();
+        open_editor_for_abs_path(file_path, line, column);
+    });
+
+    G_OUTLINE.with(|outline| *outline.borrow_mut() = Some((tree_view, store)));
+}
+
+/**
+ * Keeps the outline of a new editor in sync with its text and cursor.
+ */
+pub fn attach(view: &View, file_path: &str) {
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+
+    let changed_path = file_path.to_string();
+    buffer.connect_changed(move |_| {
+        if is_active(&changed_path) {
+            schedule_refresh();
+        }
+    });
+
+    let cursor_path = file_path.to_string();
+    buffer.connect_cursor_position_notify(move |buffer| {
+        if is_active(&cursor_path) {
+            select_item_at_cursor(buffer);
+        }
+    });
+
+    refresh();
+}
+
+/**
+ * Rebuilds the outline of the active file once its server answers, it is
+ * emptied without one.
+ */
+pub fn refresh() {
+    if G_OUTLINE.with(|outline| outline.borrow().is_none()) {
+        return;
+    }
+    let refresh_id = G_REFRESH_ID.with(|id| id.replace(id.get() + 1) + 1);
+
+    let Some(file_path) = Workspace::get_open_file_path().filter(|path| is_active(path)) else {
+        show_items(vec![]);
+        return;
+    };
+    let Some(buffer) = Editor::buffer_from_path(file_path.clone()) else {
+        show_items(vec![]);
+        return;
+    };
+    let text = buffer
+        .text(&buffer.start_iter(), &buffer.end_iter(), true)
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+    let edits = lsp::edit_count(&file_path);
+
+    // The tree keeps the previous outline until the server answers
+    lsp::request(&file_path, LspRequest::DocumentSymbols, move |result| {
+        // Answers to older refreshes or about older text are dropped
+        let is_latest = G_REFRESH_ID.with(|id| id.get()) == refresh_id;
+        if !is_latest || lsp::edit_count(&file_path) != edits {
+            return;
+        }
+
+        // Built-in parsers when the server can't tell
+        let items = match result {
+            Ok(LspResponse::DocumentSymbols(symbols)) if !symbols.is_empty() => {
+                outline_from_lsp(&symbols, &text)
+            }
+            Ok(_) => outline_for_path(&file_path, &text),
+            Err(error) => {
+                eprintln!("Document symbols failed: {error}");
+                outline_for_path(&file_path, &text)
+            }
+        };
+
+        show_items(items);
+        select_item_at_cursor(&buffer);
+    });
+}
+
+fn show_items(items: Vec<OutlineItem>) {
+    let Some((tree_view, store)) = G_OUTLINE.with(|outline| outline.borrow().clone()) else {
+        return;
+    };
+
+    store.clear();
+    insert_items(&store, None, &items);
+    tree_view.expand_all();
+    G_OUTLINE_ITEMS.with(|outline_items| *outline_items.borrow_mut() = items);
+}
+
+fn schedule_refresh() {
+    if G_REFRESH_PENDING.with(|pending| pending.replace(true)) {
+        return;
+    }
+
+    glib::timeout_add_local_once(REFRESH_DELAY, || {
+        G_REFRESH_PENDING.with(|pending| pending.set(false));
+        refresh();
+    });
+}
+
+// Tells whether a file is the one shown in the editor
+fn is_active(file_path: &str) -> bool {
+    Workspace::get_open_file_path().as_deref() == Some(file_path)
+        && NotebookTabCache::find_by_path(file_path.to_string()).is_some()
+}
+
+fn insert_items(store: &TreeStore, parent: Option<&TreeIter>, items: &[OutlineItem]) {
+    for item in items {
+        let row = store.insert_with_values(
+            parent,
+            None,
+            &[
+                (COLUMN_ICON, &icon_name(item.kind)),
+                (COLUMN_NAME, &item.name),
+                (COLUMN_LINE, &(item.line as i32 + 1)),
+                (COLUMN_COLUMN, &(item.column as i32 + 1)),
+            ],
+        );
+
+        insert_items(store, Some(&row), &item.children);
+    }
+}
+
+// Selects the innermost item around the cursor
+fn select_item_at_cursor(buffer: &TextBuffer) {
+    let Some((tree_view, _)) = G_OUTLINE.with(|outline| outline.borrow().clone()) else {
+        return;
+    };
+    let line = buffer.iter_at_offset(buffer.cursor_position()).line();
+
+    let indices: Vec<i32> = G_OUTLINE_ITEMS.with(|items| {
+        item_path_at_line(&items.borrow(), line as u32)
+            .into_iter()
+            .map(|index| index as i32)
+            .collect()
+    });
+
+    let selection = tree_view.selection();
+    if indices.is_empty() {
+        selection.unselect_all();
+        return;
+    }
+
+    let path = TreePath::from_indicesv(&indices);
+    tree_view.expand_to_path(&path);
+    selection.select_path(&path);
+    tree_view.scroll_to_cell(Some(&path), None::<&TreeViewColumn>, false, 0.0, 0.0);
+}
+
+fn icon_name(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Function | SymbolKind::Method | SymbolKind::Macro => "system-run-symbolic",
+        SymbolKind::Struct | SymbolKind::Class | SymbolKind::Enum | SymbolKind::Type => {
+            "view-grid-symbolic"
+        }
+        SymbolKind::Trait | SymbolKind::Interface | SymbolKind::Impl => "view-list-symbolic",
+        SymbolKind::Module => "folder-symbolic",
+        SymbolKind::Constant | SymbolKind::Variable | SymbolKind::Field => {
+            "format-text-plain-symbolic"
+        }
+        SymbolKind::Heading => "format-justify-left-symbolic",
+        SymbolKind::Other => "text-x-generic-symbolic",
+    }
+}
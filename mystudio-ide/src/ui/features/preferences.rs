@@ -0,0 +1,314 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{
+    prelude::{Cast, DialogExt, EntryExt, GridExt, NotebookExtManual},
+    traits::{
+        BoxExt, ComboBoxExt, ComboBoxTextExt, ContainerExt, GtkWindowExt, LabelExt, SpinButtonExt,
+        StyleContextExt, SwitchExt, WidgetExt,
+    },
+    Adjustment, ComboBoxText, Dialog, Entry, Grid, Label, Notebook, ResponseType, ScrolledWindow,
+    SpinButton, Switch, Widget,
+};
+use libmystudio::{
+    app_config::{
+        error::AppConfigErrorKind,
+        layered::{ConfigLayer, LayeredAppConfig},
+        AppConfig, AppConfigProvider, DefaultAppConfigProvider,
+    },
+    keymap::KeymapPreset,
+    terminal::TerminalLayout,
+};
+use sourceview4::{traits::StyleSchemeManagerExt, StyleSchemeManager};
+
+use crate::ui;
+
+// Sections listed first, in this order. Others follow alphabetically.
+const SECTIONS_ORDER: &[&str] = &[
+    "General",
+    "Editor",
+    "Completion",
+    "Search",
+    "Terminal",
+    "Keybindings",
+];
+
+/**
+ * Shows Preferences dialog.
+ *
+ * The dialog is generated from sections and keys of AppConfig. Every change is
+ * validated, applied to the UI and saved to config file right away, text
+ * fields once they are edited.
+ */
+pub fn show_dialog() {
+    let dialog = build_dialog();
+    dialog.show_all();
+}
+
+fn build_dialog() -> Dialog {
+    let window = ui::main_window();
+
+    let dialog = Dialog::builder()
+        .title("Preferences | MyStudio IDE")
+        .transient_for(&window)
+        .modal(true)
+        .destroy_with_parent(true)
+        .default_width(560)
+        .default_height(480)
+        .build();
+
+    let notebook = Notebook::new();
+    notebook.set_vexpand(true);
+
+    let status_label = Label::new(None);
+    status_label.set_widget_name("label_preferences_status");
+    status_label.set_xalign(0f32);
+    status_label.set_margin_start(12);
+    status_label.set_margin_top(6);
+
+    // Edit the user config, workspace settings are applied on top of it
+    let (user_config, errors) = DefaultAppConfigProvider::load_config();
+    let mut user_config_value = user_config.to_value();
+    // List every command, `[Keybindings]` only has those set by the user
+    let keybindings = user_config
+        .keybindings()
+        .into_iter()
+        .map(|(command, keys)| (command, toml::Value::String(keys)))
+        .collect();
+    user_config_value["Keybindings"] = toml::Value::Table(keybindings);
+    let layered_config = LayeredAppConfig::current();
+
+    // Saving would replace settings which couldn't be read
+    if let Some(error) = errors
+        .iter()
+        .find(|e| matches!(e.kind, AppConfigErrorKind::Syntax | AppConfigErrorKind::Io))
+    {
+        status_label.set_text(&format!(
+            "Changes can't be saved until this is fixed: {error}"
+        ));
+    }
+
+    let mut sections: Vec<_> = user_config_value
+        .as_table()
+        .expect("AppConfig is serialized to a table")
+        .iter()
+        .filter_map(|(name, value)| value.as_table().map(|table| (name, table)))
+        .collect();
+    sections.sort_by_key(|(name, _)| {
+        SECTIONS_ORDER
+            .iter()
+            .position(|s| s == name)
+            .unwrap_or(SECTIONS_ORDER.len())
+    });
+
+    for (section_name, section) in sections {
+        let grid = Grid::builder()
+            .row_spacing(8)
+            .column_spacing(12)
+            .margin(12)
+            .build();
+
+        for (row, (key, value)) in section.iter().enumerate() {
+            let dotted_key = format!("{section_name}.{key}");
+            let row = row as i32;
+
+            let label = Label::new(Some(&humanize_key(key)));
+            label.set_xalign(0f32);
+            grid.attach(&label, 0, row, 1, 1);
+
+            let field = build_field(&dotted_key, value, &status_label);
+            field.set_hexpand(true);
+            grid.attach(&field, 1, row, 1, 1);
+
+            if layered_config.origin(&dotted_key) == ConfigLayer::Workspace {
+                let note = Label::new(Some("Overridden by workspace settings"));
+                note.style_context().add_class("dim-label");
+                grid.attach(&note, 2, row, 1, 1);
+            }
+        }
+
+        let scrolled_window =
+            ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+        scrolled_window.add(&grid);
+
+        notebook.append_page(&scrolled_window, Some(&Label::new(Some(section_name))));
+    }
+
+    let content_area = dialog.content_area();
+    content_area.pack_start(&notebook, true, true, 0);
+    content_area.pack_start(&status_label, false, true, 0);
+
+    dialog.add_button("Close", ResponseType::Close);
+    dialog.connect_response(|dialog, _| dialog.close());
+
+    dialog
+}
+
+/**
+ * Builds an input widget for a given config value based on its type.
+ */
+fn build_field(key: &str, value: &toml::Value, status_label: &Label) -> Widget {
+    let key = key.to_string();
+    let status_label = status_label.clone();
+
+    match value {
+        toml::Value::Boolean(state) => {
+            let switch = Switch::new();
+            switch.set_active(*state);
+            switch.set_halign(gtk::Align::Start);
+            switch.connect_state_set(move |switch, state| {
+                on_field_changed(&key, toml::Value::Boolean(state), switch, &status_label);
+                gtk::Inhibit(false)
+            });
+            switch.upcast::<Widget>()
+        }
+        toml::Value::Integer(number) => {
+            let spin_button = SpinButton::with_range(0.0, 100_000.0, 1.0);
+            spin_button.set_value(*number as f64);
+            spin_button.connect_value_changed(move |spin_button| {
+                let value = toml::Value::Integer(spin_button.value_as_int() as i64);
+                on_field_changed(&key, value, spin_button, &status_label);
+            });
+            spin_button.upcast::<Widget>()
+        }
+        toml::Value::String(text) if key == "Editor.style_scheme" => {
+            let combo_box = ComboBoxText::new();
+            let scheme_manager = StyleSchemeManager::default().unwrap();
+            for scheme_id in scheme_manager.scheme_ids() {
+                combo_box.append(Some(scheme_id.as_str()), scheme_id.as_str());
+            }
+            combo_box.set_active_id(Some(text));
+            combo_box.connect_changed(move |combo_box| {
+                if let Some(scheme_id) = combo_box.active_id() {
+                    let value = toml::Value::String(scheme_id.to_string());
+                    on_field_changed(&key, value, combo_box, &status_label);
+                }
+            });
+            combo_box.upcast::<Widget>()
+        }
+        toml::Value::String(text) if key == "General.keymap_preset" => {
+            let combo_box = ComboBoxText::new();
+            for preset in KeymapPreset::ALL {
+                combo_box.append(Some(preset.name()), preset.name());
+            }
+            combo_box.set_active_id(Some(text));
+            combo_box.connect_changed(move |combo_box| {
+                if let Some(preset) = combo_box.active_id() {
+                    let value = toml::Value::String(preset.to_string());
+                    on_field_changed(&key, value, combo_box, &status_label);
+                }
+            });
+            combo_box.upcast::<Widget>()
+        }
+        toml::Value::String(text) if key == "Terminal.layout" => {
+            let combo_box = ComboBoxText::new();
+            for layout in TerminalLayout::ALL {
+                combo_box.append(Some(layout.name()), layout.name());
+            }
+            combo_box.set_active_id(Some(text));
+            combo_box.connect_changed(move |combo_box| {
+                if let Some(layout) = combo_box.active_id() {
+                    let value = toml::Value::String(layout.to_string());
+                    on_field_changed(&key, value, combo_box, &status_label);
+                }
+            });
+            combo_box.upcast::<Widget>()
+        }
+        toml::Value::String(text) => {
+            let entry = Entry::new();
+            entry.set_text(text);
+            connect_entry_edited(&entry, move |entry| {
+                let value = toml::Value::String(entry.text().to_string());
+                on_field_changed(&key, value, entry, &status_label);
+            });
+            entry.upcast::<Widget>()
+        }
+        toml::Value::Array(items) => {
+            // Comma separated list of strings
+            let entry = Entry::new();
+            let text = items
+                .iter()
+                .filter_map(toml::Value::as_str)
+                .collect::<Vec<&str>>()
+                .join(", ");
+            entry.set_text(&text);
+            connect_entry_edited(&entry, move |entry| {
+                let items = entry
+                    .text()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| toml::Value::String(item.to_string()))
+                    .collect();
+                on_field_changed(&key, toml::Value::Array(items), entry, &status_label);
+            });
+            entry.upcast::<Widget>()
+        }
+        value => {
+            let label = Label::new(Some(&value.to_string()));
+            label.set_xalign(0f32);
+            label.upcast::<Widget>()
+        }
+    }
+}
+
+/**
+ * Calls `callback` once the text of `entry` was edited, when Enter is pressed
+ * or the entry loses focus, rather than on every keystroke.
+ */
+fn connect_entry_edited<F: Fn(&Entry) + 'static>(entry: &Entry, callback: F) {
+    let saved_text = Rc::new(RefCell::new(entry.text().to_string()));
+    let on_edited = Rc::new(move |entry: &Entry| {
+        let text = entry.text().to_string();
+        if *saved_text.borrow() != text {
+            *saved_text.borrow_mut() = text;
+            callback(entry);
+        }
+    });
+
+    let on_activate = on_edited.clone();
+    entry.connect_activate(move |entry| on_activate(entry));
+    entry.connect_focus_out_event(move |entry, _| {
+        on_edited(entry);
+        gtk::Inhibit(false)
+    });
+}
+
+fn on_field_changed<W: WidgetExt>(key: &str, value: toml::Value, field: &W, status_label: &Label) {
+    let style_context = field.style_context();
+
+    match update_setting(key, value) {
+        Ok(_) => {
+            style_context.remove_class("error");
+            status_label.set_text("");
+        }
+        Err(message) => {
+            style_context.add_class("error");
+            status_label.set_text(&format!("{key}: {message}"));
+        }
+    }
+}
+
+/**
+ * Validates and saves a single setting to user config, then applies it to the UI.
+ */
+fn update_setting(key: &str, value: toml::Value) -> Result<(), String> {
+    DefaultAppConfigProvider::save_value(key, value)?;
+
+    LayeredAppConfig::recompute();
+    ui::app_config::apply(&AppConfig::current());
+
+    Ok(())
+}
+
+/**
+ * Converts `application_width` to `Application width`.
+ */
+fn humanize_key(key: &str) -> String {
+    let text = key.replace('_', " ");
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
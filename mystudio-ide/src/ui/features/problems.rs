@@ -0,0 +1,210 @@
+use std::{cell::RefCell, path::Path};
+
+use gtk::{
+    glib::Type,
+    prelude::{TreeModelExt, TreeStoreExtManual, TreeViewExt},
+    traits::{CellLayoutExt, ContainerExt, TreeStoreExt},
+    Adjustment, CellRendererPixbuf, CellRendererText, ScrolledWindow, TreeStore, TreeView,
+    TreeViewColumn,
+};
+use libmystudio::{
+    diagnostics::{Diagnostic, DiagnosticCollection, Severity},
+    notebook::cache::NotebookTabCache,
+    workspace::Workspace,
+};
+
+use crate::ui::{
+    bottom_panel,
+    notebook::{diagnostics as editor_diagnostics, editor::open_editor_for_abs_path},
+    statusbar::diagnostics_indicator,
+};
+
+// Diagnostics of all files, by owner
+thread_local! { static G_DIAGNOSTICS: RefCell<DiagnosticCollection> = RefCell::new(DiagnosticCollection::default()) }
+thread_local! { static G_PROBLEMS_PAGE: RefCell<Option<(ScrolledWindow, TreeView, TreeStore)>> = RefCell::new(None) }
+
+// Columns of the store
+const COLUMN_ICON: u32 = 0;
+const COLUMN_TEXT: u32 = 1;
+const COLUMN_PATH: u32 = 2;
+// Zero-based, -1 for file rows
+const COLUMN_LINE: u32 = 3;
+const COLUMN_COLUMN: u32 = 4;
+
+/**
+ * Adds the Problems page to the bottom panel.
+ */
+pub fn init() {
+    let store = TreeStore::new(&[
+        Type::STRING,
+        Type::STRING,
+        Type::STRING,
+        Type::I32,
+        Type::I32,
+    ]);
+
+    let tree_view = TreeView::with_model(&store);
+    tree_view.set_headers_visible(false);
+
+    let column = TreeViewColumn::new();
+    let cell_icon = CellRendererPixbuf::new();
+    let cell_text = CellRendererText::new();
+    column.pack_start(&cell_icon, false);
+    column.pack_start(&cell_text, true);
+    column.add_attribute(&cell_icon, "icon-name", COLUMN_ICON as i32);
+    column.add_attribute(&cell_text, "text", COLUMN_TEXT as i32);
+    tree_view.append_column(&column);
+
+    tree_view.connect_row_activated(|tree_view, path, _column| {
+        let Some(model) = tree_view.model() else {
+            return;
+        };
+        let Some(iter) = model.iter(path) else {
+            return;
+        };
+
+        let line = model.value(&iter, COLUMN_LINE as i32).get::<i32>().unwrap();
+        if line < 0 {
+            // File row
+            if tree_view.row_expanded(path) {
+                tree_view.collapse_row(path);
+            } else {
+                tree_view.expand_row(path, false);
+            }
+            return;
+        }
+
+        let file_path = model
+            .value(&iter, COLUMN_PATH as i32)
+            .get::<String>()
+            .unwrap();
+        let column = model
+            .value(&iter, COLUMN_COLUMN as i32)
+            .get::<i32>()
+            .unwrap();
+        open_editor_for_abs_path(file_path, line + 1, column + 1);
+    });
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.add(&tree_view);
+    bottom_panel::add_page(&scrolled_window, "Problems");
+
+    G_PROBLEMS_PAGE.with(|page| *page.borrow_mut() = Some((scrolled_window, tree_view, store)));
+
+    refresh_list();
+}
+
+/**
+ * Shows the Problems panel, or hides it when it is already showing.
+ */
+pub fn toggle() {
+    if let Some((page, _, _)) = G_PROBLEMS_PAGE.with(|page| page.borrow().clone()) {
+        bottom_panel::toggle_page(&page);
+    }
+}
+
+/**
+ * Replaces diagnostics of a file reported by `owner`, ex: `lsp`.
+ */
+pub fn set(owner: &str, file_path: &str, diagnostics: Vec<Diagnostic>) {
+    G_DIAGNOSTICS.with(|d| d.borrow_mut().set(owner, file_path, diagnostics));
+
+    editor_diagnostics::refresh(file_path);
+    refresh_list();
+}
+
+/**
+ * Drops all diagnostics reported by `owner`.
+ */
+pub fn clear_owner(owner: &str) {
+    G_DIAGNOSTICS.with(|d| d.borrow_mut().clear_owner(owner));
+
+    for tab in NotebookTabCache::all() {
+        editor_diagnostics::refresh(&tab.file_path);
+    }
+    refresh_list();
+}
+
+/// Returns diagnostics of a file from all owners, in file order.
+pub fn for_path(file_path: &str) -> Vec<Diagnostic> {
+    G_DIAGNOSTICS.with(|d| {
+        d.borrow()
+            .for_path(file_path)
+            .into_iter()
+            .cloned()
+            .collect()
+    })
+}
+
+fn refresh_list() {
+    let counts = G_DIAGNOSTICS.with(|d| d.borrow().counts());
+    diagnostics_indicator::update(&counts);
+
+    let Some((_, tree_view, store)) = G_PROBLEMS_PAGE.with(|page| page.borrow().clone()) else {
+        return;
+    };
+    store.clear();
+
+    let workspace_path = Workspace::get_path();
+    G_DIAGNOSTICS.with(|d| {
+        for (file_path, diagnostics) in d.borrow().by_file() {
+            let relative_path = Path::new(file_path)
+                .strip_prefix(&workspace_path)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| file_path.to_string());
+
+            let file_row = store.insert_with_values(
+                None,
+                None,
+                &[
+                    (COLUMN_ICON, &"text-x-generic-symbolic"),
+                    (
+                        COLUMN_TEXT,
+                        &format!("{relative_path} ({})", diagnostics.len()),
+                    ),
+                    (COLUMN_PATH, &file_path),
+                    (COLUMN_LINE, &-1),
+                    (COLUMN_COLUMN, &-1),
+                ],
+            );
+
+            for diagnostic in diagnostics {
+                let start = diagnostic.range.start;
+
+                store.insert_with_values(
+                    Some(&file_row),
+                    None,
+                    &[
+                        (COLUMN_ICON, &icon_name(diagnostic.severity)),
+                        (
+                            COLUMN_TEXT,
+                            &format!(
+                                "{} [{}:{}]",
+                                diagnostic.display_message(),
+                                start.line + 1,
+                                start.column + 1
+                            ),
+                        ),
+                        (COLUMN_PATH, &file_path),
+                        (COLUMN_LINE, &(start.line as i32)),
+                        (COLUMN_COLUMN, &(start.column as i32)),
+                    ],
+                );
+            }
+        }
+    });
+
+    tree_view.expand_all();
+}
+
+/**
+ * Returns the icon of a severity, shown in the list and the editor gutter.
+ */
+pub fn icon_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "dialog-error-symbolic",
+        Severity::Warning => "dialog-warning-symbolic",
+        Severity::Info | Severity::Hint => "dialog-information-symbolic",
+    }
+}
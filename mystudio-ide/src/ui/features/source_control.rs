@@ -0,0 +1,1024 @@
+// Source Control panel of the side panel: staged and unstaged changes of the
+// workspace repository, staged by file or hunk, commits and local branches.
+// Git commands run in order on a background thread, see `run_git`.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use gtk::{
+    gdk::{keys::constants as key_constants, ModifierType},
+    glib::{self, Type},
+    pango::EllipsizeMode,
+    prelude::{
+        Cast, DialogExt, DialogExtManual, EntryExt, TreeModelExt, TreeStoreExtManual, TreeViewExt,
+    },
+    traits::{
+        BoxExt, ButtonExt, CellLayoutExt, CellRendererTextExt, ComboBoxExt, ComboBoxTextExt,
+        ContainerExt, GtkWindowExt, LabelExt, MessageDialogExt, TextBufferExt, TextViewExt,
+        ToggleButtonExt, TreeSelectionExt, TreeStoreExt, WidgetExt,
+    },
+    Adjustment, Button, ButtonsType, CellRendererText, CheckButton, ComboBoxText, Dialog,
+    DialogFlags, Entry, IconSize, Label, MessageDialog, MessageType, Orientation, ReliefStyle,
+    ResponseType, ScrolledWindow, TreeIter, TreePath, TreeStore, TreeView, TreeViewColumn,
+};
+use libmystudio::{
+    diff::split_lines,
+    format::minimal_edits,
+    fs::read_file_contents,
+    git::{
+        changes::{line_changes, revert_hunk, stage_hunk, unstage_hunk, ChangeKind, LineChange},
+        error::GitError,
+        head_content, index_content,
+        repository::{
+            branches, commit, create_branch, discard_changes, last_commit_message, stage_file,
+            switch_branch, unstage_file,
+        },
+        status::{FileChange, FileStatus, GitStatus},
+    },
+    notebook::cache::NotebookTabCache,
+    tree::{tree_cell::get_git_status_markup, tree_model::TreeNodeType},
+    workspace::Workspace,
+};
+use sourceview4::{traits::ViewExt, View};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{
+        self,
+        notebook::{
+            editor::{open_editor_for_abs_path, Editor},
+            format::apply_edits,
+        },
+        side_panel,
+        statusbar::message::show_message,
+        w_explorer,
+    },
+};
+
+// Length of the first line of commit messages shown by the ruler
+const SUBJECT_LENGTH: u32 = 50;
+
+// Columns of the store
+const COLUMN_NAME: u32 = 0;
+const COLUMN_PATH: u32 = 1;
+const COLUMN_KIND: u32 = 2;
+const COLUMN_STAGED: u32 = 3;
+// Index of the hunk of hunk rows among the changes of their file
+const COLUMN_HUNK: u32 = 4;
+
+// Kinds of rows
+const KIND_SECTION: i32 = 0;
+const KIND_FILE: i32 = 1;
+const KIND_HUNK: i32 = 2;
+// Child of modified files until their hunks are listed, once expanded
+const KIND_PLACEHOLDER: i32 = 3;
+
+#[derive(Clone)]
+struct SourceControlPage {
+    page: gtk::Box,
+    label: Label,
+    branch_combo: ComboBoxText,
+    message_view: View,
+    subject_label: Label,
+    amend_button: CheckButton,
+    tree_view: TreeView,
+    store: TreeStore,
+}
+
+// A row of the tree, `path` is empty for sections
+struct Row {
+    kind: i32,
+    path: PathBuf,
+    staged: bool,
+    hunk: usize,
+}
+
+/// Output of git commands run by the panel, see `run_git`.
+pub enum GitOutput {
+    Status(Result<GitStatus, GitError>),
+    Branches(Vec<String>),
+    // Base and changed texts of a file, see `compared_texts`
+    Texts(Result<(String, String), GitError>),
+    Message(Result<String, GitError>),
+    Done(Result<(), GitError>),
+}
+
+type GitCommand = Box<dyn FnOnce() -> GitOutput + Send>;
+type OutputCallback = Box<dyn FnOnce(GitOutput)>;
+
+thread_local! { static G_SOURCE_CONTROL_PAGE: RefCell<Option<SourceControlPage>> = RefCell::new(None) }
+// Set while branches are listed, changes of the selected branch are ignored
+thread_local! { static G_UPDATING_BRANCHES: Cell<bool> = Cell::new(false) }
+// Queue of the thread running git commands, started by the first one
+thread_local! { static G_GIT_COMMANDS: RefCell<Option<mpsc::Sender<(u64, GitCommand)>>> = RefCell::new(None) }
+// Commands waiting for their output, by command id
+thread_local! { static G_PENDING_COMMANDS: RefCell<HashMap<u64, OutputCallback>> = RefCell::new(HashMap::new()) }
+thread_local! { static G_NEXT_COMMAND_ID: Cell<u64> = Cell::new(1) }
+
+/**
+ * Shows the Source Control panel, or goes back to the workspace explorer
+ * when it is already showing.
+ */
+pub fn toggle() {
+    let page = source_control_page();
+    side_panel::toggle_page(&page.page);
+}
+
+/**
+ * Lists changes of the git status currently shown by the UI, see
+ * `GitStatus::current`.
+ */
+pub fn status_changed() {
+    if let Some(page) = G_SOURCE_CONTROL_PAGE.with(|page| page.borrow().clone()) {
+        rebuild(&page);
+    }
+}
+
+/**
+ * Passes the output of a command run by `run_git` to its callback.
+ */
+pub fn handle_output(id: u64, output: GitOutput) {
+    let on_output = G_PENDING_COMMANDS.with(|commands| commands.borrow_mut().remove(&id));
+    if let Some(on_output) = on_output {
+        on_output(output);
+    }
+}
+
+/**
+ * Runs git commands on a background thread, after those run before, and
+ * calls `on_output` with their output on the UI thread.
+ */
+fn run_git<C, F>(command: C, on_output: F)
+where
+    C: FnOnce() -> GitOutput + Send + 'static,
+    F: FnOnce(GitOutput) + 'static,
+{
+    let id = G_NEXT_COMMAND_ID.with(|next_id| next_id.replace(next_id.get() + 1));
+    G_PENDING_COMMANDS.with(|commands| commands.borrow_mut().insert(id, Box::new(on_output)));
+
+    G_GIT_COMMANDS.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let queue = queue.get_or_insert_with(|| {
+            let (queue, commands) = mpsc::channel::<(u64, GitCommand)>();
+            let tx = Comms::sender();
+            thread::spawn(move || {
+                for (id, command) in commands {
+                    tx.send(CommEvents::SourceControlOutput(id, command())).ok();
+                }
+            });
+            queue
+        });
+        let command: GitCommand = Box::new(command);
+        queue.send((id, command)).ok();
+    });
+}
+
+// Reads git status of the workspace again, hunks of files are listed again
+// even when their status is the same
+fn refresh() {
+    let root = Workspace::get_path();
+
+    run_git(
+        move || GitOutput::Status(GitStatus::load(Path::new(&root))),
+        |output| match output {
+            GitOutput::Status(Ok(status)) => w_explorer::git_status_changed(status),
+            GitOutput::Status(Err(error)) => {
+                show_message(format!("Unable to read git status: {error}"))
+            }
+            _ => {}
+        },
+    );
+}
+
+// Shows the error of a failed command and reads git status again
+fn refresh_after(action: &'static str) -> impl FnOnce(GitOutput) {
+    move |output| {
+        if let GitOutput::Done(Err(error)) = output {
+            show_message(format!("Unable to {action}: {error}"));
+        }
+        refresh();
+    }
+}
+
+// Stages the selected file or hunk, or all changes when the "Changes" section
+// is selected
+fn stage_selected() {
+    let Some(row) = selected_row(&source_control_page()) else {
+        show_message(String::from("Select a file or change to stage"));
+        return;
+    };
+    if row.staged {
+        show_message(String::from("Already staged"));
+        return;
+    }
+
+    let changes = unstaged_changes(None);
+    let stage = move || {
+        GitOutput::Done(match row.kind {
+            KIND_SECTION => changes
+                .iter()
+                .try_for_each(|change| stage_file(&change.path)),
+            KIND_HUNK => compared_texts(&row.path, false).and_then(|(index, text)| {
+                let change = nth_change(&index, &text, row.hunk)?;
+                stage_hunk(&row.path, &index, &text, &change.hunk)
+            }),
+            _ => stage_file(&row.path),
+        })
+    };
+
+    run_git(stage, refresh_after("stage"));
+}
+
+// Unstages the selected file or hunk, or all staged changes when the "Staged
+// Changes" section is selected. The working tree is kept.
+fn unstage_selected() {
+    let Some(row) = selected_row(&source_control_page()) else {
+        show_message(String::from("Select a staged file or change to unstage"));
+        return;
+    };
+    if !row.staged {
+        show_message(String::from("Not staged"));
+        return;
+    }
+
+    let changes: Vec<FileChange> = GitStatus::current()
+        .changes()
+        .iter()
+        .filter(|change| change.staged.is_some())
+        .cloned()
+        .collect();
+    let unstage = move || {
+        GitOutput::Done(match row.kind {
+            KIND_SECTION => changes
+                .iter()
+                .try_for_each(|change| unstage_file(&change.path)),
+            KIND_HUNK => compared_texts(&row.path, true).and_then(|(head, index)| {
+                let change = nth_change(&head, &index, row.hunk)?;
+                unstage_hunk(&row.path, &head, &index, &change.hunk)
+            }),
+            _ => unstage_file(&row.path),
+        })
+    };
+
+    run_git(unstage, refresh_after("unstage"));
+}
+
+// Throws away unstaged changes of the selected file or hunk, or all of them
+// when the "Changes" section is selected, once confirmed
+fn discard_selected() {
+    let page = source_control_page();
+    let Some(row) = selected_row(&page) else {
+        show_message(String::from("Select a file or change to discard"));
+        return;
+    };
+    if row.staged {
+        show_message(String::from("Unstage changes before discarding them"));
+        return;
+    }
+
+    let changes = match row.kind {
+        KIND_SECTION => unstaged_changes(None),
+        _ => unstaged_changes(Some(&row.path)),
+    };
+    let file_name = row
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let title = match row.kind {
+        KIND_SECTION if changes.len() == 1 => String::from("Discard changes of 1 file?"),
+        KIND_SECTION => format!("Discard changes of {} files?", changes.len()),
+        KIND_HUNK => format!("Discard this change of '{file_name}'?"),
+        _ => format!("Discard changes of '{file_name}'?"),
+    };
+    if changes.is_empty() || !confirm(&title) {
+        return;
+    }
+
+    let unmodified = unmodified_open_files();
+    let discard = move || {
+        GitOutput::Done(match row.kind {
+            KIND_HUNK => discard_hunk(&row.path, row.hunk),
+            _ => changes.iter().try_for_each(discard_changes),
+        })
+    };
+
+    run_git(discard, move |output| {
+        if let GitOutput::Done(Ok(_)) = output {
+            reload_open_files(&unmodified);
+        }
+        refresh_after("discard changes")(output);
+    });
+}
+
+// Commits staged changes with the message of the panel, or amends the last
+// commit with them when "Amend" is checked
+fn commit_changes() {
+    let page = source_control_page();
+    let status = GitStatus::current();
+    let Some(root) = status.root.clone() else {
+        show_message(String::from("Open a git repository to commit"));
+        return;
+    };
+
+    let message = message_text(&page);
+    let amend = page.amend_button.is_active();
+    if message.trim().is_empty() {
+        show_message(String::from("Write a commit message first"));
+        return;
+    }
+    if !amend
+        && status
+            .changes()
+            .iter()
+            .all(|change| change.staged.is_none())
+    {
+        show_message(String::from("No staged changes to commit"));
+        return;
+    }
+
+    let committed_message = message.clone();
+    let commit_staged = move || GitOutput::Done(commit(&root, &committed_message, amend));
+
+    run_git(commit_staged, move |output| {
+        if let GitOutput::Done(Ok(_)) = output {
+            // Keeps a message written meanwhile
+            if message_text(&page) == message {
+                if let Some(buffer) = page.message_view.buffer() {
+                    buffer.set_text("");
+                }
+            }
+            page.amend_button.set_active(false);
+            show_message(String::from(if amend {
+                "Amended the last commit"
+            } else {
+                "Committed staged changes"
+            }));
+        }
+        refresh_after("commit")(output);
+    });
+}
+
+// Asks for the name of a new branch, creates it at HEAD and switches to it
+fn new_branch() {
+    let Some(root) = GitStatus::current().root.clone() else {
+        show_message(String::from("Open a git repository to create branches"));
+        return;
+    };
+    let Some(name) = ask_branch_name() else {
+        return;
+    };
+
+    let branch_name = name.clone();
+    let create = move || GitOutput::Done(create_branch(&root, &branch_name));
+
+    run_git(create, move |output| {
+        if let GitOutput::Done(Ok(_)) = output {
+            show_message(format!("Switched to new branch '{name}'"));
+        }
+        refresh_after("create branch")(output);
+    });
+}
+
+fn switch_to_branch(name: &str) {
+    let status = GitStatus::current();
+    let Some(root) = status.root.clone() else {
+        return;
+    };
+    if status.branch.as_deref() == Some(name) {
+        return;
+    }
+
+    let unmodified = unmodified_open_files();
+    let name = name.to_string();
+    let branch_name = name.clone();
+    let switch = move || GitOutput::Done(switch_branch(&root, &branch_name));
+
+    run_git(switch, move |output| {
+        if let GitOutput::Done(Ok(_)) = output {
+            reload_open_files(&unmodified);
+            show_message(format!("Switched to branch '{name}'"));
+        }
+        // Selects the current branch again when switching failed
+        refresh_after("switch branch")(output);
+    });
+}
+
+// Unstaged changes of the repository, or of the file at `path`
+fn unstaged_changes(path: Option<&Path>) -> Vec<FileChange> {
+    GitStatus::current()
+        .changes()
+        .iter()
+        .filter(|change| change.unstaged.is_some())
+        .filter(|change| path.map_or(true, |path| change.path == path))
+        .cloned()
+        .collect()
+}
+
+// Texts compared for hunks of the file at `path`: the index against HEAD for
+// staged changes, the working tree against the index otherwise
+fn compared_texts(path: &Path, staged: bool) -> Result<(String, String), GitError> {
+    let index = index_content(path)?.unwrap_or_default();
+
+    if staged {
+        Ok((head_content(path)?.unwrap_or_default(), index))
+    } else {
+        Ok((index, fs::read_to_string(path)?))
+    }
+}
+
+fn nth_change(base: &str, text: &str, hunk: usize) -> Result<LineChange, GitError> {
+    line_changes(base, text)
+        .get(hunk)
+        .copied()
+        .ok_or_else(|| GitError::Failed(String::from("the file changed, refresh to try again")))
+}
+
+// Turns the lines of a hunk of the working tree back into those of the index
+fn discard_hunk(path: &Path, hunk: usize) -> Result<(), GitError> {
+    let (index, text) = compared_texts(path, false)?;
+    let change = nth_change(&index, &text, hunk)?;
+    let reverted = revert_hunk(&index, &text, &change.hunk);
+
+    fs::write(path, reverted).map_err(GitError::from)
+}
+
+// Open files whose editor has the text of the file on disk, they are reloaded
+// when the working tree changes
+fn unmodified_open_files() -> Vec<String> {
+    NotebookTabCache::all()
+        .into_iter()
+        .map(|tab| tab.file_path)
+        .filter(|file_path| {
+            let text = editor_text(file_path);
+            text.is_some() && text == read_file_contents(file_path)
+        })
+        .collect()
+}
+
+fn reload_open_files(file_paths: &[String]) {
+    for file_path in file_paths {
+        let (Some(text), Some(content)) = (editor_text(file_path), read_file_contents(file_path))
+        else {
+            continue;
+        };
+        if let Some(buffer) = Editor::buffer_from_path(file_path.clone()) {
+            apply_edits(&buffer, &minimal_edits(&text, &content));
+        }
+    }
+}
+
+fn editor_text(file_path: &str) -> Option<String> {
+    NotebookTabCache::find_by_path(file_path.to_string())?;
+    let buffer = Editor::buffer_from_path(file_path.to_string())?;
+
+    Some(Editor::buffer_text(&buffer))
+}
+
+fn message_text(page: &SourceControlPage) -> String {
+    page.message_view
+        .buffer()
+        .and_then(|buffer| buffer.text(&buffer.start_iter(), &buffer.end_iter(), true))
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
+
+// Shows the length of the subject, in red when longer than the ruler
+fn update_subject_length(page: &SourceControlPage) {
+    let message = message_text(page);
+    let length = message.lines().next().unwrap_or_default().chars().count();
+
+    let text = format!("Subject: {length}/{SUBJECT_LENGTH}");
+    if length > SUBJECT_LENGTH as usize {
+        page.subject_label
+            .set_markup(&format!("<span foreground=\"#cc0000\">{text}</span>"));
+    } else {
+        page.subject_label.set_text(&text);
+    }
+}
+
+fn rebuild(page: &SourceControlPage) {
+    let status = GitStatus::current();
+    let expanded = expanded_files(page);
+
+    page.store.clear();
+    update_branches(page, &status);
+
+    let Some(root) = status.root.as_deref() else {
+        page.label.set_text("No git repository");
+        return;
+    };
+    page.label.set_text(&match status.changes().len() {
+        0 => String::from("No changes"),
+        1 => String::from("1 changed file"),
+        count => format!("{count} changed files"),
+    });
+
+    for staged in [true, false] {
+        let files: Vec<(&FileChange, FileStatus)> = status
+            .changes()
+            .iter()
+            .filter_map(|change| {
+                let file_status = if staged {
+                    change.staged
+                } else {
+                    change.unstaged
+                };
+                file_status.map(|file_status| (change, file_status))
+            })
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+
+        let title = if staged { "Staged Changes" } else { "Changes" };
+        let section = insert_row(
+            &page.store,
+            None,
+            &format!("<b>{title}</b> ({})", files.len()),
+            Path::new(""),
+            KIND_SECTION,
+            staged,
+            0,
+        );
+
+        for (change, file_status) in files {
+            let name = change.path.strip_prefix(root).unwrap_or(&change.path);
+            let markup = get_git_status_markup(
+                &name.to_string_lossy(),
+                TreeNodeType::File,
+                Some(file_status).into(),
+            );
+            let row = insert_row(
+                &page.store,
+                Some(&section),
+                &markup,
+                &change.path,
+                KIND_FILE,
+                staged,
+                0,
+            );
+
+            // Added or deleted files are a single change
+            if file_status == FileStatus::Modified {
+                insert_row(
+                    &page.store,
+                    Some(&row),
+                    "",
+                    &change.path,
+                    KIND_PLACEHOLDER,
+                    staged,
+                    0,
+                );
+            }
+        }
+
+        if let Some(tree_path) = page.store.path(&section) {
+            page.tree_view.expand_row(&tree_path, false);
+        }
+    }
+
+    // Hunks of files expanded before are listed again
+    let mut tree_paths = vec![];
+    page.store.foreach(|model, tree_path, iter| {
+        let row = row_at(model, iter);
+        if row.kind == KIND_FILE && expanded.contains(&(row.path, row.staged)) {
+            tree_paths.push(tree_path.clone());
+        }
+        false
+    });
+    for tree_path in tree_paths {
+        page.tree_view.expand_row(&tree_path, false);
+    }
+}
+
+// Files expanded to show their hunks, with the section they are listed in
+fn expanded_files(page: &SourceControlPage) -> Vec<(PathBuf, bool)> {
+    let mut files = vec![];
+    page.store.foreach(|model, tree_path, iter| {
+        let row = row_at(model, iter);
+        if row.kind == KIND_FILE && page.tree_view.row_expanded(tree_path) {
+            files.push((row.path, row.staged));
+        }
+        false
+    });
+    files
+}
+
+// Lists local branches once read, the current ones are kept until then
+fn update_branches(page: &SourceControlPage, status: &GitStatus) {
+    page.branch_combo.set_sensitive(status.root.is_some());
+    let Some(root) = status.root.clone() else {
+        set_branches(page, &[], None);
+        return;
+    };
+
+    run_git(
+        move || GitOutput::Branches(branches(&root).unwrap_or_default()),
+        |output| {
+            let GitOutput::Branches(names) = output else {
+                return;
+            };
+            // The repository was closed meanwhile
+            let status = GitStatus::current();
+            if status.root.is_some() {
+                set_branches(&source_control_page(), &names, status.branch.as_deref());
+            }
+        },
+    );
+}
+
+// Nothing is selected when HEAD is detached
+fn set_branches(page: &SourceControlPage, names: &[String], current: Option<&str>) {
+    G_UPDATING_BRANCHES.with(|updating| updating.set(true));
+
+    page.branch_combo.remove_all();
+    for name in names {
+        page.branch_combo.append(Some(name), name);
+    }
+    page.branch_combo.set_active_id(current);
+
+    G_UPDATING_BRANCHES.with(|updating| updating.set(false));
+}
+
+// Lists hunks of an expanded file in place of its placeholder, once texts of
+// the file are read
+fn load_hunks(page: &SourceControlPage, iter: &TreeIter) {
+    if !has_placeholder(page, iter) {
+        return;
+    }
+    let Some(tree_path) = page.store.path(iter) else {
+        return;
+    };
+    let row = row_at(page.store.upcast_ref(), iter);
+    let (path, staged) = (row.path.clone(), row.staged);
+
+    run_git(
+        move || GitOutput::Texts(compared_texts(&path, staged)),
+        move |output| {
+            let GitOutput::Texts(texts) = output else {
+                return;
+            };
+            let page = source_control_page();
+            // The file isn't listed there anymore, ex: after a refresh
+            let Some(iter) = page.store.iter(&tree_path) else {
+                return;
+            };
+            let current = row_at(page.store.upcast_ref(), &iter);
+            if current.kind != KIND_FILE || current.path != row.path || current.staged != staged {
+                return;
+            }
+
+            let (base, text) = texts.unwrap_or_default();
+            show_hunks(&page, &iter, &row, &base, &text);
+        },
+    );
+}
+
+// Hunks of files are listed once, on first expansion
+fn has_placeholder(page: &SourceControlPage, iter: &TreeIter) -> bool {
+    page.store
+        .iter_children(Some(iter))
+        .is_some_and(|child| row_at(page.store.upcast_ref(), &child).kind == KIND_PLACEHOLDER)
+}
+
+fn show_hunks(page: &SourceControlPage, iter: &TreeIter, row: &Row, base: &str, text: &str) {
+    let Some(placeholder) = page.store.iter_children(Some(iter)) else {
+        return;
+    };
+    if row_at(page.store.upcast_ref(), &placeholder).kind != KIND_PLACEHOLDER {
+        return;
+    }
+
+    let (base_lines, lines) = (split_lines(base), split_lines(text));
+    for (index, change) in line_changes(base, text).iter().enumerate() {
+        let hunk = &change.hunk;
+        let first_line = match change.kind {
+            ChangeKind::Deleted => base_lines.get(hunk.old_start),
+            _ => lines.get(hunk.new_start),
+        };
+        // Same header as `git diff`, line numbers are one-based
+        let markup = format!(
+            "<tt>@@ -{},{} +{},{} @@</tt> <span alpha=\"60%\">{}</span>",
+            hunk.old_start + usize::from(hunk.old_len > 0),
+            hunk.old_len,
+            hunk.new_start + usize::from(hunk.new_len > 0),
+            hunk.new_len,
+            glib::markup_escape_text(first_line.map_or("", |line| line.trim())),
+        );
+        insert_row(
+            &page.store,
+            Some(iter),
+            &markup,
+            &row.path,
+            KIND_HUNK,
+            row.staged,
+            index,
+        );
+    }
+
+    page.store.remove(&placeholder);
+}
+
+fn insert_row(
+    store: &TreeStore,
+    parent: Option<&TreeIter>,
+    markup: &str,
+    path: &Path,
+    kind: i32,
+    staged: bool,
+    hunk: usize,
+) -> TreeIter {
+    store.insert_with_values(
+        parent,
+        None,
+        &[
+            (COLUMN_NAME, &markup),
+            (COLUMN_PATH, &path.to_string_lossy().to_string()),
+            (COLUMN_KIND, &kind),
+            (COLUMN_STAGED, &staged),
+            (COLUMN_HUNK, &(hunk as i32)),
+        ],
+    )
+}
+
+fn row_at(model: &gtk::TreeModel, iter: &TreeIter) -> Row {
+    let value = |column: u32| model.value(iter, column as i32);
+
+    Row {
+        kind: value(COLUMN_KIND).get::<i32>().unwrap_or(KIND_SECTION),
+        path: PathBuf::from(value(COLUMN_PATH).get::<String>().unwrap_or_default()),
+        staged: value(COLUMN_STAGED).get::<bool>().unwrap_or_default(),
+        hunk: value(COLUMN_HUNK).get::<i32>().unwrap_or_default() as usize,
+    }
+}
+
+fn selected_row(page: &SourceControlPage) -> Option<Row> {
+    let (model, iter) = page.tree_view.selection().selected()?;
+    Some(row_at(&model, &iter))
+}
+
+// Opens the file of an activated row, at the first line of hunks
+fn open_row(page: &SourceControlPage, tree_path: &TreePath) {
+    let Some(iter) = page.store.iter(tree_path) else {
+        return;
+    };
+    let row = row_at(page.store.upcast_ref(), &iter);
+    if row.kind == KIND_SECTION || !row.path.is_file() {
+        return;
+    }
+
+    let file_path = row.path.to_string_lossy().to_string();
+    if row.kind != KIND_HUNK {
+        open_editor_for_abs_path(file_path, 1, 1);
+        return;
+    }
+
+    let (path, staged) = (row.path.clone(), row.staged);
+    run_git(
+        move || GitOutput::Texts(compared_texts(&path, staged)),
+        move |output| {
+            let GitOutput::Texts(texts) = output else {
+                return;
+            };
+            let line = texts
+                .and_then(|(base, text)| nth_change(&base, &text, row.hunk))
+                .map_or(1, |change| change.hunk.new_start as i32 + 1);
+            open_editor_for_abs_path(file_path, line, 1);
+        },
+    );
+}
+
+fn confirm(title: &str) -> bool {
+    let dialog = MessageDialog::new(
+        Some(&ui::main_window()),
+        DialogFlags::MODAL | DialogFlags::DESTROY_WITH_PARENT,
+        MessageType::Warning,
+        ButtonsType::None,
+        title,
+    );
+    dialog.set_secondary_text(Some(
+        "Changes which aren't staged are lost, untracked files are deleted.",
+    ));
+    dialog.add_buttons(&[
+        ("Cancel", ResponseType::Cancel),
+        ("Discard", ResponseType::Accept),
+    ]);
+
+    let response = dialog.run();
+    dialog.close();
+    response == ResponseType::Accept
+}
+
+fn ask_branch_name() -> Option<String> {
+    let dialog = Dialog::builder()
+        .title("New Branch")
+        .transient_for(&ui::main_window())
+        .modal(true)
+        .destroy_with_parent(true)
+        .build();
+    dialog.add_buttons(&[
+        ("Cancel", ResponseType::Cancel),
+        ("Create", ResponseType::Accept),
+    ]);
+    dialog.set_default_response(ResponseType::Accept);
+
+    let entry = Entry::new();
+    entry.set_placeholder_text(Some("Branch name, ex: fix-typo"));
+    entry.set_activates_default(true);
+    entry.set_margin(6);
+    dialog.content_area().pack_start(&entry, false, true, 0);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let name = entry.text().trim().to_string();
+    dialog.close();
+
+    (response == ResponseType::Accept && !name.is_empty()).then_some(name)
+}
+
+fn tool_button(icon_name: &str, tooltip: &str, on_clicked: fn()) -> Button {
+    let button = Button::from_icon_name(Some(icon_name), IconSize::Menu);
+    button.set_relief(ReliefStyle::None);
+    button.set_tooltip_text(Some(tooltip));
+    button.connect_clicked(move |_| on_clicked());
+    button
+}
+
+// Page of the side panel with changes and the commit message, added on first
+// use
+fn source_control_page() -> SourceControlPage {
+    if let Some(page) = G_SOURCE_CONTROL_PAGE.with(|page| page.borrow().clone()) {
+        return page;
+    }
+
+    let store = TreeStore::new(&[Type::STRING, Type::STRING, Type::I32, Type::BOOL, Type::I32]);
+    let tree_view = TreeView::with_model(&store);
+    tree_view.set_headers_visible(false);
+
+    let column = TreeViewColumn::new();
+    let cell_text = CellRendererText::new();
+    cell_text.set_ellipsize(EllipsizeMode::Middle);
+    column.pack_start(&cell_text, true);
+    column.add_attribute(&cell_text, "markup", COLUMN_NAME as i32);
+    tree_view.append_column(&column);
+
+    let tree_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    tree_window.set_vexpand(true);
+    tree_window.add(&tree_view);
+
+    let label = Label::new(None);
+    label.set_xalign(0.0);
+    label.set_margin(4);
+    label.set_ellipsize(EllipsizeMode::End);
+
+    let toolbar = gtk::Box::new(Orientation::Horizontal, 0);
+    toolbar.pack_start(&label, true, true, 0);
+    toolbar.pack_end(
+        &tool_button("edit-undo-symbolic", "Discard Changes", discard_selected),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button("list-remove-symbolic", "Unstage", unstage_selected),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button("list-add-symbolic", "Stage", stage_selected),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button("view-refresh-symbolic", "Refresh", refresh),
+        false,
+        false,
+        0,
+    );
+
+    let branch_combo = ComboBoxText::new();
+    branch_combo.set_tooltip_text(Some("Switch Branch"));
+    let branch_box = gtk::Box::new(Orientation::Horizontal, 0);
+    branch_box.set_margin(4);
+    branch_box.pack_start(&branch_combo, true, true, 0);
+    branch_box.pack_end(
+        &tool_button("document-new-symbolic", "New Branch", new_branch),
+        false,
+        false,
+        0,
+    );
+
+    let message_view = View::new();
+    message_view.set_monospace(true);
+    message_view.set_show_right_margin(true);
+    message_view.set_right_margin_position(SUBJECT_LENGTH);
+    message_view.set_left_margin(4);
+    message_view.set_tooltip_text(Some("Commit message, Ctrl+Enter to commit"));
+
+    let message_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    message_window.set_min_content_height(80);
+    message_window.set_margin_start(4);
+    message_window.set_margin_end(4);
+    message_window.add(&message_view);
+
+    let subject_label = Label::new(None);
+    subject_label.set_xalign(0.0);
+    let amend_button = CheckButton::with_label("Amend");
+    amend_button.set_tooltip_text(Some("Replace the last commit"));
+    let commit_button = Button::with_label("Commit");
+    commit_button.connect_clicked(|_| commit_changes());
+
+    let commit_box = gtk::Box::new(Orientation::Horizontal, 6);
+    commit_box.set_margin(4);
+    commit_box.pack_start(&subject_label, true, true, 0);
+    commit_box.pack_end(&commit_button, false, false, 0);
+    commit_box.pack_end(&amend_button, false, false, 0);
+
+    let page = gtk::Box::new(Orientation::Vertical, 0);
+    page.pack_start(&branch_box, false, false, 0);
+    page.pack_start(&message_window, false, false, 0);
+    page.pack_start(&commit_box, false, false, 0);
+    page.pack_start(&toolbar, false, false, 0);
+    page.pack_start(&tree_window, true, true, 0);
+    side_panel::add_page(&page, "Source Control");
+
+    let page = SourceControlPage {
+        page,
+        label,
+        branch_combo,
+        message_view,
+        subject_label,
+        amend_button,
+        tree_view,
+        store,
+    };
+    G_SOURCE_CONTROL_PAGE.with(|source_control| *source_control.borrow_mut() = Some(page.clone()));
+
+    if let Some(buffer) = page.message_view.buffer() {
+        buffer.connect_changed(|_| update_subject_length(&source_control_page()));
+    }
+    page.message_view.connect_key_press_event(|_, event| {
+        let keyval = event.keyval();
+        let is_enter = keyval == key_constants::Return || keyval == key_constants::KP_Enter;
+        if !is_enter || !event.state().contains(ModifierType::CONTROL_MASK) {
+            return gtk::Inhibit(false);
+        }
+        commit_changes();
+        gtk::Inhibit(true)
+    });
+
+    // Amending starts from the message of the last commit
+    page.amend_button.connect_toggled(|button| {
+        let page = source_control_page();
+        let root = GitStatus::current().root.clone();
+        let (Some(root), Some(buffer)) = (root, page.message_view.buffer()) else {
+            return;
+        };
+        if !button.is_active() || !message_text(&page).trim().is_empty() {
+            return;
+        }
+
+        run_git(
+            move || GitOutput::Message(last_commit_message(&root)),
+            move |output| {
+                // Still amending and nothing was written meanwhile
+                if let GitOutput::Message(Ok(message)) = output {
+                    if page.amend_button.is_active() && message_text(&page).trim().is_empty() {
+                        buffer.set_text(&message);
+                    }
+                }
+            },
+        );
+    });
+
+    page.branch_combo.connect_changed(|combo| {
+        if G_UPDATING_BRANCHES.with(Cell::get) {
+            return;
+        }
+        if let Some(name) = combo.active_id() {
+            switch_to_branch(&name);
+        }
+    });
+
+    page.tree_view.connect_row_expanded(|_, iter, _| {
+        load_hunks(&source_control_page(), iter);
+    });
+    page.tree_view.connect_row_activated(|_, tree_path, _| {
+        open_row(&source_control_page(), tree_path);
+    });
+
+    update_subject_length(&page);
+    rebuild(&page);
+    page
+}
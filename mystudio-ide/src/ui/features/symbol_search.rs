@@ -0,0 +1,286 @@
+// Workspace symbol index and the dialog to search it, see `SymbolIndex`
+
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+    thread,
+};
+
+use gtk::{
+    gdk::keys::constants as key_constants,
+    prelude::{BuilderExtManual, DialogExt, EntryExt, SearchEntryExt},
+    traits::{
+        BoxExt, ContainerExt, GtkWindowExt, LabelExt, ListBoxExt, ListBoxRowExt, StyleContextExt,
+        WidgetExt,
+    },
+    Adjustment, ApplicationWindow, Box, Builder, Dialog, Label, ListBox, ListBoxRow,
+    ScrolledWindow, SearchEntry, SelectionMode,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    symbols::{cache_path, Symbol, SymbolIndex},
+    workspace::Workspace,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{self, notebook::editor::open_editor_for_abs_path},
+};
+
+use super::command_palette::highlight_positions;
+
+// Rows listed at most, the index can hold many thousands of symbols
+const MAX_RESULTS: usize = 100;
+
+thread_local! { static G_SYMBOL_INDEX: RefCell<Option<SymbolIndex>> = RefCell::new(None) }
+// Set while the index is refreshed on a background thread
+thread_local! { static G_REFRESHING: Cell<bool> = Cell::new(false) }
+
+pub fn init(builder: &Builder) {
+    let window: ApplicationWindow = builder
+        .object("main_window")
+        .expect("Unable to find main_window");
+
+    // Files may have changed while the user was in another application
+    window.connect_focus_in_event(|_, _| {
+        refresh_index();
+        gtk::Inhibit(false)
+    });
+}
+
+/**
+ * Runs `f` with the index of the workspace, which is built first if the
+ * background refresh has not finished yet.
+ */
+pub fn with_index<T>(f: impl FnOnce(&SymbolIndex) -> T) -> T {
+    G_SYMBOL_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        let index = index.get_or_insert_with(|| {
+            let root = Workspace::get_path();
+            let mut index = SymbolIndex::load(&cache_path(&root), &root).unwrap_or_default();
+            index.refresh(&root, &AppConfig::current().Search.exclude);
+            index
+        });
+
+        f(index)
+    })
+}
+
+/**
+ * Brings the index up to date with files on disk on a background thread,
+ * starting from the copy saved by the last session.
+ */
+pub fn refresh_index() {
+    let root = Workspace::get_path();
+    if root.is_empty() || G_REFRESHING.with(|refreshing| refreshing.replace(true)) {
+        return;
+    }
+
+    let current_index = G_SYMBOL_INDEX.with(|index| index.borrow().clone());
+    let exclude = AppConfig::current().Search.exclude.clone();
+    let tx = Comms::sender();
+
+    thread::spawn(move || {
+        let cache_path = cache_path(&root);
+        let mut index = current_index
+            .or_else(|| SymbolIndex::load(&cache_path, &root))
+            .unwrap_or_default();
+
+        if index.refresh(&root, &exclude) {
+            if let Err(error) = index.save(&cache_path, &root) {
+                eprintln!("Unable to save symbol index: {error}");
+            }
+        }
+
+        tx.send(CommEvents::SymbolIndexUpdated(root, index)).ok();
+    });
+}
+
+/**
+ * Replaces the index with the one refreshed by `refresh_index`.
+ */
+pub fn index_updated(root: String, index: SymbolIndex) {
+    G_REFRESHING.with(|refreshing| refreshing.set(false));
+
+    // Refreshed for a workspace which was closed since
+    if root != Workspace::get_path() {
+        refresh_index();
+        return;
+    }
+
+    G_SYMBOL_INDEX.with(|current| *current.borrow_mut() = Some(index));
+}
+
+/**
+ * Updates the index with a saved file, if it was built.
+ */
+pub fn document_saved(file_path: &str, text: &str) {
+    G_SYMBOL_INDEX.with(|index| {
+        if let Some(index) = index.borrow_mut().as_mut() {
+            index.update_file(file_path, text);
+        }
+    });
+}
+
+/**
+ * Drops symbols of the previous workspace and indexes the new one.
+ */
+pub fn workspace_changed() {
+    G_SYMBOL_INDEX.with(|index| index.borrow_mut().take());
+    refresh_index();
+}
+
+/**
+ * Shows a searchable list of definitions of the workspace.
+ *
+ * Symbols are filtered with a fuzzy match on their name, a leading keyword
+ * like `fn` or `struct` keeps symbols of its kind. `Enter` opens the selected one.
+ */
+pub fn show_dialog() {
+    if Workspace::get_path().is_empty() {
+        return;
+    }
+    refresh_index();
+
+    let window = ui::main_window();
+
+    let dialog = Dialog::builder()
+        .title("Go to Symbol in Workspace")
+        .transient_for(&window)
+        .modal(true)
+        .destroy_with_parent(true)
+        .default_width(640)
+        .default_height(420)
+        .build();
+
+    let input = SearchEntry::new();
+    input.set_placeholder_text(Some("Type a symbol name, ex: fn save_file"));
+    input.set_margin(6);
+
+    let listbox = ListBox::new();
+    listbox.set_selection_mode(SelectionMode::Browse);
+    listbox.set_placeholder(Some(&Label::new(Some("No matching symbols"))));
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.set_vexpand(true);
+    scrolled_window.add(&listbox);
+
+    let content_area = dialog.content_area();
+    content_area.pack_start(&input, false, true, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+
+    // Symbols in the order they are listed
+    let listed_symbols: Rc<RefCell<Vec<Symbol>>> = Rc::new(RefCell::new(vec![]));
+    update_list(&listbox, "", &listed_symbols);
+
+    let listbox_clone = listbox.clone();
+    let listed_symbols_clone = listed_symbols.clone();
+    input.connect_search_changed(move |input| {
+        update_list(&listbox_clone, &input.text(), &listed_symbols_clone);
+    });
+
+    let dialog_clone = dialog.clone();
+    let listbox_clone = listbox.clone();
+    let listed_symbols_clone = listed_symbols.clone();
+    input.connect_activate(move |_| {
+        if let Some(row) = listbox_clone.selected_row() {
+            open_listed_symbol(&dialog_clone, row.index(), &listed_symbols_clone);
+        }
+    });
+
+    let dialog_clone = dialog.clone();
+    listbox.connect_row_activated(move |_, row| {
+        open_listed_symbol(&dialog_clone, row.index(), &listed_symbols);
+    });
+
+    // Keep focus in the input while moving through the list
+    let listbox_clone = listbox.clone();
+    dialog.connect_key_press_event(move |dialog, event| {
+        let keyval = event.keyval();
+
+        if keyval == key_constants::Escape {
+            dialog.close();
+            return gtk::Inhibit(true);
+        }
+
+        let step = if keyval == key_constants::Down {
+            1
+        } else if keyval == key_constants::Up {
+            -1
+        } else {
+            return gtk::Inhibit(false);
+        };
+
+        let selected_index = listbox_clone.selected_row().map_or(0, |row| row.index());
+        if let Some(row) = listbox_clone.row_at_index(selected_index + step) {
+            listbox_clone.select_row(Some(&row));
+        }
+
+        gtk::Inhibit(true)
+    });
+
+    dialog.show_all();
+    input.grab_focus();
+}
+
+fn open_listed_symbol(dialog: &Dialog, index: i32, listed_symbols: &Rc<RefCell<Vec<Symbol>>>) {
+    let symbol = listed_symbols.borrow().get(index as usize).cloned();
+
+    dialog.close();
+
+    if let Some(symbol) = symbol {
+        open_editor_for_abs_path(
+            symbol.path,
+            symbol.line as i32 + 1,
+            symbol.column as i32 + 1,
+        );
+    }
+}
+
+fn update_list(listbox: &ListBox, query: &str, listed_symbols: &Rc<RefCell<Vec<Symbol>>>) {
+    for child in listbox.children() {
+        listbox.remove(&child);
+    }
+
+    let mut listed_symbols = listed_symbols.borrow_mut();
+    listed_symbols.clear();
+
+    let workspace_path = Workspace::get_path();
+    with_index(|index| {
+        for (symbol, fuzzy_match) in index.search(query, MAX_RESULTS) {
+            let row = ListBoxRow::new();
+            let row_box = Box::new(gtk::Orientation::Horizontal, 12);
+            row_box.set_margin(6);
+
+            let name = Label::new(None);
+            name.set_markup(&highlight_positions(&symbol.name, &fuzzy_match.positions));
+            name.set_xalign(0f32);
+            row_box.pack_start(&name, false, false, 0);
+
+            let kind = Label::new(Some(symbol.kind.name()));
+            kind.style_context().add_class("dim-label");
+            row_box.pack_start(&kind, false, false, 0);
+
+            let relative_path = Path::new(&symbol.path)
+                .strip_prefix(&workspace_path)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| symbol.path.clone());
+            let location = Label::new(Some(&format!("{relative_path}:{}", symbol.line + 1)));
+            location.style_context().add_class("dim-label");
+            location.set_xalign(1f32);
+            location.set_ellipsize(gtk::pango::EllipsizeMode::Start);
+            row_box.pack_end(&location, true, true, 0);
+
+            row.add(&row_box);
+            row.show_all();
+            listbox.add(&row);
+            listed_symbols.push(symbol.clone());
+        }
+    });
+
+    if let Some(first_row) = listbox.row_at_index(0) {
+        listbox.select_row(Some(&first_row));
+    }
+}
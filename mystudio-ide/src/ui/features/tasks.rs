@@ -0,0 +1,451 @@
+// Tasks of the workspace and the Tasks panel showing output of the running
+// one, see `discover_tasks`
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+};
+
+use gtk::{
+    gdk::keys::constants as key_constants,
+    pango::{EllipsizeMode, Underline},
+    prelude::{DialogExt, EntryExt, SearchEntryExt},
+    traits::{
+        BoxExt, ButtonExt, ContainerExt, GtkWindowExt, LabelExt, ListBoxExt, ListBoxRowExt,
+        StyleContextExt, TextBufferExt, TextTagTableExt, TextViewExt, WidgetExt,
+    },
+    Adjustment, Button, Dialog, IconSize, Label, ListBox, ListBoxRow, Orientation, ReliefStyle,
+    ScrolledWindow, SearchEntry, SelectionMode, TextTag, TextView, TextWindowType,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    diagnostics::Diagnostic,
+    fuzzy::fuzzy_match,
+    tasks::{
+        discover_tasks, find_location,
+        runner::{TaskEvent, TaskRun},
+        Task,
+    },
+    workspace::Workspace,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{
+        self, bottom_panel, notebook::editor::open_editor_for_abs_path,
+        statusbar::message::show_message,
+    },
+};
+
+use super::{command_palette::highlight_positions, problems};
+
+// Owner of diagnostics found in task output, see `problems::set`
+const DIAGNOSTICS_OWNER: &str = "cargo";
+
+// Tag of output lines pointing to a file
+const LOCATION_TAG: &str = "location";
+
+thread_local! { static G_TASKS_PAGE: RefCell<Option<(gtk::Box, Label, TextView)>> = RefCell::new(None) }
+thread_local! { static G_TASK_RUN: RefCell<Option<TaskRun>> = RefCell::new(None) }
+// Task shown in the panel, run again by `rerun_last_task`
+thread_local! { static G_LAST_TASK: RefCell<Option<Task>> = RefCell::new(None) }
+// Events of older runs are dropped
+thread_local! { static G_RUN_ID: Cell<u64> = Cell::new(0) }
+// Diagnostics found in output of the current run, by file
+thread_local! { static G_TASK_DIAGNOSTICS: RefCell<BTreeMap<String, Vec<Diagnostic>>> = RefCell::new(BTreeMap::new()) }
+
+/**
+ * Shows the Tasks panel, or hides it when it is already showing.
+ */
+pub fn toggle() {
+    let (page, _, _) = tasks_page();
+    bottom_panel::toggle_page(&page);
+}
+
+/**
+ * Runs a task in the background, its output is shown in the Tasks panel.
+ *
+ * A task already running is stopped first. Returns the id its events are sent
+ * with, `None` when it failed to start.
+ */
+pub fn run_task(task: Task) -> Option<u64> {
+    if let Some(run) = G_TASK_RUN.with(|run| run.borrow_mut().take()) {
+        run.stop();
+    }
+    let id = G_RUN_ID.with(|run_id| {
+        run_id.set(run_id.get() + 1);
+        run_id.get()
+    });
+
+    G_LAST_TASK.with(|last_task| *last_task.borrow_mut() = Some(task.clone()));
+
+    // Diagnostics of the previous run are outdated
+    G_TASK_DIAGNOSTICS.with(|diagnostics| diagnostics.borrow_mut().clear());
+    problems::clear_owner(DIAGNOSTICS_OWNER);
+
+    let (page, label, text_view) = tasks_page();
+    if let Some(buffer) = text_view.buffer() {
+        buffer.set_text("");
+    }
+    append_output(&format!("> {}", task.command_line()));
+    label.set_text(&format!("Running '{}'...", task.label));
+    bottom_panel::show_page(&page);
+
+    let tx = Comms::sender();
+    match TaskRun::start(id, &task, move |event| {
+        tx.send(CommEvents::TaskEvent(event)).ok();
+    }) {
+        Ok(run) => {
+            G_TASK_RUN.with(|current| *current.borrow_mut() = Some(run));
+            Some(id)
+        }
+        Err(error) => {
+            append_output(&error.to_string());
+            label.set_text(&format!("'{}' failed to start", task.label));
+            show_message(format!("Unable to run task '{}': {error}", task.label));
+            None
+        }
+    }
+}
+
+/**
+ * Runs the last task again, or asks for one when none was run yet.
+ */
+pub fn rerun_last_task() {
+    match G_LAST_TASK.with(|last_task| last_task.borrow().clone()) {
+        Some(task) => {
+            run_task(task);
+        }
+        None => show_dialog(),
+    }
+}
+
+/// Stops the running task, if any.
+pub fn stop_task() {
+    G_TASK_RUN.with(|run| {
+        if let Some(run) = run.borrow().as_ref() {
+            run.stop();
+        }
+    });
+}
+
+/**
+ * Shows output and diagnostics sent by a running task.
+ */
+pub fn handle_event(event: TaskEvent) {
+    let current_id = G_RUN_ID.with(|run_id| run_id.get());
+
+    match event {
+        TaskEvent::Output(id, text) if id == current_id => append_output(&text),
+        TaskEvent::Diagnostic(id, diagnostic) if id == current_id => {
+            let path = diagnostic.path.clone();
+            let file_diagnostics = G_TASK_DIAGNOSTICS.with(|diagnostics| {
+                let mut diagnostics = diagnostics.borrow_mut();
+                let file_diagnostics = diagnostics.entry(path.clone()).or_default();
+                // Targets of a package, ex: lib and tests, report the same warnings
+                if !file_diagnostics.contains(&diagnostic) {
+                    file_diagnostics.push(diagnostic);
+                }
+                file_diagnostics.clone()
+            });
+            problems::set(DIAGNOSTICS_OWNER, &path, file_diagnostics);
+        }
+        TaskEvent::Finished(id, status) if id == current_id => {
+            G_TASK_RUN.with(|run| run.borrow_mut().take());
+
+            let task_label = G_LAST_TASK
+                .with(|last_task| last_task.borrow().as_ref().map(|task| task.label.clone()))
+                .unwrap_or_default();
+            let message = format!("Task '{task_label}' {status}");
+
+            let (_, label, _) = tasks_page();
+            label.set_text(&message);
+            show_message(message);
+        }
+        _ => {}
+    }
+}
+
+/**
+ * Shows a searchable list of tasks of the workspace, `Enter` runs the
+ * selected one.
+ */
+pub fn show_dialog() {
+    let config = AppConfig::current();
+    let tasks = discover_tasks(&Workspace::get_path(), &config.Tasks);
+    if tasks.is_empty() {
+        show_message(String::from(
+            "No tasks found, add them to `[Tasks]` of workspace settings",
+        ));
+        return;
+    }
+
+    let window = ui::main_window();
+
+    let dialog = Dialog::builder()
+        .title("Run Task")
+        .transient_for(&window)
+        .modal(true)
+        .destroy_with_parent(true)
+        .default_width(640)
+        .default_height(420)
+        .build();
+
+    let input = SearchEntry::new();
+    input.set_placeholder_text(Some("Type a task name, ex: cargo test"));
+    input.set_margin(6);
+
+    let listbox = ListBox::new();
+    listbox.set_selection_mode(SelectionMode::Browse);
+    listbox.set_placeholder(Some(&Label::new(Some("No matching tasks"))));
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.set_vexpand(true);
+    scrolled_window.add(&listbox);
+
+    let content_area = dialog.content_area();
+    content_area.pack_start(&input, false, true, 0);
+    content_area.pack_start(&scrolled_window, true, true, 0);
+
+    let tasks = Rc::new(tasks);
+    // Tasks in the order they are listed
+    let listed_tasks: Rc<RefCell<Vec<Task>>> = Rc::new(RefCell::new(vec![]));
+    update_list(&listbox, "", &tasks, &listed_tasks);
+
+    let listbox_clone = listbox.clone();
+    let listed_tasks_clone = listed_tasks.clone();
+    input.connect_search_changed(move |input| {
+        update_list(&listbox_clone, &input.text(), &tasks, &listed_tasks_clone);
+    });
+
+    let dialog_clone = dialog.clone();
+    let listbox_clone = listbox.clone();
+    let listed_tasks_clone = listed_tasks.clone();
+    input.connect_activate(move |_| {
+        if let Some(row) = listbox_clone.selected_row() {
+            run_listed_task(&dialog_clone, row.index(), &listed_tasks_clone);
+        }
+    });
+
+    let dialog_clone = dialog.clone();
+    listbox.connect_row_activated(move |_, row| {
+        run_listed_task(&dialog_clone, row.index(), &listed_tasks);
+    });
+
+    // Keep focus in the input while moving through the list
+    let listbox_clone = listbox.clone();
+    dialog.connect_key_press_event(move |dialog, event| {
+        let keyval = event.keyval();
+
+        if keyval == key_constants::Escape {
+            dialog.close();
+            return gtk::Inhibit(true);
+        }
+
+        let step = if keyval == key_constants::Down {
+            1
+        } else if keyval == key_constants::Up {
+            -1
+        } else {
+            return gtk::Inhibit(false);
+        };
+
+        let selected_index = listbox_clone.selected_row().map_or(0, |row| row.index());
+        if let Some(row) = listbox_clone.row_at_index(selected_index + step) {
+            listbox_clone.select_row(Some(&row));
+        }
+
+        gtk::Inhibit(true)
+    });
+
+    dialog.show_all();
+    input.grab_focus();
+}
+
+fn run_listed_task(dialog: &Dialog, index: i32, listed_tasks: &Rc<RefCell<Vec<Task>>>) {
+    let task = listed_tasks.borrow().get(index as usize).cloned();
+
+    dialog.close();
+
+    if let Some(task) = task {
+        run_task(task);
+    }
+}
+
+fn update_list(
+    listbox: &ListBox,
+    query: &str,
+    tasks: &[Task],
+    listed_tasks: &Rc<RefCell<Vec<Task>>>,
+) {
+    for child in listbox.children() {
+        listbox.remove(&child);
+    }
+
+    // Discovery order is kept for an empty query
+    let mut matches: Vec<_> = tasks
+        .iter()
+        .filter_map(|task| fuzzy_match(query, &task.label).map(|m| (task, m)))
+        .collect();
+    matches.sort_by_key(|(_, fuzzy_match)| -fuzzy_match.score);
+
+    let mut listed_tasks = listed_tasks.borrow_mut();
+    listed_tasks.clear();
+
+    for (task, fuzzy_match) in matches {
+        let row = ListBoxRow::new();
+        let row_box = gtk::Box::new(Orientation::Horizontal, 12);
+        row_box.set_margin(6);
+
+        let name = Label::new(None);
+        name.set_markup(&highlight_positions(&task.label, &fuzzy_match.positions));
+        name.set_xalign(0f32);
+        row_box.pack_start(&name, false, false, 0);
+
+        let command = Label::new(Some(&task.command_line()));
+        command.style_context().add_class("dim-label");
+        command.set_xalign(1f32);
+        command.set_ellipsize(EllipsizeMode::End);
+        row_box.pack_end(&command, true, true, 0);
+
+        row.add(&row_box);
+        row.show_all();
+        listbox.add(&row);
+        listed_tasks.push(task.clone());
+    }
+
+    if let Some(first_row) = listbox.row_at_index(0) {
+        listbox.select_row(Some(&first_row));
+    }
+}
+
+// Adds a line to the panel, lines pointing to a file are underlined
+fn append_output(text: &str) {
+    let (_, _, text_view) = tasks_page();
+    let Some(buffer) = text_view.buffer() else {
+        return;
+    };
+
+    let cwd = current_task_cwd();
+    let mut end = buffer.end_iter();
+    let line_start = end.offset();
+    buffer.insert(&mut end, &format!("{text}\n"));
+
+    if find_location(text, &cwd).is_some() {
+        let start = buffer.iter_at_offset(line_start);
+        let mut line_end = buffer.iter_at_offset(line_start);
+        line_end.forward_to_line_end();
+        buffer.apply_tag_by_name(LOCATION_TAG, &start, &line_end);
+    }
+
+    if let Some(end_mark) = buffer.mark("end") {
+        text_view.scroll_mark_onscreen(&end_mark);
+    }
+}
+
+fn current_task_cwd() -> String {
+    G_LAST_TASK
+        .with(|last_task| last_task.borrow().as_ref().map(|task| task.cwd.clone()))
+        .unwrap_or_else(Workspace::get_path)
+}
+
+// Opens the file of an output line clicked without selecting text
+fn open_clicked_location(text_view: &TextView, event: &gtk::gdk::EventButton) -> bool {
+    let Some(buffer) = text_view.buffer() else {
+        return false;
+    };
+    if event.button() != 1 || buffer.has_selection() {
+        return false;
+    }
+
+    let (x, y) = event.position();
+    let (x, y) = text_view.window_to_buffer_coords(TextWindowType::Widget, x as i32, y as i32);
+    let Some(mut start) = text_view.iter_at_location(x, y) else {
+        return false;
+    };
+    start.set_line_offset(0);
+    let mut end = start.clone();
+    end.forward_to_line_end();
+
+    let line = buffer
+        .text(&start, &end, false)
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+    match find_location(&line, &current_task_cwd()) {
+        Some(location) => {
+            open_editor_for_abs_path(location.path, location.line, location.column);
+            true
+        }
+        None => false,
+    }
+}
+
+// Page of the bottom panel with output of the last task, added on first use
+fn tasks_page() -> (gtk::Box, Label, TextView) {
+    if let Some(page) = G_TASKS_PAGE.with(|page| page.borrow().clone()) {
+        return page;
+    }
+
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_cursor_visible(false);
+    text_view.set_monospace(true);
+    text_view.set_left_margin(4);
+
+    if let Some(buffer) = text_view.buffer() {
+        let location_tag = TextTag::builder()
+            .name(LOCATION_TAG)
+            .underline(Underline::Single)
+            .build();
+        if let Some(tag_table) = buffer.tag_table() {
+            tag_table.add(&location_tag);
+        }
+        // Stays at the end, output is scrolled to it
+        buffer.create_mark(Some("end"), &buffer.end_iter(), false);
+    }
+
+    text_view.connect_button_release_event(|text_view, event| {
+        gtk::Inhibit(open_clicked_location(text_view, event))
+    });
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.add(&text_view);
+
+    let label = Label::new(Some("No task was run"));
+    label.set_xalign(0.0);
+    label.set_margin(4);
+    label.set_ellipsize(EllipsizeMode::End);
+
+    let run_button = Button::from_icon_name(Some("media-playback-start-symbolic"), IconSize::Menu);
+    run_button.set_relief(ReliefStyle::None);
+    run_button.set_tooltip_text(Some("Run Task"));
+    run_button.connect_clicked(|_| show_dialog());
+
+    let rerun_button = Button::from_icon_name(Some("view-refresh-symbolic"), IconSize::Menu);
+    rerun_button.set_relief(ReliefStyle::None);
+    rerun_button.set_tooltip_text(Some("Rerun Last Task"));
+    rerun_button.connect_clicked(|_| rerun_last_task());
+
+    let stop_button = Button::from_icon_name(Some("media-playback-stop-symbolic"), IconSize::Menu);
+    stop_button.set_relief(ReliefStyle::None);
+    stop_button.set_tooltip_text(Some("Stop Task"));
+    stop_button.connect_clicked(|_| stop_task());
+
+    let toolbar = gtk::Box::new(Orientation::Horizontal, 0);
+    toolbar.pack_start(&label, true, true, 0);
+    toolbar.pack_end(&stop_button, false, false, 0);
+    toolbar.pack_end(&rerun_button, false, false, 0);
+    toolbar.pack_end(&run_button, false, false, 0);
+
+    let page = gtk::Box::new(Orientation::Vertical, 0);
+    page.pack_start(&toolbar, false, false, 0);
+    page.pack_start(&scrolled_window, true, true, 0);
+    bottom_panel::add_page(&page, "Tasks");
+
+    let page = (page, label, text_view);
+    G_TASKS_PAGE.with(|tasks_page| *tasks_page.borrow_mut() = Some(page.clone()));
+    page
+}
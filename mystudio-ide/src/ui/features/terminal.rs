@@ -0,0 +1,365 @@
+// Terminals in the bottom panel, configured in `[Terminal]` of config.toml
+
+use std::cell::{Cell, RefCell};
+
+use gtk::{
+    gdk::{EventType, ModifierType},
+    glib,
+    pango::FontDescription,
+    prelude::{Cast, GtkMenuExt, NotebookExtManual},
+    traits::{
+        BoxExt, ButtonExt, ContainerExt, GtkMenuItemExt, MenuShellExt, NotebookExt,
+        ScrolledWindowExt, WidgetExt,
+    },
+    Adjustment, Button, Container, IconSize, Label, Menu, MenuItem, Notebook, Orientation,
+    PolicyType, ReliefStyle, ScrolledWindow, Widget,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    terminal::{resolve_link, TerminalLayout, LINK_PATTERN},
+    workspace::Workspace,
+};
+
+use crate::ui::{
+    bottom_panel, notebook::editor::open_editor_for_abs_path, statusbar::message::show_message,
+    vte::Terminal,
+};
+
+/**
+ * Commands which keep working while a terminal has focus, other keys go to
+ * the shell.
+ */
+pub const TERMINAL_COMMANDS: &[&str] = &[
+    "toggle_terminal",
+    "new_terminal",
+    "close_terminal",
+    "terminal_copy",
+    "terminal_paste",
+];
+
+thread_local! { static G_TERMINAL_PAGE: RefCell<Option<gtk::Box>> = RefCell::new(None) }
+// Holds terminals as tabs or side by side, see `TerminalLayout`
+thread_local! { static G_TERMINAL_CONTAINER: RefCell<Option<(TerminalLayout, Container)>> = RefCell::new(None) }
+// Terminals with their title, in the order they were opened
+thread_local! { static G_TERMINALS: RefCell<Vec<(Terminal, String)>> = RefCell::new(vec![]) }
+// Last focused terminal, commands act on it
+thread_local! { static G_ACTIVE_TERMINAL: RefCell<Option<Widget>> = RefCell::new(None) }
+// Numbers titles of new terminals
+thread_local! { static G_TERMINAL_COUNT: Cell<u32> = Cell::new(0) }
+
+/**
+ * Shows the terminal panel, or hides it when it is showing. A terminal is
+ * opened the first time.
+ */
+pub fn toggle() {
+    let Some(page) = G_TERMINAL_PAGE.with(|page| page.borrow().clone()) else {
+        new_terminal();
+        return;
+    };
+    if G_TERMINALS.with(|terminals| terminals.borrow().is_empty()) {
+        new_terminal();
+        return;
+    }
+
+    bottom_panel::toggle_page(&page);
+    if page.is_mapped() {
+        focus_active_terminal();
+    }
+}
+
+/**
+ * Opens a terminal running the configured shell in the workspace directory.
+ */
+pub fn new_terminal() {
+    let config = AppConfig::current();
+    let terminal = Terminal::new();
+    configure(&terminal, &config);
+    terminal.add_link_pattern(LINK_PATTERN);
+
+    let workspace_path = Workspace::get_path();
+    let working_directory = if workspace_path.is_empty() {
+        glib::home_dir().to_string_lossy().to_string()
+    } else {
+        workspace_path
+    };
+    terminal.spawn(&working_directory, &config.Terminal.shell_command());
+
+    // Closed along with its shell, ex: after `exit`
+    terminal.connect_child_exited(remove_terminal);
+
+    let widget = terminal.widget();
+    widget.connect_focus_in_event(|widget, _| {
+        G_ACTIVE_TERMINAL.with(|active| *active.borrow_mut() = Some(widget.clone()));
+        gtk::Inhibit(false)
+    });
+
+    let terminal_clone = terminal.clone();
+    widget.connect_button_press_event(move |_, event| {
+        if event.event_type() != EventType::ButtonPress {
+            return gtk::Inhibit(false);
+        }
+
+        match event.button() {
+            1 if event.state().contains(ModifierType::CONTROL_MASK) => {
+                gtk::Inhibit(open_link(&terminal_clone, event))
+            }
+            3 => {
+                show_context_menu(&terminal_clone, event);
+                gtk::Inhibit(true)
+            }
+            _ => gtk::Inhibit(false),
+        }
+    });
+
+    let number = G_TERMINAL_COUNT.with(|count| {
+        count.set(count.get() + 1);
+        count.get()
+    });
+    let title = format!("Terminal {number}");
+
+    let page = terminal_page();
+    let (_, container) = container();
+    add_to_container(&container, &terminal, &title);
+    G_TERMINALS.with(|terminals| terminals.borrow_mut().push((terminal.clone(), title)));
+
+    bottom_panel::show_page(&page);
+    terminal.widget().grab_focus();
+}
+
+/// Closes the active terminal and its shell.
+pub fn close_terminal() {
+    if let Some(terminal) = active_terminal() {
+        remove_terminal(&terminal);
+    }
+}
+
+/// Copies text selected in the active terminal.
+pub fn copy() {
+    if let Some(terminal) = active_terminal() {
+        terminal.copy_clipboard();
+    }
+}
+
+/// Pastes into the active terminal.
+pub fn paste() {
+    if let Some(terminal) = active_terminal() {
+        terminal.paste_clipboard();
+    }
+}
+
+/// Tells whether keyboard focus is in a terminal.
+pub fn has_focus() -> bool {
+    G_TERMINALS.with(|terminals| {
+        terminals
+            .borrow()
+            .iter()
+            .any(|(terminal, _)| terminal.widget().has_focus())
+    })
+}
+
+/**
+ * Applies a reloaded config to open terminals, a new layout moves them to a
+ * new container.
+ */
+pub fn apply_config(config: &AppConfig) {
+    let terminals = G_TERMINALS.with(|terminals| terminals.borrow().clone());
+    for (terminal, _) in terminals.iter() {
+        configure(terminal, config);
+    }
+
+    let Some((layout, old_container)) =
+        G_TERMINAL_CONTAINER.with(|container| container.borrow().clone())
+    else {
+        return;
+    };
+    if layout == config.Terminal.layout {
+        return;
+    }
+
+    for (terminal, _) in terminals.iter() {
+        if let Some(scrolled_window) = terminal.widget().parent() {
+            old_container.remove(&scrolled_window);
+        }
+    }
+    if let Some(page) = G_TERMINAL_PAGE.with(|page| page.borrow().clone()) {
+        page.remove(&old_container);
+    }
+    G_TERMINAL_CONTAINER.with(|container| container.borrow_mut().take());
+
+    let (_, new_container) = container();
+    for (terminal, title) in terminals.iter() {
+        add_to_container(&new_container, terminal, title);
+    }
+}
+
+fn configure(terminal: &Terminal, config: &AppConfig) {
+    let font_family = match config.Terminal.font_family.trim() {
+        "" => config.Editor.font_family.as_str(),
+        font_family => font_family,
+    };
+    let font_size = match config.Terminal.font_size {
+        0 => config.Editor.font_size,
+        font_size => font_size,
+    };
+
+    terminal.set_font(&FontDescription::from_string(&format!(
+        "{font_family} {font_size}"
+    )));
+    terminal.set_scrollback_lines(config.Terminal.scrollback_lines);
+}
+
+// Opens a `path:line:col` link clicked in a terminal, returns whether there was one
+fn open_link(terminal: &Terminal, event: &gtk::gdk::EventButton) -> bool {
+    let Some(text) = terminal.link_at_event(event) else {
+        return false;
+    };
+
+    match resolve_link(&text, &Workspace::get_path()) {
+        Some(location) => open_editor_for_abs_path(location.path, location.line, location.column),
+        None => show_message(format!("Unable to find file of '{text}'")),
+    }
+    true
+}
+
+fn show_context_menu(terminal: &Terminal, event: &gtk::gdk::EventButton) {
+    let menu = Menu::new();
+
+    let copy_item = MenuItem::with_label("Copy");
+    let terminal_clone = terminal.clone();
+    copy_item.connect_activate(move |_| terminal_clone.copy_clipboard());
+    menu.append(&copy_item);
+
+    let paste_item = MenuItem::with_label("Paste");
+    let terminal_clone = terminal.clone();
+    paste_item.connect_activate(move |_| terminal_clone.paste_clipboard());
+    menu.append(&paste_item);
+
+    menu.set_attach_widget(Some(terminal.widget()));
+    menu.show_all();
+    menu.popup_at_pointer(Some(&**event));
+}
+
+fn remove_terminal(terminal: &Terminal) {
+    let widget = terminal.widget().clone();
+
+    if let Some(scrolled_window) = widget.parent() {
+        let (_, container) = container();
+        container.remove(&scrolled_window);
+    }
+    G_TERMINALS.with(|terminals| {
+        terminals
+            .borrow_mut()
+            .retain(|(other, _)| other.widget() != &widget)
+    });
+    G_ACTIVE_TERMINAL.with(|active| {
+        let mut active = active.borrow_mut();
+        if active.as_ref() == Some(&widget) {
+            *active = None;
+        }
+    });
+
+    if G_TERMINALS.with(|terminals| terminals.borrow().is_empty()) {
+        if let Some(page) = G_TERMINAL_PAGE.with(|page| page.borrow().clone()) {
+            bottom_panel::hide_page(&page);
+        }
+    } else {
+        focus_active_terminal();
+    }
+}
+
+fn active_terminal() -> Option<Terminal> {
+    let active = G_ACTIVE_TERMINAL.with(|active| active.borrow().clone());
+
+    G_TERMINALS.with(|terminals| {
+        let terminals = terminals.borrow();
+        terminals
+            .iter()
+            .find(|(terminal, _)| Some(terminal.widget()) == active.as_ref())
+            .or_else(|| terminals.last())
+            .map(|(terminal, _)| terminal.clone())
+    })
+}
+
+fn focus_active_terminal() {
+    if let Some(terminal) = active_terminal() {
+        terminal.widget().grab_focus();
+    }
+}
+
+fn add_to_container(container: &Container, terminal: &Terminal, title: &str) {
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    // Terminals wrap lines themselves
+    scrolled_window.set_policy(PolicyType::Never, PolicyType::Automatic);
+    scrolled_window.add(terminal.widget());
+    scrolled_window.show_all();
+
+    match container.downcast_ref::<Notebook>() {
+        Some(notebook) => {
+            let page_num = notebook.append_page(&scrolled_window, Some(&Label::new(Some(title))));
+            notebook.set_current_page(Some(page_num));
+        }
+        None => {
+            if let Some(split_box) = container.downcast_ref::<gtk::Box>() {
+                split_box.pack_start(&scrolled_window, true, true, 0);
+            }
+        }
+    }
+}
+
+// Container of terminals for the configured layout, created on first use
+fn container() -> (TerminalLayout, Container) {
+    if let Some(container) = G_TERMINAL_CONTAINER.with(|container| container.borrow().clone()) {
+        return container;
+    }
+
+    let layout = AppConfig::current().Terminal.layout;
+    let container: Container = match layout {
+        TerminalLayout::Tabs => {
+            let notebook = Notebook::new();
+            notebook.set_scrollable(true);
+            notebook.set_show_border(false);
+            notebook.upcast()
+        }
+        TerminalLayout::Split => {
+            let split_box = gtk::Box::new(Orientation::Horizontal, 2);
+            split_box.set_homogeneous(true);
+            split_box.upcast()
+        }
+    };
+
+    let page = terminal_page();
+    page.pack_start(&container, true, true, 0);
+    container.show();
+
+    G_TERMINAL_CONTAINER.with(|current| *current.borrow_mut() = Some((layout, container.clone())));
+    (layout, container)
+}
+
+// Page of the bottom panel holding terminals, added on first use
+fn terminal_page() -> gtk::Box {
+    if let Some(page) = G_TERMINAL_PAGE.with(|page| page.borrow().clone()) {
+        return page;
+    }
+
+    let new_button = Button::from_icon_name(Some("list-add-symbolic"), IconSize::Menu);
+    new_button.set_relief(ReliefStyle::None);
+    new_button.set_tooltip_text(Some("New Terminal"));
+    new_button.connect_clicked(|_| new_terminal());
+
+    let close_button = Button::from_icon_name(Some("window-close-symbolic"), IconSize::Menu);
+    close_button.set_relief(ReliefStyle::None);
+    close_button.set_tooltip_text(Some("Close Terminal"));
+    close_button.connect_clicked(|_| close_terminal());
+
+    let toolbar = gtk::Box::new(Orientation::Horizontal, 0);
+    toolbar.pack_end(&close_button, false, false, 0);
+    toolbar.pack_end(&new_button, false, false, 0);
+
+    let page = gtk::Box::new(Orientation::Vertical, 0);
+    page.pack_start(&toolbar, false, false, 0);
+    bottom_panel::add_page(&page, "Terminal");
+
+    G_TERMINAL_PAGE.with(|terminal_page| *terminal_page.borrow_mut() = Some(page.clone()));
+    page
+}
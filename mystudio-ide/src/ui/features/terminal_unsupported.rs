@@ -0,0 +1,32 @@
+// Terminal panel stand-in for platforms without VTE, or builds without the
+// `terminal` feature
+
+use libmystudio::app_config::AppConfig;
+
+use crate::ui::statusbar::message::show_message;
+
+pub const TERMINAL_COMMANDS: &[&str] = &[];
+
+pub fn toggle() {
+    show_unsupported();
+}
+
+pub fn new_terminal() {
+    show_unsupported();
+}
+
+pub fn close_terminal() {}
+
+pub fn copy() {}
+
+pub fn paste() {}
+
+pub fn has_focus() -> bool {
+    false
+}
+
+pub fn apply_config(_config: &AppConfig) {}
+
+fn show_unsupported() {
+    show_message(String::from("Terminal is not available in this build"));
+}
@@ -0,0 +1,922 @@
+// Tests panel listing tests of the cargo workspace by crate and module, runs
+// them through the Tasks panel and shows their results. Tests are listed by
+// `cargo test -- --list`, located with `discover_tests`
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use gtk::{
+    glib::{self, Type},
+    pango::EllipsizeMode,
+    prelude::{Cast, ToValue, TreeModelExt, TreeStoreExtManual, TreeViewExt},
+    traits::{
+        BoxExt, ButtonExt, CellLayoutExt, ContainerExt, LabelExt, PanedExt, TextBufferExt,
+        TextViewExt, TreeSelectionExt, TreeStoreExt, WidgetExt,
+    },
+    Adjustment, Button, CellRendererPixbuf, CellRendererText, IconSize, Label, Orientation, Paned,
+    ReliefStyle, ScrolledWindow, TextView, TreeIter, TreeStore, TreeView, TreeViewColumn,
+};
+use libmystudio::{
+    tasks::runner::{TaskEvent, TaskRun, TaskStatus},
+    test_explorer::{
+        discover_tests, list_tests_task, merge_listed_tests, test_task, tests_in_file,
+        update_matches, ListedTest, TestCase, TestListParser, TestOutputParser, TestScope,
+        TestStatus, TestTarget, TestUpdate,
+    },
+    workspace::Workspace,
+};
+use sourceview4::{
+    traits::{BufferExt, MarkAttributesExt, ViewExt},
+    Buffer, MarkAttributes, View,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{
+        bottom_panel,
+        notebook::editor::{open_editor_for_abs_path, Editor},
+        statusbar::message::show_message,
+    },
+};
+
+use super::{debugger, tasks};
+
+// Gutter marks are updated once typing pauses
+const MARKS_REFRESH_DELAY: Duration = Duration::from_millis(500);
+
+// Mark category of the "Run test" gutter action
+const RUN_MARK_CATEGORY: &str = "test-run";
+
+// Columns of the store
+const COLUMN_ICON: u32 = 0;
+const COLUMN_NAME: u32 = 1;
+const COLUMN_KIND: u32 = 2;
+// Index in `G_TESTS` of the test of the row, or of the first test below it
+const COLUMN_INDEX: u32 = 3;
+// Module of module rows, ex: `parser::tests`
+const COLUMN_MODULE: u32 = 4;
+
+// Kinds of rows
+const KIND_CRATE: i32 = 0;
+// Binary or integration test of a crate, the lib has no row
+const KIND_TARGET: i32 = 1;
+const KIND_MODULE: i32 = 2;
+const KIND_TEST: i32 = 3;
+
+struct TestEntry {
+    test: TestCase,
+    status: TestStatus,
+    // Captured by the last run
+    output: String,
+}
+
+// A run of `list_tests_task`, its tests replace those found in files
+struct TestList {
+    run: TaskRun,
+    parser: TestListParser,
+    listed: Vec<ListedTest>,
+    // Found in files when the run started, to locate listed tests
+    scanned: Vec<TestCase>,
+}
+
+#[derive(Clone)]
+struct TestsPage {
+    page: gtk::Box,
+    label: Label,
+    tree_view: TreeView,
+    store: TreeStore,
+    output_view: TextView,
+}
+
+thread_local! { static G_TESTS_PAGE: RefCell<Option<TestsPage>> = RefCell::new(None) }
+// Tests of the workspace, `None` until discovered
+thread_local! { static G_TESTS: RefCell<Option<Vec<TestEntry>>> = RefCell::new(None) }
+// Task id of the running tests and the parser of their output
+thread_local! { static G_TEST_RUN: RefCell<Option<(u64, TestOutputParser)>> = RefCell::new(None) }
+thread_local! { static G_TEST_LIST: RefCell<Option<TestList>> = RefCell::new(None) }
+thread_local! { static G_TEST_LIST_ID: Cell<u64> = Cell::new(0) }
+
+/**
+ * Shows the Tests panel, or hides it when it is already showing.
+ */
+pub fn toggle() {
+    let page = tests_page();
+    ensure_discovered();
+    bottom_panel::toggle_page(&page.page);
+}
+
+/**
+ * Finds tests of the workspace again, results of tests still there are kept.
+ *
+ * Tests found in files are shown first, they are replaced by those listed by
+ * cargo once it built them.
+ */
+pub fn refresh() {
+    let root = Workspace::get_path();
+    let tests = discover_tests(&root);
+
+    set_tests(tests.clone());
+    list_tests(&root, tests);
+}
+
+// Lists tests with cargo in the background, see `handle_list_event`
+fn list_tests(root: &str, scanned: Vec<TestCase>) {
+    stop_listing();
+    if !Path::new(root).join("Cargo.toml").is_file() {
+        return;
+    }
+
+    let id = G_TEST_LIST_ID.with(|list_id| list_id.replace(list_id.get() + 1) + 1);
+    let tx = Comms::sender();
+    match TaskRun::start(id, &list_tests_task(root), move |event| {
+        tx.send(CommEvents::TestListEvent(event)).ok();
+    }) {
+        Ok(run) => G_TEST_LIST.with(|list| {
+            *list.borrow_mut() = Some(TestList {
+                run,
+                parser: TestListParser::default(),
+                listed: vec![],
+                scanned,
+            })
+        }),
+        Err(error) => eprintln!("Unable to list tests: {error}"),
+    }
+}
+
+fn stop_listing() {
+    if let Some(list) = G_TEST_LIST.with(|list| list.borrow_mut().take()) {
+        list.run.stop();
+    }
+}
+
+/**
+ * Reads tests listed by cargo, they replace tests found in files once the
+ * listing succeeded.
+ */
+pub fn handle_list_event(event: TaskEvent) {
+    let (TaskEvent::Output(id, _)
+    | TaskEvent::Diagnostic(id, _)
+    | TaskEvent::Artifact(id, _)
+    | TaskEvent::Finished(id, _)) = &event;
+    let is_current = G_TEST_LIST.with(|list| {
+        list.borrow()
+            .as_ref()
+            .is_some_and(|list| list.run.id() == *id)
+    });
+    if !is_current {
+        return;
+    }
+
+    match event {
+        TaskEvent::Output(_, line) => G_TEST_LIST.with(|list| {
+            if let Some(list) = list.borrow_mut().as_mut() {
+                let listed_test = list.parser.parse_line(&line);
+                list.listed.extend(listed_test);
+            }
+        }),
+        TaskEvent::Finished(_, status) => {
+            let Some(list) = G_TEST_LIST.with(|list| list.borrow_mut().take()) else {
+                return;
+            };
+
+            if status == TaskStatus::Succeeded {
+                let root = Workspace::get_path();
+                set_tests(merge_listed_tests(&root, &list.scanned, &list.listed));
+            } else {
+                show_message(format!(
+                    "Listing tests with cargo {status}, tests found in files are shown"
+                ));
+            }
+        }
+        TaskEvent::Diagnostic(..) | TaskEvent::Artifact(..) => {}
+    }
+}
+
+// Shows `tests`, results of tests already shown are kept
+fn set_tests(tests: Vec<TestCase>) {
+    let count = G_TESTS.with(|current| {
+        let previous = current.borrow_mut().take().unwrap_or_default();
+        let mut previous: HashMap<_, _> = previous
+            .into_iter()
+            .map(|entry| (test_key(&entry.test), entry))
+            .collect();
+
+        let entries: Vec<TestEntry> = tests
+            .into_iter()
+            .map(|test| match previous.remove(&test_key(&test)) {
+                Some(entry) => TestEntry { test, ..entry },
+                None => TestEntry {
+                    test,
+                    status: TestStatus::NotRun,
+                    output: String::new(),
+                },
+            })
+            .collect();
+        let count = entries.len();
+        *current.borrow_mut() = Some(entries);
+        count
+    });
+
+    let page = tests_page();
+    rebuild_tree(&page);
+    page.label.set_text(&match count {
+        0 => String::from("No tests found"),
+        1 => String::from("1 test"),
+        count => format!("{count} tests"),
+    });
+}
+
+/**
+ * Forgets tests of the previous workspace, they are found again when needed.
+ */
+pub fn workspace_changed() {
+    stop_listing();
+    G_TESTS.with(|tests| tests.borrow_mut().take());
+    G_TEST_RUN.with(|run| run.borrow_mut().take());
+
+    if let Some(page) = G_TESTS_PAGE.with(|page| page.borrow().clone()) {
+        page.store.clear();
+        page.label.set_text("");
+        set_output(&page, "");
+    }
+}
+
+/// Runs every test of the workspace.
+pub fn run_all_tests() {
+    run_tests(TestScope::All);
+}
+
+/// Runs the tests which failed in their last run.
+pub fn rerun_failed_tests() {
+    ensure_discovered();
+    let failed: Vec<TestCase> = G_TESTS.with(|tests| {
+        tests
+            .borrow()
+            .iter()
+            .flatten()
+            .filter(|entry| entry.status == TestStatus::Failed)
+            .map(|entry| entry.test.clone())
+            .collect()
+    });
+
+    if failed.is_empty() {
+        show_message(String::from("No failed tests to run"));
+        return;
+    }
+    run_tests(TestScope::Tests(failed));
+}
+
+/**
+ * Runs the tests of the row selected in the panel: a crate, module or
+ * single test.
+ */
+pub fn run_selected_tests() {
+    let page = tests_page();
+    match selected_scope(&page) {
+        Some(scope) => run_tests(scope),
+        None => show_message(String::from("Select a crate, module or test to run")),
+    }
+}
+
+/**
+ * Debugs the test selected in the panel, see `debugger::debug_test`.
+ */
+pub fn debug_selected_test() {
+    let page = tests_page();
+    let test = match selected_row(&page) {
+        Some((KIND_TEST, index, _)) => G_TESTS.with(|tests| {
+            tests
+                .borrow()
+                .iter()
+                .flatten()
+                .nth(index)
+                .map(|entry| entry.test.clone())
+        }),
+        _ => None,
+    };
+
+    match test {
+        Some(test) => debugger::debug_test(&test),
+        None => show_message(String::from("Select a test to debug")),
+    }
+}
+
+/**
+ * Runs `cargo test` for `scope` in the Tasks panel, results are shown in the
+ * Tests panel as they are printed.
+ */
+pub fn run_tests(scope: TestScope) {
+    let root = Workspace::get_path();
+    if root.is_empty() {
+        show_message(String::from("Open a cargo workspace to run its tests"));
+        return;
+    }
+    ensure_discovered();
+
+    let Some(id) = tasks::run_task(test_task(&root, &scope)) else {
+        return;
+    };
+    G_TEST_RUN.with(|run| *run.borrow_mut() = Some((id, TestOutputParser::default())));
+
+    G_TESTS.with(|tests| {
+        for entry in tests.borrow_mut().iter_mut().flatten() {
+            if scope.contains(&entry.test) {
+                entry.status = TestStatus::Queued;
+                entry.output.clear();
+            }
+        }
+    });
+
+    let page = tests_page();
+    update_icons(&page);
+    show_selected_output(&page);
+    page.label.set_text("Running tests...");
+    bottom_panel::show_page(&page.page);
+}
+
+/**
+ * Updates results from output of the running tests, other tasks are
+ * ignored.
+ */
+pub fn handle_task_event(event: &TaskEvent) {
+    let (TaskEvent::Output(id, _)
+    | TaskEvent::Diagnostic(id, _)
+    | TaskEvent::Artifact(id, _)
+    | TaskEvent::Finished(id, _)) = event;
+    let is_test_run = G_TEST_RUN.with(|run| {
+        run.borrow()
+            .as_ref()
+            .is_some_and(|(run_id, _)| run_id == id)
+    });
+    if !is_test_run {
+        return;
+    }
+
+    match event {
+        TaskEvent::Output(_, line) => {
+            let updates = G_TEST_RUN.with(|run| {
+                run.borrow_mut()
+                    .as_mut()
+                    .map(|(_, parser)| parser.parse_line(line))
+                    .unwrap_or_default()
+            });
+            if !updates.is_empty() {
+                apply_updates(&updates);
+            }
+        }
+        TaskEvent::Finished(_, status) => {
+            G_TEST_RUN.with(|run| run.borrow_mut().take());
+
+            // Not reported, ex: the build failed
+            G_TESTS.with(|tests| {
+                for entry in tests.borrow_mut().iter_mut().flatten() {
+                    if entry.status == TestStatus::Queued {
+                        entry.status = TestStatus::NotRun;
+                    }
+                }
+            });
+
+            let page = tests_page();
+            update_icons(&page);
+            page.label
+                .set_text(&format!("{}, tests {status}", results_summary()));
+        }
+        TaskEvent::Diagnostic(..) | TaskEvent::Artifact(..) => {}
+    }
+}
+
+/**
+ * Adds "Run test" gutter actions to a new editor of a Rust file, next to its
+ * `#[test]` functions.
+ */
+pub fn attach(view: &View, file_path: &str) {
+    if !file_path.ends_with(".rs") {
+        return;
+    }
+    let Some(buffer) = view
+        .buffer()
+        .and_then(|buffer| buffer.downcast::<Buffer>().ok())
+    else {
+        return;
+    };
+
+    let attributes = MarkAttributes::new();
+    attributes.set_icon_name("media-playback-start-symbolic");
+    attributes.connect_query_tooltip_text(|_, _| String::from("Run test"));
+    // Below diagnostics, see `diagnostics::priority`
+    view.set_mark_attributes(RUN_MARK_CATEGORY, &attributes, 0);
+
+    let file_path_clone = file_path.to_string();
+    view.connect_line_mark_activated(move |view, iter, _event| {
+        let Some(buffer) = view.buffer() else {
+            return;
+        };
+        let has_mark = buffer.downcast_ref::<Buffer>().is_some_and(|buffer| {
+            !buffer
+                .source_marks_at_line(iter.line(), Some(RUN_MARK_CATEGORY))
+                .is_empty()
+        });
+        if !has_mark {
+            return;
+        }
+
+        let text = Editor::buffer_text(&buffer);
+        let line = iter.line() as u32;
+        let test = tests_in_file(&Workspace::get_path(), &file_path_clone, &text)
+            .into_iter()
+            .find(|test| test.line == line);
+        if let Some(test) = test {
+            run_tests(TestScope::Tests(vec![test]));
+        }
+    });
+
+    let pending = Rc::new(Cell::new(false));
+    let file_path_clone = file_path.to_string();
+    buffer.connect_changed(move |buffer| {
+        if pending.replace(true) {
+            return;
+        }
+
+        let pending = pending.clone();
+        let buffer = buffer.clone();
+        let file_path = file_path_clone.clone();
+        glib::timeout_add_local_once(MARKS_REFRESH_DELAY, move || {
+            pending.set(false);
+            refresh_marks(&buffer, &file_path);
+        });
+    });
+
+    refresh_marks(&buffer, file_path);
+}
+
+fn refresh_marks(buffer: &Buffer, file_path: &str) {
+    let (start, end) = (buffer.start_iter(), buffer.end_iter());
+    buffer.remove_source_marks(&start, &end, Some(RUN_MARK_CATEGORY));
+
+    let text = Editor::buffer_text(buffer.upcast_ref());
+    for test in tests_in_file(&Workspace::get_path(), file_path, &text) {
+        buffer.create_source_mark(
+            None,
+            RUN_MARK_CATEGORY,
+            &buffer.iter_at_line(test.line as i32),
+        );
+    }
+}
+
+fn ensure_discovered() {
+    if G_TESTS.with(|tests| tests.borrow().is_none()) {
+        refresh();
+    }
+}
+
+// Identifies a test across discoveries
+fn test_key(test: &TestCase) -> (String, TestTarget, String) {
+    (
+        test.crate_name.clone(),
+        test.target.clone(),
+        test.path.clone(),
+    )
+}
+
+fn apply_updates(updates: &[TestUpdate]) {
+    G_TESTS.with(|tests| {
+        let mut tests = tests.borrow_mut();
+        for update in updates {
+            let entry = tests
+                .iter_mut()
+                .flatten()
+                .find(|entry| update_matches(update, &entry.test));
+            let Some(entry) = entry else {
+                continue;
+            };
+
+            match update {
+                TestUpdate::Result { status, .. } => entry.status = *status,
+                TestUpdate::Output { output, .. } => entry.output = output.clone(),
+            }
+        }
+    });
+
+    let page = tests_page();
+    update_icons(&page);
+    show_selected_output(&page);
+}
+
+// Ex: `12 passed, 1 failed, 2 ignored`
+fn results_summary() -> String {
+    G_TESTS.with(|tests| {
+        let tests = tests.borrow();
+        let count = |status: TestStatus| {
+            tests
+                .iter()
+                .flatten()
+                .filter(|entry| entry.status == status)
+                .count()
+        };
+
+        format!(
+            "{} passed, {} failed, {} ignored",
+            count(TestStatus::Passed),
+            count(TestStatus::Failed),
+            count(TestStatus::Ignored)
+        )
+    })
+}
+
+fn rebuild_tree(page: &TestsPage) {
+    page.store.clear();
+
+    G_TESTS.with(|tests| {
+        let tests = tests.borrow();
+        // Rows of crates, targets and modules by their names
+        let mut parents: HashMap<String, TreeIter> = HashMap::new();
+
+        for (index, entry) in tests.iter().flatten().enumerate() {
+            let test = &entry.test;
+            let mut key = test.crate_name.clone();
+            let mut parent = parents
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    insert_row(&page.store, None, &test.crate_name, KIND_CRATE, index, "")
+                })
+                .clone();
+
+            let target_name = match &test.target {
+                TestTarget::Lib => None,
+                TestTarget::Bin(name) => Some(format!("bin {name}")),
+                TestTarget::Test(name) => Some(format!("tests/{name}")),
+            };
+            if let Some(target_name) = target_name {
+                key = format!("{key}/{target_name}");
+                parent = parents
+                    .entry(key.clone())
+                    .or_insert_with(|| {
+                        insert_row(
+                            &page.store,
+                            Some(&parent),
+                            &target_name,
+                            KIND_TARGET,
+                            index,
+                            "",
+                        )
+                    })
+                    .clone();
+            }
+
+            let modules = test.modules();
+            for (depth, module) in modules.iter().enumerate() {
+                let module_path = modules[..=depth].join("::");
+                key = format!("{key}::{module}");
+                parent = parents
+                    .entry(key.clone())
+                    .or_insert_with(|| {
+                        insert_row(
+                            &page.store,
+                            Some(&parent),
+                            module,
+                            KIND_MODULE,
+                            index,
+                            &module_path,
+                        )
+                    })
+                    .clone();
+            }
+
+            insert_row(
+                &page.store,
+                Some(&parent),
+                test.name(),
+                KIND_TEST,
+                index,
+                "",
+            );
+        }
+    });
+
+    update_icons(page);
+    // Crates stay expanded to show their modules
+    page.tree_view.expand_all();
+}
+
+fn insert_row(
+    store: &TreeStore,
+    parent: Option<&TreeIter>,
+    name: &str,
+    kind: i32,
+    index: usize,
+    module_path: &str,
+) -> TreeIter {
+    store.insert_with_values(
+        parent,
+        None,
+        &[
+            (COLUMN_ICON, &status_icon_name(TestStatus::NotRun)),
+            (COLUMN_NAME, &name),
+            (COLUMN_KIND, &kind),
+            (COLUMN_INDEX, &(index as i32)),
+            (COLUMN_MODULE, &module_path),
+        ],
+    )
+}
+
+fn update_icons(page: &TestsPage) {
+    G_TESTS.with(|tests| {
+        if let Some(tests) = tests.borrow().as_ref() {
+            update_row_icons(&page.store, tests, None);
+        }
+    });
+}
+
+// Sets icons of rows below `parent` and returns statuses of their tests
+fn update_row_icons(
+    store: &TreeStore,
+    tests: &[TestEntry],
+    parent: Option<&TreeIter>,
+) -> Vec<TestStatus> {
+    let mut statuses = vec![];
+    let Some(iter) = store.iter_children(parent) else {
+        return statuses;
+    };
+
+    loop {
+        let kind = store.value(&iter, COLUMN_KIND as i32).get::<i32>().unwrap();
+        let row_statuses = if kind == KIND_TEST {
+            let index = store
+                .value(&iter, COLUMN_INDEX as i32)
+                .get::<i32>()
+                .unwrap();
+            tests
+                .get(index as usize)
+                .map(|entry| vec![entry.status])
+                .unwrap_or_default()
+        } else {
+            update_row_icons(store, tests, Some(&iter))
+        };
+
+        let icon_name = status_icon_name(group_status(&row_statuses));
+        store.set_value(&iter, COLUMN_ICON, &icon_name.to_value());
+        statuses.extend(row_statuses);
+
+        if !store.iter_next(&iter) {
+            break;
+        }
+    }
+
+    statuses
+}
+
+// Status of a crate or module from those of its tests
+fn group_status(statuses: &[TestStatus]) -> TestStatus {
+    if statuses.contains(&TestStatus::Failed) {
+        TestStatus::Failed
+    } else if statuses.contains(&TestStatus::Queued) {
+        TestStatus::Queued
+    } else if statuses.contains(&TestStatus::NotRun) || statuses.is_empty() {
+        TestStatus::NotRun
+    } else if statuses.contains(&TestStatus::Passed) {
+        TestStatus::Passed
+    } else {
+        TestStatus::Ignored
+    }
+}
+
+fn status_icon_name(status: TestStatus) -> &'static str {
+    match status {
+        TestStatus::NotRun => "media-record-symbolic",
+        TestStatus::Queued => "content-loading-symbolic",
+        TestStatus::Passed => "emblem-ok-symbolic",
+        TestStatus::Failed => "dialog-error-symbolic",
+        TestStatus::Ignored => "action-unavailable-symbolic",
+    }
+}
+
+// Row selected in the tree with its kind and test index
+fn selected_row(page: &TestsPage) -> Option<(i32, usize, String)> {
+    let (model, iter) = page.tree_view.selection().selected()?;
+    let kind = model.value(&iter, COLUMN_KIND as i32).get::<i32>().ok()?;
+    let index = model.value(&iter, COLUMN_INDEX as i32).get::<i32>().ok()?;
+    let module_path = model
+        .value(&iter, COLUMN_MODULE as i32)
+        .get::<String>()
+        .ok()?;
+
+    Some((kind, index as usize, module_path))
+}
+
+fn selected_scope(page: &TestsPage) -> Option<TestScope> {
+    let (kind, index, module_path) = selected_row(page)?;
+
+    G_TESTS.with(|tests| {
+        let tests = tests.borrow();
+        let tests = tests.as_ref()?;
+        let test = &tests.get(index)?.test;
+
+        Some(match kind {
+            KIND_CRATE => TestScope::Crate(test.crate_name.clone()),
+            KIND_TARGET => TestScope::Tests(
+                tests
+                    .iter()
+                    .filter(|entry| {
+                        entry.test.crate_name == test.crate_name && entry.test.target == test.target
+                    })
+                    .map(|entry| entry.test.clone())
+                    .collect(),
+            ),
+            KIND_MODULE => TestScope::Module {
+                crate_name: test.crate_name.clone(),
+                target: test.target.clone(),
+                path: module_path,
+            },
+            _ => TestScope::Tests(vec![test.clone()]),
+        })
+    })
+}
+
+// Shows the captured output of the selected test
+fn show_selected_output(page: &TestsPage) {
+    let text = match selected_row(page) {
+        Some((KIND_TEST, index, _)) => G_TESTS.with(|tests| {
+            let tests = tests.borrow();
+            let Some(entry) = tests.iter().flatten().nth(index) else {
+                return String::new();
+            };
+
+            let status = match entry.status {
+                TestStatus::NotRun => "not run",
+                TestStatus::Queued => "running",
+                TestStatus::Passed => "passed",
+                TestStatus::Failed => "failed",
+                TestStatus::Ignored => "ignored",
+            };
+            match entry.output.as_str() {
+                "" => format!("{}: {status}", entry.test.path),
+                output => format!("{}: {status}\n\n{output}", entry.test.path),
+            }
+        }),
+        _ => String::new(),
+    };
+
+    set_output(page, &text);
+}
+
+fn set_output(page: &TestsPage, text: &str) {
+    if let Some(buffer) = page.output_view.buffer() {
+        buffer.set_text(text);
+    }
+}
+
+// Opens the file of an activated test at its `fn`
+fn open_test(page: &TestsPage) {
+    let Some((KIND_TEST, index, _)) = selected_row(page) else {
+        return;
+    };
+
+    let location = G_TESTS.with(|tests| {
+        tests
+            .borrow()
+            .iter()
+            .flatten()
+            .nth(index)
+            .map(|entry| (entry.test.file.clone(), entry.test.line))
+    });
+    if let Some((file, line)) = location {
+        open_editor_for_abs_path(file, line as i32 + 1, 1);
+    }
+}
+
+fn tool_button(icon_name: &str, tooltip: &str, on_clicked: fn()) -> Button {
+    let button = Button::from_icon_name(Some(icon_name), IconSize::Menu);
+    button.set_relief(ReliefStyle::None);
+    button.set_tooltip_text(Some(tooltip));
+    button.connect_clicked(move |_| on_clicked());
+    button
+}
+
+// Page of the bottom panel with tests and their output, added on first use
+fn tests_page() -> TestsPage {
+    if let Some(page) = G_TESTS_PAGE.with(|page| page.borrow().clone()) {
+        return page;
+    }
+
+    let store = TreeStore::new(&[
+        Type::STRING,
+        Type::STRING,
+        Type::I32,
+        Type::I32,
+        Type::STRING,
+    ]);
+    let tree_view = TreeView::with_model(&store);
+    tree_view.set_headers_visible(false);
+
+    let column = TreeViewColumn::new();
+    let cell_icon = CellRendererPixbuf::new();
+    let cell_text = CellRendererText::new();
+    column.pack_start(&cell_icon, false);
+    column.pack_start(&cell_text, true);
+    column.add_attribute(&cell_icon, "icon-name", COLUMN_ICON as i32);
+    column.add_attribute(&cell_text, "text", COLUMN_NAME as i32);
+    tree_view.append_column(&column);
+
+    let output_view = TextView::new();
+    output_view.set_editable(false);
+    output_view.set_cursor_visible(false);
+    output_view.set_monospace(true);
+    output_view.set_left_margin(4);
+
+    let tree_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    tree_window.add(&tree_view);
+    let output_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    output_window.add(&output_view);
+
+    let paned = Paned::new(Orientation::Horizontal);
+    paned.pack1(&tree_window, true, false);
+    paned.pack2(&output_window, true, false);
+    paned.set_position(320);
+
+    let label = Label::new(None);
+    label.set_xalign(0.0);
+    label.set_margin(4);
+    label.set_ellipsize(EllipsizeMode::End);
+
+    let toolbar = gtk::Box::new(Orientation::Horizontal, 0);
+    toolbar.pack_start(&label, true, true, 0);
+    toolbar.pack_end(
+        &tool_button("media-playback-stop-symbolic", "Stop", tasks::stop_task),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button(
+            "edit-redo-symbolic",
+            "Rerun Failed Tests",
+            rerun_failed_tests,
+        ),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button(
+            "media-playback-pause-symbolic",
+            "Debug Selected Test",
+            debug_selected_test,
+        ),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button("system-run-symbolic", "Run Selected", run_selected_tests),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button(
+            "media-playback-start-symbolic",
+            "Run All Tests",
+            run_all_tests,
+        ),
+        false,
+        false,
+        0,
+    );
+    toolbar.pack_end(
+        &tool_button("view-refresh-symbolic", "Refresh Tests", refresh),
+        false,
+        false,
+        0,
+    );
+
+    let page = gtk::Box::new(Orientation::Vertical, 0);
+    page.pack_start(&toolbar, false, false, 0);
+    page.pack_start(&paned, true, true, 0);
+    bottom_panel::add_page(&page, "Tests");
+
+    let page = TestsPage {
+        page,
+        label,
+        tree_view,
+        store,
+        output_view,
+    };
+    G_TESTS_PAGE.with(|tests_page| *tests_page.borrow_mut() = Some(page.clone()));
+
+    page.tree_view.selection().connect_changed(|_| {
+        show_selected_output(&tests_page());
+    });
+    page.tree_view.set_activate_on_single_click(true);
+    page.tree_view.connect_row_activated(|_, _, _| {
+        open_test(&tests_page());
+    });
+
+    page
+}
@@ -1,5 +1,27 @@
+use gtk::{prelude::BuilderExtManual, ApplicationWindow};
+
+use crate::G_BUILDER;
+
 pub mod action_row;
+pub mod app_config;
+pub mod bottom_panel;
 pub mod features;
 pub mod notebook;
+pub mod side_panel;
 pub mod statusbar;
 pub mod w_explorer;
+#[cfg(all(unix, feature = "terminal"))]
+pub mod vte;
+
+/**
+ * Returns the main window, ex: to make dialogs transient for it.
+ */
+pub fn main_window() -> ApplicationWindow {
+    G_BUILDER.with(|b| {
+        b.borrow()
+            .as_ref()
+            .unwrap()
+            .object::<ApplicationWindow>("main_window")
+            .expect("Unable to find main_window")
+    })
+}
@@ -0,0 +1,438 @@
+// Completion popup of editors, each completion source is shown by a provider
+// of the sourceview completion
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use gtk::{
+    glib::{self, subclass::prelude::ObjectSubclassIsExt},
+    prelude::{Cast, IsA, ObjectExt},
+    traits::{TextBufferExt, TextViewExt},
+    TextBuffer, TextIter,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    completion::{
+        complete,
+        paths::PathsSource,
+        snippets::{expand_snippet, SnippetsSource},
+        words::WordsSource,
+        Candidate, CandidateKind, Completion, CompletionConfig, CompletionRequest,
+        CompletionSource,
+    },
+    lsp::types::{CompletionItem as LspCompletionItem, LspRequest, LspResponse, Position},
+    notebook::cache::NotebookTabCache,
+};
+use sourceview4::{
+    traits::{BufferExt, CompletionContextExt, CompletionExt, LanguageExt, ViewExt},
+    Buffer, CompletionActivation, CompletionContext, CompletionItem, CompletionProposal, View,
+};
+
+use super::{editor::Editor, lsp};
+
+mod imp {
+    use gtk::subclass::prelude::{ObjectImpl, ObjectSubclass};
+    use sourceview4::subclass::prelude::CompletionProviderImpl;
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct SourceProvider {
+        pub(super) source: RefCell<Option<Box<dyn CompletionSource>>>,
+        // Tells whether the source is turned on in `[Completion]` config
+        pub(super) is_enabled: Cell<Option<fn(&CompletionConfig) -> bool>>,
+        pub(super) file_path: RefCell<String>,
+        // Candidates come from the language server, they arrive after `populate`
+        pub(super) from_server: Cell<bool>,
+        // Proposals shown last, with the candidates they were built from
+        pub(super) proposals: RefCell<Vec<(CompletionProposal, Candidate)>>,
+        pub(super) prefix_length: Cell<usize>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SourceProvider {
+        const NAME: &'static str = "MysCompletionSourceProvider";
+
+        type Type = super::SourceProvider;
+        type ParentType = glib::Object;
+
+        type Interfaces = (sourceview4::CompletionProvider,);
+    }
+
+    impl ObjectImpl for SourceProvider {}
+
+    impl CompletionProviderImpl for SourceProvider {
+        fn name(&self, _provider: &Self::Type) -> Option<String> {
+            let source = self.source.borrow();
+            source.as_ref().map(|source| source.name().to_string())
+        }
+
+        fn match_(&self, provider: &Self::Type, context: &CompletionContext) -> bool {
+            provider.matches(context)
+        }
+
+        fn populate(&self, provider: &Self::Type, context: &CompletionContext) {
+            provider.populate(context);
+        }
+
+        fn start_iter(
+            &self,
+            _provider: &Self::Type,
+            context: &CompletionContext,
+            _proposal: &CompletionProposal,
+        ) -> Option<TextIter> {
+            let mut iter = context.iter()?;
+            iter.backward_chars(self.prefix_length.get() as i32);
+            Some(iter)
+        }
+
+        fn activate_proposal(
+            &self,
+            provider: &Self::Type,
+            proposal: &CompletionProposal,
+            iter: &TextIter,
+        ) -> bool {
+            provider.activate(proposal, iter)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SourceProvider(ObjectSubclass<imp::SourceProvider>)
+        @implements sourceview4::CompletionProvider;
+}
+
+impl SourceProvider {
+    fn new(
+        source: Box<dyn CompletionSource>,
+        is_enabled: fn(&CompletionConfig) -> bool,
+        file_path: &str,
+    ) -> Self {
+        let provider: Self = glib::Object::new(&[]).unwrap();
+
+        let imp = provider.imp();
+        imp.source.replace(Some(source));
+        imp.is_enabled.set(Some(is_enabled));
+        imp.file_path.replace(file_path.to_string());
+
+        provider
+    }
+
+    fn for_language_server(file_path: &str) -> Self {
+        let provider = Self::new(
+            Box::new(LanguageServerSource(vec![])),
+            |config| config.language_server,
+            file_path,
+        );
+        provider.imp().from_server.set(true);
+
+        provider
+    }
+
+    fn matches(&self, context: &CompletionContext) -> bool {
+        let config = AppConfig::current();
+        let is_enabled = self.imp().is_enabled.get();
+        if !is_enabled.map_or(false, |is_enabled| is_enabled(&config.Completion)) {
+            return false;
+        }
+
+        if context
+            .activation()
+            .contains(CompletionActivation::USER_REQUESTED)
+        {
+            return true;
+        }
+
+        context.iter().map_or(false, |iter| {
+            config.Completion.triggers(&line_before(&iter))
+        })
+    }
+
+    fn populate(&self, context: &CompletionContext) {
+        let imp = self.imp();
+
+        let Some(iter) = context.iter() else {
+            self.show(context, None);
+            return;
+        };
+        let uses_documents = imp
+            .source
+            .borrow()
+            .as_ref()
+            .is_some_and(|source| source.uses_documents());
+        let request = request_at(&imp.file_path.borrow(), &iter, uses_documents);
+
+        if imp.from_server.get() {
+            // Other sources are shown while waiting for the server
+            context.add_proposals(self, &[], false);
+            self.request_server_items(context, request);
+            return;
+        }
+
+        let completion = {
+            let source = imp.source.borrow();
+            source
+                .as_deref()
+                .and_then(|source| complete(source, &request))
+        };
+        self.show(context, completion);
+    }
+
+    fn request_server_items(&self, context: &CompletionContext, request: CompletionRequest) {
+        let server_request = LspRequest::Completion(Position::from_char_column(
+            request.line,
+            &request.line_before_cursor,
+            request.column as usize,
+        ));
+        let file_path = request.file_path.clone();
+        let edits = lsp::edit_count(&file_path);
+        let provider = self.clone();
+        let context = context.clone();
+
+        // The completion cancels contexts it no longer shows, ex: on typing
+        let cancelled = Rc::new(Cell::new(false));
+        let handler = context.connect_cancelled({
+            let cancelled = cancelled.clone();
+            move |_| cancelled.set(true)
+        });
+
+        lsp::request(&file_path.clone(), server_request, move |result| {
+            context.disconnect(handler);
+            if cancelled.get() {
+                return;
+            }
+            // Items of older text, the context is finished empty
+            if lsp::edit_count(&file_path) != edits {
+                provider.show(&context, None);
+                return;
+            }
+
+            let items = match result {
+                Ok(LspResponse::Completion(items)) => items,
+                Ok(_) => vec![],
+                Err(error) => {
+                    eprintln!("Completion failed: {error}");
+                    vec![]
+                }
+            };
+
+            let completion = complete(&LanguageServerSource(items), &request);
+            provider.show(&context, completion);
+        });
+    }
+
+    fn show(&self, context: &CompletionContext, completion: Option<Completion>) {
+        let imp = self.imp();
+
+        let (prefix_length, candidates) = completion
+            .map(|completion| (completion.prefix_length, completion.candidates))
+            .unwrap_or_default();
+
+        let proposals: Vec<(CompletionProposal, Candidate)> = candidates
+            .into_iter()
+            .map(|candidate| (proposal_for(&candidate), candidate))
+            .collect();
+        let items: Vec<CompletionProposal> = proposals.iter().map(|(p, _)| p.clone()).collect();
+
+        imp.prefix_length.set(prefix_length);
+        imp.proposals.replace(proposals);
+
+        // Finished, even without proposals
+        context.add_proposals(self, &items, true);
+    }
+
+    // Snippets are expanded, other proposals are inserted by the completion
+    fn activate(&self, proposal: &CompletionProposal, iter: &TextIter) -> bool {
+        let imp = self.imp();
+
+        let proposals = imp.proposals.borrow();
+        let Some((_, candidate)) = proposals.iter().find(|(p, _)| p == proposal) else {
+            return false;
+        };
+        if candidate.kind != CandidateKind::Snippet {
+            return false;
+        }
+
+        let Some(buffer) = iter.buffer() else {
+            return false;
+        };
+        let mut start = iter.clone();
+        start.backward_chars(imp.prefix_length.get() as i32);
+        insert_snippet(&buffer, &start, iter, &candidate.text);
+
+        true
+    }
+}
+
+/**
+ * Adds the completion providers to a new editor, sources turned off in
+ * config are skipped until they are turned on.
+ */
+pub fn attach(view: &View, file_path: &str) {
+    let Some(completion) = view.completion() else {
+        return;
+    };
+    completion.set_show_headers(true);
+    completion.set_remember_info_visibility(true);
+
+    let providers = [
+        SourceProvider::for_language_server(file_path),
+        SourceProvider::new(
+            Box::new(SnippetsSource),
+            |config| config.snippets,
+            file_path,
+        ),
+        SourceProvider::new(Box::new(PathsSource), |config| config.paths, file_path),
+        SourceProvider::new(Box::new(WordsSource), |config| config.words, file_path),
+    ];
+
+    for provider in providers {
+        if let Err(error) = completion.add_provider(&provider) {
+            eprintln!("Unable to add completion provider: {error}");
+        }
+    }
+}
+
+/**
+ * Shows completions at the cursor of the active editor.
+ */
+pub fn show() {
+    if let Some(view) = Editor::active() {
+        view.emit_show_completion();
+    }
+}
+
+/// Completes with items answered by the language server handling the file.
+struct LanguageServerSource(Vec<LspCompletionItem>);
+
+impl CompletionSource for LanguageServerSource {
+    fn name(&self) -> &str {
+        "Language Server"
+    }
+
+    fn candidates(&self, _request: &CompletionRequest, _prefix: &str) -> Vec<Candidate> {
+        self.0
+            .iter()
+            .map(|item| Candidate {
+                text: item.text().to_string(),
+                label: item.label.clone(),
+                detail: item.detail.clone(),
+                documentation: item.documentation.clone(),
+                kind: CandidateKind::LanguageServer,
+            })
+            .collect()
+    }
+}
+
+fn request_at(file_path: &str, iter: &TextIter, with_documents: bool) -> CompletionRequest {
+    let buffer = iter.buffer();
+    let language = buffer
+        .as_ref()
+        .and_then(|buffer| buffer.downcast_ref::<Buffer>())
+        .and_then(|buffer| buffer.language())
+        .and_then(|language| language.id())
+        .map(|id| id.to_string());
+
+    // Current document first
+    let mut documents = vec![];
+    if with_documents {
+        documents.extend(buffer.iter().map(Editor::buffer_text));
+        for tab in NotebookTabCache::all() {
+            if tab.file_path != file_path {
+                documents.extend(
+                    Editor::buffer_from_path(tab.file_path).map(|b| Editor::buffer_text(&b)),
+                );
+            }
+        }
+    }
+
+    CompletionRequest {
+        file_path: file_path.to_string(),
+        language,
+        line: iter.line() as u32,
+        column: iter.line_offset() as u32,
+        line_before_cursor: line_before(iter),
+        documents,
+    }
+}
+
+fn proposal_for(candidate: &Candidate) -> CompletionProposal {
+    let info = match (&candidate.detail, &candidate.documentation) {
+        (Some(detail), Some(documentation)) => Some(format!("{detail}\n\n{documentation}")),
+        (detail, documentation) => detail.clone().or_else(|| documentation.clone()),
+    };
+
+    let mut builder = CompletionItem::builder()
+        .label(&candidate.label)
+        .text(&candidate.text)
+        .icon_name(icon_name(candidate.kind));
+    if let Some(info) = &info {
+        builder = builder.info(info);
+    }
+
+    builder.build().upcast()
+}
+
+fn icon_name(kind: CandidateKind) -> &'static str {
+    match kind {
+        CandidateKind::Word => "format-text-plain-symbolic",
+        CandidateKind::File => "text-x-generic-symbolic",
+        CandidateKind::Directory => "folder-symbolic",
+        CandidateKind::Snippet => "insert-text-symbolic",
+        CandidateKind::LanguageServer => "system-run-symbolic",
+    }
+}
+
+// Replaces `start..end` with a snippet, indented like the current line
+fn insert_snippet(buffer: &impl IsA<TextBuffer>, start: &TextIter, end: &TextIter, body: &str) {
+    let line_start = buffer.iter_at_line(start.line());
+    let line_text = buffer
+        .text(&line_start, start, true)
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+    let indent: String = line_text
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+
+    let snippet = expand_snippet(body);
+    let text = snippet.text.replace('\n', &format!("\n{indent}"));
+    // Selection offsets move by the indent added on each line before them
+    let offset_in_text = |offset: usize| {
+        let lines_before = snippet
+            .text
+            .chars()
+            .take(offset)
+            .filter(|c| *c == '\n')
+            .count();
+        offset + lines_before * indent.chars().count()
+    };
+
+    buffer.begin_user_action();
+    let (mut start, mut end) = (start.clone(), end.clone());
+    buffer.delete(&mut start, &mut end);
+    let insert_offset = start.offset();
+    buffer.insert(&mut start, &text);
+    buffer.end_user_action();
+
+    let selection_start = insert_offset + offset_in_text(snippet.selection.0) as i32;
+    let selection_end = insert_offset + offset_in_text(snippet.selection.1) as i32;
+    buffer.select_range(
+        &buffer.iter_at_offset(selection_end),
+        &buffer.iter_at_offset(selection_start),
+    );
+}
+
+fn line_before(iter: &TextIter) -> String {
+    let Some(buffer) = iter.buffer() else {
+        return String::new();
+    };
+    let line_start = buffer.iter_at_line(iter.line());
+
+    buffer
+        .text(&line_start, iter, true)
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
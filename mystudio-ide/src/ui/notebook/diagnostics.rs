@@ -0,0 +1,175 @@
+// Shows diagnostics in editors, underlined in the text and marked in the gutter
+
+use gtk::{
+    gdk::RGBA,
+    pango::Underline,
+    prelude::{Cast, TextTagTableExt},
+    traits::{TextBufferExt, TextViewExt, WidgetExt},
+    TextIter, TextTag, TextWindowType,
+};
+use libmystudio::{
+    diagnostics::{Diagnostic, Severity, TextPosition},
+    notebook::cache::NotebookTabCache,
+};
+use sourceview4::{
+    traits::{BufferExt, ViewExt},
+    Buffer, MarkAttributes, View,
+};
+
+use crate::ui::features::problems;
+
+use super::editor::Editor;
+
+// Hints are shown like infos
+const SEVERITIES: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Info];
+
+/**
+ * Sets up gutter icons and tooltips of a new editor, then shows the current
+ * diagnostics of its file.
+ */
+pub fn attach(view: &View, file_path: &str) {
+    for severity in SEVERITIES {
+        let attributes = MarkAttributes::new();
+        attributes.set_icon_name(problems::icon_name(severity));
+        view.set_mark_attributes(category(severity), &attributes, priority(severity));
+    }
+
+    let file_path_clone = file_path.to_string();
+    view.set_has_tooltip(true);
+    view.connect_query_tooltip(move |view, x, y, keyboard_mode, tooltip| {
+        if keyboard_mode {
+            return false;
+        }
+
+        let (buffer_x, buffer_y) = view.window_to_buffer_coords(TextWindowType::Widget, x, y);
+        let Some(iter) = view.iter_at_location(buffer_x.max(0), buffer_y) else {
+            return false;
+        };
+        let position = TextPosition {
+            line: iter.line() as u32,
+            column: iter.line_offset() as u32,
+        };
+        // Pointer left of the text is over the gutter, where marks are per line
+        let in_gutter = buffer_x < 0;
+
+        let messages: Vec<String> = problems::for_path(&file_path_clone)
+            .iter()
+            .filter(|d| {
+                if in_gutter {
+                    d.range.start.line == position.line
+                } else {
+                    d.range.contains(position)
+                }
+            })
+            .map(Diagnostic::display_message)
+            .collect();
+        if messages.is_empty() {
+            return false;
+        }
+
+        tooltip.set_text(Some(&messages.join("\n")));
+        true
+    });
+
+    refresh(file_path);
+}
+
+/**
+ * Shows the latest diagnostics of a file in its editor, if it is open.
+ */
+pub fn refresh(file_path: &str) {
+    if NotebookTabCache::find_by_path(file_path.to_string()).is_none() {
+        return;
+    }
+    let Some(buffer) = Editor::buffer_from_path(file_path.to_string())
+        .and_then(|buffer| buffer.downcast::<Buffer>().ok())
+    else {
+        return;
+    };
+
+    apply(&buffer, &problems::for_path(file_path));
+}
+
+fn apply(buffer: &Buffer, diagnostics: &[Diagnostic]) {
+    ensure_tags(buffer);
+
+    let (buffer_start, buffer_end) = (buffer.start_iter(), buffer.end_iter());
+    for severity in SEVERITIES {
+        buffer.remove_tag_by_name(category(severity), &buffer_start, &buffer_end);
+        buffer.remove_source_marks(&buffer_start, &buffer_end, Some(category(severity)));
+    }
+
+    for diagnostic in diagnostics {
+        let mut start = iter_at(buffer, diagnostic.range.start);
+        let mut end = iter_at(buffer, diagnostic.range.end);
+
+        // Empty ranges still get a visible underline
+        if start == end && !end.forward_char() {
+            start.backward_char();
+        }
+
+        let category = category(diagnostic.severity);
+        buffer.apply_tag_by_name(category, &start, &end);
+        buffer.create_source_mark(None, category, &buffer.iter_at_line(start.line()));
+    }
+}
+
+// Tags are per buffer, a new buffer is set when a file is opened
+fn ensure_tags(buffer: &Buffer) {
+    let Some(tag_table) = buffer.tag_table() else {
+        return;
+    };
+
+    for severity in SEVERITIES {
+        if tag_table.lookup(category(severity)).is_none() {
+            let tag = TextTag::builder()
+                .name(category(severity))
+                .underline(Underline::Error)
+                .underline_rgba(&underline_color(severity))
+                .build();
+            tag_table.add(&tag);
+        }
+    }
+}
+
+// Clamps positions of outdated diagnostics to the text
+fn iter_at(buffer: &Buffer, position: TextPosition) -> TextIter {
+    if position.line as i32 >= buffer.line_count() {
+        return buffer.end_iter();
+    }
+
+    let mut iter = buffer.iter_at_line(position.line as i32);
+    let mut line_end = iter.clone();
+    if !line_end.ends_line() {
+        line_end.forward_to_line_end();
+    }
+    iter.set_line_offset((position.column as i32).min(line_end.line_offset()));
+
+    iter
+}
+
+// Name of the text tag and the mark category of a severity
+fn category(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "diagnostic-error",
+        Severity::Warning => "diagnostic-warning",
+        Severity::Info | Severity::Hint => "diagnostic-info",
+    }
+}
+
+// Marks of higher priority are shown when a line has several
+fn priority(severity: Severity) -> i32 {
+    match severity {
+        Severity::Error => 3,
+        Severity::Warning => 2,
+        Severity::Info | Severity::Hint => 1,
+    }
+}
+
+fn underline_color(severity: Severity) -> RGBA {
+    match severity {
+        Severity::Error => RGBA::new(0.88, 0.11, 0.14, 1.0),
+        Severity::Warning => RGBA::new(0.96, 0.62, 0.04, 1.0),
+        Severity::Info | Severity::Hint => RGBA::new(0.21, 0.52, 0.89, 1.0),
+    }
+}
@@ -0,0 +1,432 @@
+// Notebook pages comparing two texts side by side, ex: a file and its
+// committed version. Both sides are highlighted and scroll together.
+
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+    thread,
+};
+
+use gtk::{
+    gdk::{self, RGBA},
+    pango::EllipsizeMode,
+    prelude::{AdjustmentExt, Cast, TextTagTableExt},
+    traits::{BoxExt, ContainerExt, LabelExt, TextBufferExt, TextViewExt, WidgetExt},
+    Adjustment, Clipboard, Label, Orientation, ScrolledWindow, TextTag,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    compare::{Comparison, Side},
+    fs::read_file_contents,
+    git::{error::GitError, head_content},
+    notebook::cache::NotebookTabCache,
+    workspace::Workspace,
+};
+use sourceview4::{
+    traits::{BufferExt, LanguageManagerExt, ViewExt},
+    Buffer, LanguageManager, View,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::statusbar::message::show_message,
+};
+
+use super::{editor::Editor, nbmain::MysNotebook};
+
+// Tags of changed lines and of their changed parts
+const TAG_DELETED_LINE: &str = "diff-deleted-line";
+const TAG_DELETED_TEXT: &str = "diff-deleted-text";
+const TAG_ADDED_LINE: &str = "diff-added-line";
+const TAG_ADDED_TEXT: &str = "diff-added-text";
+
+/// A compared text, `file_path` picks its syntax highlighting.
+pub struct DiffSide {
+    pub title: String,
+    pub text: String,
+    pub file_path: String,
+}
+
+// File picked in the explorer, compared with the next one picked
+thread_local! { static G_COMPARED_FILE: RefCell<Option<String>> = RefCell::new(None) }
+
+/**
+ * Opens a page comparing `old` on the left with `new` on the right.
+ */
+pub fn open(title: &str, old: DiffSide, new: DiffSide) {
+    let comparison = Rc::new(Comparison::new(&old.text, &new.text));
+
+    let (old_box, old_view, old_window) = side(&old);
+    let (new_box, new_view, new_window) = side(&new);
+    highlight_changes(&old_view, &new_view, &comparison);
+
+    let syncing = Rc::new(Cell::new(false));
+    sync_scrolling(
+        (&old_view, &old_window),
+        (&new_view, &new_window),
+        Side::Old,
+        &comparison,
+        &syncing,
+    );
+    sync_scrolling(
+        (&new_view, &new_window),
+        (&old_view, &old_window),
+        Side::New,
+        &comparison,
+        &syncing,
+    );
+
+    let page = gtk::Box::new(Orientation::Horizontal, 2);
+    page.set_homogeneous(true);
+    page.pack_start(&old_box, true, true, 0);
+    page.pack_start(&new_box, true, true, 0);
+
+    MysNotebook::new_page_tab(page.upcast_ref(), title, "view-dual-symbolic");
+}
+
+/**
+ * Compares the open file with its version in the last commit, which is read on
+ * a background thread, see `head_read`.
+ */
+pub fn compare_with_head() {
+    let Some((file_path, _)) = active_file() else {
+        return;
+    };
+
+    let tx = Comms::sender();
+    thread::spawn(move || {
+        let head = head_content(Path::new(&file_path));
+        tx.send(CommEvents::CompareHeadRead(file_path, head)).ok();
+    });
+}
+
+/**
+ * Opens the comparison asked by `compare_with_head` with the committed text of
+ * the file and its current text.
+ */
+pub fn head_read(file_path: String, head: Result<Option<String>, GitError>) {
+    let head = match head {
+        Ok(Some(head)) => head,
+        Ok(None) => {
+            show_message(format!("'{}' isn't committed", file_name(&file_path)));
+            return;
+        }
+        Err(error) => {
+            show_message(format!("Unable to read the committed file: {error}"));
+            return;
+        }
+    };
+    // Unsaved changes made meanwhile are included
+    let Some(text) = file_text(&file_path) else {
+        show_message(format!("Unable to read '{}'", file_name(&file_path)));
+        return;
+    };
+
+    open_if_changed(
+        &format!("{} (HEAD)", file_name(&file_path)),
+        DiffSide {
+            title: String::from("HEAD"),
+            text: head,
+            file_path: file_path.clone(),
+        },
+        DiffSide {
+            title: String::from("Working Tree"),
+            text,
+            file_path: file_path.clone(),
+        },
+    );
+}
+
+/// Compares the open file on disk with its unsaved changes.
+pub fn compare_with_saved() {
+    let Some((file_path, text)) = active_file() else {
+        return;
+    };
+    let Some(saved) = read_file_contents(&file_path) else {
+        show_message(format!("Unable to read '{}'", file_name(&file_path)));
+        return;
+    };
+
+    open_if_changed(
+        &format!("{} (Unsaved)", file_name(&file_path)),
+        DiffSide {
+            title: String::from("Saved"),
+            text: saved,
+            file_path: file_path.clone(),
+        },
+        DiffSide {
+            title: String::from("Unsaved"),
+            text,
+            file_path: file_path.clone(),
+        },
+    );
+}
+
+/// Compares text of the clipboard with the text selected in the open file.
+pub fn compare_selection_with_clipboard() {
+    let (Some(file_path), Some(buffer)) = (
+        Workspace::get_open_file_path(),
+        Editor::active().and_then(|view| view.buffer()),
+    ) else {
+        return;
+    };
+    let Some((start, end)) = buffer.selection_bounds() else {
+        show_message(String::from("Select text to compare with the clipboard"));
+        return;
+    };
+    let Some(clipboard) = Clipboard::get(&gdk::SELECTION_CLIPBOARD).wait_for_text() else {
+        show_message(String::from("The clipboard has no text"));
+        return;
+    };
+    let selection = buffer
+        .text(&start, &end, true)
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+
+    open_if_changed(
+        "Clipboard ↔ Selection",
+        DiffSide {
+            title: String::from("Clipboard"),
+            text: clipboard.to_string(),
+            file_path: file_path.clone(),
+        },
+        DiffSide {
+            title: String::from("Selection"),
+            text: selection,
+            file_path: file_path.clone(),
+        },
+    );
+}
+
+/**
+ * Picks a file of the explorer to compare with the next one, see
+ * `compare_with_selected`.
+ */
+pub fn select_for_compare(file_path: &str) {
+    G_COMPARED_FILE.with(|file| *file.borrow_mut() = Some(file_path.to_string()));
+}
+
+/// Returns the file picked by `select_for_compare`, if any.
+pub fn selected_for_compare() -> Option<String> {
+    G_COMPARED_FILE.with(|file| file.borrow().clone())
+}
+
+/**
+ * Compares the file picked by `select_for_compare` on the left with
+ * `file_path` on the right.
+ */
+pub fn compare_with_selected(file_path: &str) {
+    let Some(selected) = selected_for_compare() else {
+        return;
+    };
+
+    let mut sides = vec![];
+    for path in [&selected, file_path] {
+        let Some(text) = file_text(path) else {
+            show_message(format!("Unable to read '{}'", file_name(path)));
+            return;
+        };
+        sides.push(DiffSide {
+            title: file_name(path),
+            text,
+            file_path: path.to_string(),
+        });
+    }
+
+    let new = sides.pop().unwrap();
+    let old = sides.pop().unwrap();
+    open_if_changed(&format!("{} ↔ {}", old.title, new.title), old, new);
+}
+
+fn open_if_changed(title: &str, old: DiffSide, new: DiffSide) {
+    if old.text == new.text {
+        show_message(format!("{} and {} are the same", old.title, new.title));
+        return;
+    }
+
+    open(title, old, new);
+}
+
+// Path and text of the open file, unsaved changes included
+fn active_file() -> Option<(String, String)> {
+    let file_path = Workspace::get_open_file_path();
+    let text = Editor::active()
+        .and_then(|view| view.buffer())
+        .map(|buffer| Editor::buffer_text(&buffer));
+    if file_path.is_none() || text.is_none() {
+        show_message(String::from("Open a file to compare"));
+    }
+
+    Some((file_path?, text?))
+}
+
+// Text of a file, from its editor when it is open to include unsaved changes
+fn file_text(file_path: &str) -> Option<String> {
+    if NotebookTabCache::find_by_path(file_path.to_string()).is_none() {
+        return read_file_contents(file_path);
+    }
+
+    Editor::buffer_from_path(file_path.to_string()).map(|buffer| Editor::buffer_text(&buffer))
+}
+
+fn file_name(file_path: &str) -> String {
+    Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_path.to_string())
+}
+
+// Title and read-only view of one side
+fn side(diff_side: &DiffSide) -> (gtk::Box, View, ScrolledWindow) {
+    let config = AppConfig::current();
+
+    let buffer = Buffer::builder().text(diff_side.text.as_str()).build();
+    let language = LanguageManager::new().guess_language(Some(&diff_side.file_path), None);
+    buffer.set_language(language.as_ref());
+    Editor::apply_style_scheme(&buffer, &config);
+
+    let view = View::with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_show_line_numbers(true);
+    view.set_tab_width(config.Editor.tab_width);
+
+    let window = ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    window.add(&view);
+
+    let title = Label::new(Some(&diff_side.title));
+    title.set_tooltip_text(Some(&diff_side.file_path));
+    title.set_ellipsize(EllipsizeMode::Start);
+    title.set_margin(4);
+
+    let side_box = gtk::Box::new(Orientation::Vertical, 0);
+    side_box.pack_start(&title, false, false, 0);
+    side_box.pack_start(&window, true, true, 0);
+
+    (side_box, view, window)
+}
+
+fn highlight_changes(old_view: &View, new_view: &View, comparison: &Comparison) {
+    let (Some(old_buffer), Some(new_buffer)) = (old_view.buffer(), new_view.buffer()) else {
+        return;
+    };
+    add_tags(
+        &old_buffer,
+        TAG_DELETED_LINE,
+        TAG_DELETED_TEXT,
+        0.8,
+        0.0,
+        0.0,
+    );
+    add_tags(&new_buffer, TAG_ADDED_LINE, TAG_ADDED_TEXT, 0.31, 0.6, 0.02);
+
+    for hunk in &comparison.hunks {
+        tag_lines(
+            &old_buffer,
+            TAG_DELETED_LINE,
+            hunk.old_start,
+            hunk.old_end(),
+        );
+        tag_lines(&new_buffer, TAG_ADDED_LINE, hunk.new_start, hunk.new_end());
+    }
+
+    for change in &comparison.inline_changes {
+        for range in &change.old_ranges {
+            let start = old_buffer.iter_at_line_offset(change.old_line as i32, range.start as i32);
+            let end = old_buffer.iter_at_line_offset(change.old_line as i32, range.end as i32);
+            old_buffer.apply_tag_by_name(TAG_DELETED_TEXT, &start, &end);
+        }
+        for range in &change.new_ranges {
+            let start = new_buffer.iter_at_line_offset(change.new_line as i32, range.start as i32);
+            let end = new_buffer.iter_at_line_offset(change.new_line as i32, range.end as i32);
+            new_buffer.apply_tag_by_name(TAG_ADDED_TEXT, &start, &end);
+        }
+    }
+}
+
+// Changed lines get a light background, their changed parts a darker one
+fn add_tags(buffer: &gtk::TextBuffer, line_tag: &str, text_tag: &str, r: f64, g: f64, b: f64) {
+    let Some(tag_table) = buffer.tag_table() else {
+        return;
+    };
+
+    tag_table.add(
+        &TextTag::builder()
+            .name(line_tag)
+            .paragraph_background_rgba(&RGBA::new(r, g, b, 0.15))
+            .build(),
+    );
+    tag_table.add(
+        &TextTag::builder()
+            .name(text_tag)
+            .background_rgba(&RGBA::new(r, g, b, 0.35))
+            .build(),
+    );
+}
+
+fn tag_lines(buffer: &gtk::TextBuffer, tag: &str, start: usize, end: usize) {
+    if start == end {
+        return;
+    }
+
+    let start = buffer.iter_at_line(start as i32);
+    let end = if end as i32 >= buffer.line_count() {
+        buffer.end_iter()
+    } else {
+        buffer.iter_at_line(end as i32)
+    };
+    buffer.apply_tag_by_name(tag, &start, &end);
+}
+
+// Scrolls the other side to the lines matching those at the top of a side
+fn sync_scrolling(
+    (view, window): (&View, &ScrolledWindow),
+    (other_view, other_window): (&View, &ScrolledWindow),
+    side: Side,
+    comparison: &Rc<Comparison>,
+    syncing: &Rc<Cell<bool>>,
+) {
+    let view = view.clone();
+    let other_view = other_view.clone();
+    let other_adjustment = other_window.vadjustment();
+    let comparison = comparison.clone();
+    let syncing_clone = syncing.clone();
+    window
+        .vadjustment()
+        .connect_value_changed(move |adjustment| {
+            if syncing_clone.replace(true) {
+                return;
+            }
+
+            // Same part of the line is shown at the top of both sides
+            let y = adjustment.value() as i32;
+            let (iter, line_top) = view.line_at_y(y);
+            let (_, height) = view.line_yrange(&iter);
+            let fraction = match height {
+                0 => 0.0,
+                height => f64::from(y - line_top) / f64::from(height),
+            };
+
+            let line = comparison.matching_line(iter.line() as usize, side);
+            if let Some(buffer) = other_view.buffer() {
+                let (other_top, other_height) =
+                    other_view.line_yrange(&buffer.iter_at_line(line as i32));
+                other_adjustment
+                    .set_value(f64::from(other_top) + fraction * f64::from(other_height));
+            }
+
+            syncing_clone.set(false);
+        });
+
+    let other_adjustment = other_window.hadjustment();
+    let syncing = syncing.clone();
+    window
+        .hadjustment()
+        .connect_value_changed(move |adjustment| {
+            if syncing.replace(true) {
+                return;
+            }
+            other_adjustment.set_value(adjustment.value());
+            syncing.set(false);
+        });
+}
@@ -2,13 +2,16 @@ use std::time::Duration;
 
 use gtk::{
     prelude::{Cast, ContainerExt, NotebookExtManual, ObjectExt, ScrolledWindowExt},
-    traits::{TextBufferExt, TextViewExt},
+    traits::{StyleContextExt, TextBufferExt, TextViewExt, WidgetExt},
     Adjustment, ScrolledWindow, Widget, TextBuffer,
 };
-use libmystudio::{notebook::cache::NotebookTabCache, tree::tree_model::RootTreeModel};
+use libmystudio::{
+    app_config::AppConfig, notebook::cache::NotebookTabCache, tree::tree_model::RootTreeModel,
+    workspace::Workspace,
+};
 use sourceview4::{
-    traits::{BufferExt, LanguageManagerExt, ViewExt},
-    LanguageManager, View,
+    traits::{BufferExt, LanguageManagerExt, StyleSchemeManagerExt, ViewExt},
+    Buffer, LanguageManager, StyleSchemeManager, View,
 };
 
 use crate::{
@@ -18,6 +21,35 @@ use crate::{
 
 use super::nbmain::MysNotebook;
 
+/**
+ * Commands editing or reading the text at the cursor of the open editor, they
+ * only run from keys while the editor has focus.
+ */
+pub const EDITOR_COMMANDS: &[&str] = &[
+    "show_hover",
+    "show_completion",
+    "go_to_definition",
+    "find_references",
+    "move_line_start",
+    "move_line_end",
+    "forward_char",
+    "backward_char",
+    "next_line",
+    "previous_line",
+    "set_mark",
+    "kill_line",
+    "kill_region",
+    "copy_region",
+    "yank",
+    "yank_pop",
+    "isearch_forward",
+    "isearch_backward",
+    "keyboard_quit",
+];
+
+// Style class of editor views, the editor font only applies to them
+pub const EDITOR_STYLE_CLASS: &str = "editor";
+
 pub struct Editor {
     pub inner: View,
 }
@@ -26,32 +58,51 @@ impl Editor {
     pub fn new() -> Editor {
         let view = sourceview4::View::new();
         Self::set_editor_defaut_options(&view);
+        super::vim::attach(&view);
+        super::emacs::attach(&view);
 
         Editor { inner: view }
     }
 
+    /**
+     * Returns the view of the editor tab of a file, `None` when the file has
+     * no tab or its tab isn't an editor, ex: a diff.
+     */
     pub fn from_path(file_path: String) -> Option<View> {
-        let notebook_tab = NotebookTabCache::find_by_path(file_path);
-        let page_num = notebook_tab.map(|f| f.position);
-
-        let notebook = MysNotebook::get().unwrap();
-        let page = notebook.nth_page(page_num);
-        let scrolled_window = page.map(|page| page.downcast::<ScrolledWindow>().unwrap());
+        let notebook_tab = NotebookTabCache::find_by_path(file_path)?;
+        let page = MysNotebook::get()?.nth_page(Some(notebook_tab.position))?;
 
-        let view = scrolled_window
-            .unwrap()
+        page.downcast::<ScrolledWindow>()
+            .ok()?
             .children()
-            .first()
-            .unwrap()
+            .first()?
             .clone()
             .downcast::<View>()
-            .unwrap();
+            .ok()
+    }
+
+    /**
+     * Returns the view of the open file, if any.
+     */
+    pub fn active() -> Option<View> {
+        Workspace::get_open_file_path().and_then(Self::from_path)
+    }
 
-        Some(view)
+    /// Whether keyboard focus is in the open editor.
+    pub fn has_focus() -> bool {
+        Self::active().is_some_and(|view| view.has_focus())
     }
 
     pub fn buffer_from_path(file_path: String) -> Option<TextBuffer> {
-        Self::from_path(file_path).unwrap().buffer()   
+        Self::from_path(file_path)?.buffer()
+    }
+
+    /// Returns the whole text of a buffer, hidden text included.
+    pub fn buffer_text(buffer: &TextBuffer) -> String {
+        buffer
+            .text(&buffer.start_iter(), &buffer.end_iter(), true)
+            .map(|text| text.to_string())
+            .unwrap_or_default()
     }
 
     pub fn set_text(
@@ -78,6 +129,8 @@ impl Editor {
                         source_buffer.set_language(sourceview4::Language::NONE);
                     }
                 }
+                Self::apply_style_scheme(&source_buffer, &AppConfig::current());
+
                 // update buffer in View
                 editor.inner.set_buffer(Some(&source_buffer));
 
@@ -95,13 +148,63 @@ impl Editor {
 
     fn set_editor_defaut_options(view: &View) {
         view.set_show_line_marks(true);
-        view.set_show_line_numbers(true);
         view.set_auto_indent(true);
-        view.set_highlight_current_line(true);
+        view.style_context().add_class(EDITOR_STYLE_CLASS);
+
+        Self::apply_config(view, &AppConfig::current());
+    }
+
+    /**
+     * Applies `[Editor]` options from AppConfig to a given editor.
+     */
+    pub fn apply_config(view: &View, config: &AppConfig) {
+        let options = &config.Editor;
+
+        view.set_show_line_numbers(options.show_line_numbers);
+        view.set_highlight_current_line(options.highlight_current_line);
+        view.set_tab_width(options.tab_width);
+        view.set_insert_spaces_instead_of_tabs(options.insert_spaces);
+        super::vim::sync_view(view, options.vim_mode);
+
+        if let Some(buffer) = view.buffer() {
+            if let Ok(buffer) = buffer.downcast::<Buffer>() {
+                Self::apply_style_scheme(&buffer, config);
+            }
+        }
+    }
+
+    pub fn apply_style_scheme(buffer: &Buffer, config: &AppConfig) {
+        let scheme_manager = StyleSchemeManager::default().unwrap();
+        match scheme_manager.scheme(&config.Editor.style_scheme) {
+            Some(scheme) => buffer.set_style_scheme(Some(&scheme)),
+            None => eprintln!(
+                "Unable to find style scheme '{}'",
+                config.Editor.style_scheme
+            ),
+        }
     }
 }
 
+/**
+ * Opens a file at a given line and column, the location left is recorded in
+ * navigation history.
+ */
 pub fn open_editor_for_abs_path(abs_path: String, line: i32, col: i32) {
+    super::navigation::record_current_location();
+    open_editor_at(abs_path, line, col);
+}
+
+/**
+ * Same as `open_editor_for_abs_path` without recording navigation history,
+ * ex: when going back.
+ */
+pub fn open_editor_at(abs_path: String, line: i32, col: i32) {
+    // No need to wait when the file is the active one
+    if Workspace::get_open_file_path().as_ref() == Some(&abs_path) {
+        jump_to_line_for_active_tab(line, col);
+        return;
+    }
+
     // create a mock RootTreeModel for convenience
     let tree_model = RootTreeModel::default();
     tree_model.set_property("abs-path", abs_path);
@@ -175,5 +278,9 @@ mod tests {
         // Verify if editor is available
         let editor = Editor::from_path(mock_cache.file_path);
         assert!(editor.is_some());
+
+        // Files without a tab have no editor
+        let editor = Editor::from_path(root_dir.path().join("main.js").to_str().unwrap().into());
+        assert!(editor.is_none());
     }
 }
@@ -0,0 +1,304 @@
+// Editing commands bound by the Emacs keymap preset
+
+use std::cell::RefCell;
+
+use gtk::{
+    gdk::{keys::constants as key_constants, ModifierType},
+    prelude::TextBufferExt,
+    traits::{TextViewExt, WidgetExt},
+    MovementStep, TextBuffer,
+};
+use libmystudio::emacs::{kill_line_range, IncrementalSearch, KillRing};
+use sourceview4::View;
+
+use crate::ui::statusbar::message::show_message;
+
+use super::editor::Editor;
+
+#[derive(Default)]
+struct EmacsState {
+    kill_ring: KillRing,
+    // Movements extend the selection while the mark is set
+    mark_active: bool,
+    // Consecutive kills are joined, `yank_pop` only follows a yank
+    last_command: Option<&'static str>,
+    // Offsets of the text inserted by the last yank
+    yank_range: Option<(i32, i32)>,
+    isearch: Option<IncrementalSearch>,
+}
+
+thread_local! { static G_EMACS: RefCell<EmacsState> = RefCell::new(EmacsState::default()) }
+
+/**
+ * Runs an editing command on the open editor and remembers it as the last
+ * command.
+ */
+fn run(name: &'static str, command: impl FnOnce(&View, &TextBuffer, &mut EmacsState)) {
+    let Some(view) = Editor::active() else {
+        return;
+    };
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+
+    G_EMACS.with(|state| {
+        let mut state = state.borrow_mut();
+        command(&view, &buffer, &mut state);
+        state.last_command = Some(name);
+    });
+}
+
+/**
+ * Handles keys typed during an incremental search, other keys end the
+ * search and reset the last command.
+ */
+pub fn attach(view: &View) {
+    view.connect_key_press_event(|view, event| {
+        let Some(buffer) = view.buffer() else {
+            return gtk::Inhibit(false);
+        };
+
+        G_EMACS.with(|state| {
+            let mut state = state.borrow_mut();
+            state.last_command = None;
+
+            let Some(search) = state.isearch.as_mut() else {
+                return gtk::Inhibit(false);
+            };
+
+            if event.is_modifier() {
+                return gtk::Inhibit(false);
+            }
+
+            let keyval = event.keyval();
+            let has_modifiers = event
+                .state()
+                .intersects(ModifierType::CONTROL_MASK | ModifierType::MOD1_MASK);
+            let typed_char = keyval
+                .to_unicode()
+                .filter(|c| !c.is_control() && !has_modifiers);
+
+            if keyval == key_constants::BackSpace {
+                search.pop_char();
+            } else if let Some(c) = typed_char {
+                search.push_char(c, &chars_of(&buffer));
+            } else {
+                // Enter and Escape only end the search, other keys also do their usual thing
+                let ends_search =
+                    keyval == key_constants::Return || keyval == key_constants::Escape;
+                state.isearch = None;
+                return gtk::Inhibit(ends_search);
+            }
+
+            select_match(view, &buffer, search);
+            gtk::Inhibit(true)
+        })
+    });
+}
+
+pub fn move_line_start() {
+    move_cursor("move_line_start", MovementStep::ParagraphEnds, -1);
+}
+
+pub fn move_line_end() {
+    move_cursor("move_line_end", MovementStep::ParagraphEnds, 1);
+}
+
+pub fn forward_char() {
+    move_cursor("forward_char", MovementStep::LogicalPositions, 1);
+}
+
+pub fn backward_char() {
+    move_cursor("backward_char", MovementStep::LogicalPositions, -1);
+}
+
+pub fn next_line() {
+    move_cursor("next_line", MovementStep::DisplayLines, 1);
+}
+
+pub fn previous_line() {
+    move_cursor("previous_line", MovementStep::DisplayLines, -1);
+}
+
+fn move_cursor(name: &'static str, step: MovementStep, count: i32) {
+    run(name, |view, _, state| {
+        view.emit_move_cursor(step, count, state.mark_active);
+    });
+}
+
+pub fn set_mark() {
+    run("set_mark", |_, buffer, state| {
+        let cursor = cursor_iter(buffer);
+        buffer.select_range(&cursor, &cursor);
+        state.mark_active = true;
+
+        show_message(String::from("Mark set"));
+    });
+}
+
+/**
+ * Cancels an incremental search or deactivates the mark.
+ */
+pub fn keyboard_quit() {
+    run("keyboard_quit", |view, buffer, state| {
+        if let Some(search) = state.isearch.take() {
+            // Go back to where the search started
+            buffer.place_cursor(&buffer.iter_at_offset(search.start() as i32));
+        } else {
+            buffer.place_cursor(&cursor_iter(buffer));
+        }
+        state.mark_active = false;
+
+        scroll_to_cursor(view, buffer);
+        show_message(String::from("Quit"));
+    });
+}
+
+pub fn kill_line() {
+    run("kill_line", |_, buffer, state| {
+        let cursor = buffer.cursor_position() as usize;
+        let (start, end) = kill_line_range(&chars_of(buffer), cursor);
+        if start == end {
+            return;
+        }
+
+        let append = state.last_command == Some("kill_line");
+        kill(buffer, state, start as i32, end as i32, append);
+    });
+}
+
+pub fn kill_region() {
+    run("kill_region", |_, buffer, state| {
+        if let Some((start, end)) = buffer.selection_bounds() {
+            kill(buffer, state, start.offset(), end.offset(), false);
+        }
+        state.mark_active = false;
+    });
+}
+
+pub fn copy_region() {
+    run("copy_region", |_, buffer, state| {
+        if let Some((start, end)) = buffer.selection_bounds() {
+            if let Some(text) = buffer.text(&start, &end, true) {
+                state.kill_ring.kill(&text, false);
+            }
+            buffer.place_cursor(&cursor_iter(buffer));
+        }
+        state.mark_active = false;
+    });
+}
+
+pub fn yank() {
+    run("yank", |view, buffer, state| {
+        let Some(text) = state.kill_ring.yank().map(String::from) else {
+            return;
+        };
+
+        state.yank_range = Some(insert_at_cursor(buffer, &text));
+        state.mark_active = false;
+        scroll_to_cursor(view, buffer);
+    });
+}
+
+/**
+ * Replaces the text just yanked with an older kill.
+ */
+pub fn yank_pop() {
+    run("yank_pop", |view, buffer, state| {
+        let follows_yank = matches!(state.last_command, Some("yank" | "yank_pop"));
+        let (Some((start, end)), true) = (state.yank_range, follows_yank) else {
+            show_message(String::from("Previous command was not a yank"));
+            return;
+        };
+        let Some(text) = state.kill_ring.yank_pop().map(String::from) else {
+            return;
+        };
+
+        buffer.begin_user_action();
+        buffer.delete(
+            &mut buffer.iter_at_offset(start),
+            &mut buffer.iter_at_offset(end),
+        );
+        buffer.place_cursor(&buffer.iter_at_offset(start));
+        state.yank_range = Some(insert_at_cursor(buffer, &text));
+        buffer.end_user_action();
+
+        scroll_to_cursor(view, buffer);
+    });
+}
+
+pub fn isearch_forward() {
+    isearch("isearch_forward", true);
+}
+
+pub fn isearch_backward() {
+    isearch("isearch_backward", false);
+}
+
+fn isearch(name: &'static str, forward: bool) {
+    run(name, |view, buffer, state| {
+        match state.isearch.as_mut() {
+            // Repeating the key moves to the next match
+            Some(search) => {
+                search.next(&chars_of(buffer), forward);
+            }
+            None => {
+                let start = buffer.cursor_position() as usize;
+                state.isearch = Some(IncrementalSearch::new(start, forward));
+            }
+        }
+
+        if let Some(search) = state.isearch.as_ref() {
+            select_match(view, buffer, search);
+        }
+    });
+}
+
+fn select_match(view: &View, buffer: &TextBuffer, search: &IncrementalSearch) {
+    if let Some((start, end)) = search.current() {
+        let start = buffer.iter_at_offset(start as i32);
+        let end = buffer.iter_at_offset(end as i32);
+
+        // Cursor ends up past the match in the search direction
+        if search.forward {
+            buffer.select_range(&end, &start);
+        } else {
+            buffer.select_range(&start, &end);
+        }
+        scroll_to_cursor(view, buffer);
+    }
+
+    show_message(search.prompt());
+}
+
+fn kill(buffer: &TextBuffer, state: &mut EmacsState, start: i32, end: i32, append: bool) {
+    let mut start = buffer.iter_at_offset(start);
+    let mut end = buffer.iter_at_offset(end);
+
+    if let Some(text) = buffer.text(&start, &end, true) {
+        state.kill_ring.kill(&text, append);
+    }
+    buffer.delete(&mut start, &mut end);
+}
+
+// Returns offsets of the inserted text
+fn insert_at_cursor(buffer: &TextBuffer, text: &str) -> (i32, i32) {
+    let start = buffer.cursor_position();
+    buffer.insert_at_cursor(text);
+
+    (start, buffer.cursor_position())
+}
+
+fn cursor_iter(buffer: &TextBuffer) -> gtk::TextIter {
+    buffer.iter_at_offset(buffer.cursor_position())
+}
+
+fn scroll_to_cursor(view: &View, buffer: &TextBuffer) {
+    if let Some(mark) = buffer.get_insert() {
+        view.scroll_mark_onscreen(&mark);
+    }
+}
+
+fn chars_of(buffer: &TextBuffer) -> Vec<char> {
+    Editor::buffer_text(buffer).chars().collect()
+}
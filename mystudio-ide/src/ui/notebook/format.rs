@@ -0,0 +1,99 @@
+// Format Document command and format on save, formatters are configured in
+// `[Formatters]` of config.toml
+
+use gtk::{traits::TextBufferExt, TextBuffer};
+use libmystudio::{
+    app_config::AppConfig,
+    format::{format_text, formatter_for_path, minimal_edits, TextEdit},
+    notebook::cache::NotebookTabCache,
+    workspace::Workspace,
+};
+
+use crate::ui::statusbar::message::show_message;
+
+use super::editor::Editor;
+
+/// Formats the text of the active editor.
+pub fn format_document() {
+    let Some(file_path) = Workspace::get_open_file_path() else {
+        return;
+    };
+    if NotebookTabCache::find_by_path(file_path.clone()).is_none() {
+        return;
+    }
+    let Some(buffer) = Editor::buffer_from_path(file_path.clone()) else {
+        return;
+    };
+
+    let config = AppConfig::current();
+    if formatter_for_path(&config.Formatters, &file_path).is_none() {
+        show_message(format!("No formatter configured for '{file_path}'"));
+        return;
+    }
+
+    match format_buffer(&buffer, &file_path) {
+        Ok(true) => show_message(format!("Formatted '{file_path}'")),
+        Ok(false) => show_message(format!("'{file_path}' is already formatted")),
+        Err(message) => show_message(message),
+    }
+}
+
+/**
+ * Formats a buffer about to be saved when `format_on_save` is turned on and
+ * the file has a formatter.
+ */
+pub fn format_on_save(buffer: &TextBuffer, file_path: &str) -> Result<(), String> {
+    let config = AppConfig::current();
+    if !config.Editor.format_on_save || formatter_for_path(&config.Formatters, file_path).is_none()
+    {
+        return Ok(());
+    }
+
+    format_buffer(buffer, file_path).map(|_| ())
+}
+
+/**
+ * Replaces the text of `buffer` with the output of the formatter of
+ * `file_path`, returns whether the text changed.
+ *
+ * Only lines changed by the formatter are replaced so the cursor and marks
+ * elsewhere stay in place, and a single undo reverts the whole formatting.
+ */
+fn format_buffer(buffer: &TextBuffer, file_path: &str) -> Result<bool, String> {
+    let config = AppConfig::current();
+    let Some(formatter) = formatter_for_path(&config.Formatters, file_path) else {
+        return Ok(false);
+    };
+
+    let text = buffer
+        .text(&buffer.start_iter(), &buffer.end_iter(), true)
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+    let formatted = format_text(formatter, file_path, &text)
+        .map_err(|error| format!("Unable to format '{file_path}': {error}"))?;
+
+    let edits = minimal_edits(&text, &formatted);
+    if edits.is_empty() {
+        return Ok(false);
+    }
+
+    apply_edits(buffer, &edits);
+
+    Ok(true)
+}
+
+/**
+ * Applies edits from `minimal_edits` to `buffer` as a single user action, so
+ * that one undo reverts all of them.
+ */
+pub fn apply_edits(buffer: &TextBuffer, edits: &[TextEdit]) {
+    buffer.begin_user_action();
+    // Last to first, offsets of earlier edits are unchanged
+    for edit in edits.iter().rev() {
+        let mut start = buffer.iter_at_offset(edit.start as i32);
+        let mut end = buffer.iter_at_offset(edit.end as i32);
+        buffer.delete(&mut start, &mut end);
+        buffer.insert(&mut start, &edit.text);
+    }
+    buffer.end_user_action();
+}
@@ -0,0 +1,461 @@
+// Marks lines changed since the last commit in the editor gutter. Clicking a
+// mark peeks at the committed lines, to revert or stage the change. Committed
+// text is read on a background thread, see `load_heads`.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    path::Path,
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+use gtk::{
+    gdk::{Rectangle, RGBA},
+    glib,
+    prelude::{Cast, PopoverExt, TextBufferExt},
+    traits::{BoxExt, ButtonExt, ContainerExt, LabelExt, TextViewExt, WidgetExt},
+    Align, Button, Label, Orientation, Popover, ScrolledWindow, TextView, TextWindowType,
+};
+use libmystudio::{
+    diff::split_lines,
+    format::minimal_edits,
+    git::{
+        changes::{line_changes, revert_hunk, stage_hunk, ChangeKind, LineChange},
+        head_content,
+    },
+    notebook::{cache::NotebookTabCache, editor::jump_to_line_with_editor},
+    workspace::Workspace,
+};
+use sourceview4::{
+    traits::{GutterExt, GutterRendererExt, ViewExt},
+    GutterRendererPixbuf, View,
+};
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{statusbar::message::show_message, w_explorer},
+};
+
+use super::{editor::Editor, format::apply_edits};
+
+// Marks are updated once typing pauses
+const CHANGES_DELAY: Duration = Duration::from_millis(300);
+// Between line marks (-20) and the text
+const RENDERER_POSITION: i32 = -10;
+const RENDERER_WIDTH: i32 = 4;
+
+struct FileChanges {
+    // Text of the file in HEAD, `None` when it isn't committed or not read yet
+    head: Option<String>,
+    // Id of the last read of `head`, older reads are dropped
+    head_load: u64,
+    changes: Vec<LineChange>,
+    // Lines of the buffer when `changes` were computed
+    line_count: usize,
+    renderer: GutterRendererPixbuf,
+}
+
+struct Peek {
+    popover: Popover,
+    title: Label,
+    committed: ScrolledWindow,
+    committed_view: TextView,
+}
+
+// Changes of open files, by file path
+thread_local! { static G_FILE_CHANGES: RefCell<HashMap<String, FileChanges>> = RefCell::new(HashMap::new()) }
+thread_local! { static G_PEEK: RefCell<Option<Peek>> = RefCell::new(None) }
+// File path and line of the change shown by the peek
+thread_local! { static G_PEEK_TARGET: RefCell<Option<(String, usize)>> = RefCell::new(None) }
+thread_local! { static G_NEXT_HEAD_LOAD: Cell<u64> = Cell::new(1) }
+
+/**
+ * Adds the gutter column showing changes of a new editor and keeps it up to
+ * date while typing.
+ */
+pub fn attach(view: &View, file_path: &str) {
+    let Some(gutter) = view.gutter(TextWindowType::Left) else {
+        return;
+    };
+    let renderer = GutterRendererPixbuf::new();
+    renderer.set_size(RENDERER_WIDTH);
+    gutter.insert(&renderer, RENDERER_POSITION);
+
+    let file_path_clone = file_path.to_string();
+    renderer.connect_query_data(move |renderer, start, _end, _state| {
+        let color = change_at_line(&file_path_clone, start.line() as usize)
+            .map(|change| change_color(change.kind));
+        renderer.set_background(color.as_ref());
+    });
+
+    let file_path_clone = file_path.to_string();
+    renderer.connect_query_activatable(move |_, iter, _, _| {
+        change_at_line(&file_path_clone, iter.line() as usize).is_some()
+    });
+
+    let file_path_clone = file_path.to_string();
+    renderer.connect_activate(move |renderer, iter, _, _| {
+        if let Some(view) = renderer
+            .view()
+            .and_then(|view| view.downcast::<View>().ok())
+        {
+            show_peek(&view, &file_path_clone, iter.line() as usize);
+        }
+    });
+
+    let pending = Rc::new(Cell::new(false));
+    let file_path_clone = file_path.to_string();
+    if let Some(buffer) = view.buffer() {
+        buffer.connect_changed(move |_| {
+            if pending.replace(true) {
+                return;
+            }
+
+            let pending = pending.clone();
+            let file_path = file_path_clone.clone();
+            glib::timeout_add_local_once(CHANGES_DELAY, move || {
+                pending.set(false);
+                update(&file_path);
+            });
+        });
+    }
+
+    G_FILE_CHANGES.with(|files| {
+        files.borrow_mut().insert(
+            file_path.to_string(),
+            FileChanges {
+                head: None,
+                head_load: 0,
+                changes: vec![],
+                line_count: 0,
+                renderer,
+            },
+        );
+    });
+    load_heads(vec![file_path.to_string()]);
+}
+
+/// Forgets changes of a file whose tab was closed.
+pub fn detach(file_path: &str) {
+    G_FILE_CHANGES.with(|files| files.borrow_mut().remove(file_path));
+}
+
+/**
+ * Reads committed versions of open files again, ex: after a commit or after
+ * switching branches.
+ */
+pub fn reload_heads() {
+    let file_paths: Vec<String> =
+        G_FILE_CHANGES.with(|files| files.borrow().keys().cloned().collect());
+
+    load_heads(file_paths);
+}
+
+/**
+ * Marks changes of a file against its committed text read by `load_heads`.
+ */
+pub fn head_loaded(load: u64, file_path: &str, head: Option<String>) {
+    let current = G_FILE_CHANGES.with(|files| {
+        let mut files = files.borrow_mut();
+        let Some(file) = files.get_mut(file_path) else {
+            return false;
+        };
+        if file.head_load != load {
+            return false;
+        }
+
+        file.head = head;
+        true
+    });
+
+    if current {
+        update(file_path);
+    }
+}
+
+// Reads committed text of files on a background thread, each is sent as
+// `CommEvents::GitHeadLoaded`
+fn load_heads(file_paths: Vec<String>) {
+    if file_paths.is_empty() {
+        return;
+    }
+
+    let load = G_NEXT_HEAD_LOAD.with(|next_load| next_load.replace(next_load.get() + 1));
+    G_FILE_CHANGES.with(|files| {
+        let mut files = files.borrow_mut();
+        for file_path in &file_paths {
+            if let Some(file) = files.get_mut(file_path) {
+                file.head_load = load;
+            }
+        }
+    });
+
+    let tx = Comms::sender();
+    thread::spawn(move || {
+        for file_path in file_paths {
+            let head = head_content(Path::new(&file_path)).ok().flatten();
+            tx.send(CommEvents::GitHeadLoaded(load, file_path, head))
+                .ok();
+        }
+    });
+}
+
+/// Moves the cursor to the next change of the active editor.
+pub fn next_change() {
+    go_to_change(true);
+}
+
+/// Moves the cursor to the previous change of the active editor.
+pub fn previous_change() {
+    go_to_change(false);
+}
+
+fn go_to_change(forward: bool) {
+    let Some(file_path) = Workspace::get_open_file_path() else {
+        return;
+    };
+    let Some(view) = editor_view(&file_path) else {
+        return;
+    };
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+
+    let lines: Vec<usize> = G_FILE_CHANGES.with(|files| {
+        files
+            .borrow()
+            .get(&file_path)
+            .map_or_else(Vec::new, |file| {
+                file.changes
+                    .iter()
+                    .map(|change| change.marked_lines(file.line_count).0)
+                    .collect()
+            })
+    });
+    if lines.is_empty() {
+        show_message(String::from("No changes since last commit"));
+        return;
+    }
+
+    // Wraps around at both ends of the file
+    let cursor_line = buffer.iter_at_offset(buffer.cursor_position()).line() as usize;
+    let line = if forward {
+        lines
+            .iter()
+            .find(|line| **line > cursor_line)
+            .or(lines.first())
+    } else {
+        lines
+            .iter()
+            .rev()
+            .find(|line| **line < cursor_line)
+            .or(lines.last())
+    };
+
+    if let Some(line) = line {
+        jump_to_line_with_editor(&view, *line as i32 + 1, 1);
+    }
+}
+
+// Computes changes again from the text of the buffer
+fn update(file_path: &str) {
+    let Some((text, line_count)) = buffer_text(file_path) else {
+        return;
+    };
+
+    G_FILE_CHANGES.with(|files| {
+        if let Some(file) = files.borrow_mut().get_mut(file_path) {
+            file.changes = file
+                .head
+                .as_deref()
+                .map(|head| line_changes(head, &text))
+                .unwrap_or_default();
+            file.line_count = line_count;
+            file.renderer.queue_draw();
+        }
+    });
+}
+
+fn change_at_line(file_path: &str, line: usize) -> Option<LineChange> {
+    G_FILE_CHANGES.with(|files| {
+        let files = files.borrow();
+        let file = files.get(file_path)?;
+        file.changes.iter().copied().find(|change| {
+            let (start, end) = change.marked_lines(file.line_count);
+            (start..end).contains(&line)
+        })
+    })
+}
+
+// Committed text of the file and the change marked at `line`
+fn head_and_change(file_path: &str, line: usize) -> Option<(String, LineChange)> {
+    let head = G_FILE_CHANGES.with(|files| {
+        files
+            .borrow()
+            .get(file_path)
+            .and_then(|file| file.head.clone())
+    })?;
+
+    change_at_line(file_path, line).map(|change| (head, change))
+}
+
+fn show_peek(view: &View, file_path: &str, line: usize) {
+    // Typing may not have updated marks yet
+    update(file_path);
+    let Some((head, change)) = head_and_change(file_path, line) else {
+        return;
+    };
+
+    let hunk = change.hunk;
+    let (kind, count) = match change.kind {
+        ChangeKind::Added => ("added", hunk.new_len),
+        ChangeKind::Modified => ("modified", hunk.new_len),
+        ChangeKind::Deleted => ("deleted", hunk.old_len),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    let committed = split_lines(&head)[hunk.old_start..hunk.old_end()].concat();
+
+    G_PEEK.with(|peek| {
+        let mut peek = peek.borrow_mut();
+        let peek = peek.get_or_insert_with(build_peek);
+
+        peek.title.set_markup(&format!(
+            "<b>{count} line{plural} {kind}</b> since last commit"
+        ));
+        if let Some(buffer) = peek.committed_view.buffer() {
+            buffer.set_text(committed.trim_end_matches('\n'));
+        }
+
+        let iter = view.buffer().map(|buffer| buffer.iter_at_line(line as i32));
+        if let Some(iter) = iter {
+            let location = view.iter_location(&iter);
+            let (_, y) =
+                view.buffer_to_window_coords(TextWindowType::Widget, location.x(), location.y());
+            peek.popover.set_relative_to(Some(view));
+            peek.popover
+                .set_pointing_to(&Rectangle::new(0, y, 1, location.height()));
+        }
+
+        G_PEEK_TARGET.with(|target| *target.borrow_mut() = Some((file_path.to_string(), line)));
+        peek.popover.show_all();
+        // Nothing was committed in place of added lines
+        peek.committed.set_visible(!committed.is_empty());
+        peek.popover.popup();
+    });
+}
+
+fn build_peek() -> Peek {
+    let title = Label::builder().halign(Align::Start).build();
+
+    let committed_view = TextView::builder()
+        .editable(false)
+        .monospace(true)
+        .cursor_visible(false)
+        .build();
+    let committed = ScrolledWindow::builder()
+        .min_content_width(420)
+        .max_content_height(240)
+        .propagate_natural_height(true)
+        .build();
+    committed.add(&committed_view);
+
+    let revert_button = Button::with_label("Revert");
+    revert_button.connect_clicked(|_| {
+        if let Some((file_path, line)) = take_peek_target() {
+            revert_change(&file_path, line);
+        }
+    });
+    let stage_button = Button::with_label("Stage");
+    stage_button.connect_clicked(|_| {
+        if let Some((file_path, line)) = take_peek_target() {
+            stage_change(&file_path, line);
+        }
+    });
+
+    let buttons = gtk::Box::new(Orientation::Horizontal, 6);
+    buttons.set_halign(Align::End);
+    buttons.pack_start(&revert_button, false, false, 0);
+    buttons.pack_start(&stage_button, false, false, 0);
+
+    let content = gtk::Box::new(Orientation::Vertical, 6);
+    content.set_margin(8);
+    content.pack_start(&title, false, false, 0);
+    content.pack_start(&committed, true, true, 0);
+    content.pack_start(&buttons, false, false, 0);
+
+    let popover = Popover::new(None::<&gtk::Widget>);
+    popover.add(&content);
+
+    Peek {
+        popover,
+        title,
+        committed,
+        committed_view,
+    }
+}
+
+// Closes the peek, returning the change it showed
+fn take_peek_target() -> Option<(String, usize)> {
+    G_PEEK.with(|peek| {
+        if let Some(peek) = peek.borrow().as_ref() {
+            peek.popover.popdown();
+        }
+    });
+    G_PEEK_TARGET.with(|target| target.borrow_mut().take())
+}
+
+fn revert_change(file_path: &str, line: usize) {
+    update(file_path);
+    let Some((head, change)) = head_and_change(file_path, line) else {
+        return;
+    };
+    let Some(buffer) = editor_view(file_path).and_then(|view| view.buffer()) else {
+        return;
+    };
+    let Some((text, _)) = buffer_text(file_path) else {
+        return;
+    };
+
+    let reverted = revert_hunk(&head, &text, &change.hunk);
+    apply_edits(&buffer, &minimal_edits(&text, &reverted));
+}
+
+fn stage_change(file_path: &str, line: usize) {
+    update(file_path);
+    let Some((head, change)) = head_and_change(file_path, line) else {
+        return;
+    };
+    let Some((text, _)) = buffer_text(file_path) else {
+        return;
+    };
+
+    match stage_hunk(Path::new(file_path), &head, &text, &change.hunk) {
+        Ok(_) => {
+            show_message(format!("Staged change of '{file_path}'"));
+            w_explorer::refresh_git_status();
+        }
+        Err(error) => show_message(format!("Unable to stage change: {error}")),
+    }
+}
+
+fn editor_view(file_path: &str) -> Option<View> {
+    NotebookTabCache::find_by_path(file_path.to_string())?;
+    Editor::from_path(file_path.to_string())
+}
+
+// Text of the editor of the file and its number of lines
+fn buffer_text(file_path: &str) -> Option<(String, usize)> {
+    let buffer = editor_view(file_path)?.buffer()?;
+
+    Some((Editor::buffer_text(&buffer), buffer.line_count() as usize))
+}
+
+fn change_color(kind: ChangeKind) -> RGBA {
+    match kind {
+        ChangeKind::Added => RGBA::new(0.31, 0.6, 0.02, 1.0),
+        ChangeKind::Modified => RGBA::new(0.2, 0.4, 0.8, 1.0),
+        ChangeKind::Deleted => RGBA::new(0.8, 0.0, 0.0, 1.0),
+    }
+}
@@ -39,17 +39,28 @@ pub fn handle_notebook_event(content: Option<String>, file_path: Option<String>)
     editor.set_text(Some(file_path.clone()), content, true);
 
     // create new tab
+    let view = editor.inner.clone();
     let icon_name = get_icon_for_name(&file_name, TreeNodeType::File);
     let tab_position = MysNotebook::new_tab(editor.inner, &file_name, &icon_name);
 
     let tab = NotebookTabCache {
-        file_path,
+        file_path: file_path.clone(),
         position: tab_position,
         icon_name,
     };
 
     // Save to cache
     NotebookTabCache::insert(tab);
+
+    // Sync with language server
+    super::lsp::open_document(&view, &file_path);
+    super::diagnostics::attach(&view, &file_path);
+    super::completion::attach(&view, &file_path);
+    super::navigation::attach(&view);
+    super::git_gutter::attach(&view, &file_path);
+    crate::ui::features::outline::attach(&view, &file_path);
+    crate::ui::features::test_explorer::attach(&view, &file_path);
+    crate::ui::features::debugger::attach(&view, &file_path);
 }
 
 fn focus_tab_if_exists(file_path: Option<String>, notebook: &gtk::Notebook) -> ControlFlow<()> {
@@ -84,6 +95,14 @@ fn reset_ui_if_needed(
         // reset tabs cache
         NotebookTabCache::reset();
 
+        // Servers belong to the previous workspace
+        super::lsp::reset();
+        super::navigation::reset();
+        crate::ui::features::symbol_search::workspace_changed();
+        crate::ui::features::outline::refresh();
+        crate::ui::features::test_explorer::workspace_changed();
+        crate::ui::features::debugger::workspace_changed();
+
         return ControlFlow::Break(());
     }
     ControlFlow::Continue(())
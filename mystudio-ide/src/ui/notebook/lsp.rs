@@ -0,0 +1,349 @@
+// Keeps language servers in sync with editor buffers, servers run on a
+// worker thread and answer requests through `CommEvents`
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use gtk::{
+    gdk::Rectangle,
+    glib,
+    prelude::{LabelExt, PopoverExt, TextBufferExt},
+    traits::{ContainerExt, TextViewExt, WidgetExt},
+    Label, Popover, TextBuffer, TextIter, TextWindowType,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    diagnostics::Diagnostic,
+    lsp::{
+        error::LspError,
+        types::{LspEvent, LspRequest, LspResponse, Position},
+        worker::LspWorker,
+        LspManager,
+    },
+    notebook::cache::NotebookTabCache,
+    workspace::Workspace,
+};
+use sourceview4::View;
+
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{features::problems, statusbar::message::show_message},
+};
+
+use super::editor::Editor;
+
+type ResponseCallback = Box<dyn FnOnce(Result<LspResponse, LspError>)>;
+
+// Changes are sent once typing pauses
+const CHANGE_DELAY: Duration = Duration::from_millis(300);
+// Owner of diagnostics published by servers in the Problems panel
+const DIAGNOSTICS_OWNER: &str = "lsp";
+
+// Servers of the open workspace, created when the first document is opened
+thread_local! { static G_LSP: RefCell<Option<LspWorker>> = RefCell::new(None) }
+// Requests waiting for their answer, by request id
+thread_local! { static G_PENDING_REQUESTS: RefCell<HashMap<u64, ResponseCallback>> = RefCell::new(HashMap::new()) }
+thread_local! { static G_NEXT_REQUEST_ID: Cell<u64> = Cell::new(1) }
+// Buffers changed since their text was last sent, by file path
+thread_local! { static G_PENDING_CHANGES: RefCell<BTreeMap<String, TextBuffer>> = RefCell::new(BTreeMap::new()) }
+// Number of changes of each open document, answers to older text are stale
+thread_local! { static G_EDIT_COUNTS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new()) }
+thread_local! { static G_HOVER_POPOVER: RefCell<Option<(Popover, Label)>> = RefCell::new(None) }
+
+fn with_worker<R>(f: impl FnOnce(&LspWorker) -> R) -> R {
+    G_LSP.with(|lsp| {
+        let mut lsp = lsp.borrow_mut();
+        let worker = lsp.get_or_insert_with(|| {
+            let tx = Comms::sender();
+            let servers = AppConfig::current().LanguageServers.clone();
+
+            LspWorker::new(LspManager::new(
+                &Workspace::get_path(),
+                servers,
+                move |event| {
+                    tx.send(CommEvents::LanguageServerEvent(event)).ok();
+                },
+            ))
+        });
+
+        f(worker)
+    })
+}
+
+/**
+ * Opens the document of a new editor on its server and sends its changes
+ * from now on. The server is started in the background on first use.
+ */
+pub fn open_document(view: &View, file_path: &str) {
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+
+    with_worker(|worker| worker.did_open(file_path, Editor::buffer_text(&buffer)));
+
+    let file_path = file_path.to_string();
+    buffer.connect_changed(move |buffer| schedule_change(&file_path, buffer));
+}
+
+fn schedule_change(file_path: &str, buffer: &TextBuffer) {
+    G_EDIT_COUNTS.with(|counts| {
+        *counts
+            .borrow_mut()
+            .entry(file_path.to_string())
+            .or_default() += 1
+    });
+
+    let is_first_change = G_PENDING_CHANGES.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let is_first_change = pending.is_empty();
+        pending.insert(file_path.to_string(), buffer.clone());
+        is_first_change
+    });
+
+    if is_first_change {
+        glib::timeout_add_local_once(CHANGE_DELAY, flush_changes);
+    }
+}
+
+/**
+ * Sends pending changes right away, requests should see the latest text.
+ */
+pub fn flush_changes() {
+    let pending = G_PENDING_CHANGES.with(|pending| std::mem::take(&mut *pending.borrow_mut()));
+
+    for (file_path, buffer) in pending {
+        with_worker(|worker| worker.did_change(&file_path, Editor::buffer_text(&buffer)));
+    }
+}
+
+pub fn document_saved(file_path: &str) {
+    flush_changes();
+
+    with_worker(|worker| worker.did_save(file_path));
+}
+
+pub fn close_document(file_path: &str) {
+    G_PENDING_CHANGES.with(|pending| pending.borrow_mut().remove(file_path));
+    G_EDIT_COUNTS.with(|counts| counts.borrow_mut().remove(file_path));
+
+    with_worker(|worker| worker.did_close(file_path));
+}
+
+/**
+ * Stops all servers, ex: when another workspace is opened. Answers to
+ * requests sent before are dropped.
+ */
+pub fn reset() {
+    G_PENDING_CHANGES.with(|pending| pending.borrow_mut().clear());
+    G_PENDING_REQUESTS.with(|requests| requests.borrow_mut().clear());
+    G_EDIT_COUNTS.with(|counts| counts.borrow_mut().clear());
+    problems::clear_owner(DIAGNOSTICS_OWNER);
+
+    // Servers are shut down on the worker thread
+    G_LSP.with(|lsp| lsp.borrow_mut().take());
+}
+
+/**
+ * Restarts servers with the `[LanguageServers]` of the current config, the
+ * documents of open editors are opened again on them.
+ */
+pub fn servers_changed() {
+    reset();
+
+    for tab in NotebookTabCache::all() {
+        if let Some(buffer) = Editor::buffer_from_path(tab.file_path.clone()) {
+            with_worker(|worker| worker.did_open(&tab.file_path, Editor::buffer_text(&buffer)));
+        }
+    }
+}
+
+/**
+ * Sends a request about a document to its server, after pending changes.
+ *
+ * `on_response` is called once the server answers, with
+ * `LspResponse::Unsupported` when no running server can.
+ */
+pub fn request<F>(file_path: &str, request: LspRequest, on_response: F)
+where
+    F: FnOnce(Result<LspResponse, LspError>) + 'static,
+{
+    flush_changes();
+
+    let id = G_NEXT_REQUEST_ID.with(|next_id| next_id.replace(next_id.get() + 1));
+    G_PENDING_REQUESTS.with(|requests| requests.borrow_mut().insert(id, Box::new(on_response)));
+
+    with_worker(|worker| worker.request(id, file_path, request));
+}
+
+/**
+ * Returns the number of changes of an open document, compared before and
+ * after a request to drop answers about older text.
+ */
+pub fn edit_count(file_path: &str) -> u64 {
+    G_EDIT_COUNTS.with(|counts| counts.borrow().get(file_path).copied().unwrap_or_default())
+}
+
+/**
+ * Returns a check telling whether the active editor still has the same
+ * text and cursor as now.
+ */
+pub fn cursor_unchanged() -> impl Fn() -> bool {
+    let view = Editor::active();
+    let file_path = Workspace::get_open_file_path().unwrap_or_default();
+    let offset = cursor_offset(view.as_ref());
+    let edits = edit_count(&file_path);
+
+    move || {
+        let active = Editor::active();
+        active.is_some()
+            && active == view
+            && cursor_offset(active.as_ref()) == offset
+            && edit_count(&file_path) == edits
+    }
+}
+
+fn cursor_offset(view: Option<&View>) -> Option<i32> {
+    Some(view?.buffer()?.cursor_position())
+}
+
+pub fn handle_event(event: LspEvent) {
+    match event {
+        LspEvent::Diagnostics { path, diagnostics } => {
+            // Servers count columns in UTF-16, the editor in chars
+            let text = open_buffer(&path).map(|buffer| Editor::buffer_text(&buffer));
+
+            let diagnostics = diagnostics
+                .iter()
+                .map(|d| Diagnostic::from_lsp(&path, d, text.as_deref()))
+                .collect();
+            problems::set(DIAGNOSTICS_OWNER, &path, diagnostics);
+        }
+        LspEvent::ShowMessage(message) => show_message(message),
+        LspEvent::Exited(language) => {
+            // Servers also exit when they are shut down on purpose
+            G_LSP.with(|lsp| {
+                if let Some(worker) = lsp.borrow().as_ref() {
+                    worker.server_exited(&language);
+                }
+            });
+        }
+        LspEvent::Response { id, result } => {
+            let on_response = G_PENDING_REQUESTS.with(|requests| requests.borrow_mut().remove(&id));
+            if let Some(on_response) = on_response {
+                on_response(result);
+            }
+        }
+    }
+}
+
+/**
+ * Shows documentation of the symbol at cursor in a popover, once the server
+ * answers.
+ */
+pub fn show_hover() {
+    let (Some(view), Some(file_path)) = (Editor::active(), Workspace::get_open_file_path()) else {
+        return;
+    };
+    let Some(buffer) = view.buffer() else {
+        return;
+    };
+
+    let offset = buffer.cursor_position();
+    let position = position_of(&buffer, &buffer.iter_at_offset(offset));
+    let is_current = cursor_unchanged();
+
+    request(&file_path, LspRequest::Hover(position), move |result| {
+        if !is_current() {
+            return;
+        }
+
+        match result {
+            Ok(LspResponse::Hover(Some(hover))) => show_popover(
+                &view,
+                &buffer.iter_at_offset(offset),
+                &plain_text(&hover.contents),
+            ),
+            Ok(LspResponse::Unsupported) => {
+                show_message(String::from("No language server for this file"))
+            }
+            Ok(_) => show_message(String::from("No information at cursor")),
+            Err(error) => show_message(format!("Hover failed: {error}")),
+        }
+    });
+}
+
+/**
+ * Returns the server position of `iter`.
+ */
+pub fn position_of(buffer: &TextBuffer, iter: &TextIter) -> Position {
+    let line_start = buffer.iter_at_line(iter.line());
+    let before = buffer
+        .text(&line_start, iter, true)
+        .map(|text| text.to_string())
+        .unwrap_or_default();
+
+    Position::from_char_column(iter.line() as u32, &before, before.chars().count())
+}
+
+fn show_popover(view: &View, iter: &TextIter, text: &str) {
+    let (popover, label) = G_HOVER_POPOVER.with(|popover| {
+        popover
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                let label = Label::builder()
+                    .selectable(true)
+                    .wrap(true)
+                    .max_width_chars(80)
+                    .margin(8)
+                    .build();
+                let popover = Popover::new(None::<&gtk::Widget>);
+                popover.add(&label);
+                (popover, label)
+            })
+            .clone()
+    });
+    label.set_text(text);
+
+    let location = view.iter_location(iter);
+    let (x, y) = view.buffer_to_window_coords(TextWindowType::Widget, location.x(), location.y());
+
+    popover.set_relative_to(Some(view));
+    popover.set_pointing_to(&Rectangle::new(x, y, 1, location.height()));
+    popover.show_all();
+    popover.popup();
+}
+
+// Drops code fences of markdown, code is shown as is
+fn plain_text(markdown: &str) -> String {
+    markdown
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+// Returns the buffer of a file open in a tab
+fn open_buffer(file_path: &str) -> Option<TextBuffer> {
+    NotebookTabCache::find_by_path(file_path.to_string())?;
+    Editor::buffer_from_path(file_path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::plain_text;
+
+    #[test]
+    fn plain_text_test() {
+        let markdown = "```rust\npub fn save_file_changes(path: String)\n```\n\nSaves a file.\n";
+
+        assert_eq!(
+            plain_text(markdown),
+            "pub fn save_file_changes(path: String)\n\nSaves a file."
+        );
+    }
+}
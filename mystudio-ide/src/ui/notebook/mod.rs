@@ -9,9 +9,18 @@ use libmystudio::{notebook::cache::NotebookTabCache, workspace::Workspace};
 
 use self::nbmain::MysNotebook;
 
+pub mod completion;
+pub mod diagnostics;
+pub mod diff_view;
 pub mod editor;
+pub mod emacs;
+pub mod format;
+pub mod git_gutter;
 pub mod handler;
+pub mod lsp;
+pub mod navigation;
 pub mod nbmain;
+pub mod vim;
 
 thread_local! { pub static G_NOTEBOOK: RefCell<Option<Notebook>> = RefCell::new(None) }
 
@@ -34,6 +43,7 @@ pub fn init(builder: &Builder) {
             Workspace::set_open_file_path(Some(tab_cache.file_path));
 
             crate::ui::statusbar::sync();
+            crate::ui::features::outline::refresh();
         }
     });
 }
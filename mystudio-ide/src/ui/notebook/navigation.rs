@@ -0,0 +1,402 @@
+// Go to definition, find references and back/forward history of jumps
+
+use std::{cell::RefCell, collections::BTreeMap, fs, path::Path};
+
+use gtk::{
+    gdk::{EventType, ModifierType},
+    glib::Type,
+    prelude::{Cast, GtkListStoreExtManual, TreeModelExt, TreeViewExt},
+    traits::{
+        BoxExt, CellLayoutExt, ContainerExt, GtkListStoreExt, LabelExt, TextBufferExt, TextViewExt,
+        WidgetExt,
+    },
+    Adjustment, CellRendererText, Label, ListStore, Orientation, ScrolledWindow, TextBuffer,
+    TextWindowType, TreeView, TreeViewColumn,
+};
+use libmystudio::{
+    lsp::types::{Location as LspLocation, LspRequest, LspResponse, Position},
+    navigation::{Location, NavigationHistory},
+    notebook::{cache::NotebookTabCache, editor::fetch_line_number_by_buffer},
+    symbols::word_at,
+    workspace::Workspace,
+};
+use sourceview4::{Buffer, View};
+
+use crate::ui::{bottom_panel, features::symbol_search, statusbar::message::show_message};
+
+use super::{
+    editor::{open_editor_at, open_editor_for_abs_path, Editor},
+    lsp,
+};
+
+thread_local! { static G_NAV_HISTORY: RefCell<NavigationHistory> = RefCell::new(NavigationHistory::default()) }
+thread_local! { static G_REFERENCES_PAGE: RefCell<Option<(gtk::Box, Label, ListStore)>> = RefCell::new(None) }
+
+// Columns of the references store
+const COLUMN_TEXT: u32 = 0;
+const COLUMN_PATH: u32 = 1;
+// One-based like `open_editor_for_abs_path`
+const COLUMN_LINE: u32 = 2;
+const COLUMN_COLUMN: u32 = 3;
+
+/**
+ * Goes to the definition of the symbol under the pointer on Ctrl+click.
+ */
+pub fn attach(view: &View) {
+    view.connect_button_press_event(|view, event| {
+        let is_ctrl_click = event.event_type() == EventType::ButtonPress
+            && event.button() == 1
+            && event.state().contains(ModifierType::CONTROL_MASK);
+        // Clicks on the gutter are left alone
+        let on_text = event.window() == view.window(TextWindowType::Text);
+        if !is_ctrl_click || !on_text {
+            return gtk::Inhibit(false);
+        }
+
+        let (x, y) = event.position();
+        let (x, y) = view.window_to_buffer_coords(TextWindowType::Text, x as i32, y as i32);
+        let (Some(buffer), Some(iter)) = (view.buffer(), view.iter_at_location(x, y)) else {
+            return gtk::Inhibit(false);
+        };
+
+        buffer.place_cursor(&iter);
+        go_to_definition();
+        gtk::Inhibit(true)
+    });
+}
+
+/// Location of the cursor in the active editor.
+pub fn current_location() -> Option<Location> {
+    let file_path = Workspace::get_open_file_path()?;
+    let buffer = Editor::active()?.buffer()?.downcast::<Buffer>().ok()?;
+    let (line, column) = fetch_line_number_by_buffer(&buffer);
+
+    Some(Location::new(&file_path, line + 1, column + 1))
+}
+
+/**
+ * Records the cursor location before a jump, see `go_back`.
+ */
+pub fn record_current_location() {
+    if let Some(location) = current_location() {
+        G_NAV_HISTORY.with(|history| history.borrow_mut().record(location));
+    }
+}
+
+/// Returns to the location left by the last jump.
+pub fn go_back() {
+    let Some(current) = current_location() else {
+        return;
+    };
+
+    match G_NAV_HISTORY.with(|history| history.borrow_mut().back(current)) {
+        Some(location) => open_location_at(location),
+        None => show_message(String::from("Nothing to go back to")),
+    }
+}
+
+/// Undoes `go_back`.
+pub fn go_forward() {
+    let Some(current) = current_location() else {
+        return;
+    };
+
+    match G_NAV_HISTORY.with(|history| history.borrow_mut().forward(current)) {
+        Some(location) => open_location_at(location),
+        None => show_message(String::from("Nothing to go forward to")),
+    }
+}
+
+/**
+ * Goes to the definition of the symbol at cursor, found by the language
+ * server or in the symbol index. Many definitions are listed in the
+ * References panel.
+ */
+pub fn go_to_definition() {
+    let Some((file_path, word, position)) = symbol_at_cursor() else {
+        return;
+    };
+    let request = LspRequest::Definition(position);
+    let is_current = lsp::cursor_unchanged();
+
+    lsp::request(&file_path, request, move |result| {
+        if !is_current() {
+            return;
+        }
+
+        let server_locations = match result {
+            Ok(LspResponse::Definition(locations)) => locations,
+            Ok(_) => vec![],
+            Err(error) => {
+                eprintln!("Go to definition failed: {error}");
+                vec![]
+            }
+        };
+        show_definitions(&word, &server_locations);
+    });
+}
+
+fn show_definitions(word: &str, server_locations: &[LspLocation]) {
+    let mut lines = FileLines::default();
+    let mut locations: Vec<Location> = server_locations
+        .iter()
+        .map(|location| lines.location_from_lsp(location))
+        .collect();
+
+    if locations.is_empty() {
+        locations = symbol_search::with_index(|index| {
+            index
+                .definitions(word)
+                .iter()
+                .map(|symbol| {
+                    Location::new(
+                        &symbol.path,
+                        symbol.line as i32 + 1,
+                        symbol.column as i32 + 1,
+                    )
+                })
+                .collect()
+        });
+    }
+
+    match locations.len() {
+        0 => show_message(format!("No definition found for '{word}'")),
+        1 => open_location(locations.remove(0)),
+        _ => show_locations(&format!("Definitions of '{word}'"), locations, &mut lines),
+    }
+}
+
+/**
+ * Lists references to the symbol at cursor in the References panel.
+ *
+ * Without a language server, whole word matches in the workspace are listed.
+ */
+pub fn find_references() {
+    let Some((file_path, word, position)) = symbol_at_cursor() else {
+        return;
+    };
+    let request = LspRequest::References(position);
+    let is_current = lsp::cursor_unchanged();
+
+    lsp::request(&file_path, request, move |result| {
+        if !is_current() {
+            return;
+        }
+
+        // `None` when no server could answer
+        let server_locations = match result {
+            Ok(LspResponse::References(locations)) => Some(locations),
+            Ok(_) => None,
+            Err(error) => {
+                eprintln!("Find references failed: {error}");
+                None
+            }
+        };
+        show_references(&word, server_locations.as_deref());
+    });
+}
+
+fn show_references(word: &str, server_locations: Option<&[LspLocation]>) {
+    let mut lines = FileLines::default();
+    let locations = match server_locations {
+        Some(locations) => locations
+            .iter()
+            .map(|location| lines.location_from_lsp(location))
+            .collect(),
+        None => match Workspace::search(format!(r"\b{word}\b")) {
+            Ok(results) => results
+                .iter()
+                .map(|result| {
+                    Location::new(
+                        &result.path.to_string_lossy(),
+                        result.line_number,
+                        result.offset_start + 1,
+                    )
+                })
+                .collect(),
+            Err(error) => {
+                show_message(format!("Find references failed: {error}"));
+                return;
+            }
+        },
+    };
+
+    if locations.is_empty() {
+        show_message(format!("No references found for '{word}'"));
+        return;
+    }
+    show_locations(&format!("References to '{word}'"), locations, &mut lines);
+}
+
+/**
+ * Forgets history and references of the previous workspace.
+ */
+pub fn reset() {
+    G_NAV_HISTORY.with(|history| history.borrow_mut().clear());
+
+    if let Some((_, _, store)) = G_REFERENCES_PAGE.with(|page| page.borrow().clone()) {
+        store.clear();
+    }
+}
+
+fn open_location(location: Location) {
+    open_editor_for_abs_path(location.path, location.line, location.column);
+}
+
+fn open_location_at(location: Location) {
+    open_editor_at(location.path, location.line, location.column);
+}
+
+// Returns the file, word and server position at cursor of the active editor
+fn symbol_at_cursor() -> Option<(String, String, Position)> {
+    let file_path = Workspace::get_open_file_path()?;
+    let buffer = Editor::active()?.buffer()?;
+    let cursor = buffer.iter_at_offset(buffer.cursor_position());
+    let line = buffer_line(&buffer, cursor.line());
+
+    let Some(word) = word_at(&line, cursor.line_index() as usize) else {
+        show_message(String::from("No symbol at cursor"));
+        return None;
+    };
+
+    Some((
+        file_path,
+        word.to_string(),
+        lsp::position_of(&buffer, &cursor),
+    ))
+}
+
+// Lines of files, each file is read once per lookup
+#[derive(Default)]
+struct FileLines(BTreeMap<String, Vec<String>>);
+
+impl FileLines {
+    // Open files are read from their editor, they may have unsaved changes
+    fn line(&mut self, file_path: &str, line: usize) -> &str {
+        let lines = self.0.entry(file_path.to_string()).or_insert_with(|| {
+            let text = match NotebookTabCache::find_by_path(file_path.to_string()) {
+                Some(_) => Editor::buffer_from_path(file_path.to_string())
+                    .and_then(|buffer| buffer.text(&buffer.start_iter(), &buffer.end_iter(), true))
+                    .map(|text| text.to_string()),
+                None => fs::read_to_string(file_path).ok(),
+            };
+            text.unwrap_or_default()
+                .lines()
+                .map(str::to_string)
+                .collect()
+        });
+
+        lines.get(line).map_or("", String::as_str)
+    }
+
+    // Servers count columns in UTF-16, jumps use byte offsets
+    fn location_from_lsp(&mut self, location: &LspLocation) -> Location {
+        let start = location.range.start;
+        let line = self.line(&location.path, start.line as usize);
+        let column = start.char_column(line);
+        let byte_column = line
+            .char_indices()
+            .nth(column)
+            .map_or(line.len(), |(index, _)| index);
+
+        Location::new(
+            &location.path,
+            start.line as i32 + 1,
+            byte_column as i32 + 1,
+        )
+    }
+}
+
+fn buffer_line(buffer: &TextBuffer, line: i32) -> String {
+    let start = buffer.iter_at_line(line);
+    let mut end = start.clone();
+    // Already at the end on empty lines
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+
+    buffer
+        .text(&start, &end, true)
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+}
+
+fn show_locations(title: &str, locations: Vec<Location>, lines: &mut FileLines) {
+    let (page, label, store) = references_page();
+    label.set_text(&format!("{title} ({})", locations.len()));
+    store.clear();
+
+    let workspace_path = Workspace::get_path();
+    for location in locations {
+        let relative_path = Path::new(&location.path)
+            .strip_prefix(&workspace_path)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| location.path.clone());
+        let line = lines.line(&location.path, location.line as usize - 1);
+
+        store.insert_with_values(
+            None,
+            &[
+                (
+                    COLUMN_TEXT,
+                    &format!("{relative_path}:{}  {}", location.line, line.trim()),
+                ),
+                (COLUMN_PATH, &location.path),
+                (COLUMN_LINE, &location.line),
+                (COLUMN_COLUMN, &location.column),
+            ],
+        );
+    }
+
+    bottom_panel::show_page(&page);
+}
+
+// Page of the bottom panel listing locations, added on first use
+fn references_page() -> (gtk::Box, Label, ListStore) {
+    if let Some(page) = G_REFERENCES_PAGE.with(|page| page.borrow().clone()) {
+        return page;
+    }
+
+    let store = ListStore::new(&[Type::STRING, Type::STRING, Type::I32, Type::I32]);
+
+    let tree_view = TreeView::with_model(&store);
+    tree_view.set_headers_visible(false);
+    let column = TreeViewColumn::new();
+    let cell_text = CellRendererText::new();
+    column.pack_start(&cell_text, true);
+    column.add_attribute(&cell_text, "text", COLUMN_TEXT as i32);
+    tree_view.append_column(&column);
+
+    tree_view.connect_row_activated(|tree_view, path, _column| {
+        let Some(model) = tree_view.model() else {
+            return;
+        };
+        let Some(iter) = model.iter(path) else {
+            return;
+        };
+
+        let value = |column: u32| model.value(&iter, column as i32);
+        open_location(Location {
+            path: value(COLUMN_PATH).get::<String>().unwrap(),
+            line: value(COLUMN_LINE).get::<i32>().unwrap(),
+            column: value(COLUMN_COLUMN).get::<i32>().unwrap(),
+        });
+    });
+
+    let scrolled_window =
+        ScrolledWindow::new(Some(&Adjustment::default()), Some(&Adjustment::default()));
+    scrolled_window.add(&tree_view);
+
+    let label = Label::new(None);
+    label.set_xalign(0.0);
+    label.set_margin(4);
+
+    let page = gtk::Box::new(Orientation::Vertical, 0);
+    page.pack_start(&label, false, false, 0);
+    page.pack_start(&scrolled_window, true, true, 0);
+    bottom_panel::add_page(&page, "References");
+
+    let page = (page, label, store);
+    G_REFERENCES_PAGE.with(|references| *references.borrow_mut() = Some(page.clone()));
+    page
+}
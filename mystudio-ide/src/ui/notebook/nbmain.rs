@@ -4,7 +4,7 @@ use gtk::{
     traits::{BoxExt, ButtonExt, ContainerExt, WidgetExt},
     IconSize, Notebook, Orientation, ReliefStyle, Widget,
 };
-use libmystudio::notebook::cache::NotebookTabCache;
+use libmystudio::{notebook::cache::NotebookTabCache, workspace::Workspace};
 
 use super::{editor::enable_scroll_for_sourceview, G_NOTEBOOK};
 
@@ -15,8 +15,25 @@ impl MysNotebook {
         G_NOTEBOOK.with(move |notebook| notebook.borrow().clone())
     }
 
-    // Borrowed from https://github.com/gtk-rs/gtk3-rs/blob/9046f47158093d6fa40aa32ffbb0abaa75d57fd0/examples/notebook/notebook.rs#L18
     pub fn new_tab(editor: sourceview4::View, title: &str, icon_name: &str) -> u32 {
+        let editor_widget = editor.clone().upcast::<Widget>();
+
+        let my_scroll_window_widget = enable_scroll_for_sourceview(&editor_widget);
+        let index = Self::new_page_tab(&my_scroll_window_widget, title, icon_name);
+
+        // Set focus on first open
+        editor.set_has_focus(true);
+        editor.set_is_focus(true);
+
+        index
+    }
+
+    /**
+     * Adds a tab showing `page` rather than an editor, ex: a diff. Such tabs
+     * aren't in `NotebookTabCache`.
+     */
+    // Borrowed from https://github.com/gtk-rs/gtk3-rs/blob/9046f47158093d6fa40aa32ffbb0abaa75d57fd0/examples/notebook/notebook.rs#L18
+    pub fn new_page_tab(page: &Widget, title: &str, icon_name: &str) -> u32 {
         let close_image = gtk::Image::from_icon_name(Some("window-close"), IconSize::Button);
         let button = gtk::Button::new();
         let label = gtk::Label::new(Some(title));
@@ -31,15 +48,12 @@ impl MysNotebook {
         tab.pack_start(&button, false, false, 0);
         tab.show_all();
 
-        let editor_widget = editor.clone().upcast::<Widget>();
-
-        let my_scroll_window_widget = enable_scroll_for_sourceview(&editor_widget);
-
         let notebook = Self::get().unwrap();
-        let index = notebook.append_page(&my_scroll_window_widget, Some(&tab));
+        let index = notebook.append_page(page, Some(&tab));
 
+        let page = page.clone();
         button.connect_clicked(glib::clone!(@weak notebook => move |_| {
-            Self::close_tab(&my_scroll_window_widget);
+            Self::close_tab(&page);
         }));
 
         // Show Notebook widget (GTK+ widgets hide themselves by default)
@@ -48,24 +62,31 @@ impl MysNotebook {
         // open the newly created page
         notebook.set_current_page(Some(index));
 
-        // Set focus on first open
-        editor.set_has_focus(true);
-        editor.set_is_focus(true);
-
         index
     }
 
-    fn close_tab(widget: &Widget) {
+    pub fn close_tab(widget: &Widget) {
         let notebook = Self::get().unwrap();
         let Some(index) = notebook.page_num(widget) else { 
             eprintln!("MysNotebook::close_tab: Couldn't get page number for widget.");
             return
         };
+        if let Some(tab) = NotebookTabCache::find_by_position(index) {
+            super::lsp::close_document(&tab.file_path);
+            super::git_gutter::detach(&tab.file_path);
+
+            // Nothing is open anymore until another tab is switched to
+            if Workspace::get_open_file_path().as_deref() == Some(tab.file_path.as_str()) {
+                Workspace::set_open_file_path(None);
+            }
+        }
+
         notebook.remove_page(Some(index));
         // Also remove from cache
         NotebookTabCache::remove(index);
 
         // Hide statusbar UI if there are no open tabs
         crate::ui::statusbar::reset_and_hide();
+        crate::ui::features::outline::refresh();
     }
 }
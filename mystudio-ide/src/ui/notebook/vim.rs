@@ -0,0 +1,211 @@
+// Connects libmystudio Vim emulation to editor views
+
+use std::cell::RefCell;
+
+use gtk::{
+    gdk::{keys::constants as key_constants, EventKey, ModifierType},
+    prelude::{Cast, TextBufferExt},
+    traits::{TextViewExt, WidgetExt},
+    TextBuffer,
+};
+use libmystudio::{
+    app_config::AppConfig,
+    notebook::editor::jump_to_line_with_editor,
+    vim::{ExCommand, Vim, VimBuffer, VimKey, VimMode},
+};
+use sourceview4::{traits::BufferExt, Buffer, View};
+
+use crate::{
+    commands,
+    ui::{
+        action_row::handler::save_open_file,
+        statusbar::{message::show_message, vim_indicator},
+    },
+};
+
+use super::nbmain::MysNotebook;
+
+// Mode and registers are shared by all editors
+thread_local! { static G_VIM: RefCell<Vim> = RefCell::new(Vim::new()) }
+
+/**
+ * `VimBuffer` backed by the buffer of an editor view.
+ */
+struct EditorBuffer {
+    view: View,
+    buffer: TextBuffer,
+}
+
+impl EditorBuffer {
+    fn new(view: &View) -> Option<Self> {
+        Some(Self {
+            view: view.clone(),
+            buffer: view.buffer()?,
+        })
+    }
+
+    fn scroll_to_cursor(&self) {
+        if let Some(mark) = self.buffer.get_insert() {
+            self.view.scroll_mark_onscreen(&mark);
+        }
+    }
+
+    fn source_buffer(&self) -> Option<Buffer> {
+        self.buffer.clone().downcast::<Buffer>().ok()
+    }
+}
+
+impl VimBuffer for EditorBuffer {
+    fn text(&self) -> String {
+        self.buffer
+            .text(&self.buffer.start_iter(), &self.buffer.end_iter(), true)
+            .map(|text| text.to_string())
+            .unwrap_or_default()
+    }
+
+    fn cursor(&self) -> usize {
+        self.buffer.cursor_position() as usize
+    }
+
+    fn set_cursor(&mut self, offset: usize) {
+        let iter = self.buffer.iter_at_offset(offset as i32);
+        self.buffer.place_cursor(&iter);
+        self.scroll_to_cursor();
+    }
+
+    fn set_selection(&mut self, anchor: usize, cursor: usize) {
+        let cursor_iter = self.buffer.iter_at_offset(cursor as i32);
+        let anchor_iter = self.buffer.iter_at_offset(anchor as i32);
+        self.buffer.select_range(&cursor_iter, &anchor_iter);
+        self.scroll_to_cursor();
+    }
+
+    fn delete(&mut self, start: usize, end: usize) {
+        let mut start_iter = self.buffer.iter_at_offset(start as i32);
+        let mut end_iter = self.buffer.iter_at_offset(end as i32);
+        self.buffer.delete(&mut start_iter, &mut end_iter);
+    }
+
+    fn insert(&mut self, offset: usize, text: &str) {
+        let mut iter = self.buffer.iter_at_offset(offset as i32);
+        self.buffer.insert(&mut iter, text);
+    }
+
+    fn undo(&mut self) {
+        if let Some(buffer) = self.source_buffer().filter(|b| b.can_undo()) {
+            buffer.undo();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(buffer) = self.source_buffer().filter(|b| b.can_redo()) {
+            buffer.redo();
+        }
+    }
+
+    // User actions are undone at once by SourceView
+    fn begin_change(&mut self) {
+        self.buffer.begin_user_action();
+    }
+
+    fn end_change(&mut self) {
+        self.buffer.end_user_action();
+    }
+}
+
+/**
+ * Routes key presses of an editor through Vim emulation when `vim_mode` is
+ * enabled.
+ */
+pub fn attach(view: &View) {
+    view.connect_key_press_event(|view, event| {
+        // Read-only views (ex: Find in Files previews) keep default keys
+        if !AppConfig::current().Editor.vim_mode || !view.is_editable() {
+            return gtk::Inhibit(false);
+        }
+
+        let Some(key) = vim_key_from_event(event) else {
+            return gtk::Inhibit(false);
+        };
+        let Some(mut buffer) = EditorBuffer::new(view) else {
+            return gtk::Inhibit(false);
+        };
+
+        let response = G_VIM.with(|vim| vim.borrow_mut().handle_key(key, &mut buffer));
+        sync_view(view, true);
+
+        if let Some(command) = response.command {
+            run_ex_command(view, command);
+        }
+
+        gtk::Inhibit(response.consumed)
+    });
+}
+
+/**
+ * Updates cursor style and status bar to the current Vim mode.
+ */
+pub fn sync_view(view: &View, vim_mode: bool) {
+    let mode = G_VIM.with(|vim| vim.borrow().mode());
+
+    // Overwrite mode draws a block cursor
+    view.set_overwrite(vim_mode && mode != VimMode::Insert);
+
+    vim_indicator::update(&status(), vim_mode);
+}
+
+pub fn status() -> String {
+    G_VIM.with(|vim| vim.borrow().status())
+}
+
+fn vim_key_from_event(event: &EventKey) -> Option<VimKey> {
+    let keyval = event.keyval();
+    let state = event.state();
+
+    if event.is_modifier() || state.contains(ModifierType::MOD1_MASK) {
+        return None;
+    }
+
+    if state.contains(ModifierType::CONTROL_MASK) {
+        // Ctrl+[ is Escape in Vim
+        if keyval == key_constants::bracketleft {
+            return Some(VimKey::Escape);
+        }
+        return keyval.to_lower().to_unicode().map(VimKey::Ctrl);
+    }
+
+    if keyval == key_constants::Escape {
+        Some(VimKey::Escape)
+    } else if keyval == key_constants::Return || keyval == key_constants::KP_Enter {
+        Some(VimKey::Enter)
+    } else if keyval == key_constants::BackSpace {
+        Some(VimKey::Backspace)
+    } else {
+        keyval
+            .to_unicode()
+            .filter(|c| !c.is_control() || *c == '\t')
+            .map(VimKey::Char)
+    }
+}
+
+fn run_ex_command(view: &View, command: ExCommand) {
+    match command {
+        ExCommand::Write => commands::run("save_file"),
+        ExCommand::Quit => close_editor(view),
+        ExCommand::WriteQuit => {
+            // Keep the editor open if saving fails
+            if save_open_file() {
+                close_editor(view);
+            }
+        }
+        ExCommand::GotoLine(line) => jump_to_line_with_editor(view, line as i32, 1),
+        ExCommand::Unknown(command) => show_message(format!("Not an editor command: {command}")),
+    }
+}
+
+fn close_editor(view: &View) {
+    // Tabs hold the ScrolledWindow around the view
+    if let Some(scrolled_window) = view.parent() {
+        MysNotebook::close_tab(&scrolled_window);
+    }
+}
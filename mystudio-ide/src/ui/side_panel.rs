@@ -0,0 +1,59 @@
+// Notebook left of the editor, the workspace explorer is its first page and
+// other panels are added next to it, ex: Source Control
+
+use std::cell::RefCell;
+
+use gtk::{
+    prelude::{BuilderExtManual, IsA, NotebookExtManual},
+    traits::WidgetExt,
+    Builder, Label, Notebook, Widget,
+};
+
+thread_local! { static G_SIDE_PANEL: RefCell<Option<Notebook>> = RefCell::new(None) }
+
+pub fn init(builder: &Builder) {
+    G_SIDE_PANEL.with(|panel| {
+        *panel.borrow_mut() = builder.object("side_notebook");
+        assert!(panel.borrow().is_some());
+    });
+}
+
+fn get() -> Notebook {
+    G_SIDE_PANEL.with(|panel| {
+        panel
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| panic!("{}", "Unable to find side panel"))
+    })
+}
+
+/**
+ * Adds a page after the workspace explorer.
+ */
+pub fn add_page(page: &impl IsA<Widget>, title: &str) {
+    get().append_page(page, Some(&Label::new(Some(title))));
+}
+
+/**
+ * Selects `page`.
+ */
+pub fn show_page(page: &impl IsA<Widget>) {
+    let panel = get();
+
+    page.show_all();
+    panel.set_current_page(panel.page_num(page));
+}
+
+/**
+ * Selects `page`, or goes back to the workspace explorer when `page` is
+ * already selected.
+ */
+pub fn toggle_page(page: &impl IsA<Widget>) {
+    let panel = get();
+
+    if panel.current_page() == panel.page_num(page) {
+        panel.set_current_page(Some(0));
+    } else {
+        show_page(page);
+    }
+}
@@ -0,0 +1,40 @@
+use gtk::{
+    prelude::{BuilderExtManual, LabelExt},
+    traits::WidgetExt,
+};
+use libmystudio::git::status::GitStatus;
+
+use crate::G_BUILDER;
+
+use super::G_GIT_BRANCH;
+
+// Short commit id shown when HEAD is detached
+const SHORT_COMMIT_LENGTH: usize = 7;
+
+pub fn init() {
+    let builder = G_BUILDER.with(|builder| builder.borrow().clone().unwrap());
+
+    G_GIT_BRANCH.with(|indicator| {
+        *indicator.borrow_mut() = builder.object("label_git_branch");
+        assert!(indicator.borrow().is_some());
+    });
+}
+
+/**
+ * Shows the branch checked out in the workspace repository, hidden outside
+ * of repositories.
+ */
+pub fn update(status: &GitStatus) {
+    let Some(indicator) = G_GIT_BRANCH.with(|i| i.borrow().clone()) else {
+        return;
+    };
+
+    let label = match (&status.branch, &status.head) {
+        (Some(branch), _) => branch.clone(),
+        (None, Some(head)) => head.chars().take(SHORT_COMMIT_LENGTH).collect(),
+        (None, None) => String::new(),
+    };
+
+    indicator.set_visible(status.root.is_some() && !label.is_empty());
+    indicator.set_label(&label);
+}
@@ -0,0 +1,29 @@
+use gtk::prelude::{BuilderExtManual, ButtonExt};
+use libmystudio::diagnostics::DiagnosticCounts;
+
+use crate::{ui::features::problems, G_BUILDER};
+
+use super::G_DIAGNOSTICS_COUNT;
+
+pub fn init() {
+    let builder = G_BUILDER.with(|builder| builder.borrow().clone().unwrap());
+
+    G_DIAGNOSTICS_COUNT.with(|indicator| {
+        *indicator.borrow_mut() = builder.object("button_diagnostics_count");
+        assert!(indicator.borrow().is_some());
+
+        let indicator = indicator.borrow().clone().unwrap();
+        indicator.connect_clicked(|_| problems::toggle());
+    });
+}
+
+/**
+ * Shows the number of errors and warnings, ex: `2 errors, 1 warning`.
+ */
+pub fn update(counts: &DiagnosticCounts) {
+    let Some(indicator) = G_DIAGNOSTICS_COUNT.with(|i| i.borrow().clone()) else {
+        return;
+    };
+
+    indicator.set_label(&counts.to_string());
+}
@@ -8,7 +8,10 @@ use gtk::{
 use libmystudio::{notebook::editor::jump_to_line_with_editor, workspace::Workspace};
 use regex::Regex;
 
-use crate::{ui::notebook::editor, G_BUILDER};
+use crate::{
+    ui::notebook::{editor, navigation},
+    G_BUILDER,
+};
 
 pub fn show_goto_dialog() {
     let builder = G_BUILDER.with(|b| b.borrow().clone().unwrap());
@@ -69,6 +72,7 @@ fn read_user_input_and_process(input_field: &Entry, dialog: &Dialog) {
         line = FromStr::from_str(value).unwrap_or(line);
     }
 
+    navigation::record_current_location();
     jump_to_line_for_active_tab(line, col);
 
     // reset UI & hide
@@ -1,7 +1,10 @@
+pub mod branch_indicator;
+pub mod diagnostics_indicator;
 pub mod encoding_indicator;
 pub mod line_indicator;
 pub mod message;
 pub mod goto_line;
+pub mod vim_indicator;
 
 use std::cell::RefCell;
 
@@ -13,6 +16,9 @@ use crate::G_BUILDER;
 thread_local! { pub static G_STATUS_BAR: RefCell<Option<Statusbar>> = RefCell::new(None) }
 thread_local! { pub(self) static G_LINE_NUMBER: RefCell<Option<Button>> = RefCell::new(None) }
 thread_local! { pub(self) static G_FILE_ENCODING: RefCell<Option<Label>> = RefCell::new(None) }
+thread_local! { pub(self) static G_VIM_MODE: RefCell<Option<Label>> = RefCell::new(None) }
+thread_local! { pub(self) static G_GIT_BRANCH: RefCell<Option<Label>> = RefCell::new(None) }
+thread_local! { pub(self) static G_DIAGNOSTICS_COUNT: RefCell<Option<Button>> = RefCell::new(None) }
 
 pub(self) fn get_status_bar() -> Statusbar {
     G_STATUS_BAR.with(|status_bar| {
@@ -33,6 +39,9 @@ pub fn init() {
 
     line_indicator::init();
     encoding_indicator::init();
+    branch_indicator::init();
+    vim_indicator::init();
+    diagnostics_indicator::init();
 }
 
 /**
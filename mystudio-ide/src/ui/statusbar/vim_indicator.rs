@@ -0,0 +1,33 @@
+use gtk::{
+    prelude::{BuilderExtManual, LabelExt},
+    traits::WidgetExt,
+};
+use libmystudio::app_config::AppConfig;
+
+use crate::{ui::notebook::vim, G_BUILDER};
+
+use super::G_VIM_MODE;
+
+pub fn init() {
+    let builder = G_BUILDER.with(|builder| builder.borrow().clone().unwrap());
+
+    G_VIM_MODE.with(|indicator| {
+        *indicator.borrow_mut() = builder.object("label_vim_mode");
+        assert!(indicator.borrow().is_some());
+    });
+
+    update(&vim::status(), AppConfig::current().Editor.vim_mode);
+}
+
+/**
+ * Shows the Vim mode (ex: `-- INSERT --`), hidden when Vim mode is disabled.
+ */
+pub fn update(status: &str, vim_mode: bool) {
+    // Config is applied before the status bar is initialized
+    let Some(indicator) = G_VIM_MODE.with(|i| i.borrow().clone()) else {
+        return;
+    };
+
+    indicator.set_label(status);
+    indicator.set_visible(vim_mode);
+}
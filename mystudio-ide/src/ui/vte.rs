@@ -0,0 +1,261 @@
+// Bindings to the parts of libvte used by the terminal panel. There are no
+// published bindings of the GTK 3 version of VTE matching our gtk-rs version,
+// they are built with the `terminal` feature only.
+//
+// All pointers passed to VTE are either owned by the `Terminal` widget, which
+// outlives the call, or by locals of the calling function. VTE copies what it
+// keeps, see each call.
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int, c_long, c_uint, c_void},
+    ptr,
+};
+
+use gtk::{
+    gdk,
+    glib::{
+        self,
+        translate::{from_glib, from_glib_full, from_glib_none, ToGlibPtr},
+        GString, ObjectExt, ObjectType,
+    },
+    pango::FontDescription,
+    Widget,
+};
+
+use crate::ui::statusbar::message::show_message;
+
+#[repr(C)]
+struct VteTerminal {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct VteRegex {
+    _private: [u8; 0],
+}
+
+type SpawnCallback =
+    unsafe extern "C" fn(*mut VteTerminal, c_int, *mut glib::ffi::GError, *mut c_void);
+
+// VTE_PTY_DEFAULT
+const PTY_DEFAULT: c_int = 0;
+// G_SPAWN_SEARCH_PATH
+const SPAWN_SEARCH_PATH: c_uint = 1 << 2;
+// VTE_FORMAT_TEXT
+const FORMAT_TEXT: c_int = 1;
+// PCRE2_UTF | PCRE2_NO_UTF_CHECK | PCRE2_UCP | PCRE2_MULTILINE, VTE requires
+// the latter for match regexes
+const REGEX_FLAGS: u32 = 0x0008_0000 | 0x4000_0000 | 0x0002_0000 | 0x0000_0400;
+
+#[link(name = "vte-2.91")]
+extern "C" {
+    fn vte_terminal_new() -> *mut gtk::ffi::GtkWidget;
+    #[allow(clippy::too_many_arguments)]
+    fn vte_terminal_spawn_async(
+        terminal: *mut VteTerminal,
+        pty_flags: c_int,
+        working_directory: *const c_char,
+        argv: *mut *mut c_char,
+        envv: *mut *mut c_char,
+        spawn_flags: c_uint,
+        child_setup: Option<unsafe extern "C" fn(*mut c_void)>,
+        child_setup_data: *mut c_void,
+        child_setup_data_destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+        timeout: c_int,
+        cancellable: *mut c_void,
+        callback: Option<SpawnCallback>,
+        user_data: *mut c_void,
+    );
+    fn vte_terminal_copy_clipboard_format(terminal: *mut VteTerminal, format: c_int);
+    fn vte_terminal_paste_clipboard(terminal: *mut VteTerminal);
+    fn vte_terminal_get_has_selection(terminal: *mut VteTerminal) -> glib::ffi::gboolean;
+    fn vte_terminal_set_font(
+        terminal: *mut VteTerminal,
+        font_desc: *const gtk::pango::ffi::PangoFontDescription,
+    );
+    fn vte_terminal_set_scrollback_lines(terminal: *mut VteTerminal, lines: c_long);
+    fn vte_terminal_match_add_regex(
+        terminal: *mut VteTerminal,
+        regex: *mut VteRegex,
+        flags: u32,
+    ) -> c_int;
+    fn vte_terminal_match_set_cursor_name(
+        terminal: *mut VteTerminal,
+        tag: c_int,
+        cursor_name: *const c_char,
+    );
+    fn vte_terminal_check_match_at(
+        terminal: *mut VteTerminal,
+        x: f64,
+        y: f64,
+        tag: *mut c_int,
+    ) -> *mut c_char;
+    fn vte_regex_new_for_match(
+        pattern: *const c_char,
+        pattern_length: isize,
+        flags: u32,
+        error: *mut *mut glib::ffi::GError,
+    ) -> *mut VteRegex;
+    fn vte_regex_unref(regex: *mut VteRegex) -> *mut VteRegex;
+}
+
+/// A `VteTerminal` widget.
+#[derive(Clone)]
+pub struct Terminal(Widget);
+
+impl Terminal {
+    pub fn new() -> Self {
+        // SAFETY: `vte_terminal_new` returns a new widget with a floating
+        // reference, which `from_glib_none` sinks and owns.
+        Self(unsafe { from_glib_none(vte_terminal_new()) })
+    }
+
+    pub fn widget(&self) -> &Widget {
+        &self.0
+    }
+
+    // Valid as long as `self`, the widget is a `VteTerminal` made by `new`
+    fn as_ptr(&self) -> *mut VteTerminal {
+        self.0.as_ptr() as *mut VteTerminal
+    }
+
+    /**
+     * Starts `argv` in the terminal, the program is looked up in `PATH`.
+     * Failures are shown in the status bar.
+     */
+    pub fn spawn(&self, working_directory: &str, argv: &[String]) {
+        let working_directory = CString::new(working_directory).ok();
+        let argv: Vec<CString> = argv
+            .iter()
+            .filter_map(|arg| CString::new(arg.as_str()).ok())
+            .collect();
+        let mut argv_ptrs: Vec<*mut c_char> = argv
+            .iter()
+            .map(|arg| arg.as_ptr() as *mut c_char)
+            .chain([ptr::null_mut()])
+            .collect();
+
+        // SAFETY: `argv_ptrs` is null terminated and points into `argv`, both
+        // alive until VTE returns, it copies the strings and the working
+        // directory before that. `spawn_callback` uses no user data.
+        unsafe {
+            vte_terminal_spawn_async(
+                self.as_ptr(),
+                PTY_DEFAULT,
+                working_directory
+                    .as_ref()
+                    .map_or(ptr::null(), |directory| directory.as_ptr()),
+                argv_ptrs.as_mut_ptr(),
+                ptr::null_mut(),
+                SPAWN_SEARCH_PATH,
+                None,
+                ptr::null_mut(),
+                None,
+                -1,
+                ptr::null_mut(),
+                Some(spawn_callback),
+                ptr::null_mut(),
+            );
+        }
+    }
+
+    /// Copies the selected text, if any.
+    pub fn copy_clipboard(&self) {
+        // SAFETY: the terminal pointer is valid, see `as_ptr`.
+        unsafe {
+            let has_selection: bool = from_glib(vte_terminal_get_has_selection(self.as_ptr()));
+            if has_selection {
+                vte_terminal_copy_clipboard_format(self.as_ptr(), FORMAT_TEXT);
+            }
+        }
+    }
+
+    pub fn paste_clipboard(&self) {
+        // SAFETY: the terminal pointer is valid, see `as_ptr`.
+        unsafe { vte_terminal_paste_clipboard(self.as_ptr()) }
+    }
+
+    pub fn set_font(&self, font: &FontDescription) {
+        // SAFETY: VTE copies the font description, borrowed for the call.
+        unsafe { vte_terminal_set_font(self.as_ptr(), font.to_glib_none().0) }
+    }
+
+    pub fn set_scrollback_lines(&self, lines: u32) {
+        // SAFETY: the terminal pointer is valid, see `as_ptr`.
+        unsafe { vte_terminal_set_scrollback_lines(self.as_ptr(), lines as c_long) }
+    }
+
+    /**
+     * Underlines text matching `pattern` (PCRE2 syntax) under the pointer,
+     * see `link_at_event`.
+     */
+    pub fn add_link_pattern(&self, pattern: &str) {
+        let Ok(pattern) = CString::new(pattern) else {
+            return;
+        };
+
+        // SAFETY: `pattern` is nul terminated, length -1 tells VTE so. On
+        // failure `error` is set and owned by us, on success the terminal
+        // takes its own reference to `regex` and we release ours. The cursor
+        // name is copied.
+        unsafe {
+            let mut error = ptr::null_mut();
+            let regex = vte_regex_new_for_match(pattern.as_ptr(), -1, REGEX_FLAGS, &mut error);
+            if regex.is_null() {
+                let error: glib::Error = from_glib_full(error);
+                eprintln!("Invalid terminal link pattern: {error}");
+                return;
+            }
+
+            let tag = vte_terminal_match_add_regex(self.as_ptr(), regex, 0);
+            let cursor_name = CString::new("pointer").unwrap();
+            vte_terminal_match_set_cursor_name(self.as_ptr(), tag, cursor_name.as_ptr());
+            vte_regex_unref(regex);
+        }
+    }
+
+    /// Returns the link under the pointer of a click.
+    pub fn link_at_event(&self, event: &gdk::Event) -> Option<String> {
+        let (x, y) = event.coords()?;
+
+        // SAFETY: `tag` is a local written by VTE, the returned text is newly
+        // allocated or null, and freed by `from_glib_full`.
+        unsafe {
+            let mut tag = 0;
+            let text = vte_terminal_check_match_at(self.as_ptr(), x, y, &mut tag);
+            if text.is_null() {
+                return None;
+            }
+
+            let text: GString = from_glib_full(text);
+            Some(text.to_string())
+        }
+    }
+
+    /// Calls `f` when the program started by `spawn` exits.
+    pub fn connect_child_exited<F: Fn(&Terminal) + 'static>(&self, f: F) {
+        // Weak, the terminal owns the handler
+        let weak_widget = self.0.downgrade();
+        self.0.connect_local("child-exited", false, move |_| {
+            if let Some(widget) = weak_widget.upgrade() {
+                f(&Terminal(widget));
+            }
+            None
+        });
+    }
+}
+
+// SAFETY: called by VTE on the main thread with a valid or null `error`,
+// which VTE frees after the call. Nothing else is dereferenced.
+unsafe extern "C" fn spawn_callback(
+    _terminal: *mut VteTerminal,
+    _pid: c_int,
+    error: *mut glib::ffi::GError,
+    _user_data: *mut c_void,
+) {
+    if !error.is_null() {
+        let message = CStr::from_ptr((*error).message).to_string_lossy();
+        show_message(format!("Unable to start terminal: {message}"));
+    }
+}
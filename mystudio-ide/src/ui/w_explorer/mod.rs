@@ -1,12 +1,26 @@
 use std::cell::RefCell;
 
-use gtk::{prelude::BuilderExtManual, Builder, TreeView};
+use gtk::{
+    prelude::{BuilderExtManual, Cast},
+    traits::TreeViewExt,
+    Builder, TreeStore, TreeView,
+};
+use libmystudio::{
+    git::{status::GitStatus, watcher::GitStatusWatcher},
+    tree::tree_model::RootTreeModel,
+};
 
-use crate::{comms::Comms, ui::w_explorer::tree_view::setup_tree};
+use crate::{
+    comms::{CommEvents, Comms},
+    ui::{
+        features::source_control, statusbar::branch_indicator, w_explorer::tree_view::setup_tree,
+    },
+};
 
 pub mod tree_view;
 
 thread_local! { pub static G_TREE: RefCell<Option<TreeView>> = RefCell::new(None) }
+thread_local! { static G_GIT_WATCHER: RefCell<Option<GitStatusWatcher>> = RefCell::new(None) }
 
 pub fn init(builder: &Builder) {
     let tx = Comms::sender();
@@ -15,4 +29,44 @@ pub fn init(builder: &Builder) {
         assert!(tree.borrow().is_some());
         setup_tree(builder, tx);
     });
+
+    // Decorate tree with git status
+    let tx = Comms::sender();
+    let watcher = GitStatusWatcher::watch(move |status| {
+        tx.send(CommEvents::GitStatusChanged(status)).ok();
+    });
+    G_GIT_WATCHER.with(|w| *w.borrow_mut() = Some(watcher));
+}
+
+/**
+ * Reads git status again right away, ex: after saving a file.
+ */
+pub fn refresh_git_status() {
+    G_GIT_WATCHER.with(|watcher| {
+        if let Some(watcher) = watcher.borrow().as_ref() {
+            watcher.refresh();
+        }
+    });
+}
+
+pub fn git_status_changed(status: GitStatus) {
+    GitStatus::replace(status);
+
+    G_TREE.with(|tree| {
+        let store = tree
+            .borrow()
+            .as_ref()
+            .and_then(|tree| tree.model())
+            .and_then(|model| model.downcast::<TreeStore>().ok());
+        if let Some(store) = store {
+            RootTreeModel::apply_git_status(&store);
+        }
+    });
+
+    // Committed versions of files may differ, ex: after a commit
+    crate::ui::notebook::git_gutter::reload_heads();
+
+    let status = GitStatus::current();
+    branch_indicator::update(&status);
+    source_control::status_changed();
 }
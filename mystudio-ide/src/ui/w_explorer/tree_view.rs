@@ -1,14 +1,16 @@
 use std::path::Path;
 
 use gtk::{
+    gdk::{EventButton, EventType},
     glib::{self, Sender},
     prelude::{
         BuilderExtManual, Cast, ObjectExt, ToValue, TreeModelExt, TreeSelectionExt, TreeViewExt,
     },
-    TreeStore,
+    traits::{GtkMenuExt, GtkMenuItemExt, MenuShellExt, WidgetExt},
+    Menu, MenuItem, TreeStore,
 };
 
-use crate::comms::CommEvents;
+use crate::{comms::CommEvents, ui::notebook::diff_view};
 
 use libmystudio::{
     fs::{read_dir_recursive, read_file_contents},
@@ -66,6 +68,40 @@ pub fn setup_tree(builder: &gtk::Builder, tx: glib::Sender<CommEvents>) {
             }
         });
 
+        // Files are compared from their context menu
+        tree.connect_button_press_event(|tree, event| {
+            if event.event_type() != EventType::ButtonPress || event.button() != 3 {
+                return gtk::Inhibit(false);
+            }
+
+            let (x, y) = event.position();
+            let data_model = tree
+                .path_at_pos(x as i32, y as i32)
+                .and_then(|(path, ..)| path)
+                .zip(tree.model())
+                .and_then(|(path, model)| model.iter(&path).map(|iter| model.value(&iter, 0)))
+                .and_then(|value| value.get::<RootTreeModel>().ok());
+            let Some(data_model) = data_model else {
+                return gtk::Inhibit(false);
+            };
+
+            let item_type = data_model
+                .property_value("item-type")
+                .get::<TreeNodeType>()
+                .unwrap();
+            if item_type != TreeNodeType::File {
+                return gtk::Inhibit(false);
+            }
+            let abs_path = data_model
+                .property_value("abs-path")
+                .get::<String>()
+                .unwrap();
+
+            // Handled, so that the file isn't selected and opened
+            show_compare_menu(&abs_path, event);
+            gtk::Inhibit(true)
+        });
+
         tree.selection().connect_changed(move |selected_data| {
             let selected_data = selected_data.selected();
             if let Some((tree_model, tree_iter)) = selected_data {
@@ -114,6 +150,31 @@ pub fn setup_tree(builder: &gtk::Builder, tx: glib::Sender<CommEvents>) {
     });
 }
 
+// Context menu of a file, to compare it with another file of the tree
+fn show_compare_menu(file_path: &str, event: &EventButton) {
+    let menu = Menu::new();
+
+    let select_item = MenuItem::with_label("Select for Compare");
+    let file_path_clone = file_path.to_string();
+    select_item.connect_activate(move |_| diff_view::select_for_compare(&file_path_clone));
+    menu.append(&select_item);
+
+    let selected = diff_view::selected_for_compare().filter(|selected| selected != file_path);
+    if let Some(selected) = selected {
+        let name = Path::new(&selected)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(selected);
+        let compare_item = MenuItem::with_label(&format!("Compare with '{name}'"));
+        let file_path = file_path.to_string();
+        compare_item.connect_activate(move |_| diff_view::compare_with_selected(&file_path));
+        menu.append(&compare_item);
+    }
+
+    menu.show_all();
+    menu.popup_at_pointer(Some(&**event));
+}
+
 pub fn handle_tree_view_event(tree_model: Option<RootTreeModel>, tx: &Sender<CommEvents>) {
     if tree_model.is_none() {
         // Reset workspace's 'current open file' tracker